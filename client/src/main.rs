@@ -1,8 +1,11 @@
 use anyhow::Result;
 use clap::{Parser, Subcommand};
-use common::{Command, Response};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use common::{
+    ClientMessage, Command, ErrorCode, Hello, Request, Response, ServerMessage, WallpaperError,
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::UnixStream;
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
 
 #[derive(Parser)]
 #[command(name = "wwctl")]
@@ -11,16 +14,28 @@ use tokio::net::UnixStream;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Return as soon as the daemon has queued the command, without waiting for the result
+    #[arg(long, global = true)]
+    no_wait: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Set wallpaper from image or video file
     Set {
-        /// Path to the wallpaper file
+        /// Path or URL (file://, http://, https://) of the wallpaper, or '-' to read an image from stdin
         path: String,
 
-        /// Target output (monitor), or 'all' for all outputs
+        /// Treat stdin as raw pixels of this size (e.g. 1920x1080) instead of an image file
+        #[arg(long, value_name = "WIDTHxHEIGHT")]
+        raw: Option<String>,
+
+        /// Pixel layout of raw stdin data (rgba8, bgra8, rgb8, gray8)
+        #[arg(long, default_value = "rgba8", requires = "raw")]
+        pixel_format: String,
+
+        /// Target output: a name like DP-1, a selector like model:*U2720Q* or all-but:eDP-1, or 'all'
         #[arg(short, long)]
         output: Option<String>,
 
@@ -39,6 +54,15 @@ enum Commands {
         /// Image scaling mode (center, fill, fit, stretch, tile)
         #[arg(short, long, default_value = "fill")]
         scale: String,
+
+        /// Point to keep in view when fill crops the image, as fractions of its size (e.g. 0.5,0.3)
+        #[arg(long, value_name = "X,Y")]
+        focus: Option<common::FocalPoint>,
+
+        /// Background around images that don't cover the output in fit and center modes
+        /// (dominant, blur, mirror, black or a hex colour)
+        #[arg(long, value_name = "BACKGROUND")]
+        letterbox: Option<common::Letterbox>,
     },
 
     /// Set solid color background
@@ -46,17 +70,49 @@ enum Commands {
         /// Color in hex format (e.g., #FF5733 or FF5733)
         color: String,
 
-        /// Target output (monitor), or 'all' for all outputs
+        /// Target output: a name like DP-1, a selector like model:*U2720Q* or all-but:eDP-1, or 'all'
         #[arg(short, long)]
         output: Option<String>,
     },
 
+    /// Set gradient background
+    Gradient {
+        /// Colours in order, each optionally with a position from 0 to 1 (e.g. #1e1e2e #89b4fa@0.8)
+        #[arg(required = true, num_args = 2..)]
+        stops: Vec<common::GradientStop>,
+
+        /// Gradient shape (linear, radial, conic)
+        #[arg(short, long, default_value = "linear")]
+        kind: common::GradientKind,
+
+        /// Direction of linear gradients or start of conic ones, in degrees clockwise from right
+        /// (0=left to right, 90=top to bottom)
+        #[arg(long, default_value = "90", allow_negative_numbers = true)]
+        angle: f32,
+
+        /// Don't dither (may show banding on 8-bit displays)
+        #[arg(long)]
+        no_dither: bool,
+
+        /// Target output: a name like DP-1, a selector like model:*U2720Q* or all-but:eDP-1, or 'all'
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Transition effect
+        #[arg(short, long)]
+        transition: Option<String>,
+
+        /// Transition duration in milliseconds
+        #[arg(short, long, default_value = "500")]
+        duration: u32,
+    },
+
     /// Set animated shader wallpaper
     Shader {
         /// Shader name (plasma, waves, matrix, gradient, starfield, raymarching, tunnel)
         shader: String,
 
-        /// Target output (monitor), or 'all' for all outputs
+        /// Target output: a name like DP-1, a selector like model:*U2720Q* or all-but:eDP-1, or 'all'
         #[arg(short, long)]
         output: Option<String>,
 
@@ -106,7 +162,7 @@ enum Commands {
         /// Overlay name (vignette, scanlines, film-grain, chromatic, crt, pixelate, tint)
         overlay: String,
 
-        /// Target output (monitor), or 'all' for all outputs
+        /// Target output: a name like DP-1, a selector like model:*U2720Q* or all-but:eDP-1, or 'all'
         #[arg(short, long)]
         output: Option<String>,
 
@@ -145,15 +201,77 @@ enum Commands {
         /// Blue component (0.0-1.0) - for tint effect
         #[arg(long)]
         tint_b: Option<f32>,
+
+        /// Fade in (or interpolate from the current parameters) over this many milliseconds
+        #[arg(long)]
+        fade: Option<u32>,
+
+        /// Easing curve for the fade (linear, ease-in, ease-out, ease-in-out)
+        #[arg(long, default_value = "ease-in-out")]
+        easing: String,
     },
 
     /// Clear shader overlay
     ClearOverlay {
-        /// Target output (monitor), or 'all' for all outputs
+        /// Target output: a name like DP-1, a selector like model:*U2720Q* or all-but:eDP-1, or 'all'
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Fade out over this many milliseconds
+        #[arg(long)]
+        fade: Option<u32>,
+
+        /// Easing curve for the fade (linear, ease-in, ease-out, ease-in-out)
+        #[arg(long, default_value = "ease-in-out")]
+        easing: String,
+    },
+
+    /// Save what an output is currently showing to a PNG
+    Capture {
+        /// Where to write the PNG
+        path: String,
+
+        /// Output (monitor) to capture; all outputs are stitched together by default
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Show the colour palette extracted from the current wallpaper
+    Palette {
+        /// Output (monitor) to show; all outputs by default
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Set the volume of video wallpapers' audio
+    Volume {
+        /// Volume in percent
+        #[arg(
+            value_parser = clap::value_parser!(u8).range(0..=100),
+            required_unless_present_any = ["mute", "unmute"]
+        )]
+        percent: Option<u8>,
+
+        /// Mute the audio
+        #[arg(long, conflicts_with = "unmute")]
+        mute: bool,
+
+        /// Unmute the audio
+        #[arg(long)]
+        unmute: bool,
+
+        /// Target output: a name like DP-1, a selector like model:*U2720Q* or all-but:eDP-1, or 'all'
         #[arg(short, long)]
         output: Option<String>,
     },
 
+    /// Show what the image cache holds
+    Cache {
+        /// Drop all cached images instead
+        #[arg(long)]
+        clear: bool,
+    },
+
     /// Query daemon status and outputs
     Query,
 
@@ -195,22 +313,79 @@ async fn main() -> Result<()> {
     let command = match cli.command {
         Commands::Set {
             path,
+            raw,
+            pixel_format,
             output,
             transition,
             duration,
             angle,
             scale,
+            focus,
+            letterbox,
         } => {
             let transition_type = parse_transition(&transition, duration, angle);
             let scale_mode = parse_scale_mode(&scale);
-            Command::SetWallpaper {
-                path,
-                output,
-                transition: Some(transition_type),
-                scale: Some(scale_mode),
+
+            if path == "-" {
+                if focus.is_some() {
+                    anyhow::bail!("--focus is only valid with a file path or URL");
+                }
+
+                let (width, height, format) = match raw {
+                    Some(size) => {
+                        let (width, height) = parse_size(&size)?;
+                        (width, height, pixel_format.parse()?)
+                    }
+                    None => (0, 0, common::PixelFormat::Encoded),
+                };
+
+                let mut bytes = Vec::new();
+                tokio::io::stdin().read_to_end(&mut bytes).await?;
+
+                Command::SetImageData {
+                    width,
+                    height,
+                    format,
+                    bytes,
+                    output,
+                    transition: Some(transition_type),
+                    scale: Some(scale_mode),
+                    letterbox,
+                }
+            } else {
+                if raw.is_some() {
+                    anyhow::bail!("--raw is only valid when reading from stdin ('-')");
+                }
+
+                Command::SetWallpaper {
+                    path,
+                    output,
+                    transition: Some(transition_type),
+                    scale: Some(scale_mode),
+                    focus,
+                    letterbox,
+                }
             }
         }
         Commands::Color { color, output } => Command::SetColor { color, output },
+        Commands::Gradient {
+            stops,
+            kind,
+            angle,
+            no_dither,
+            output,
+            transition,
+            duration,
+        } => Command::SetGradient {
+            gradient: common::Gradient {
+                kind,
+                angle,
+                stops,
+                dither: !no_dither,
+            },
+            output,
+            transition: transition.map(|t| parse_transition(&t, duration, 45.0)),
+        },
         Commands::Shader {
             shader,
             output,
@@ -281,6 +456,8 @@ async fn main() -> Result<()> {
             tint_r,
             tint_g,
             tint_b,
+            fade,
+            easing,
         } => {
             // Only create params if at least one parameter is specified
             let params = if intensity.is_some()
@@ -312,9 +489,35 @@ async fn main() -> Result<()> {
                 overlay,
                 params,
                 output,
+                fade: fade.map(|ms| parse_overlay_fade(ms, &easing)),
             }
         }
-        Commands::ClearOverlay { output } => Command::ClearOverlay { output },
+        Commands::ClearOverlay {
+            output,
+            fade,
+            easing,
+        } => Command::ClearOverlay {
+            output,
+            fade: fade.map(|ms| parse_overlay_fade(ms, &easing)),
+        },
+        Commands::Capture { path, output } => Command::Capture {
+            output,
+            // The daemon has its own working directory
+            path: std::path::absolute(&path)?.to_string_lossy().into_owned(),
+        },
+        Commands::Palette { output } => Command::GetPalette { output },
+        Commands::Volume {
+            percent,
+            mute,
+            unmute,
+            output,
+        } => Command::SetVolume {
+            output,
+            volume: percent.map(|percent| percent as f32 / 100.0),
+            muted: (mute || unmute).then_some(mute),
+        },
+        Commands::Cache { clear: false } => Command::GetCacheStatus,
+        Commands::Cache { clear: true } => Command::ClearCache,
         Commands::Query => Command::Query,
         Commands::Kill => Command::Kill,
         Commands::ListOutputs => Command::ListOutputs,
@@ -327,7 +530,7 @@ async fn main() -> Result<()> {
         Commands::Resources => Command::GetResources,
     };
 
-    match send_command(command).await {
+    match send_command(command, cli.no_wait).await {
         Ok(response) => {
             handle_response(response);
             Ok(())
@@ -340,25 +543,74 @@ async fn main() -> Result<()> {
     }
 }
 
-async fn send_command(command: Command) -> Result<Response> {
+async fn send_command(command: Command, no_wait: bool) -> Result<Response> {
+    const REQUEST_ID: u64 = 1;
+
     let socket_path = common::get_socket_path();
 
     let stream = UnixStream::connect(&socket_path).await?;
-    let (reader, mut writer) = stream.into_split();
-    let mut reader = BufReader::new(reader);
+    let (mut reader, mut writer) = stream.into_split();
+
+    // Negotiate protocol version and check the daemon knows this command
+    let hello = ClientMessage::Hello(Hello {
+        protocol_version: common::PROTOCOL_VERSION,
+        client: Some(format!("wwctl {}", env!("CARGO_PKG_VERSION"))),
+    });
+    write_frame(&mut writer, &hello).await?;
+
+    let welcome = match read_frame(&mut reader).await? {
+        ServerMessage::Welcome(welcome) => welcome,
+        ServerMessage::Rejected(e) => return Ok(Response::Error(e)),
+        ServerMessage::Reply(_) => anyhow::bail!("Daemon did not answer the handshake"),
+    };
+
+    if !welcome.supports(&command) {
+        return Ok(Response::Error(WallpaperError::UnsupportedCommand(
+            format!(
+                "{} (daemon {} speaks protocol v{})",
+                command.name(),
+                welcome.daemon_version,
+                welcome.protocol_version
+            ),
+        )));
+    }
 
     // Send command
-    let command_json = serde_json::to_string(&command)?;
-    writer.write_all(command_json.as_bytes()).await?;
-    writer.write_all(b"\n").await?;
-    writer.flush().await?;
+    let request = ClientMessage::Request(Request {
+        request_id: REQUEST_ID,
+        command,
+        no_wait,
+    });
+    write_frame(&mut writer, &request).await?;
 
     // Read response
-    let mut response_line = String::new();
-    reader.read_line(&mut response_line).await?;
+    match read_frame(&mut reader).await? {
+        ServerMessage::Reply(reply) if reply.request_id == REQUEST_ID => Ok(reply.response),
+        ServerMessage::Reply(reply) => anyhow::bail!(
+            "Reply for unexpected request {} (expected {})",
+            reply.request_id,
+            REQUEST_ID
+        ),
+        ServerMessage::Welcome(_) | ServerMessage::Rejected(_) => {
+            anyhow::bail!("Unexpected handshake from daemon")
+        }
+    }
+}
+
+async fn write_frame(writer: &mut OwnedWriteHalf, message: &ClientMessage) -> Result<()> {
+    writer.write_all(&common::encode_frame(message)?).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+async fn read_frame(reader: &mut OwnedReadHalf) -> Result<ServerMessage> {
+    let mut header = [0u8; 4];
+    reader.read_exact(&mut header).await?;
+
+    let mut payload = vec![0u8; common::decode_frame_len(header)?];
+    reader.read_exact(&mut payload).await?;
 
-    let response: Response = serde_json::from_str(&response_line)?;
-    Ok(response)
+    Ok(serde_json::from_slice(&payload)?)
 }
 
 fn handle_response(response: Response) {
@@ -367,8 +619,8 @@ fn handle_response(response: Response) {
             println!("✓ Success");
         }
         Response::Error(e) => {
-            eprintln!("✗ Error: {}", e);
-            std::process::exit(1);
+            eprintln!("✗ Error [{}]: {}", e.code(), e);
+            std::process::exit(exit_code(e.code()));
         }
         Response::Status(status) => {
             println!("Daemon Status:");
@@ -386,6 +638,28 @@ fn handle_response(response: Response) {
                     "  {} - {}x{} (scale: {})",
                     output.name, output.width, output.height, output.scale
                 );
+                if !output.make.is_empty() || !output.model.is_empty() {
+                    println!("    Monitor: {} {}", output.make, output.model);
+                }
+                if let Some(serial) = &output.serial {
+                    println!("    Serial: {}", serial);
+                }
+                if let Some(description) = &output.description {
+                    println!("    Description: {}", description);
+                }
+                if let Some((x, y)) = output.position {
+                    println!("    Position: {},{}", x, y);
+                }
+                let (width_mm, height_mm) = output.physical_size;
+                if width_mm > 0 && height_mm > 0 {
+                    println!("    Physical size: {}x{} mm", width_mm, height_mm);
+                }
+                if let Some(refresh) = output.refresh_rate {
+                    println!("    Refresh rate: {:.2} Hz", refresh as f64 / 1000.0);
+                }
+                if !output.transform.is_empty() {
+                    println!("    Transform: {}", output.transform);
+                }
             }
         }
         Response::Pong => {
@@ -401,6 +675,82 @@ fn handle_response(response: Response) {
                 println!("  Battery: {}%", pct);
             }
         }
+        Response::Palette(palettes) => {
+            for entry in palettes {
+                println!("{}:", entry.output);
+                println!("  Colors: {}", entry.palette.colors.join(" "));
+                println!("  Background: {}", entry.palette.background);
+                println!("  Foreground: {}", entry.palette.foreground);
+                println!("  Accent: {}", entry.palette.accent);
+            }
+        }
+        Response::Cache(cache) => {
+            let mib = |bytes: u64| bytes as f64 / 1024.0 / 1024.0;
+            println!("Image Cache:");
+            match cache.limit_bytes {
+                Some(limit) => println!("  Usage: {:.1} / {:.1} MiB", mib(cache.bytes), mib(limit)),
+                None => println!("  Usage: {:.1} MiB (unlimited)", mib(cache.bytes)),
+            }
+            println!(
+                "  Hits: {}  Misses: {}  Evictions: {}",
+                cache.hits, cache.misses, cache.evictions
+            );
+            if !cache.entries.is_empty() {
+                println!("  Entries:");
+            }
+            for entry in cache.entries {
+                let scale = entry
+                    .scale
+                    .map(|mode| format!("{:?}", mode).to_lowercase())
+                    .unwrap_or_else(|| "original".to_string());
+                println!(
+                    "    {} ({}x{}, {}, {:.1} MiB)",
+                    entry.path,
+                    entry.width,
+                    entry.height,
+                    scale,
+                    mib(entry.bytes)
+                );
+            }
+        }
+        Response::Applied(results) => {
+            if results.is_empty() {
+                println!("✓ Success (no matching outputs)");
+                return;
+            }
+
+            let mut first_error = None;
+            for result in results {
+                match result.error {
+                    None => println!("✓ {}", result.output),
+                    Some(e) => {
+                        eprintln!("✗ {} [{}]: {}", result.output, e.code(), e);
+                        first_error.get_or_insert(e.code());
+                    }
+                }
+            }
+
+            if let Some(code) = first_error {
+                std::process::exit(exit_code(code));
+            }
+        }
+    }
+}
+
+/// Map an error code to a process exit status so scripts can tell failures apart
+fn exit_code(code: ErrorCode) -> i32 {
+    match code {
+        ErrorCode::FileNotFound | ErrorCode::NotFound | ErrorCode::FetchFailed => 2,
+        ErrorCode::UnknownOutput => 3,
+        ErrorCode::UnsupportedFormat => 4,
+        ErrorCode::UnknownShader | ErrorCode::UnknownOverlay | ErrorCode::InvalidArgument => 5,
+        ErrorCode::Gstreamer | ErrorCode::Video => 6,
+        ErrorCode::GpuUnavailable => 7,
+        ErrorCode::FeatureDisabled
+        | ErrorCode::UnsupportedCommand
+        | ErrorCode::UnsupportedProtocol => 8,
+        ErrorCode::NoPlaylist | ErrorCode::PlaylistEmpty => 9,
+        _ => 1,
     }
 }
 
@@ -426,6 +776,27 @@ fn parse_transition(name: &str, duration_ms: u32, angle: f32) -> common::Transit
     }
 }
 
+fn parse_overlay_fade(duration_ms: u32, easing: &str) -> common::OverlayFade {
+    let easing = easing.parse().unwrap_or_else(|_| {
+        eprintln!("Warning: Unknown easing '{}', using 'ease-in-out'", easing);
+        common::Easing::EaseInOut
+    });
+
+    common::OverlayFade {
+        duration_ms,
+        easing,
+    }
+}
+
+/// Parse a `WIDTHxHEIGHT` size such as `1920x1080`
+fn parse_size(size: &str) -> Result<(u32, u32)> {
+    let (width, height) = size
+        .split_once(['x', 'X'])
+        .ok_or_else(|| anyhow::anyhow!("Invalid size '{}', expected WIDTHxHEIGHT", size))?;
+
+    Ok((width.trim().parse()?, height.trim().parse()?))
+}
+
 fn parse_scale_mode(name: &str) -> common::ScaleMode {
     match name.to_lowercase().as_str() {
         "center" => common::ScaleMode::Center,
@@ -10,6 +10,15 @@
 //! messages. The client sends [`Command`] variants and receives [`Response`]
 //! variants.
 //!
+//! Each message is a length-prefixed frame (see [`encode_frame`]). A client
+//! may open with [`ClientMessage::Hello`] to negotiate the protocol version and
+//! learn which commands the daemon supports, then send any number of
+//! [`ClientMessage::Request`]s. Every [`Reply`] carries the `request_id` of the
+//! request it answers, so requests can be pipelined.
+//!
+//! The daemon still accepts the original protocol (one bare JSON [`Command`]
+//! per line) for older clients and shell scripts.
+//!
 //! # Examples
 //!
 //! ```no_run
@@ -36,8 +45,10 @@ use thiserror::Error;
 
 /// Common error types shared between client and daemon.
 ///
-/// All errors are serializable for transmission over IPC.
-#[derive(Error, Debug, Serialize, Deserialize)]
+/// All errors are serializable for transmission over IPC. Structured variants
+/// carry the context needed to act on them; use [`WallpaperError::code`] for a
+/// stable machine-readable classification.
+#[derive(Error, Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum WallpaperError {
     #[error("IO error: {0}")]
     Io(String),
@@ -56,6 +67,174 @@ pub enum WallpaperError {
 
     #[error("Not found: {0}")]
     NotFound(String),
+
+    #[error("Unsupported command: {0}")]
+    UnsupportedCommand(String),
+
+    #[error("Unsupported protocol v{client} (daemon speaks v{min} to v{max})")]
+    UnsupportedProtocol { client: u32, min: u32, max: u32 },
+
+    #[error("Failed to fetch {url}: {reason}")]
+    FetchFailed { url: String, reason: String },
+
+    #[error("Unknown output: {output}")]
+    UnknownOutput { output: String },
+
+    #[error("File not found: {path}")]
+    FileNotFound { path: String },
+
+    #[error("Unsupported format for {path}: {reason}")]
+    UnsupportedFormat { path: String, reason: String },
+
+    #[error("Unknown shader: {shader}")]
+    UnknownShader { shader: String },
+
+    #[error("Unknown overlay: {overlay}")]
+    UnknownOverlay { overlay: String },
+
+    #[error("GStreamer element '{element}' failed: {message}")]
+    Gstreamer { element: String, message: String },
+
+    #[error("GPU unavailable: {reason}")]
+    GpuUnavailable { reason: String },
+
+    #[error("Feature '{feature}' is not compiled in")]
+    FeatureDisabled { feature: String },
+
+    #[error("Invalid {field} '{value}': {reason}")]
+    InvalidArgument {
+        field: String,
+        value: String,
+        reason: String,
+    },
+
+    #[error("No playlist configured")]
+    NoPlaylist,
+
+    #[error("Playlist is empty")]
+    PlaylistEmpty,
+
+    #[error("Not available yet: {0}")]
+    Unavailable(String),
+
+    #[error("Not implemented: {0}")]
+    NotImplemented(String),
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+}
+
+impl WallpaperError {
+    /// Get the machine-readable error code
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            WallpaperError::Io(_) => ErrorCode::Io,
+            WallpaperError::Ipc(_) => ErrorCode::Ipc,
+            WallpaperError::Wayland(_) => ErrorCode::Wayland,
+            WallpaperError::Image(_) => ErrorCode::Image,
+            WallpaperError::Video(_) => ErrorCode::Video,
+            WallpaperError::NotFound(_) => ErrorCode::NotFound,
+            WallpaperError::UnsupportedCommand(_) => ErrorCode::UnsupportedCommand,
+            WallpaperError::UnsupportedProtocol { .. } => ErrorCode::UnsupportedProtocol,
+            WallpaperError::FetchFailed { .. } => ErrorCode::FetchFailed,
+            WallpaperError::UnknownOutput { .. } => ErrorCode::UnknownOutput,
+            WallpaperError::FileNotFound { .. } => ErrorCode::FileNotFound,
+            WallpaperError::UnsupportedFormat { .. } => ErrorCode::UnsupportedFormat,
+            WallpaperError::UnknownShader { .. } => ErrorCode::UnknownShader,
+            WallpaperError::UnknownOverlay { .. } => ErrorCode::UnknownOverlay,
+            WallpaperError::Gstreamer { .. } => ErrorCode::Gstreamer,
+            WallpaperError::GpuUnavailable { .. } => ErrorCode::GpuUnavailable,
+            WallpaperError::FeatureDisabled { .. } => ErrorCode::FeatureDisabled,
+            WallpaperError::InvalidArgument { .. } => ErrorCode::InvalidArgument,
+            WallpaperError::NoPlaylist => ErrorCode::NoPlaylist,
+            WallpaperError::PlaylistEmpty => ErrorCode::PlaylistEmpty,
+            WallpaperError::Unavailable(_) => ErrorCode::Unavailable,
+            WallpaperError::NotImplemented(_) => ErrorCode::NotImplemented,
+            WallpaperError::Internal(_) => ErrorCode::Internal,
+        }
+    }
+
+    /// Create an [`WallpaperError::InvalidArgument`] error
+    pub fn invalid_argument(
+        field: impl Into<String>,
+        value: impl Into<String>,
+        reason: impl Into<String>,
+    ) -> Self {
+        WallpaperError::InvalidArgument {
+            field: field.into(),
+            value: value.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Machine-readable classification of a [`WallpaperError`].
+///
+/// Codes are stable across releases and serialize as snake_case strings
+/// (e.g. `"unknown_output"`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    Io,
+    Ipc,
+    Wayland,
+    Image,
+    Video,
+    NotFound,
+    UnsupportedCommand,
+    UnsupportedProtocol,
+    FetchFailed,
+    UnknownOutput,
+    FileNotFound,
+    UnsupportedFormat,
+    UnknownShader,
+    UnknownOverlay,
+    Gstreamer,
+    GpuUnavailable,
+    FeatureDisabled,
+    InvalidArgument,
+    NoPlaylist,
+    PlaylistEmpty,
+    Unavailable,
+    NotImplemented,
+    Internal,
+}
+
+impl ErrorCode {
+    /// Get the code as a string (same as its serialized form)
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::Io => "io",
+            ErrorCode::Ipc => "ipc",
+            ErrorCode::Wayland => "wayland",
+            ErrorCode::Image => "image",
+            ErrorCode::Video => "video",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::UnsupportedCommand => "unsupported_command",
+            ErrorCode::UnsupportedProtocol => "unsupported_protocol",
+            ErrorCode::FetchFailed => "fetch_failed",
+            ErrorCode::UnknownOutput => "unknown_output",
+            ErrorCode::FileNotFound => "file_not_found",
+            ErrorCode::UnsupportedFormat => "unsupported_format",
+            ErrorCode::UnknownShader => "unknown_shader",
+            ErrorCode::UnknownOverlay => "unknown_overlay",
+            ErrorCode::Gstreamer => "gstreamer",
+            ErrorCode::GpuUnavailable => "gpu_unavailable",
+            ErrorCode::FeatureDisabled => "feature_disabled",
+            ErrorCode::InvalidArgument => "invalid_argument",
+            ErrorCode::NoPlaylist => "no_playlist",
+            ErrorCode::PlaylistEmpty => "playlist_empty",
+            ErrorCode::Unavailable => "unavailable",
+            ErrorCode::NotImplemented => "not_implemented",
+            ErrorCode::Internal => "internal",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
 }
 
 impl From<std::io::Error> for WallpaperError {
@@ -86,6 +265,8 @@ impl From<serde_json::Error> for WallpaperError {
 ///     output: None,  // Apply to all outputs
 ///     transition: None,  // Use default transition
 ///     scale: None,  // Use default scale mode
+///     focus: None,  // Keep the most detailed part of the image in view
+///     letterbox: None,  // Use the configured letterbox background
 /// };
 /// ```
 #[derive(Debug, Serialize, Deserialize)]
@@ -94,14 +275,48 @@ pub enum Command {
     ///
     /// Supported formats: PNG, JPEG, WebP, GIF (converted to WebM), SVG, MP4, WebM
     SetWallpaper {
-        /// Path to the wallpaper file (must be absolute)
+        /// Absolute path to the wallpaper file, or a `file://`, `http://` or
+        /// `https://` URL (remote files are fetched into the daemon's cache directory)
         path: String,
-        /// Target output name (e.g., "DP-1"), or None for all outputs
+        /// Target outputs: a name (e.g. "DP-1") or selector (e.g. "model:U2720Q",
+        /// "all-but:eDP-1"; see the configuration guide), or None for all outputs
+        output: Option<String>,
+        /// Transition effect to use when changing wallpaper
+        transition: Option<TransitionType>,
+        /// How to scale/fit the image to the output
+        scale: Option<ScaleMode>,
+        /// Point of the image kept in view when [`ScaleMode::Fill`] crops it
+        /// (default: a sidecar file, the config, or detected from the image)
+        #[serde(default)]
+        focus: Option<FocalPoint>,
+        /// What fills the rest of the output in [`ScaleMode::Fit`] and
+        /// [`ScaleMode::Center`] (default: the config, or black)
+        #[serde(default)]
+        letterbox: Option<Letterbox>,
+    },
+    /// Set wallpaper from image data sent over the socket.
+    ///
+    /// Used for `wwctl set -` and programmatic generators. The daemon stores the
+    /// image in its cache directory and then treats it like [`Command::SetWallpaper`].
+    SetImageData {
+        /// Width in pixels (ignored for [`PixelFormat::Encoded`])
+        width: u32,
+        /// Height in pixels (ignored for [`PixelFormat::Encoded`])
+        height: u32,
+        /// Layout of `bytes`
+        format: PixelFormat,
+        /// Pixel data or encoded image file contents (base64 on the wire)
+        #[serde(with = "base64_bytes")]
+        bytes: Vec<u8>,
+        /// Target output name or selector, or None for all outputs
         output: Option<String>,
         /// Transition effect to use when changing wallpaper
         transition: Option<TransitionType>,
         /// How to scale/fit the image to the output
         scale: Option<ScaleMode>,
+        /// What fills the rest of the output in [`ScaleMode::Fit`] and [`ScaleMode::Center`]
+        #[serde(default)]
+        letterbox: Option<Letterbox>,
     },
     /// Set a solid color background.
     ///
@@ -110,16 +325,26 @@ pub enum Command {
     SetColor {
         /// Hex color code (e.g., "FF0000" for red)
         color: String,
-        /// Target output name, or None for all outputs
+        /// Target output name or selector, or None for all outputs
         output: Option<String>,
     },
+    /// Set a linear, radial or conic gradient background.
+    ///
+    /// Rendered once at each output's resolution.
+    SetGradient {
+        gradient: Gradient,
+        /// Target output name or selector, or None for all outputs
+        output: Option<String>,
+        /// Transition effect when switching to this gradient
+        transition: Option<TransitionType>,
+    },
     /// Set a procedural shader as wallpaper.
     ///
     /// Available shaders: plasma, waves, gradient, starfield, matrix, raymarching, tunnel
     SetShader {
         /// Name of the shader to use
         shader: String,
-        /// Target output name, or None for all outputs
+        /// Target output name or selector, or None for all outputs
         output: Option<String>,
         /// Transition effect when switching to this shader
         transition: Option<TransitionType>,
@@ -143,6 +368,7 @@ pub enum Command {
     ///         ..Default::default()
     ///     }),
     ///     output: None,
+    ///     fade: None,
     /// };
     /// ```
     SetOverlay {
@@ -150,11 +376,46 @@ pub enum Command {
         overlay: String,
         /// Parameters for the overlay effect
         params: Option<OverlayParams>,
-        /// Target output name, or None for all outputs
+        /// Target output name or selector, or None for all outputs
         output: Option<String>,
+        /// Fade the overlay in, or interpolate from the current parameters
+        #[serde(default)]
+        fade: Option<OverlayFade>,
     },
     /// Clear shader overlay
-    ClearOverlay { output: Option<String> },
+    ClearOverlay {
+        output: Option<String>,
+        /// Fade the overlay out instead of removing it instantly
+        #[serde(default)]
+        fade: Option<OverlayFade>,
+    },
+    /// Save the frame an output is currently showing to a PNG.
+    ///
+    /// The capture includes scaling, overlays and any transition in progress.
+    Capture {
+        /// Output to capture, or None (or "all") to stitch all outputs by their layout
+        output: Option<String>,
+        /// Absolute path of the PNG to write (on the daemon's filesystem)
+        path: String,
+    },
+    /// Get the colour palette extracted from what each output is showing
+    GetPalette {
+        /// Output to get the palette of, or None (or "all") for every output
+        output: Option<String>,
+    },
+    /// Set the volume of video wallpapers' audio.
+    ///
+    /// A video shown on several outputs plays at the loudest of their volumes.
+    SetVolume {
+        /// Target output name or selector, or None for all outputs
+        output: Option<String>,
+        /// Volume from 0.0 to 1.0, or None to keep the current volume
+        #[serde(default)]
+        volume: Option<f32>,
+        /// Mute or unmute, or None to keep the current state
+        #[serde(default)]
+        muted: Option<bool>,
+    },
     /// Query daemon status
     Query,
     /// Kill the daemon
@@ -171,10 +432,146 @@ pub enum Command {
     PlaylistToggleShuffle,
     /// Get current resource usage and performance mode
     GetResources,
+    /// Get the contents and memory use of the decoded image cache
+    GetCacheStatus,
+    /// Drop all decoded and pre-scaled images from the cache
+    ClearCache,
     /// Set performance mode (performance, balanced, powersave)
     SetPerformanceMode { mode: String },
 }
 
+impl Command {
+    /// Names of all commands in this protocol version (as they appear on the wire)
+    pub const NAMES: &'static [&'static str] = &[
+        "SetWallpaper",
+        "SetImageData",
+        "SetColor",
+        "SetGradient",
+        "SetShader",
+        "SetOverlay",
+        "ClearOverlay",
+        "Capture",
+        "GetPalette",
+        "SetVolume",
+        "Query",
+        "Kill",
+        "ListOutputs",
+        "Ping",
+        "PlaylistNext",
+        "PlaylistPrev",
+        "PlaylistToggleShuffle",
+        "GetResources",
+        "GetCacheStatus",
+        "ClearCache",
+        "SetPerformanceMode",
+    ];
+
+    /// Get the wire name of this command
+    pub fn name(&self) -> &'static str {
+        match self {
+            Command::SetWallpaper { .. } => "SetWallpaper",
+            Command::SetImageData { .. } => "SetImageData",
+            Command::SetColor { .. } => "SetColor",
+            Command::SetGradient { .. } => "SetGradient",
+            Command::SetShader { .. } => "SetShader",
+            Command::SetOverlay { .. } => "SetOverlay",
+            Command::ClearOverlay { .. } => "ClearOverlay",
+            Command::Capture { .. } => "Capture",
+            Command::GetPalette { .. } => "GetPalette",
+            Command::SetVolume { .. } => "SetVolume",
+            Command::Query => "Query",
+            Command::Kill => "Kill",
+            Command::ListOutputs => "ListOutputs",
+            Command::Ping => "Ping",
+            Command::PlaylistNext => "PlaylistNext",
+            Command::PlaylistPrev => "PlaylistPrev",
+            Command::PlaylistToggleShuffle => "PlaylistToggleShuffle",
+            Command::GetResources => "GetResources",
+            Command::GetCacheStatus => "GetCacheStatus",
+            Command::ClearCache => "ClearCache",
+            Command::SetPerformanceMode { .. } => "SetPerformanceMode",
+        }
+    }
+
+    /// Get the command name from a raw JSON command (`"Ping"` or `{"SetColor": {...}}`).
+    ///
+    /// Used to report which command a peer asked for when it can't be parsed.
+    pub fn name_of_raw(value: &serde_json::Value) -> Option<&str> {
+        match value {
+            serde_json::Value::String(name) => Some(name),
+            serde_json::Value::Object(map) if map.len() == 1 => {
+                map.keys().next().map(|k| k.as_str())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Layout of the bytes in [`Command::SetImageData`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PixelFormat {
+    /// A complete image file (PNG, JPEG, WebP, ...); the format is detected from its contents
+    Encoded,
+    /// 8-bit RGBA, row-major, no padding
+    Rgba8,
+    /// 8-bit BGRA, row-major, no padding
+    Bgra8,
+    /// 8-bit RGB, row-major, no padding
+    Rgb8,
+    /// 8-bit grayscale, row-major, no padding
+    Gray8,
+}
+
+impl PixelFormat {
+    /// Bytes per pixel for raw formats, or None for [`PixelFormat::Encoded`]
+    pub fn bytes_per_pixel(&self) -> Option<usize> {
+        match self {
+            PixelFormat::Encoded => None,
+            PixelFormat::Rgba8 | PixelFormat::Bgra8 => Some(4),
+            PixelFormat::Rgb8 => Some(3),
+            PixelFormat::Gray8 => Some(1),
+        }
+    }
+}
+
+impl std::str::FromStr for PixelFormat {
+    type Err = WallpaperError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "encoded" => Ok(PixelFormat::Encoded),
+            "rgba" | "rgba8" => Ok(PixelFormat::Rgba8),
+            "bgra" | "bgra8" => Ok(PixelFormat::Bgra8),
+            "rgb" | "rgb8" => Ok(PixelFormat::Rgb8),
+            "gray" | "gray8" | "grey" => Ok(PixelFormat::Gray8),
+            _ => Err(WallpaperError::invalid_argument(
+                "pixel format",
+                s,
+                "expected encoded, rgba8, bgra8, rgb8 or gray8",
+            )),
+        }
+    }
+}
+
+/// Serialize byte buffers as base64 strings instead of JSON number arrays
+mod base64_bytes {
+    use base64::Engine;
+    use base64::engine::general_purpose::STANDARD;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let encoded = String::deserialize(deserializer)?;
+        STANDARD
+            .decode(encoded)
+            .map_err(|e| serde::de::Error::custom(format!("invalid base64: {}", e)))
+    }
+}
+
 /// Response from daemon to client
 #[derive(Debug, Serialize, Deserialize)]
 pub enum Response {
@@ -184,6 +581,152 @@ pub enum Response {
     Outputs(Vec<OutputInfo>),
     Pong,
     Resources(ResourceStatus),
+    /// A wallpaper command was applied, with the outcome for each matching output
+    Applied(Vec<OutputResult>),
+    /// Colour palettes of the requested outputs
+    Palette(Vec<OutputPalette>),
+    /// Image cache contents and memory use
+    Cache(CacheStatus),
+}
+
+impl Response {
+    /// Collapse an [`Response::Applied`] into `Ok` or the first output error.
+    ///
+    /// Used for clients that only understand a single success/failure.
+    pub fn into_simple(self) -> Self {
+        match self {
+            Response::Applied(results) => match results.into_iter().find_map(|r| r.error) {
+                Some(err) => Response::Error(err),
+                None => Response::Ok,
+            },
+            other => other,
+        }
+    }
+}
+
+/// Outcome of applying a command to a single output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputResult {
+    /// Output name (e.g., "DP-1")
+    pub output: String,
+    /// Why the command failed on this output, or None on success
+    #[serde(default)]
+    pub error: Option<WallpaperError>,
+}
+
+/// Current IPC protocol version.
+///
+/// Bump this when the envelope or the meaning of existing commands changes.
+/// Adding a command doesn't need a bump, since clients check [`Welcome::commands`].
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Oldest IPC protocol version the daemon still speaks
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// Maximum size of a single frame payload (64 MiB)
+pub const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Messages sent from client to daemon
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ClientMessage {
+    /// Optional handshake, answered with [`ServerMessage::Welcome`]
+    Hello(Hello),
+    /// A command to execute, answered with [`ServerMessage::Reply`]
+    Request(Request),
+}
+
+/// Messages sent from daemon to client
+#[derive(Debug, Serialize, Deserialize)]
+pub enum ServerMessage {
+    Welcome(Welcome),
+    /// The handshake was refused (e.g. the client's protocol is too old); the
+    /// daemon closes the connection after sending it
+    Rejected(WallpaperError),
+    Reply(Reply),
+}
+
+/// Client handshake
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hello {
+    /// Highest protocol version the client speaks
+    pub protocol_version: u32,
+    /// Client name and version, for logging (e.g. "wwctl 0.1.0")
+    #[serde(default)]
+    pub client: Option<String>,
+}
+
+/// Daemon handshake reply
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Welcome {
+    /// Protocol version both sides will use (the lower of the two)
+    pub protocol_version: u32,
+    /// Daemon version
+    pub daemon_version: String,
+    /// Commands the daemon understands (see [`Command::name`])
+    pub commands: Vec<String>,
+    /// Optional features compiled into the daemon (e.g. "video", "gpu")
+    pub features: Vec<String>,
+}
+
+impl Welcome {
+    /// Check if the daemon understands a command
+    pub fn supports(&self, command: &Command) -> bool {
+        self.commands.iter().any(|name| name == command.name())
+    }
+}
+
+/// A command tagged with a client-chosen ID
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Request {
+    pub request_id: u64,
+    pub command: Command,
+    /// Reply as soon as the command is queued instead of waiting for it to be applied
+    #[serde(default)]
+    pub no_wait: bool,
+}
+
+/// The response to a [`Request`], tagged with the same ID
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Reply {
+    pub request_id: u64,
+    pub response: Response,
+}
+
+/// Encode a message as a frame: a 4-byte big-endian payload length followed by JSON.
+pub fn encode_frame<T: Serialize>(message: &T) -> Result<Vec<u8>, WallpaperError> {
+    let payload = serde_json::to_vec(message)?;
+    if payload.len() > MAX_FRAME_LEN {
+        return Err(WallpaperError::Ipc(format!(
+            "Frame too large: {} bytes (max {})",
+            payload.len(),
+            MAX_FRAME_LEN
+        )));
+    }
+
+    let mut frame = Vec::with_capacity(4 + payload.len());
+    frame.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&payload);
+    Ok(frame)
+}
+
+/// Get the payload length from a frame header, rejecting oversized frames.
+pub fn decode_frame_len(header: [u8; 4]) -> Result<usize, WallpaperError> {
+    let len = u32::from_be_bytes(header) as usize;
+    if len > MAX_FRAME_LEN {
+        return Err(WallpaperError::Ipc(format!(
+            "Frame too large: {} bytes (max {})",
+            len, MAX_FRAME_LEN
+        )));
+    }
+    Ok(len)
+}
+
+/// Check if a connection uses length-prefixed frames, based on its first byte.
+///
+/// Legacy clients send a JSON line, which starts with `{`, `"` or whitespace;
+/// a frame header starts with a small length byte.
+pub fn is_framed(first_byte: u8) -> bool {
+    !matches!(first_byte, b'{' | b'"' | b' ' | b'\t' | b'\r' | b'\n')
 }
 
 /// Daemon status information
@@ -200,23 +743,46 @@ pub struct WallpaperStatus {
     pub wallpaper: WallpaperType,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum WallpaperType {
     None,
     Color(String),
     Image(String),
     Video(String),
     Shader(String),
+    Gradient(Gradient),
 }
 
 /// Output (monitor) information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct OutputInfo {
+    /// Connector name (e.g. "DP-1")
     pub name: String,
+    /// Size of the wallpaper in logical pixels
     pub width: u32,
     pub height: u32,
     pub scale: f64,
+    /// Refresh rate of the current mode in millihertz (e.g. 59951)
     pub refresh_rate: Option<u32>,
+    /// Description from the compositor (usually make, model and serial)
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub make: String,
+    #[serde(default)]
+    pub model: String,
+    /// Serial number, if the compositor's description includes one
+    #[serde(default)]
+    pub serial: Option<String>,
+    /// Top-left corner in the compositor's layout, in logical pixels
+    #[serde(default)]
+    pub position: Option<(i32, i32)>,
+    /// Physical size in millimetres (0x0 for projectors and virtual outputs)
+    #[serde(default)]
+    pub physical_size: (u32, u32),
+    /// Rotation and flip (e.g. "normal", "90", "flipped-270")
+    #[serde(default)]
+    pub transform: String,
 }
 
 /// Resource usage and performance mode status
@@ -229,6 +795,51 @@ pub struct ResourceStatus {
     pub battery_percent: Option<u8>,
 }
 
+/// Decoded image cache status
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CacheStatus {
+    /// Memory used by cached images in bytes
+    pub bytes: u64,
+    /// Eviction threshold in bytes (None = unlimited)
+    pub limit_bytes: Option<u64>,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    /// Cached images, most recently used first
+    pub entries: Vec<CacheEntry>,
+}
+
+/// A decoded image, or a copy pre-scaled for an output, held in the cache
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: u64,
+    /// Scale mode of a pre-scaled copy (None for the decoded original)
+    pub scale: Option<ScaleMode>,
+}
+
+/// Colours extracted from a wallpaper, as `#rrggbb`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Palette {
+    /// Dominant colours, most common first
+    pub colors: Vec<String>,
+    /// Dark colour suitable as a background
+    pub background: String,
+    /// Light colour readable on the background
+    pub foreground: String,
+    /// Most vivid of the dominant colours
+    pub accent: String,
+}
+
+/// Palette of a single output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputPalette {
+    pub output: String,
+    pub palette: Palette,
+}
+
 /// Transition effect types
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TransitionType {
@@ -410,6 +1021,65 @@ impl OverlayParams {
     }
 }
 
+/// Easing curve used for animated changes
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub enum Easing {
+    /// Constant speed
+    Linear,
+    /// Slow start, fast end
+    EaseIn,
+    /// Fast start, slow end
+    EaseOut,
+    /// Slow start and end
+    #[default]
+    EaseInOut,
+}
+
+impl std::str::FromStr for Easing {
+    type Err = WallpaperError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "linear" => Ok(Self::Linear),
+            "ease-in" | "ease_in" | "easein" => Ok(Self::EaseIn),
+            "ease-out" | "ease_out" | "easeout" => Ok(Self::EaseOut),
+            "ease-in-out" | "ease_in_out" | "easeinout" => Ok(Self::EaseInOut),
+            _ => Err(WallpaperError::invalid_argument(
+                "easing",
+                s,
+                "expected linear, ease-in, ease-out or ease-in-out",
+            )),
+        }
+    }
+}
+
+/// Fade settings for overlay changes.
+///
+/// When an overlay of the same kind is already active, its parameters are
+/// interpolated to the new values. Otherwise the overlay fades in (or out when
+/// clearing) over the given duration.
+///
+/// # Examples
+///
+/// ```
+/// use common::{Easing, OverlayFade};
+///
+/// // Dim over two seconds
+/// let fade = OverlayFade {
+///     duration_ms: 2000,
+///     easing: Easing::Linear,
+/// };
+/// assert_eq!(fade.duration_ms, 2000);
+/// ```
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+pub struct OverlayFade {
+    /// Duration of the fade in milliseconds
+    pub duration_ms: u32,
+    /// Easing curve applied to the fade
+    #[serde(default)]
+    pub easing: Easing,
+}
+
 /// Overlay effect types for post-processing
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum OverlayEffect {
@@ -462,7 +1132,7 @@ impl OverlayEffect {
 }
 
 /// Image scaling/fitting mode
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 pub enum ScaleMode {
     /// Center image without scaling
     Center,
@@ -477,6 +1147,345 @@ pub enum ScaleMode {
     Tile,
 }
 
+/// Point of interest in an image, as fractions of its width and height
+///
+/// `(0.0, 0.0)` is the top-left corner and `(0.5, 0.5)` the centre. When an
+/// image is cropped to fill an output, the crop is moved as close to centring
+/// this point as the image allows.
+///
+/// # Examples
+///
+/// ```
+/// use common::FocalPoint;
+///
+/// let focus: FocalPoint = "0.3,0.25".parse().unwrap();
+/// assert_eq!(focus, FocalPoint { x: 0.3, y: 0.25 });
+/// ```
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct FocalPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl FocalPoint {
+    /// The centre of the image
+    pub const CENTER: FocalPoint = FocalPoint { x: 0.5, y: 0.5 };
+
+    /// Whether both coordinates lie within the image
+    pub fn is_valid(&self) -> bool {
+        (0.0..=1.0).contains(&self.x) && (0.0..=1.0).contains(&self.y)
+    }
+
+    /// Bit patterns with -0.0 folded into 0.0 and every NaN into one, so
+    /// equality and hashing agree and focal points can key caches
+    fn key(&self) -> (u32, u32) {
+        let bits = |v: f32| {
+            if v == 0.0 {
+                0.0f32.to_bits()
+            } else if v.is_nan() {
+                f32::NAN.to_bits()
+            } else {
+                v.to_bits()
+            }
+        };
+        (bits(self.x), bits(self.y))
+    }
+}
+
+impl Default for FocalPoint {
+    fn default() -> Self {
+        Self::CENTER
+    }
+}
+
+impl PartialEq for FocalPoint {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for FocalPoint {}
+
+impl std::hash::Hash for FocalPoint {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl std::str::FromStr for FocalPoint {
+    type Err = WallpaperError;
+
+    /// Parse `x,y` (or `x y`) with both coordinates between 0 and 1
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            WallpaperError::invalid_argument(
+                "focal point",
+                s,
+                "expected x,y with both between 0 and 1, e.g. 0.5,0.3",
+            )
+        };
+
+        let coordinates: Vec<f32> = s
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .map(|part| part.parse::<f32>().map_err(|_| invalid()))
+            .collect::<Result<_, _>>()?;
+
+        match coordinates[..] {
+            [x, y] if (FocalPoint { x, y }).is_valid() => Ok(FocalPoint { x, y }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// Background around an image that doesn't cover its output
+///
+/// Used for the bars left by [`ScaleMode::Fit`] and the border around
+/// [`ScaleMode::Center`].
+///
+/// # Examples
+///
+/// ```
+/// use common::Letterbox;
+///
+/// assert_eq!("blur".parse::<Letterbox>().unwrap(), Letterbox::Blur);
+/// assert_eq!("#1e1e2e".parse::<Letterbox>().unwrap(), Letterbox::Color([0x1e, 0x1e, 0x2e]));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Letterbox {
+    /// A solid RGB colour
+    Color([u8; 3]),
+    /// The most common colour of the image
+    Dominant,
+    /// A blurred, darkened copy of the image scaled to cover the output
+    Blur,
+    /// The image's edges reflected outwards
+    Mirror,
+}
+
+impl Default for Letterbox {
+    fn default() -> Self {
+        Letterbox::Color([0, 0, 0])
+    }
+}
+
+impl std::str::FromStr for Letterbox {
+    type Err = WallpaperError;
+
+    /// Parse `dominant`, `blur`, `mirror`, `black` or a hex colour (`#RRGGBB`)
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "dominant" => return Ok(Letterbox::Dominant),
+            "blur" => return Ok(Letterbox::Blur),
+            "mirror" => return Ok(Letterbox::Mirror),
+            "black" => return Ok(Letterbox::default()),
+            _ => {}
+        }
+
+        let hex = s.trim_start_matches('#');
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|c| u8::from_str_radix(c, 16).ok())
+        };
+        match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Letterbox::Color([r, g, b])),
+            _ => Err(WallpaperError::invalid_argument(
+                "letterbox",
+                s,
+                "expected dominant, blur, mirror, black or a hex colour like #1e1e2e",
+            )),
+        }
+    }
+}
+
+/// Operator compressing HDR images (OpenEXR, Radiance HDR) to display range
+///
+/// # Examples
+///
+/// ```
+/// use common::ToneMap;
+///
+/// assert_eq!("hable".parse::<ToneMap>().unwrap(), ToneMap::Hable);
+/// assert_eq!(ToneMap::default(), ToneMap::Aces);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ToneMap {
+    /// `x / (1 + x)`: gentle, keeps colours but flattens highlights
+    Reinhard,
+    /// Filmic curve fitted to the ACES reference transform (contrasty)
+    #[default]
+    Aces,
+    /// John Hable's filmic curve from Uncharted 2 (soft toe and shoulder)
+    Hable,
+}
+
+impl std::str::FromStr for ToneMap {
+    type Err = WallpaperError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "reinhard" => Ok(ToneMap::Reinhard),
+            "aces" => Ok(ToneMap::Aces),
+            "hable" | "filmic" => Ok(ToneMap::Hable),
+            _ => Err(WallpaperError::invalid_argument(
+                "tone map",
+                s,
+                "expected reinhard, aces or hable",
+            )),
+        }
+    }
+}
+
+/// Shape of a [`Gradient`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GradientKind {
+    /// Colours change along a straight line at the gradient's angle
+    #[default]
+    Linear,
+    /// Colours change from the centre out to the corners
+    Radial,
+    /// Colours change around the centre, starting at the gradient's angle
+    Conic,
+}
+
+impl std::str::FromStr for GradientKind {
+    type Err = WallpaperError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "linear" => Ok(GradientKind::Linear),
+            "radial" => Ok(GradientKind::Radial),
+            "conic" => Ok(GradientKind::Conic),
+            _ => Err(WallpaperError::invalid_argument(
+                "gradient kind",
+                s,
+                "expected linear, radial or conic",
+            )),
+        }
+    }
+}
+
+/// A colour of a [`Gradient`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GradientStop {
+    /// Hex colour (e.g. "#FF5733")
+    pub color: String,
+    /// Position from 0.0 (start) to 1.0 (end), or None to space it evenly
+    /// between its neighbours
+    #[serde(default)]
+    pub position: Option<f32>,
+}
+
+impl std::str::FromStr for GradientStop {
+    type Err = WallpaperError;
+
+    /// Parse `COLOR` or `COLOR@POSITION`, e.g. `#FF5733@0.25`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            WallpaperError::invalid_argument(
+                "gradient stop",
+                s,
+                "expected a hex colour with an optional position between 0 and 1, e.g. #FF5733@0.25",
+            )
+        };
+
+        let (color, position) = match s.split_once('@') {
+            Some((color, position)) => {
+                let position: f32 = position.trim().parse().map_err(|_| invalid())?;
+                (color.trim(), Some(position))
+            }
+            None => (s.trim(), None),
+        };
+
+        let stop = GradientStop {
+            color: color.to_string(),
+            position,
+        };
+        if !stop.is_valid() {
+            return Err(invalid());
+        }
+        Ok(stop)
+    }
+}
+
+impl GradientStop {
+    /// Whether the colour parses and the position (if any) lies within the gradient
+    pub fn is_valid(&self) -> bool {
+        ShaderParams::parse_color(&self.color).is_some()
+            && self
+                .position
+                .is_none_or(|position| (0.0..=1.0).contains(&position))
+    }
+}
+
+/// A static gradient wallpaper, rendered once at each output's resolution
+///
+/// # Examples
+///
+/// ```
+/// use common::{Gradient, GradientKind};
+///
+/// let gradient = Gradient {
+///     kind: GradientKind::Linear,
+///     angle: 90.0, // Top to bottom
+///     stops: vec!["#1e1e2e".parse().unwrap(), "#89b4fa@0.8".parse().unwrap()],
+///     dither: true,
+/// };
+/// assert!(gradient.validate().is_ok());
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Gradient {
+    #[serde(default)]
+    pub kind: GradientKind,
+    /// Degrees clockwise from pointing right: the direction of linear
+    /// gradients (90 = top to bottom) or where conic ones start
+    #[serde(default)]
+    pub angle: f32,
+    /// Colours in order, at least two
+    pub stops: Vec<GradientStop>,
+    /// Add ordered dithering so 8-bit panels don't show bands
+    #[serde(default = "default_dither")]
+    pub dither: bool,
+}
+
+fn default_dither() -> bool {
+    true
+}
+
+impl Gradient {
+    /// Check the gradient can be rendered
+    pub fn validate(&self) -> Result<(), WallpaperError> {
+        if self.stops.len() < 2 {
+            return Err(WallpaperError::invalid_argument(
+                "gradient",
+                format!("{} stop(s)", self.stops.len()),
+                "a gradient needs at least two colour stops",
+            ));
+        }
+
+        if let Some(stop) = self.stops.iter().find(|stop| !stop.is_valid()) {
+            return Err(WallpaperError::invalid_argument(
+                "gradient stop",
+                format!("{}@{:?}", stop.color, stop.position),
+                "colours must be hex like #FF5733 and positions between 0 and 1",
+            ));
+        }
+
+        if !self.angle.is_finite() {
+            return Err(WallpaperError::invalid_argument(
+                "gradient angle",
+                self.angle.to_string(),
+                "must be a number of degrees",
+            ));
+        }
+
+        Ok(())
+    }
+}
+
 /// IPC socket path helper
 pub fn get_socket_path() -> std::path::PathBuf {
     let runtime_dir = std::env::var("XDG_RUNTIME_DIR")
@@ -623,6 +1632,8 @@ mod tests {
             output: Some("DP-1".to_string()),
             transition: Some(TransitionType::Fade { duration_ms: 500 }),
             scale: Some(ScaleMode::Fill),
+            focus: None,
+            letterbox: None,
         };
         let json = serde_json::to_string(&cmd).unwrap();
         let deserialized: Command = serde_json::from_str(&json).unwrap();
@@ -683,6 +1694,251 @@ mod tests {
         matches!(wall_err, WallpaperError::Ipc(_));
     }
 
+    #[test]
+    fn test_error_codes() {
+        let errors = [
+            WallpaperError::UnknownOutput {
+                output: "DP-9".to_string(),
+            },
+            WallpaperError::Gstreamer {
+                element: "vah264dec".to_string(),
+                message: "no such element".to_string(),
+            },
+            WallpaperError::invalid_argument("color", "ZZZ", "not a hex color"),
+            WallpaperError::PlaylistEmpty,
+            WallpaperError::Internal("shader failed to compile".to_string()),
+            WallpaperError::UnsupportedProtocol {
+                client: 0,
+                min: 1,
+                max: 1,
+            },
+        ];
+
+        for err in &errors {
+            // Serialized code matches as_str
+            let code = serde_json::to_value(err.code()).unwrap();
+            assert_eq!(code.as_str(), Some(err.code().as_str()));
+
+            // Structured context survives the roundtrip
+            let json = serde_json::to_string(err).unwrap();
+            let deserialized: WallpaperError = serde_json::from_str(&json).unwrap();
+            assert_eq!(&deserialized, err);
+        }
+
+        assert_eq!(errors[0].code().as_str(), "unknown_output");
+        assert_eq!(errors[1].code().as_str(), "gstreamer");
+        assert_eq!(errors[4].code().as_str(), "internal");
+        assert_eq!(errors[5].code().as_str(), "unsupported_protocol");
+        assert_eq!(
+            errors[2].to_string(),
+            "Invalid color 'ZZZ': not a hex color"
+        );
+    }
+
+    #[test]
+    fn test_image_data_serialization() {
+        let cmd = Command::SetImageData {
+            width: 2,
+            height: 1,
+            format: PixelFormat::Rgb8,
+            bytes: vec![255, 0, 0, 0, 0, 255],
+            output: None,
+            transition: None,
+            scale: None,
+            letterbox: None,
+        };
+
+        // Bytes travel as a base64 string, not a number array
+        let json = serde_json::to_value(&cmd).unwrap();
+        assert_eq!(json["SetImageData"]["bytes"], "/wAAAAD/");
+        assert_eq!(json["SetImageData"]["format"], "rgb8");
+
+        let deserialized: Command = serde_json::from_value(json).unwrap();
+        match deserialized {
+            Command::SetImageData { bytes, format, .. } => {
+                assert_eq!(bytes, vec![255, 0, 0, 0, 0, 255]);
+                assert_eq!(format.bytes_per_pixel(), Some(3));
+            }
+            _ => panic!("Wrong command type"),
+        }
+
+        assert_eq!("rgba".parse::<PixelFormat>().unwrap(), PixelFormat::Rgba8);
+        assert!("yuv420".parse::<PixelFormat>().is_err());
+    }
+
+    #[test]
+    fn test_focal_point_parse() {
+        let focus: FocalPoint = "0.25, 1".parse().unwrap();
+        assert_eq!(focus, FocalPoint { x: 0.25, y: 1.0 });
+        assert_eq!("0 0.5".parse::<FocalPoint>().unwrap().y, 0.5);
+
+        // -0.0 equals 0.0, so it must hash the same
+        use std::hash::BuildHasher;
+        let state = std::hash::RandomState::new();
+        let hash = |focus: &FocalPoint| state.hash_one(focus);
+        let zero = FocalPoint { x: 0.0, y: 0.5 };
+        let negative = FocalPoint { x: -0.0, y: 0.5 };
+        assert_eq!(zero, negative);
+        assert_eq!(hash(&zero), hash(&negative));
+
+        for invalid in [
+            "",
+            "0.5",
+            "0.5,0.5,0.5",
+            "1.5,0.5",
+            "-0.1,0",
+            "a,b",
+            "NaN,0.5",
+        ] {
+            assert!(invalid.parse::<FocalPoint>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_gradient_parse_and_validate() {
+        let stop: GradientStop = "#FF5733@0.25".parse().unwrap();
+        assert_eq!(stop.color, "#FF5733");
+        assert_eq!(stop.position, Some(0.25));
+        assert_eq!("00ff00".parse::<GradientStop>().unwrap().position, None);
+        for invalid in ["", "#FFF", "#FF5733@", "#FF5733@1.5", "red"] {
+            assert!(invalid.parse::<GradientStop>().is_err(), "{}", invalid);
+        }
+        assert_eq!(
+            "Conic".parse::<GradientKind>().unwrap(),
+            GradientKind::Conic
+        );
+        assert!("diamond".parse::<GradientKind>().is_err());
+
+        // Older or minimal JSON gets a linear, dithered gradient
+        let json = r##"{"stops":[{"color":"#000000"},{"color":"#FFFFFF","position":1.0}]}"##;
+        let gradient: Gradient = serde_json::from_str(json).unwrap();
+        assert_eq!(gradient.kind, GradientKind::Linear);
+        assert!(gradient.dither);
+        assert!(gradient.validate().is_ok());
+
+        let single = Gradient {
+            stops: gradient.stops[..1].to_vec(),
+            ..gradient.clone()
+        };
+        assert!(single.validate().is_err());
+        let bad_color = Gradient {
+            stops: vec![
+                gradient.stops[0].clone(),
+                GradientStop {
+                    color: "nope".to_string(),
+                    position: None,
+                },
+            ],
+            ..gradient
+        };
+        assert!(bad_color.validate().is_err());
+    }
+
+    #[test]
+    fn test_letterbox_parse() {
+        assert_eq!("Mirror".parse::<Letterbox>().unwrap(), Letterbox::Mirror);
+        assert_eq!(
+            "dominant".parse::<Letterbox>().unwrap(),
+            Letterbox::Dominant
+        );
+        assert_eq!("black".parse::<Letterbox>().unwrap(), Letterbox::default());
+        assert_eq!(
+            "FF8000".parse::<Letterbox>().unwrap(),
+            Letterbox::Color([255, 128, 0])
+        );
+
+        for invalid in ["", "#FFF", "#FF800080", "gg0000", "fade"] {
+            assert!(invalid.parse::<Letterbox>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_tone_map_parse() {
+        assert_eq!("Reinhard".parse::<ToneMap>().unwrap(), ToneMap::Reinhard);
+        assert_eq!("aces".parse::<ToneMap>().unwrap(), ToneMap::Aces);
+        assert_eq!("filmic".parse::<ToneMap>().unwrap(), ToneMap::Hable);
+        assert!("linear".parse::<ToneMap>().is_err());
+    }
+
+    #[test]
+    fn test_easing_from_str() {
+        assert_eq!("linear".parse::<Easing>().unwrap(), Easing::Linear);
+        assert_eq!("Ease-In".parse::<Easing>().unwrap(), Easing::EaseIn);
+        assert_eq!("ease_out".parse::<Easing>().unwrap(), Easing::EaseOut);
+        assert_eq!("ease-in-out".parse::<Easing>().unwrap(), Easing::EaseInOut);
+        assert!("bounce".parse::<Easing>().is_err());
+        assert_eq!(Easing::default(), Easing::EaseInOut);
+    }
+
+    #[test]
+    fn test_overlay_fade_default_easing() {
+        let fade: OverlayFade = serde_json::from_str(r#"{"duration_ms": 1500}"#).unwrap();
+        assert_eq!(fade.duration_ms, 1500);
+        assert_eq!(fade.easing, Easing::EaseInOut);
+    }
+
+    #[test]
+    fn test_command_names() {
+        let commands = [
+            Command::Ping,
+            Command::Query,
+            Command::Kill,
+            Command::ListOutputs,
+            Command::PlaylistNext,
+            Command::PlaylistPrev,
+            Command::PlaylistToggleShuffle,
+            Command::GetResources,
+            Command::SetPerformanceMode {
+                mode: "balanced".to_string(),
+            },
+            Command::ClearOverlay {
+                output: None,
+                fade: None,
+            },
+            Command::GetPalette { output: None },
+            Command::SetVolume {
+                output: None,
+                volume: Some(0.5),
+                muted: None,
+            },
+            Command::GetCacheStatus,
+            Command::ClearCache,
+        ];
+
+        for cmd in &commands {
+            assert!(Command::NAMES.contains(&cmd.name()));
+
+            // The wire name must match the serde variant name
+            let value = serde_json::to_value(cmd).unwrap();
+            assert_eq!(Command::name_of_raw(&value), Some(cmd.name()));
+        }
+    }
+
+    #[test]
+    fn test_frame_roundtrip() {
+        let frame = encode_frame(&Command::Ping).unwrap();
+        let len = decode_frame_len(frame[..4].try_into().unwrap()).unwrap();
+        assert_eq!(len, frame.len() - 4);
+        assert!(is_framed(frame[0]));
+
+        let cmd: Command = serde_json::from_slice(&frame[4..]).unwrap();
+        assert!(matches!(cmd, Command::Ping));
+    }
+
+    #[test]
+    fn test_frame_too_large() {
+        let header = ((MAX_FRAME_LEN + 1) as u32).to_be_bytes();
+        assert!(decode_frame_len(header).is_err());
+    }
+
+    #[test]
+    fn test_legacy_detection() {
+        assert!(!is_framed(b'{'));
+        assert!(!is_framed(b'"'));
+        assert!(!is_framed(b'\n'));
+        assert!(is_framed(0));
+    }
+
     #[test]
     fn test_socket_path() {
         let path = get_socket_path();
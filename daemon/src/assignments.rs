@@ -0,0 +1,162 @@
+//! Wallpapers remembered per monitor
+//!
+//! Connector names change when a monitor is plugged into another port, so the
+//! wallpaper last set on a monitor is remembered by its make, model and serial
+//! number. When it comes back it gets that wallpaper again, and monitors never
+//! seen before get the wallpaper last set on every output.
+//!
+//! The whole command is kept (scale mode, focal point, letterbox, shader
+//! params), so a wallpaper comes back exactly as it was set.
+
+use crate::WallpaperCommand;
+use crate::output_selector::OutputSelector;
+use common::OutputInfo;
+use std::collections::HashMap;
+
+/// Wallpapers last set on each monitor and on all of them
+#[derive(Debug, Default)]
+pub struct Assignments {
+    /// Keyed by monitor identity (see [`monitor_id`])
+    monitors: HashMap<String, WallpaperCommand>,
+    global: Option<WallpaperCommand>,
+}
+
+impl Assignments {
+    /// Remember the command that set a wallpaper on the outputs an output filter picks
+    pub fn record(
+        &mut self,
+        filter: Option<&str>,
+        wallpaper: &WallpaperCommand,
+        outputs: &[OutputInfo],
+    ) {
+        let selector = match filter.map(str::parse::<OutputSelector>).transpose() {
+            Ok(selector) => selector.unwrap_or(OutputSelector::All),
+            Err(_) => return,
+        };
+
+        if matches!(selector, OutputSelector::All) {
+            self.global = Some(wallpaper.clone());
+        }
+        for output in selector.select(outputs) {
+            self.monitors.insert(monitor_id(output), wallpaper.clone());
+        }
+    }
+
+    /// The command that set the wallpaper to give an output that was just plugged in
+    pub fn recall(&self, output: &OutputInfo) -> Option<&WallpaperCommand> {
+        self.monitors
+            .get(&monitor_id(output))
+            .or(self.global.as_ref())
+    }
+}
+
+/// Identity of the monitor behind an output
+///
+/// Monitors without a serial number can't be told apart from others of the
+/// same model, so their connector name is part of the identity, and outputs
+/// that don't report a make and model are known by their connector alone.
+pub fn monitor_id(output: &OutputInfo) -> String {
+    if output.make.is_empty() && output.model.is_empty() {
+        return output.name.clone();
+    }
+
+    let tail = output.serial.as_deref().unwrap_or(&output.name);
+    format!("{} {} {}", output.make, output.model, tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monitor(name: &str, serial: Option<&str>) -> OutputInfo {
+        OutputInfo {
+            name: name.to_string(),
+            make: "Dell Inc.".to_string(),
+            model: "DELL U2720Q".to_string(),
+            serial: serial.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn image(path: &str) -> WallpaperCommand {
+        WallpaperCommand::SetImage {
+            path: path.to_string(),
+            output: None,
+            scale: common::ScaleMode::Fit,
+            transition: None,
+            focus: None,
+            letterbox: None,
+        }
+    }
+
+    /// Path of the image a recalled command shows
+    fn recalled<'a>(assignments: &'a Assignments, output: &OutputInfo) -> Option<&'a str> {
+        match assignments.recall(output)? {
+            WallpaperCommand::SetImage { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_monitor_id() {
+        // Serial numbers follow the monitor to another connector
+        assert_eq!(
+            monitor_id(&monitor("DP-1", Some("8XYZ123"))),
+            monitor_id(&monitor("HDMI-A-1", Some("8XYZ123")))
+        );
+        assert_ne!(
+            monitor_id(&monitor("DP-1", Some("8XYZ123"))),
+            monitor_id(&monitor("DP-1", Some("9ABC456")))
+        );
+        assert_ne!(
+            monitor_id(&monitor("DP-1", None)),
+            monitor_id(&monitor("DP-2", None))
+        );
+
+        let anonymous = OutputInfo {
+            name: "WL-1".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(monitor_id(&anonymous), "WL-1");
+    }
+
+    #[test]
+    fn test_recall() {
+        let dell = monitor("DP-1", Some("8XYZ123"));
+        let other = monitor("DP-2", Some("9ABC456"));
+        let outputs = [dell.clone(), other.clone()];
+        let mut assignments = Assignments::default();
+        assert!(assignments.recall(&dell).is_none());
+
+        assignments.record(None, &image("/all.jpg"), &outputs);
+        assignments.record(Some("serial:8XYZ123"), &image("/dell.jpg"), &outputs);
+
+        // The monitor gets its own wallpaper back on any connector
+        let replugged = monitor("HDMI-A-1", Some("8XYZ123"));
+        assert_eq!(recalled(&assignments, &replugged), Some("/dell.jpg"));
+        assert_eq!(recalled(&assignments, &other), Some("/all.jpg"));
+
+        // New monitors get the wallpaper last set everywhere
+        let new = monitor("DP-3", Some("0NEW000"));
+        assert_eq!(recalled(&assignments, &new), Some("/all.jpg"));
+
+        // Selecting some outputs isn't setting a global wallpaper
+        assignments.record(Some("all-but:DP-1"), &image("/rest.jpg"), &outputs);
+        assert_eq!(recalled(&assignments, &other), Some("/rest.jpg"));
+        assert_eq!(recalled(&assignments, &new), Some("/all.jpg"));
+
+        // Later global wallpapers replace earlier ones on the outputs they covered
+        assignments.record(Some("all"), &image("/new.jpg"), &outputs);
+        assert_eq!(recalled(&assignments, &replugged), Some("/new.jpg"));
+        assert_eq!(recalled(&assignments, &new), Some("/new.jpg"));
+
+        // The command comes back with the scale mode it was set with
+        assert!(matches!(
+            assignments.recall(&new),
+            Some(WallpaperCommand::SetImage {
+                scale: common::ScaleMode::Fit,
+                ..
+            })
+        ));
+    }
+}
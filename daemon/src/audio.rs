@@ -0,0 +1,219 @@
+//! Audio of video wallpapers
+//!
+//! Each output has its own volume and mute switch. A video is decoded once for
+//! all the outputs showing it, so it plays at the loudest of their volumes.
+//! While another application plays audio the video is ducked to a fraction of
+//! that, and while the session is idle it is muted.
+//!
+//! Other applications' audio is found by polling `pactl list sink-inputs`,
+//! which works with both PulseAudio and PipeWire's PulseAudio server.
+
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// How often to check whether other applications are playing audio
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Volume of an output's video audio
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Volume {
+    /// 0.0 to 1.0
+    pub level: f32,
+    pub muted: bool,
+}
+
+impl Volume {
+    /// The level heard, 0 when muted
+    pub fn audible(&self) -> f32 {
+        if self.muted { 0.0 } else { self.level }
+    }
+}
+
+/// What turns video audio down
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Ducking {
+    /// Another application is playing audio
+    pub other_audio: bool,
+    /// The session is idle
+    pub idle: bool,
+    /// Fraction of the volume left while another application plays audio
+    pub duck_level: f32,
+}
+
+/// Volume to play a video at, given the volumes of the outputs showing it
+pub fn mix(volumes: impl IntoIterator<Item = Volume>, ducking: Ducking) -> f32 {
+    if ducking.idle {
+        return 0.0;
+    }
+
+    let loudest = volumes
+        .into_iter()
+        .map(|volume| volume.audible())
+        .fold(0.0, f32::max);
+    if ducking.other_audio {
+        loudest * ducking.duck_level
+    } else {
+        loudest
+    }
+}
+
+/// Check `pactl list sink-inputs` output for streams of other processes that are playing
+///
+/// Paused streams are corked; streams without a process id count as another
+/// application's.
+pub fn others_playing(sink_inputs: &str, own_pid: u32) -> bool {
+    sink_inputs.split("Sink Input #").skip(1).any(|stream| {
+        let mut corked = false;
+        let mut pid = None;
+        for line in stream.lines().map(str::trim) {
+            if let Some(value) = line.strip_prefix("Corked:") {
+                corked = value.trim() == "yes";
+            } else if let Some(value) = line.strip_prefix("application.process.id = ") {
+                pid = value.trim_matches('"').parse::<u32>().ok();
+            }
+        }
+        !corked && pid != Some(own_pid)
+    })
+}
+
+/// Watches for other applications playing audio
+pub struct OtherAudio {
+    /// Result of the last check
+    playing: Arc<AtomicBool>,
+    /// A check is running
+    polling: Arc<AtomicBool>,
+    /// Cleared when `pactl` can't be run, which stops further checks
+    available: Arc<AtomicBool>,
+    last_poll: Option<Instant>,
+}
+
+impl OtherAudio {
+    pub fn new() -> Self {
+        Self {
+            playing: Arc::new(AtomicBool::new(false)),
+            polling: Arc::new(AtomicBool::new(false)),
+            available: Arc::new(AtomicBool::new(true)),
+            last_poll: None,
+        }
+    }
+
+    /// Whether another application was playing audio when last checked
+    pub fn is_playing(&self) -> bool {
+        self.playing.load(Ordering::Relaxed)
+    }
+
+    /// Check again in the background if the last check is old enough
+    pub fn refresh(&mut self) {
+        if self
+            .last_poll
+            .is_some_and(|last| last.elapsed() < POLL_INTERVAL)
+            || !self.available.load(Ordering::Relaxed)
+            || self.polling.swap(true, Ordering::Acquire)
+        {
+            return;
+        }
+        self.last_poll = Some(Instant::now());
+
+        let playing = Arc::clone(&self.playing);
+        let polling = Arc::clone(&self.polling);
+        let available = Arc::clone(&self.available);
+        std::thread::spawn(move || {
+            // (untranslated, so the field names can be matched)
+            match Command::new("pactl")
+                .args(["list", "sink-inputs"])
+                .env("LC_ALL", "C")
+                .output()
+            {
+                Ok(output) if output.status.success() => {
+                    let sink_inputs = String::from_utf8_lossy(&output.stdout);
+                    playing.store(
+                        others_playing(&sink_inputs, std::process::id()),
+                        Ordering::Relaxed,
+                    );
+                }
+                Ok(output) => log::debug!("pactl failed: {}", output.status),
+                Err(e) => {
+                    log::info!("Can't run pactl ({}), video audio won't be ducked", e);
+                    available.store(false, Ordering::Relaxed);
+                }
+            }
+            polling.store(false, Ordering::Release);
+        });
+    }
+}
+
+impl Default for OtherAudio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SINK_INPUTS: &str = r#"Sink Input #42
+	Driver: PipeWire
+	Owner Module: n/a
+	Client: 41
+	Sink: 50
+	Corked: no
+	Mute: no
+	Properties:
+		application.name = "momoi"
+		application.process.id = "1000"
+		media.name = "playback"
+
+Sink Input #77
+	Driver: PipeWire
+	Client: 70
+	Sink: 50
+	Corked: yes
+	Mute: no
+	Properties:
+		application.name = "Firefox"
+		application.process.id = "2000"
+"#;
+
+    fn volume(level: f32, muted: bool) -> Volume {
+        Volume { level, muted }
+    }
+
+    #[test]
+    fn test_mix() {
+        let quiet = Ducking::default();
+        let volumes = [volume(0.3, false), volume(0.8, false), volume(1.0, true)];
+        assert_eq!(mix(volumes, quiet), 0.8);
+        assert_eq!(mix([volume(1.0, true)], quiet), 0.0);
+        assert_eq!(mix([], quiet), 0.0);
+
+        let ducked = Ducking {
+            other_audio: true,
+            duck_level: 0.25,
+            ..quiet
+        };
+        assert_eq!(mix(volumes, ducked), 0.2);
+
+        let idle = Ducking {
+            idle: true,
+            ..quiet
+        };
+        assert_eq!(mix(volumes, idle), 0.0);
+    }
+
+    #[test]
+    fn test_others_playing() {
+        // Our own stream and a paused one
+        assert!(!others_playing(SINK_INPUTS, 1000));
+        assert!(!others_playing("", 1000));
+
+        // Once the other application plays
+        let playing = SINK_INPUTS.replace("Corked: yes", "Corked: no");
+        assert!(others_playing(&playing, 1000));
+
+        // Our stream is someone else's to another process
+        assert!(others_playing(SINK_INPUTS, 3000));
+    }
+}
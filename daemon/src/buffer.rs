@@ -1,6 +1,7 @@
+use std::borrow::Cow;
 use std::os::fd::AsFd;
 use std::sync::{Arc, Mutex};
-use wayland_client::protocol::{wl_buffer, wl_shm, wl_shm_pool};
+use wayland_client::protocol::{wl_buffer, wl_output, wl_shm, wl_shm_pool, wl_surface};
 use wayland_client::{Dispatch, QueueHandle};
 
 /// Buffer state tracked by Wayland compositor
@@ -21,17 +22,27 @@ pub struct ShmBuffer {
     pool: wl_shm_pool::WlShmPool,
     buffer: wl_buffer::WlBuffer,
     mmap: memmap2::MmapMut,
+    /// Size of the frames written to the buffer (surface orientation)
     width: u32,
     height: u32,
+    /// ARGB8888, or XRGB2101010 for 10-bit frames
+    format: wl_shm::Format,
+    /// How frames are turned to match the output (see [`crate::output_transform`])
+    transform: wl_output::Transform,
+    /// Buffer pixels per surface unit (frames are this many times the output's size)
+    scale: u32,
     /// Shared state for tracking buffer usage
     pub state: Arc<Mutex<BufferState>>,
 }
 
 impl ShmBuffer {
-    pub fn new<D>(
+    /// Create a buffer in a 32-bit format (ARGB8888 or XRGB2101010), holding
+    /// `width`x`height` frames turned by an output transform
+    pub fn with_format<D>(
         shm: &wl_shm::WlShm,
         width: u32,
         height: u32,
+        (format, transform): (wl_shm::Format, wl_output::Transform),
         qh: &QueueHandle<D>,
     ) -> anyhow::Result<Self>
     where
@@ -39,8 +50,10 @@ impl ShmBuffer {
             + Dispatch<wl_buffer::WlBuffer, Arc<Mutex<BufferState>>>
             + 'static,
     {
-        let stride = width * 4; // 4 bytes per pixel (ARGB8888)
-        let size = stride * height;
+        let (buffer_width, buffer_height) =
+            crate::output_transform::buffer_size(width, height, transform);
+        let stride = buffer_width * 4; // 4 bytes per pixel (both formats)
+        let size = stride * buffer_height;
 
         // Create a temporary file for shared memory
         let file = tempfile::tempfile()?;
@@ -58,10 +71,10 @@ impl ShmBuffer {
         // Create buffer from pool with state as user data
         let buffer = pool.create_buffer(
             0,
-            width as i32,
-            height as i32,
+            buffer_width as i32,
+            buffer_height as i32,
             stride as i32,
-            wl_shm::Format::Argb8888,
+            format,
             qh,
             state.clone(),
         );
@@ -72,10 +85,20 @@ impl ShmBuffer {
             mmap,
             width,
             height,
+            format,
+            transform,
+            scale: 1,
             state,
         })
     }
 
+    /// Show the buffer at a HiDPI buffer scale; its frames must be `scale`
+    /// times the surface size
+    pub fn with_scale(mut self, scale: u32) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
     /// Check if the buffer is safe to reuse (not busy)
     pub fn is_released(&self) -> bool {
         self.state.lock().map(|s| !s.busy).unwrap_or(false)
@@ -98,7 +121,8 @@ impl ShmBuffer {
     }
 
     /// Write image data to the buffer
-    /// Data must be in ARGB8888 format (BGRA byte order)
+    /// Data must be in ARGB8888 format (BGRA byte order), and is converted for
+    /// XRGB2101010 buffers
     pub fn write_image_data(&mut self, data: &[u8]) -> anyhow::Result<()> {
         if data.len() != self.mmap.len() {
             anyhow::bail!(
@@ -108,18 +132,87 @@ impl ShmBuffer {
             );
         }
 
-        self.mmap.copy_from_slice(data);
+        let data = self.to_buffer(data);
+        match self.format {
+            wl_shm::Format::Xrgb2101010 => {
+                crate::hdr::argb8888_to_xrgb2101010(&data, &mut self.mmap)
+            }
+            _ => self.mmap.copy_from_slice(&data),
+        }
+        Ok(())
+    }
+
+    /// Write data that is already in the buffer's own format
+    pub fn write_native_data(&mut self, data: &[u8]) -> anyhow::Result<()> {
+        if data.len() != self.mmap.len() {
+            anyhow::bail!(
+                "Image data size mismatch: expected {}, got {}",
+                self.mmap.len(),
+                data.len()
+            );
+        }
+
+        let data = self.to_buffer(data);
+        self.mmap.copy_from_slice(&data);
         Ok(())
     }
 
     /// Read the current buffer data
-    /// Returns a copy of the buffer data in ARGB8888 format
+    /// Returns a copy of the buffer data in ARGB8888 format (surface orientation),
+    /// at the surface size for buffers with a scale
     pub fn read_data(&self) -> anyhow::Result<Vec<u8>> {
-        Ok(self.mmap.to_vec())
+        let data = match self.format {
+            wl_shm::Format::Xrgb2101010 => crate::hdr::xrgb2101010_to_argb8888(&self.mmap),
+            _ => self.mmap.to_vec(),
+        };
+
+        let frame = match self.transform {
+            wl_output::Transform::Normal => data,
+            transform => {
+                crate::output_transform::from_buffer(&data, self.width, self.height, transform)
+            }
+        };
+
+        match self.scale {
+            1 => Ok(frame),
+            scale => Ok(downsample(&frame, self.width, self.height, scale)),
+        }
     }
 
-    pub fn buffer(&self) -> &wl_buffer::WlBuffer {
-        &self.buffer
+    /// Turn a frame to the buffer's orientation
+    fn to_buffer<'a>(&self, data: &'a [u8]) -> Cow<'a, [u8]> {
+        match self.transform {
+            wl_output::Transform::Normal => Cow::Borrowed(data),
+            transform => Cow::Owned(crate::output_transform::to_buffer(
+                data,
+                self.width,
+                self.height,
+                transform,
+            )),
+        }
+    }
+
+    /// Attach the buffer to a surface with its transform, damaging all of it
+    pub fn attach_to(&self, surface: &wl_surface::WlSurface) {
+        let (buffer_width, buffer_height) =
+            crate::output_transform::buffer_size(self.width, self.height, self.transform);
+
+        surface.set_buffer_transform(self.transform);
+        surface.set_buffer_scale(self.scale as i32);
+        surface.attach(Some(&self.buffer), 0, 0);
+        surface.damage_buffer(0, 0, buffer_width as i32, buffer_height as i32);
+    }
+
+    pub fn format(&self) -> wl_shm::Format {
+        self.format
+    }
+
+    pub fn transform(&self) -> wl_output::Transform {
+        self.transform
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
     }
 
     pub fn width(&self) -> u32 {
@@ -138,6 +231,19 @@ impl Drop for ShmBuffer {
     }
 }
 
+/// Shrink an ARGB8888 frame by an integer factor, keeping every `scale`th pixel
+fn downsample(frame: &[u8], width: u32, height: u32, scale: u32) -> Vec<u8> {
+    let (scale, width) = (scale as usize, width as usize);
+    let mut out = Vec::with_capacity(frame.len() / (scale * scale));
+    for y in (0..height as usize).step_by(scale) {
+        let row = &frame[y * width * 4..(y + 1) * width * 4];
+        for pixel in row.chunks_exact(4).step_by(scale) {
+            out.extend_from_slice(pixel);
+        }
+    }
+    out
+}
+
 /// Parse a hex color string (e.g., "#FF5733" or "FF5733") to RGBA
 pub fn parse_hex_color(color: &str) -> Option<(u8, u8, u8, u8)> {
     let color = color.trim_start_matches('#');
@@ -171,4 +277,11 @@ mod tests {
         assert_eq!(parse_hex_color("FFFFFF"), Some((255, 255, 255, 255)));
         assert_eq!(parse_hex_color("invalid"), None);
     }
+
+    #[test]
+    fn test_downsample() {
+        // 4x2 frame of pixels numbered 0..8, halved to its even columns of row 0
+        let frame: Vec<u8> = (0..8u8).flat_map(|i| [i; 4]).collect();
+        assert_eq!(downsample(&frame, 4, 2, 2), [[0u8; 4], [2; 4]].concat());
+    }
 }
@@ -0,0 +1,133 @@
+//! Frame capture
+//!
+//! Converts displayed ARGB8888 frames back to RGBA, stitches several outputs
+//! together by their layout position and writes the result as a PNG.
+
+use anyhow::{Context, Result};
+use image::RgbaImage;
+use std::path::Path;
+
+/// A captured output frame and its position in the compositor layout
+pub struct CapturedFrame {
+    pub x: i32,
+    pub y: i32,
+    pub image: RgbaImage,
+}
+
+/// Convert an ARGB8888 frame (BGRA in memory) to an RGBA image
+pub fn argb8888_to_rgba(data: &[u8], width: u32, height: u32) -> Result<RgbaImage> {
+    let expected = width as usize * height as usize * 4;
+    let data = data.get(..expected).with_context(|| {
+        format!(
+            "Frame is smaller than {}x{} ({} bytes)",
+            width,
+            height,
+            data.len()
+        )
+    })?;
+
+    let mut rgba = data.to_vec();
+    for pixel in rgba.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+
+    RgbaImage::from_raw(width, height, rgba).context("Failed to create capture image buffer")
+}
+
+/// Combine frames into one image covering their bounding box
+///
+/// Gaps between outputs that don't touch are left transparent.
+pub fn stitch(frames: &[CapturedFrame]) -> Option<RgbaImage> {
+    let min_x = frames.iter().map(|f| f.x).min()?;
+    let min_y = frames.iter().map(|f| f.y).min()?;
+    let max_x = frames.iter().map(|f| f.x + f.image.width() as i32).max()?;
+    let max_y = frames.iter().map(|f| f.y + f.image.height() as i32).max()?;
+
+    let mut canvas = RgbaImage::new((max_x - min_x) as u32, (max_y - min_y) as u32);
+    for frame in frames {
+        image::imageops::replace(
+            &mut canvas,
+            &frame.image,
+            (frame.x - min_x) as i64,
+            (frame.y - min_y) as i64,
+        );
+    }
+
+    Some(canvas)
+}
+
+/// Write an image as PNG, favouring speed over size since this runs on the render thread
+pub fn save_png(image: &RgbaImage, path: &Path) -> Result<()> {
+    use image::ImageEncoder;
+    use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("Failed to create {}", path.display()))?;
+    let encoder = PngEncoder::new_with_quality(
+        std::io::BufWriter::new(file),
+        CompressionType::Fast,
+        FilterType::Adaptive,
+    );
+
+    encoder
+        .write_image(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            image::ExtendedColorType::Rgba8,
+        )
+        .with_context(|| format!("Failed to write PNG: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_argb8888_to_rgba() {
+        // One red pixel in ARGB8888 (BGRA in memory)
+        let image = argb8888_to_rgba(&[0, 0, 255, 255], 1, 1).unwrap();
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0, 255]);
+
+        assert!(argb8888_to_rgba(&[0, 0, 255, 255], 2, 1).is_err());
+    }
+
+    #[test]
+    fn test_stitch_by_layout() {
+        let red = RgbaImage::from_pixel(4, 2, image::Rgba([255, 0, 0, 255]));
+        let blue = RgbaImage::from_pixel(2, 3, image::Rgba([0, 0, 255, 255]));
+
+        // Blue output to the left of red, offset downwards
+        let frames = [
+            CapturedFrame {
+                x: 100,
+                y: 0,
+                image: red,
+            },
+            CapturedFrame {
+                x: 98,
+                y: 1,
+                image: blue,
+            },
+        ];
+
+        let canvas = stitch(&frames).unwrap();
+        assert_eq!(canvas.dimensions(), (6, 4));
+        assert_eq!(canvas.get_pixel(2, 0).0, [255, 0, 0, 255]);
+        assert_eq!(canvas.get_pixel(0, 1).0, [0, 0, 255, 255]);
+        // Not covered by any output
+        assert_eq!(canvas.get_pixel(0, 0).0, [0, 0, 0, 0]);
+
+        assert!(stitch(&[]).is_none());
+    }
+
+    #[test]
+    fn test_save_png() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("capture.png");
+        let image = RgbaImage::from_pixel(3, 2, image::Rgba([1, 2, 3, 255]));
+
+        save_png(&image, &path).unwrap();
+        assert_eq!(image::open(&path).unwrap().to_rgba8(), image);
+    }
+}
@@ -0,0 +1,355 @@
+//! Colour profiles and EXIF orientation of photos
+//!
+//! Cameras and phones store photos the way the sensor saw them and record how
+//! to turn them upright in their EXIF data, and wide-gamut photos (Display P3,
+//! Adobe RGB) embed an ICC profile describing their colours. Both are applied
+//! when an image is decoded, so cached images are upright and in sRGB.
+//!
+//! Outputs can be given their display's ICC profile in the config; frames
+//! shown on them are then converted from sRGB to it.
+
+use anyhow::{Context, Result};
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageBuffer, ImageDecoder, ImageReader, Pixel};
+use moxcms::{
+    ColorProfile, DataColorSpace, Layout, Transform8BitExecutor, TransformExecutor,
+    TransformOptions,
+};
+use rayon::prelude::*;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Bytes of an ARGB8888 frame converted per parallel task
+const CHUNK_BYTES: usize = 64 * 1024;
+
+/// Start of a bare JPEG XL codestream
+const JXL_CODESTREAM: &[u8] = &[0xff, 0x0a];
+/// Start of a JPEG XL file in the ISOBMFF container
+const JXL_CONTAINER: &[u8] = b"\0\0\0\x0cJXL \r\n\x87\n";
+
+/// Decode an image file, turned upright and converted to sRGB
+pub fn decode(path: &Path) -> Result<DynamicImage> {
+    let mut decoder = open_decoder(path)?;
+
+    // Broken metadata shouldn't keep the image from showing
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let icc = decoder.icc_profile().ok().flatten();
+
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    if orientation != Orientation::NoTransforms {
+        log::debug!("Applying EXIF orientation {:?}", orientation);
+        image.apply_orientation(orientation);
+    }
+
+    if let Some(icc) = icc {
+        match to_srgb(&image, &icc) {
+            Ok(Some(converted)) => image = converted,
+            Ok(None) => {}
+            Err(e) => log::warn!("Ignoring the colour profile of {}: {:#}", path.display(), e),
+        }
+    }
+
+    Ok(image)
+}
+
+/// Size of an image file once turned upright, read from its header
+///
+/// Returns None for files the image decoders can't read (videos, SVGs).
+pub fn upright_dimensions(path: &Path) -> Option<(u32, u32)> {
+    let mut decoder = open_decoder(path).ok()?;
+
+    let (width, height) = decoder.dimensions();
+    match decoder.orientation().unwrap_or(Orientation::NoTransforms) {
+        Orientation::Rotate90
+        | Orientation::Rotate270
+        | Orientation::Rotate90FlipH
+        | Orientation::Rotate270FlipH => Some((height, width)),
+        _ => Some((width, height)),
+    }
+}
+
+/// Open a decoder for an image file, picked by its contents
+///
+/// The image crate has no JPEG XL decoder, so those files go to jxl-oxide.
+fn open_decoder(path: &Path) -> Result<Box<dyn ImageDecoder>> {
+    let mut magic = [0; JXL_CONTAINER.len()];
+    let read = std::fs::File::open(path)?.read(&mut magic)?;
+    let magic = &magic[..read];
+
+    if magic.starts_with(JXL_CODESTREAM) || magic.starts_with(JXL_CONTAINER) {
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        return Ok(Box::new(jxl_oxide::integration::JxlDecoder::new(file)?));
+    }
+
+    let decoder = ImageReader::open(path)?
+        .with_guessed_format()?
+        .into_decoder()?;
+    Ok(Box::new(decoder))
+}
+
+/// Convert an image from its embedded ICC profile to sRGB
+///
+/// Returns None if there's nothing to convert: grey images and profiles, and
+/// floating-point images (those are linear and don't carry profiles).
+fn to_srgb(image: &DynamicImage, icc: &[u8]) -> Result<Option<DynamicImage>> {
+    let source = ColorProfile::new_from_slice(icc).context("Invalid ICC profile")?;
+    if source.color_space != DataColorSpace::Rgb {
+        return Ok(None);
+    }
+
+    let srgb = ColorProfile::new_srgb();
+    let options = TransformOptions::default();
+    let converted = match image {
+        DynamicImage::ImageRgb8(rgb) => {
+            let transform =
+                source.create_transform_8bit(Layout::Rgb, &srgb, Layout::Rgb, options)?;
+            DynamicImage::ImageRgb8(convert(rgb, transform.as_ref())?)
+        }
+        DynamicImage::ImageRgba8(rgba) => {
+            let transform =
+                source.create_transform_8bit(Layout::Rgba, &srgb, Layout::Rgba, options)?;
+            DynamicImage::ImageRgba8(convert(rgba, transform.as_ref())?)
+        }
+        DynamicImage::ImageRgb16(rgb) => {
+            let transform =
+                source.create_transform_16bit(Layout::Rgb, &srgb, Layout::Rgb, options)?;
+            DynamicImage::ImageRgb16(convert(rgb, transform.as_ref())?)
+        }
+        DynamicImage::ImageRgba16(rgba) => {
+            let transform =
+                source.create_transform_16bit(Layout::Rgba, &srgb, Layout::Rgba, options)?;
+            DynamicImage::ImageRgba16(convert(rgba, transform.as_ref())?)
+        }
+        _ => return Ok(None),
+    };
+
+    log::debug!("Converted embedded colour profile to sRGB");
+    Ok(Some(converted))
+}
+
+/// Run a colour transform over an image, a row at a time in parallel
+fn convert<P>(
+    image: &ImageBuffer<P, Vec<P::Subpixel>>,
+    transform: &(dyn TransformExecutor<P::Subpixel> + Send + Sync),
+) -> Result<ImageBuffer<P, Vec<P::Subpixel>>>
+where
+    P: Pixel,
+    P::Subpixel: Default + Send + Sync,
+{
+    let row_len = (image.width() as usize * P::CHANNEL_COUNT as usize).max(1);
+    let mut converted = ImageBuffer::new(image.width(), image.height());
+
+    converted
+        .par_chunks_mut(row_len)
+        .zip(image.par_chunks(row_len))
+        .try_for_each(|(converted, source)| transform.transform(source, converted))
+        .context("Colour conversion failed")?;
+
+    Ok(converted)
+}
+
+/// Conversion of frames from sRGB to a display's ICC profile
+#[derive(Clone)]
+pub struct DisplayProfile {
+    /// Hash of the profile, telling frames converted for it apart in the cache
+    id: u64,
+    transform: Arc<Transform8BitExecutor>,
+}
+
+impl DisplayProfile {
+    /// Load a display profile from an ICC file
+    pub fn load(path: &Path) -> Result<Self> {
+        let icc = std::fs::read(path)
+            .with_context(|| format!("Failed to read ICC profile: {}", path.display()))?;
+        Self::from_icc(&icc)
+            .with_context(|| format!("Failed to load ICC profile: {}", path.display()))
+    }
+
+    pub fn from_icc(icc: &[u8]) -> Result<Self> {
+        let target = ColorProfile::new_from_slice(icc).context("Invalid ICC profile")?;
+        if target.color_space != DataColorSpace::Rgb {
+            anyhow::bail!("Display profiles must be RGB profiles");
+        }
+
+        let transform = ColorProfile::new_srgb().create_transform_8bit(
+            Layout::Rgba,
+            &target,
+            Layout::Rgba,
+            TransformOptions::default(),
+        )?;
+
+        let mut hasher = DefaultHasher::new();
+        icc.hash(&mut hasher);
+
+        Ok(Self {
+            id: hasher.finish(),
+            transform: Arc::from(transform),
+        })
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Convert an ARGB8888 frame from sRGB to the display's colours
+    pub fn apply(&self, argb: &mut [u8]) -> Result<()> {
+        argb.par_chunks_mut(CHUNK_BYTES)
+            .try_for_each(|chunk| {
+                // There's no BGRA layout, so red and blue are swapped around the transform
+                swap_red_blue(chunk);
+                let rgba = chunk.to_vec();
+                let result = self.transform.transform(&rgba, chunk);
+                swap_red_blue(chunk);
+                result
+            })
+            .context("Colour conversion failed")
+    }
+}
+
+fn swap_red_blue(pixels: &mut [u8]) {
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageEncoder, Rgb, RgbImage};
+
+    /// Save a 2x1 PNG with the given ICC profile and EXIF data
+    fn save_png(path: &Path, pixels: [[u8; 3]; 2], icc: Option<Vec<u8>>, exif: Option<Vec<u8>>) {
+        let image = RgbImage::from_fn(2, 1, |x, _| Rgb(pixels[x as usize]));
+        let file = std::fs::File::create(path).unwrap();
+        let mut encoder = image::codecs::png::PngEncoder::new(file);
+        if let Some(icc) = icc {
+            encoder.set_icc_profile(icc).unwrap();
+        }
+        if let Some(exif) = exif {
+            encoder.set_exif_metadata(exif).unwrap();
+        }
+        encoder
+            .write_image(image.as_raw(), 2, 1, image::ExtendedColorType::Rgb8)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_exif_orientation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.png");
+
+        // Big-endian TIFF header and one IFD entry: orientation 6 (rotate 90°)
+        let exif = vec![
+            0x4d, 0x4d, 0x00, 0x2a, 0x00, 0x00, 0x00, 0x08, // header
+            0x00, 0x01, // one entry
+            0x01, 0x12, 0x00, 0x03, 0x00, 0x00, 0x00, 0x01, 0x00, 0x06, 0x00,
+            0x00, // orientation
+            0x00, 0x00, 0x00, 0x00, // no next IFD
+        ];
+        save_png(&path, [[255, 0, 0], [0, 0, 255]], None, Some(exif));
+
+        let image = decode(&path).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (1, 2));
+        assert_eq!(upright_dimensions(&path), Some((1, 2)));
+        assert_eq!(image.get_pixel(0, 0).0, [255, 0, 0]);
+        assert_eq!(image.get_pixel(0, 1).0, [0, 0, 255]);
+    }
+
+    #[test]
+    fn test_embedded_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("plain.png");
+        let wide = dir.path().join("wide.png");
+        let p3 = ColorProfile::new_display_p3().encode().unwrap();
+
+        let pixels = [[200, 100, 50], [128, 128, 128]];
+        save_png(&plain, pixels, None, None);
+        save_png(&wide, pixels, Some(p3.clone()), None);
+
+        // Untagged images are taken to be sRGB already
+        let image = decode(&plain).unwrap().to_rgb8();
+        assert_eq!(image.get_pixel(0, 0).0, [200, 100, 50]);
+
+        // The same values in Display P3 are more saturated in sRGB
+        let image = decode(&wide).unwrap().to_rgb8();
+        let [r, g, b] = image.get_pixel(0, 0).0;
+        assert!(r > 200 && g < 100 && b < 50, "{:?}", (r, g, b));
+
+        // Both share sRGB's white point and curve, so greys stay put
+        let [r, g, b] = image.get_pixel(1, 0).0;
+        assert!(
+            [r, g, b].iter().all(|&v| v.abs_diff(128) <= 1),
+            "{:?}",
+            (r, g, b)
+        );
+
+        // Broken profiles are ignored
+        let broken = dir.path().join("broken.png");
+        save_png(&broken, pixels, Some(vec![0; 200]), None);
+        assert_eq!(
+            decode(&broken).unwrap().to_rgb8().get_pixel(0, 0).0,
+            [200, 100, 50]
+        );
+    }
+
+    /// Path of an 8x8 orange image in `tests/fixtures`
+    fn fixture(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn test_decode_jpeg_xl() {
+        let path = fixture("orange.jxl");
+        assert_eq!(upright_dimensions(&path), Some((8, 8)));
+
+        let image = decode(&path).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (8, 8));
+        assert_eq!(image.get_pixel(3, 3).0, [255, 128, 0]);
+    }
+
+    #[cfg(feature = "avif")]
+    #[test]
+    fn test_decode_avif() {
+        let path = fixture("orange.avif");
+        assert_eq!(upright_dimensions(&path), Some((8, 8)));
+
+        // Lossy, so only close to the original colour
+        let image = decode(&path).unwrap().to_rgb8();
+        assert_eq!(image.dimensions(), (8, 8));
+        let [r, g, b] = image.get_pixel(3, 3).0;
+        assert!(
+            r.abs_diff(255) <= 4 && g.abs_diff(128) <= 4 && b <= 4,
+            "{:?}",
+            (r, g, b)
+        );
+    }
+
+    #[test]
+    fn test_display_profile() {
+        let srgb = DisplayProfile::from_icc(&ColorProfile::new_srgb().encode().unwrap()).unwrap();
+        let p3_icc = ColorProfile::new_display_p3().encode().unwrap();
+        let p3 = DisplayProfile::from_icc(&p3_icc).unwrap();
+        assert_ne!(srgb.id(), p3.id());
+        assert_eq!(p3.id(), DisplayProfile::from_icc(&p3_icc).unwrap().id());
+
+        // Saturated red as ARGB8888 (BGRA in memory)
+        let frame = [0u8, 0, 255, 255].repeat(4);
+
+        let mut same = frame.clone();
+        srgb.apply(&mut same).unwrap();
+        assert!(same.iter().zip(&frame).all(|(a, b)| a.abs_diff(*b) <= 1));
+
+        // sRGB red is inside the P3 gamut, so it needs less red and some green there
+        let mut converted = frame.clone();
+        p3.apply(&mut converted).unwrap();
+        let [b, g, r, a] = converted[..4].try_into().unwrap();
+        assert!(r < 255 && g > 0, "{:?}", (r, g, b));
+        assert_eq!(a, 255);
+
+        assert!(DisplayProfile::from_icc(&[0; 16]).is_err());
+    }
+}
@@ -1,3 +1,5 @@
+use crate::output_selector::OutputSelector;
+use crate::output_transform::Orientation;
 use crate::validate_enum;
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
@@ -25,6 +27,19 @@ pub struct Config {
     #[serde(default)]
     pub shader_preset: Vec<ShaderPreset>,
 
+    #[serde(default)]
+    pub svg: SvgSettings,
+
+    #[serde(default)]
+    pub palette: PaletteSettings,
+
+    #[serde(default)]
+    pub hdr: HdrSettings,
+
+    /// Points Fill crops around, by image path (see `focal_point`)
+    #[serde(default)]
+    pub focal_points: std::collections::BTreeMap<String, common::FocalPoint>,
+
     #[serde(default)]
     pub advanced: AdvancedSettings,
 }
@@ -43,6 +58,10 @@ pub struct GeneralSettings {
 
     #[serde(default = "default_scale")]
     pub default_scale: String,
+
+    /// Background around images in fit and center modes
+    #[serde(default = "default_letterbox")]
+    pub letterbox: String,
 }
 
 impl Default for GeneralSettings {
@@ -52,6 +71,7 @@ impl Default for GeneralSettings {
             default_transition: default_transition(),
             default_duration: default_duration(),
             default_scale: default_scale(),
+            letterbox: default_letterbox(),
         }
     }
 }
@@ -72,6 +92,10 @@ fn default_scale() -> String {
     "fill".to_string()
 }
 
+fn default_letterbox() -> String {
+    "black".to_string()
+}
+
 /// Playlist configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct PlaylistSettings {
@@ -95,6 +119,17 @@ pub struct PlaylistSettings {
 
     #[serde(default = "default_extensions")]
     pub extensions: Vec<String>,
+
+    /// Only keep images in this orientation ("landscape" or "portrait")
+    #[serde(default)]
+    pub orientation: Option<String>,
+}
+
+impl PlaylistSettings {
+    /// Get the orientation images are filtered by, if any
+    pub fn orientation(&self) -> Result<Option<Orientation>> {
+        self.orientation.as_deref().map(str::parse).transpose()
+    }
 }
 
 fn default_interval() -> u64 {
@@ -106,7 +141,12 @@ fn default_extensions() -> Vec<String> {
         "jpg".to_string(),
         "jpeg".to_string(),
         "png".to_string(),
+        "svg".to_string(),
         "webp".to_string(),
+        "avif".to_string(),
+        "jxl".to_string(),
+        "exr".to_string(),
+        "hdr".to_string(),
         "gif".to_string(),
         "mp4".to_string(),
         "webm".to_string(),
@@ -151,6 +191,25 @@ pub struct OutputConfig {
 
     #[serde(default)]
     pub playlist_sources: Vec<String>,
+
+    /// ICC profile of the display; image colours are converted to it from sRGB
+    #[serde(default)]
+    pub icc_profile: Option<String>,
+
+    /// Wallpaper shown instead of `wallpaper` while the output is in portrait
+    #[serde(default)]
+    pub portrait_wallpaper: Option<String>,
+}
+
+impl OutputConfig {
+    /// Get the wallpaper configured for an orientation of the output
+    pub fn wallpaper_for(&self, orientation: Orientation) -> Option<&str> {
+        match orientation {
+            Orientation::Portrait => self.portrait_wallpaper.as_deref(),
+            Orientation::Landscape => None,
+        }
+        .or(self.wallpaper.as_deref())
+    }
 }
 
 /// Named collection of wallpapers
@@ -221,6 +280,75 @@ impl ShaderPreset {
     }
 }
 
+/// SVG wallpaper settings
+#[derive(Debug, Clone, Deserialize, Serialize, Default)]
+pub struct SvgSettings {
+    /// Colour substitutions: `name = "#rrggbb"` resolves `var(--name)`, and
+    /// `"#rrggbb" = "#rrggbb"` replaces a literal colour
+    #[serde(default)]
+    pub colors: std::collections::BTreeMap<String, String>,
+}
+
+/// Colour palette extraction settings
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PaletteSettings {
+    /// Number of dominant colours to extract
+    #[serde(default = "default_palette_colors")]
+    pub colors: usize,
+
+    /// Output (name or selector) whose palette fills the templates (default:
+    /// whichever changed last)
+    #[serde(default)]
+    pub output: Option<String>,
+
+    /// Templates rendered with the palette on every wallpaper change
+    #[serde(default)]
+    pub templates: Vec<PaletteTemplate>,
+}
+
+impl Default for PaletteSettings {
+    fn default() -> Self {
+        Self {
+            colors: default_palette_colors(),
+            output: None,
+            templates: Vec::new(),
+        }
+    }
+}
+
+/// HDR and high-bit-depth image settings
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HdrSettings {
+    /// Tone mapping operator for OpenEXR and Radiance HDR images
+    #[serde(default = "default_tone_map")]
+    pub tone_map: String,
+
+    /// Exposure adjustment in stops, applied before tone mapping
+    #[serde(default)]
+    pub exposure: f32,
+
+    /// Show high-bit-depth images with 10 bits per channel when the compositor can
+    #[serde(default = "default_true")]
+    pub ten_bit: bool,
+}
+
+impl Default for HdrSettings {
+    fn default() -> Self {
+        Self {
+            tone_map: default_tone_map(),
+            exposure: 0.0,
+            ten_bit: true,
+        }
+    }
+}
+
+/// A template file and where to write it once filled in
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PaletteTemplate {
+    pub template: String,
+    pub target: String,
+}
+
 /// Advanced settings
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AdvancedSettings {
@@ -233,10 +361,22 @@ pub struct AdvancedSettings {
     #[serde(default = "default_true")]
     pub video_loop: bool,
 
+    #[serde(default)]
+    pub video_crossfade_ms: u64,
+
+    #[serde(default = "default_video_volume")]
+    pub video_volume: f32,
+
+    #[serde(default = "default_video_duck_volume")]
+    pub video_duck_volume: f32,
+
+    #[serde(default = "default_video_idle_mute")]
+    pub video_idle_mute_secs: u32,
+
     #[serde(default = "default_max_fps")]
     pub max_fps: u32,
 
-    #[serde(default)]
+    #[serde(default = "default_cache_limit")]
     pub cache_limit_mb: u64,
 
     #[serde(default = "default_true")]
@@ -281,8 +421,12 @@ impl Default for AdvancedSettings {
             enable_video: true,
             video_muted: true,
             video_loop: true,
+            video_crossfade_ms: 0,
+            video_volume: default_video_volume(),
+            video_duck_volume: default_video_duck_volume(),
+            video_idle_mute_secs: default_video_idle_mute(),
             max_fps: default_max_fps(),
-            cache_limit_mb: 500,
+            cache_limit_mb: default_cache_limit(),
             preload_next: true,
             performance_mode: default_performance_mode(),
             auto_battery_mode: true,
@@ -298,6 +442,23 @@ impl Default for AdvancedSettings {
     }
 }
 
+impl AdvancedSettings {
+    /// Length of the crossfade between the end and start of looping videos
+    #[cfg_attr(not(feature = "video"), allow(dead_code))]
+    pub fn video_crossfade(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.video_crossfade_ms)
+    }
+
+    /// Volume outputs start with
+    #[cfg_attr(not(feature = "video"), allow(dead_code))]
+    pub fn video_volume(&self) -> crate::audio::Volume {
+        crate::audio::Volume {
+            level: self.video_volume,
+            muted: self.video_muted,
+        }
+    }
+}
+
 fn default_true() -> bool {
     true
 }
@@ -306,6 +467,14 @@ fn default_false() -> bool {
     false
 }
 
+fn default_palette_colors() -> usize {
+    8
+}
+
+fn default_tone_map() -> String {
+    "aces".to_string()
+}
+
 fn default_max_fps() -> u32 {
     60
 }
@@ -314,6 +483,10 @@ fn default_performance_mode() -> String {
     "balanced".to_string()
 }
 
+fn default_cache_limit() -> u64 {
+    500
+}
+
 fn default_memory_limit() -> usize {
     300
 }
@@ -338,6 +511,18 @@ pub fn default_max_video_fps() -> u32 {
     15
 }
 
+fn default_video_volume() -> f32 {
+    1.0
+}
+
+fn default_video_duck_volume() -> f32 {
+    0.3
+}
+
+fn default_video_idle_mute() -> u32 {
+    300
+}
+
 impl Config {
     /// Load configuration from the default location
     pub fn load() -> Result<Self> {
@@ -389,6 +574,7 @@ impl Config {
 
         if let Some(ref playlist) = self.playlist {
             self.validate_transition(&playlist.transition)?;
+            playlist.orientation()?;
         }
 
         for schedule in &self.schedule {
@@ -398,12 +584,41 @@ impl Config {
         }
 
         for output in &self.output {
+            output.name.parse::<OutputSelector>()?;
             self.validate_transition(&output.transition)?;
             self.validate_scale(&output.scale)?;
         }
 
+        if let Some(output) = &self.palette.output {
+            output.parse::<OutputSelector>()?;
+        }
+
+        for (path, focus) in &self.focal_points {
+            if !focus.is_valid() {
+                anyhow::bail!(
+                    "Invalid focal point for {}: coordinates must be between 0 and 1",
+                    path
+                );
+            }
+        }
+
         // Validate scale modes
         self.validate_scale(&self.general.default_scale)?;
+        self.general.letterbox.parse::<common::Letterbox>()?;
+
+        for (name, volume) in [
+            ("video_volume", self.advanced.video_volume),
+            ("video_duck_volume", self.advanced.video_duck_volume),
+        ] {
+            if !(0.0..=1.0).contains(&volume) {
+                anyhow::bail!("Invalid {}: {} (must be between 0 and 1)", name, volume);
+            }
+        }
+
+        self.hdr.tone_map.parse::<common::ToneMap>()?;
+        if !self.hdr.exposure.is_finite() {
+            anyhow::bail!("Invalid exposure: {}", self.hdr.exposure);
+        }
 
         Ok(())
     }
@@ -451,15 +666,55 @@ impl Config {
         Ok(())
     }
 
-    /// Get output configuration by name
-    pub fn get_output_config(&self, output_name: &str) -> Option<&OutputConfig> {
-        self.output.iter().find(|o| o.name == output_name)
+    /// Get the first output section whose selector picks an output, given
+    /// every connected output
+    pub fn get_output_config(
+        &self,
+        output: &common::OutputInfo,
+        outputs: &[common::OutputInfo],
+    ) -> Option<&OutputConfig> {
+        self.output.iter().find(|section| {
+            section
+                .name
+                .parse::<OutputSelector>()
+                .is_ok_and(|selector| selector.matches(output, outputs))
+        })
+    }
+
+    /// Get the ICC profile configured for an output, with `~` expanded
+    pub fn icc_profile(
+        &self,
+        output: &common::OutputInfo,
+        outputs: &[common::OutputInfo],
+    ) -> Option<std::path::PathBuf> {
+        let path = self
+            .get_output_config(output, outputs)?
+            .icc_profile
+            .as_ref()?;
+        Some(std::path::PathBuf::from(shellexpand::tilde(path).as_ref()))
     }
 
     /// Get collection by name
     pub fn get_collection(&self, name: &str) -> Option<&Collection> {
         self.collection.iter().find(|c| c.name == name)
     }
+
+    /// Get the default letterbox background (black if it doesn't parse)
+    pub fn letterbox(&self) -> common::Letterbox {
+        self.general.letterbox.parse().unwrap_or_default()
+    }
+
+    /// Get how HDR images are tone mapped (ACES if the operator doesn't parse)
+    pub fn tone_mapping(&self) -> crate::hdr::ToneMapping {
+        crate::hdr::ToneMapping {
+            operator: self.hdr.tone_map.parse().unwrap_or_default(),
+            exposure: if self.hdr.exposure.is_finite() {
+                self.hdr.exposure
+            } else {
+                0.0
+            },
+        }
+    }
 }
 
 #[cfg(test)]
@@ -474,6 +729,275 @@ mod tests {
         assert_eq!(config.general.default_duration, 500);
     }
 
+    #[test]
+    fn test_svg_colors() {
+        let config: Config = toml::from_str(
+            r##"
+            [svg.colors]
+            accent = "#88c0d0"
+            "#000000" = "#2e3440"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(config.svg.colors["accent"], "#88c0d0");
+        assert_eq!(config.svg.colors["#000000"], "#2e3440");
+        assert!(Config::default().svg.colors.is_empty());
+    }
+
+    #[test]
+    fn test_palette_templates() {
+        let config: Config = toml::from_str(
+            r#"
+            [palette]
+            output = "DP-1"
+
+            [[palette.templates]]
+            template = "~/.config/momoi/templates/colors.css"
+            target = "~/.cache/momoi/colors.css"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.palette.colors, 8);
+        assert_eq!(config.palette.output.as_deref(), Some("DP-1"));
+        assert_eq!(config.palette.templates.len(), 1);
+        assert!(Config::default().palette.templates.is_empty());
+    }
+
+    #[test]
+    fn test_focal_points() {
+        let config: Config = toml::from_str(
+            r#"
+            [focal_points]
+            "~/Pictures/portrait.jpg" = { x = 0.5, y = 0.25 }
+            "#,
+        )
+        .unwrap();
+
+        let focus = config.focal_points["~/Pictures/portrait.jpg"];
+        assert_eq!((focus.x, focus.y), (0.5, 0.25));
+        assert!(config.validate().is_ok());
+
+        let invalid: Config = toml::from_str(
+            r#"
+            [focal_points]
+            "/a.jpg" = { x = 1.5, y = 0.5 }
+            "#,
+        )
+        .unwrap();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_letterbox() {
+        let config: Config = toml::from_str(
+            r#"
+            [general]
+            letterbox = "blur"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.letterbox(), common::Letterbox::Blur);
+        assert!(config.validate().is_ok());
+        assert_eq!(Config::default().letterbox(), common::Letterbox::default());
+
+        let mut invalid = Config::default();
+        invalid.general.letterbox = "sparkles".to_string();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_icc_profile() {
+        let config: Config = toml::from_str(
+            r#"
+            [[output]]
+            name = "DP-1"
+            icc_profile = "/usr/share/color/icc/display.icc"
+
+            [[output]]
+            name = "HDMI-A-1"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let outputs = [output("DP-1"), output("HDMI-A-1"), output("eDP-1")];
+        assert_eq!(
+            config.icc_profile(&outputs[0], &outputs),
+            Some(std::path::PathBuf::from("/usr/share/color/icc/display.icc"))
+        );
+        assert_eq!(config.icc_profile(&outputs[1], &outputs), None);
+        assert_eq!(config.icc_profile(&outputs[2], &outputs), None);
+    }
+
+    fn output(name: &str) -> common::OutputInfo {
+        common::OutputInfo {
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_output_selectors() {
+        let config: Config = toml::from_str(
+            r#"
+            [[output]]
+            name = "model:*U2720Q*"
+            wallpaper = "~/dell.jpg"
+
+            [[output]]
+            name = "all-but:eDP-1"
+            wallpaper = "~/external.jpg"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+
+        let mut dell = output("DP-7");
+        dell.model = "DELL U2720Q".to_string();
+        let outputs = [dell, output("HDMI-A-1"), output("eDP-1")];
+        let wallpaper = |index: usize| {
+            config
+                .get_output_config(&outputs[index], &outputs)
+                .and_then(|section| section.wallpaper.as_deref())
+        };
+
+        // The first matching section applies, whichever connector the monitor is on
+        assert_eq!(wallpaper(0), Some("~/dell.jpg"));
+        assert_eq!(wallpaper(1), Some("~/external.jpg"));
+        assert_eq!(wallpaper(2), None);
+
+        let mut invalid = config.clone();
+        invalid.output[0].name = "model:[".to_string();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_orientation_settings() {
+        let config: Config = toml::from_str(
+            r#"
+            [playlist]
+            orientation = "portrait"
+
+            [[output]]
+            name = "DP-1"
+            wallpaper = "~/wide.jpg"
+            portrait_wallpaper = "~/tall.jpg"
+
+            [[output]]
+            name = "HDMI-A-1"
+            wallpaper = "~/wide.jpg"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(
+            config.playlist.as_ref().unwrap().orientation().unwrap(),
+            Some(Orientation::Portrait)
+        );
+
+        let outputs = [output("DP-1"), output("HDMI-A-1")];
+        let rotated = config.get_output_config(&outputs[0], &outputs).unwrap();
+        assert_eq!(
+            rotated.wallpaper_for(Orientation::Portrait),
+            Some("~/tall.jpg")
+        );
+        assert_eq!(
+            rotated.wallpaper_for(Orientation::Landscape),
+            Some("~/wide.jpg")
+        );
+
+        // Outputs without a portrait wallpaper show theirs either way
+        let fixed = config.get_output_config(&outputs[1], &outputs).unwrap();
+        assert_eq!(
+            fixed.wallpaper_for(Orientation::Portrait),
+            Some("~/wide.jpg")
+        );
+
+        let mut invalid = config.clone();
+        invalid.playlist.as_mut().unwrap().orientation = Some("diagonal".to_string());
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_hdr() {
+        let config: Config = toml::from_str(
+            r#"
+            [hdr]
+            tone_map = "hable"
+            exposure = -1.5
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert!(config.hdr.ten_bit);
+        assert_eq!(
+            config.tone_mapping(),
+            crate::hdr::ToneMapping {
+                operator: common::ToneMap::Hable,
+                exposure: -1.5,
+            }
+        );
+        assert_eq!(
+            Config::default().tone_mapping(),
+            crate::hdr::ToneMapping::default()
+        );
+
+        let mut invalid = Config::default();
+        invalid.hdr.tone_map = "gamma".to_string();
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = Config::default();
+        invalid.hdr.exposure = f32::NAN;
+        assert!(invalid.validate().is_err());
+        assert_eq!(invalid.tone_mapping().exposure, 0.0);
+    }
+
+    #[test]
+    fn test_video_crossfade() {
+        assert!(Config::default().advanced.video_crossfade().is_zero());
+
+        let config: Config = toml::from_str(
+            r#"
+            [advanced]
+            video_crossfade_ms = 750
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.advanced.video_crossfade(),
+            std::time::Duration::from_millis(750)
+        );
+    }
+
+    #[test]
+    fn test_video_volume() {
+        let volume = Config::default().advanced.video_volume();
+        assert!(volume.muted);
+        assert_eq!(volume.level, 1.0);
+
+        let config: Config = toml::from_str(
+            r#"
+            [advanced]
+            video_muted = false
+            video_volume = 0.6
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.advanced.video_volume().audible(), 0.6);
+        assert!(config.validate().is_ok());
+
+        let config: Config = toml::from_str(
+            r#"
+            [advanced]
+            video_duck_volume = 1.5
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_err());
+    }
+
     #[test]
     fn test_validate_transition() {
         let config = Config::default();
@@ -0,0 +1,151 @@
+//! Parallel CPU rendering
+//!
+//! The software fallbacks for shaders, transitions and overlays work on
+//! ARGB8888 frames row by row. These helpers split a frame into bands of rows
+//! and process the bands on rayon's global pool, passing each row its `y` so
+//! the per-pixel maths is the same as in a plain loop.
+//!
+//! With the `simd` feature the blending loop is also compiled for the SIMD
+//! extensions of the target (e.g. AVX2), picked at runtime.
+
+use rayon::prelude::*;
+
+/// Rows per work item (enough that scheduling costs little next to the pixels)
+const BAND_ROWS: usize = 16;
+
+/// Bytes per work item when blending whole frames
+const BLEND_CHUNK: usize = 64 * 1024;
+
+/// Render a new `width`x`height` frame, calling `render_row(y, row)` for each row
+pub fn render_rows<F>(width: u32, height: u32, render_row: F) -> Vec<u8>
+where
+    F: Fn(u32, &mut [u8]) + Sync,
+{
+    let mut buffer = vec![0u8; width as usize * height as usize * 4];
+    for_each_row(&mut buffer, width, render_row);
+    buffer
+}
+
+/// Update a frame in place, calling `update_row(y, row)` for each row
+pub fn for_each_row<F>(buffer: &mut [u8], width: u32, update_row: F)
+where
+    F: Fn(u32, &mut [u8]) + Sync,
+{
+    let stride = width as usize * 4;
+    if stride == 0 {
+        return;
+    }
+
+    buffer
+        .par_chunks_mut(stride * BAND_ROWS)
+        .enumerate()
+        .for_each(|(band, rows)| {
+            for (i, row) in rows.chunks_mut(stride).enumerate() {
+                update_row((band * BAND_ROWS + i) as u32, row);
+            }
+        });
+}
+
+/// Like [`for_each_row`], also passing the same row of `source` (a frame of the same size)
+pub fn for_each_row_with<F>(buffer: &mut [u8], source: &[u8], width: u32, update_row: F)
+where
+    F: Fn(u32, &mut [u8], &[u8]) + Sync,
+{
+    let stride = width as usize * 4;
+    if stride == 0 {
+        return;
+    }
+
+    buffer
+        .par_chunks_mut(stride * BAND_ROWS)
+        .zip(source.par_chunks(stride * BAND_ROWS))
+        .enumerate()
+        .for_each(|(band, (rows, source_rows))| {
+            for (i, (row, source_row)) in rows
+                .chunks_mut(stride)
+                .zip(source_rows.chunks(stride))
+                .enumerate()
+            {
+                update_row((band * BAND_ROWS + i) as u32, row, source_row);
+            }
+        });
+}
+
+/// Blend `buffer` towards `target` by `t` (0.0 keeps `buffer`, 1.0 gives `target`)
+pub fn mix(buffer: &mut [u8], target: &[u8], t: f32) {
+    // 8-bit fixed point weight, so 1.0 reproduces `target` exactly
+    let weight = (t.clamp(0.0, 1.0) * 256.0).round() as u16;
+
+    buffer
+        .par_chunks_mut(BLEND_CHUNK)
+        .zip(target.par_chunks(BLEND_CHUNK))
+        .for_each(|(buffer, target)| mix_chunk(buffer, target, weight));
+}
+
+#[cfg_attr(feature = "simd", multiversion::multiversion(targets = "simd"))]
+fn mix_chunk(buffer: &mut [u8], target: &[u8], weight: u16) {
+    for (out, &to) in buffer.iter_mut().zip(target) {
+        *out = ((*out as u16 * (256 - weight) + to as u16 * weight) >> 8) as u8;
+    }
+}
+
+/// Like [`mix`] towards a frame filled with one ARGB8888 `color`
+pub fn mix_color(buffer: &mut [u8], color: [u8; 4], t: f32) {
+    let weight = (t.clamp(0.0, 1.0) * 256.0).round() as u16;
+
+    buffer
+        .par_chunks_mut(BLEND_CHUNK)
+        .for_each(|buffer| mix_color_chunk(buffer, color, weight));
+}
+
+#[cfg_attr(feature = "simd", multiversion::multiversion(targets = "simd"))]
+fn mix_color_chunk(buffer: &mut [u8], color: [u8; 4], weight: u16) {
+    for pixel in buffer.chunks_exact_mut(4) {
+        for (out, &to) in pixel.iter_mut().zip(&color) {
+            *out = ((*out as u16 * (256 - weight) + to as u16 * weight) >> 8) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_get_their_index() {
+        // More rows than one band, with a partial last band
+        let (width, height) = (3, BAND_ROWS as u32 * 2 + 5);
+        let frame = render_rows(width, height, |y, row| row.fill(y as u8));
+
+        for (y, row) in frame.chunks(width as usize * 4).enumerate() {
+            assert!(row.iter().all(|&v| v == y as u8));
+        }
+
+        let mut copy = vec![0; frame.len()];
+        for_each_row_with(&mut copy, &frame, width, |y, row, source| {
+            assert_eq!(source[0], y as u8);
+            row.copy_from_slice(source);
+        });
+        assert_eq!(copy, frame);
+    }
+
+    #[test]
+    fn test_mix() {
+        let from = vec![0, 100, 200, 255];
+        let to = vec![255, 100, 0, 255];
+
+        let mut buffer = from.clone();
+        mix(&mut buffer, &to, 0.0);
+        assert_eq!(buffer, from);
+
+        mix(&mut buffer, &to, 0.5);
+        assert_eq!(buffer, vec![127, 100, 100, 255]);
+
+        mix(&mut buffer, &to, 1.0);
+        assert_eq!(buffer, to);
+
+        let mut buffer = from.clone();
+        mix_color(&mut buffer, [255, 100, 0, 255], 0.5);
+        assert_eq!(buffer, vec![127, 100, 100, 255]);
+    }
+}
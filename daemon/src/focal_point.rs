@@ -0,0 +1,166 @@
+//! Focal points for Fill cropping
+//!
+//! Fill scales an image to cover the output and crops what sticks out. Instead
+//! of always keeping the centre, the crop is placed around the image's focal
+//! point, which comes from (in order of preference):
+//!
+//! 1. the `SetWallpaper` command,
+//! 2. a sidecar file next to the image (`photo.jpg.focus` containing `x,y`),
+//! 3. the `[focal_points]` table in the config,
+//! 4. the image itself: the centroid of its edge energy, which sits on the
+//!    detailed subject rather than on sky, walls or blurred backgrounds.
+
+use common::FocalPoint;
+use image::DynamicImage;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Longest side of the thumbnail analysed by [`detect`]
+const ANALYSIS_SIZE: u32 = 128;
+
+/// Find the focal point given for an image by a sidecar file or the config
+///
+/// Returns None when there is none, in which case it is detected when scaling.
+pub fn configured(path: &Path, config_points: &BTreeMap<String, FocalPoint>) -> Option<FocalPoint> {
+    from_sidecar(path).or_else(|| {
+        config_points
+            .iter()
+            .find(|(configured, _)| Path::new(&*shellexpand::tilde(configured)) == path)
+            .map(|(_, focus)| *focus)
+    })
+}
+
+/// Read `<image>.focus`, if present
+pub fn from_sidecar(path: &Path) -> Option<FocalPoint> {
+    let mut sidecar = path.as_os_str().to_owned();
+    sidecar.push(".focus");
+
+    let contents = std::fs::read_to_string(&sidecar).ok()?;
+    match contents.trim().parse() {
+        Ok(focus) => Some(focus),
+        Err(e) => {
+            log::warn!("Ignoring {}: {}", Path::new(&sidecar).display(), e);
+            None
+        }
+    }
+}
+
+/// Estimate where the subject of an image is from its edge energy
+pub fn detect(image: &DynamicImage) -> FocalPoint {
+    let luma = image.thumbnail(ANALYSIS_SIZE, ANALYSIS_SIZE).to_luma8();
+    let (width, height) = luma.dimensions();
+    if width < 3 || height < 3 {
+        return FocalPoint::CENTER;
+    }
+
+    let value = |x: u32, y: u32| luma.get_pixel(x, y)[0] as f64;
+    let mut total = 0.0;
+    let mut sum_x = 0.0;
+    let mut sum_y = 0.0;
+
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let gx = value(x + 1, y) - value(x - 1, y);
+            let gy = value(x, y + 1) - value(x, y - 1);
+
+            // Squared gradient magnitude, so strong edges outweigh texture and noise
+            let energy = gx * gx + gy * gy;
+            total += energy;
+            sum_x += energy * (x as f64 + 0.5);
+            sum_y += energy * (y as f64 + 0.5);
+        }
+    }
+
+    // A flat image has nothing to focus on
+    if total == 0.0 {
+        return FocalPoint::CENTER;
+    }
+
+    FocalPoint {
+        x: (sum_x / total / width as f64) as f32,
+        y: (sum_y / total / height as f64) as f32,
+    }
+}
+
+/// Offset of a `target`-sized crop of a `scaled`-sized image, centred on `focus` where possible
+pub fn crop_offset(scaled: (u32, u32), target: (u32, u32), focus: FocalPoint) -> (u32, u32) {
+    let offset = |scaled: u32, target: u32, focus: f32| {
+        let max = scaled.saturating_sub(target);
+        let centred = focus.clamp(0.0, 1.0) * scaled as f32 - target as f32 / 2.0;
+        (centred.round().max(0.0) as u32).min(max)
+    };
+
+    (
+        offset(scaled.0, target.0, focus.x),
+        offset(scaled.1, target.1, focus.y),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crop_offset() {
+        let focus = |x, y| FocalPoint { x, y };
+
+        // Centre matches the old behaviour
+        assert_eq!(
+            crop_offset((300, 100), (100, 100), FocalPoint::CENTER),
+            (100, 0)
+        );
+        // Points near the edges clamp so the crop stays inside the image
+        assert_eq!(crop_offset((300, 100), (100, 100), focus(0.0, 0.0)), (0, 0));
+        assert_eq!(
+            crop_offset((300, 100), (100, 100), focus(1.0, 1.0)),
+            (200, 0)
+        );
+        assert_eq!(
+            crop_offset((300, 100), (100, 100), focus(0.25, 0.5)),
+            (25, 0)
+        );
+        assert_eq!(
+            crop_offset((100, 400), (100, 100), focus(0.9, 0.75)),
+            (0, 250)
+        );
+    }
+
+    #[test]
+    fn test_detect_finds_detail() {
+        // Flat grey with a checkered patch in the top-right quarter
+        let mut image = image::RgbImage::from_pixel(200, 100, image::Rgb([128, 128, 128]));
+        for y in 10..40 {
+            for x in 140..180 {
+                let value = if (x / 4 + y / 4) % 2 == 0 { 0 } else { 255 };
+                image.put_pixel(x, y, image::Rgb([value, value, value]));
+            }
+        }
+
+        let focus = detect(&DynamicImage::ImageRgb8(image));
+        assert!((0.65..0.95).contains(&focus.x), "{:?}", focus);
+        assert!((0.05..0.45).contains(&focus.y), "{:?}", focus);
+
+        let flat = DynamicImage::new_rgb8(64, 64);
+        assert_eq!(detect(&flat), FocalPoint::CENTER);
+    }
+
+    #[test]
+    fn test_sidecar_and_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+        let config = BTreeMap::from([(path.display().to_string(), FocalPoint { x: 0.1, y: 0.2 })]);
+
+        assert_eq!(configured(&path, &BTreeMap::new()), None);
+        assert_eq!(
+            configured(&path, &config),
+            Some(FocalPoint { x: 0.1, y: 0.2 })
+        );
+
+        // The sidecar wins over the config
+        std::fs::write(dir.path().join("photo.jpg.focus"), "0.7, 0.4\n").unwrap();
+        assert_eq!(
+            configured(&path, &config),
+            Some(FocalPoint { x: 0.7, y: 0.4 })
+        );
+    }
+}
@@ -0,0 +1,345 @@
+//! Where video frames land on an output
+//!
+//! Videos are scaled like images: Fill covers the output and crops the middle
+//! of the frame, Fit shows all of it between letterbox bars, Center keeps it at
+//! its own size and Tile repeats it from the top left corner. A layout maps
+//! positions on the output to positions in the frame; the GPU scale pass gets
+//! it as a uniform and the CPU path samples frames with the same arithmetic.
+
+use common::{Letterbox, ScaleMode};
+
+/// Brightness of blurred letterboxes, so the frame itself stands out
+pub const BLUR_BRIGHTNESS: f32 = 0.5;
+
+/// Samples per axis averaged into a blurred letterbox pixel (video.wgsl takes
+/// the same 5x5 grid)
+pub const BLUR_TAPS: i32 = 5;
+
+/// Distance between those samples, as a fraction of the frame
+pub const BLUR_STEP: f32 = 0.03;
+
+/// Blurred letterboxes are worked out on the CPU at this fraction of the output
+/// size, then scaled up
+const CPU_BLUR_DOWNSCALE: u32 = 8;
+
+/// How a frame is placed on an output, and the background around it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Placement {
+    pub mode: ScaleMode,
+    pub letterbox: Letterbox,
+}
+
+/// Mapping from output positions to frame positions, both as fractions of
+/// their size: `frame = output * scale - offset`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameLayout {
+    pub scale: [f32; 2],
+    pub offset: [f32; 2],
+    /// Repeat the frame instead of showing the letterbox around it
+    pub tile: bool,
+}
+
+impl FrameLayout {
+    /// Layout of a `source`-sized frame on a `target`-sized output
+    pub fn new(mode: ScaleMode, source: (u32, u32), target: (u32, u32)) -> Self {
+        let (source_width, source_height) = (source.0.max(1) as f32, source.1.max(1) as f32);
+        let (target_width, target_height) = (target.0.max(1) as f32, target.1.max(1) as f32);
+
+        let zoom = match mode {
+            ScaleMode::Stretch => {
+                return Self {
+                    scale: [1.0, 1.0],
+                    offset: [0.0, 0.0],
+                    tile: false,
+                };
+            }
+            ScaleMode::Fill => (target_width / source_width).max(target_height / source_height),
+            ScaleMode::Fit => (target_width / source_width).min(target_height / source_height),
+            ScaleMode::Center | ScaleMode::Tile => 1.0,
+        };
+
+        // Size of the frame on the output, and where its top left corner is
+        let (width, height) = (source_width * zoom, source_height * zoom);
+        let (x, y) = match mode {
+            ScaleMode::Tile => (0.0, 0.0),
+            // Whole pixels, like centred images
+            ScaleMode::Center => (
+                ((target_width - width) / 2.0).floor(),
+                ((target_height - height) / 2.0).floor(),
+            ),
+            _ => ((target_width - width) / 2.0, (target_height - height) / 2.0),
+        };
+
+        Self {
+            scale: [target_width / width, target_height / height],
+            offset: [x / width, y / height],
+            tile: mode == ScaleMode::Tile,
+        }
+    }
+
+    /// Frame position shown at an output position
+    pub fn frame_position(&self, [x, y]: [f32; 2]) -> [f32; 2] {
+        let position = [
+            x * self.scale[0] - self.offset[0],
+            y * self.scale[1] - self.offset[1],
+        ];
+        if self.tile {
+            position.map(|v| v.rem_euclid(1.0))
+        } else {
+            position
+        }
+    }
+}
+
+/// Check if a frame position is on the frame (rather than on the letterbox)
+pub fn on_frame([x, y]: [f32; 2]) -> bool {
+    (0.0..=1.0).contains(&x) && (0.0..=1.0).contains(&y)
+}
+
+/// Reflect a frame position back onto the frame, mirroring it at its edges
+pub fn mirror(position: [f32; 2]) -> [f32; 2] {
+    position.map(|v| 1.0 - ((v * 0.5).rem_euclid(1.0) * 2.0 - 1.0).abs())
+}
+
+/// Replace a dominant-colour letterbox with the colour of a BGRA frame
+pub fn resolve_letterbox(letterbox: Letterbox, frame: &[u8]) -> Letterbox {
+    match letterbox {
+        Letterbox::Dominant => Letterbox::Color(dominant_color(frame)),
+        other => other,
+    }
+}
+
+/// Most common colour of a BGRA frame (black if it is fully transparent)
+pub fn dominant_color(frame: &[u8]) -> [u8; 3] {
+    // BGRA bytes are ARGB8888 on little-endian machines
+    crate::palette::dominant(frame).unwrap_or([0; 3])
+}
+
+/// Scale a BGRA frame to an output on the CPU
+pub fn scale_frame(
+    frame: &[u8],
+    source: (u32, u32),
+    target: (u32, u32),
+    placement: Placement,
+) -> Vec<u8> {
+    let (width, height) = target;
+    let frame = Frame {
+        pixels: frame,
+        width: source.0,
+        height: source.1,
+    };
+    let complete = frame.pixels.len() >= source.0 as usize * source.1 as usize * 4;
+    if source == target || source.0 == 0 || source.1 == 0 || !complete {
+        return frame.pixels.to_vec();
+    }
+
+    let layout = FrameLayout::new(placement.mode, source, target);
+    let letterbox = resolve_letterbox(placement.letterbox, frame.pixels);
+    let blurred = (letterbox == Letterbox::Blur).then(|| blurred_background(&frame, target));
+    let blurred = blurred.as_ref().map(|(pixels, (width, height))| Frame {
+        pixels,
+        width: *width,
+        height: *height,
+    });
+
+    crate::cpu_render::render_rows(width, height, |y, row| {
+        let v = (y as f32 + 0.5) / height as f32;
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let u = (x as f32 + 0.5) / width as f32;
+            let position = layout.frame_position([u, v]);
+
+            let color = if on_frame(position) {
+                frame.sample(position)
+            } else {
+                match (letterbox, &blurred) {
+                    (Letterbox::Mirror, _) => frame.sample(mirror(position)),
+                    (Letterbox::Blur, Some(blurred)) => blurred.sample([u, v]),
+                    (Letterbox::Color([r, g, b]), _) => [b, g, r, 255],
+                    _ => [0, 0, 0, 255],
+                }
+            };
+            pixel.copy_from_slice(&color);
+        }
+    })
+}
+
+/// A blurred, darkened copy of a frame covering the output, at a fraction of its size
+fn blurred_background(frame: &Frame, (width, height): (u32, u32)) -> (Vec<u8>, (u32, u32)) {
+    let small_width = (width / CPU_BLUR_DOWNSCALE).max(1);
+    let small_height = (height / CPU_BLUR_DOWNSCALE).max(1);
+    let cover = FrameLayout::new(
+        ScaleMode::Fill,
+        (frame.width, frame.height),
+        (width, height),
+    );
+
+    let pixels = crate::cpu_render::render_rows(small_width, small_height, |y, row| {
+        let v = (y as f32 + 0.5) / small_height as f32;
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let u = (x as f32 + 0.5) / small_width as f32;
+            pixel.copy_from_slice(&frame.blur(cover.frame_position([u, v])));
+        }
+    });
+    (pixels, (small_width, small_height))
+}
+
+/// A BGRA frame sampled like a texture: bilinearly, clamped to its edges
+struct Frame<'a> {
+    pixels: &'a [u8],
+    width: u32,
+    height: u32,
+}
+
+impl Frame<'_> {
+    fn sample(&self, [u, v]: [f32; 2]) -> [u8; 4] {
+        let x = (u * self.width as f32 - 0.5).clamp(0.0, (self.width - 1) as f32);
+        let y = (v * self.height as f32 - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x as u32, y as u32);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x.fract(), y.fract());
+
+        let pixel = |x: u32, y: u32| {
+            let index = (y as usize * self.width as usize + x as usize) * 4;
+            &self.pixels[index..index + 4]
+        };
+        let (top_left, top_right) = (pixel(x0, y0), pixel(x1, y0));
+        let (bottom_left, bottom_right) = (pixel(x0, y1), pixel(x1, y1));
+
+        std::array::from_fn(|c| {
+            let top = top_left[c] as f32 * (1.0 - fx) + top_right[c] as f32 * fx;
+            let bottom = bottom_left[c] as f32 * (1.0 - fx) + bottom_right[c] as f32 * fx;
+            (top * (1.0 - fy) + bottom * fy).round() as u8
+        })
+    }
+
+    /// Average of the samples around a position, darkened
+    fn blur(&self, [u, v]: [f32; 2]) -> [u8; 4] {
+        let reach = BLUR_TAPS / 2;
+        let mut sum = [0.0f32; 3];
+        for j in -reach..=reach {
+            for i in -reach..=reach {
+                let sample = self.sample([u + i as f32 * BLUR_STEP, v + j as f32 * BLUR_STEP]);
+                for (total, &value) in sum.iter_mut().zip(&sample[..3]) {
+                    *total += value as f32;
+                }
+            }
+        }
+
+        let scale = BLUR_BRIGHTNESS / (BLUR_TAPS * BLUR_TAPS) as f32;
+        let [b, g, r] = sum.map(|total| (total * scale).round() as u8);
+        [b, g, r, 255]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: [f32; 2], b: [f32; 2]) -> bool {
+        a.iter().zip(&b).all(|(a, b)| (a - b).abs() < 1e-5)
+    }
+
+    fn placement(mode: ScaleMode, letterbox: Letterbox) -> Placement {
+        Placement { mode, letterbox }
+    }
+
+    #[test]
+    fn test_layouts() {
+        // A 16:9 frame on a 4:3 output
+        let (source, target) = ((1600, 900), (1200, 900));
+
+        // Fill crops the sides: the middle three quarters of the frame show
+        let fill = FrameLayout::new(ScaleMode::Fill, source, target);
+        assert!(close(fill.frame_position([0.0, 0.0]), [0.125, 0.0]));
+        assert!(close(fill.frame_position([1.0, 1.0]), [0.875, 1.0]));
+
+        // Fit leaves bars above and below the 1200x675 frame
+        let fit = FrameLayout::new(ScaleMode::Fit, source, target);
+        assert!(close(fit.frame_position([0.5, 0.5]), [0.5, 0.5]));
+        assert!(!on_frame(fit.frame_position([0.5, 0.05])));
+        assert!(on_frame(fit.frame_position([0.5, 0.2])));
+
+        // Center shows the frame 1:1, cropped on this smaller output
+        let center = FrameLayout::new(ScaleMode::Center, source, target);
+        assert!(close(center.frame_position([0.0, 0.0]), [0.125, 0.0]));
+        assert!(close(center.frame_position([1.0, 0.0]), [0.875, 0.0]));
+
+        // Tile repeats a small frame from the top left
+        let tile = FrameLayout::new(ScaleMode::Tile, (300, 300), target);
+        assert!(close(tile.frame_position([0.0, 0.0]), [0.0, 0.0]));
+        assert!(close(tile.frame_position([0.375, 0.5]), [0.5, 0.5]));
+
+        let stretch = FrameLayout::new(ScaleMode::Stretch, source, target);
+        assert!(close(stretch.frame_position([0.3, 0.7]), [0.3, 0.7]));
+    }
+
+    #[test]
+    fn test_scale_frame() {
+        // Blue on the left, red on the right (BGRA)
+        let frame = [255, 0, 0, 255, 0, 0, 255, 255];
+        let pixel = |frame: &[u8], x: usize, y: usize| {
+            let index = (y * 4 + x) * 4;
+            frame[index..index + 4].to_vec()
+        };
+
+        // Fill shows the middle of the frame, where the colours blend
+        let fill = scale_frame(
+            &frame,
+            (2, 1),
+            (4, 4),
+            placement(ScaleMode::Fill, Letterbox::Mirror),
+        );
+        let (left, right) = (pixel(&fill, 0, 0), pixel(&fill, 3, 3));
+        assert!(
+            left[0] > left[2] && right[2] > right[0],
+            "{:?}",
+            (left, right)
+        );
+
+        let fit = scale_frame(
+            &frame,
+            (2, 1),
+            (4, 4),
+            placement(ScaleMode::Fit, Letterbox::Color([1, 2, 3])),
+        );
+        assert_eq!(pixel(&fit, 0, 0), [3, 2, 1, 255]);
+        assert_eq!(pixel(&fit, 0, 1), [255, 0, 0, 255]);
+        assert_eq!(pixel(&fit, 3, 2), [0, 0, 255, 255]);
+        assert_eq!(pixel(&fit, 3, 3), [3, 2, 1, 255]);
+
+        // Bars mirror the rows next to them, or are a darker blur of the frame
+        let mirrored = scale_frame(
+            &frame,
+            (2, 1),
+            (4, 4),
+            placement(ScaleMode::Fit, Letterbox::Mirror),
+        );
+        assert_eq!(pixel(&mirrored, 0, 0), [255, 0, 0, 255]);
+        let blurred = scale_frame(
+            &frame,
+            (2, 1),
+            (4, 4),
+            placement(ScaleMode::Fit, Letterbox::Blur),
+        );
+        let bar = pixel(&blurred, 0, 0);
+        assert!(bar[0] < 255 && bar[3] == 255, "{:?}", bar);
+
+        // Frames already at the output's size are passed through
+        assert_eq!(
+            scale_frame(
+                &frame,
+                (2, 1),
+                (2, 1),
+                placement(ScaleMode::Fit, Letterbox::Blur)
+            ),
+            frame
+        );
+    }
+
+    #[test]
+    fn test_mirror() {
+        assert!(close(mirror([0.25, 0.75]), [0.25, 0.75]));
+        assert!(close(mirror([-0.25, 1.25]), [0.25, 0.75]));
+        assert!(close(mirror([2.25, -1.0]), [0.25, 1.0]));
+    }
+}
@@ -1,5 +1,5 @@
 /// GPU context management - handles wgpu device/queue initialization
-use anyhow::{Context, Result};
+use anyhow::Result;
 use wgpu;
 
 /// GPU context containing device, queue, and adapter info
@@ -32,7 +32,9 @@ impl GpuContext {
                 force_fallback_adapter: false,
             })
             .await
-            .context("Failed to find suitable GPU adapter")?;
+            .map_err(|e| common::WallpaperError::GpuUnavailable {
+                reason: format!("no suitable adapter: {}", e),
+            })?;
 
         let adapter_info = adapter.get_info();
         log::info!(
@@ -52,7 +54,9 @@ impl GpuContext {
                 trace: wgpu::Trace::default(),
             })
             .await
-            .context("Failed to create GPU device")?;
+            .map_err(|e| common::WallpaperError::GpuUnavailable {
+                reason: format!("failed to create device: {}", e),
+            })?;
 
         let limits = device.limits();
 
@@ -14,6 +14,8 @@ pub struct GpuRenderer {
     blit_pipeline: wgpu::RenderPipeline,
     /// Render pipeline for image scaling
     scale_pipeline: wgpu::RenderPipeline,
+    /// Render pipeline placing video frames per their scale mode
+    video_pipeline: wgpu::RenderPipeline,
     /// Render pipeline for plasma shader
     plasma_pipeline: wgpu::RenderPipeline,
     /// Render pipeline for waves shader
@@ -45,15 +47,33 @@ pub struct GpuRenderer {
     /// Sampler for texture sampling
     #[allow(dead_code)] // Sampler used by overlay and other features
     sampler: wgpu::Sampler,
-    /// Video buffer pools for async readback, keyed by resolution (width, height)
-    /// Each resolution needs its own buffer pool for proper double buffering
-    video_buffer_pools: Arc<Mutex<std::collections::HashMap<(u32, u32), VideoBufferPool>>>,
+    /// Video buffer pools for async readback, keyed by output resolution and placement
+    /// Each target needs its own buffer pool for proper double buffering
+    video_buffer_pools: Arc<Mutex<std::collections::HashMap<VideoTarget, VideoBufferPool>>>,
     /// Source texture pool: shared across all scaling operations with the same source resolution
     /// Keyed by (source_w, source_h). Only ONE source texture per source resolution.
     video_source_textures: Arc<Mutex<std::collections::HashMap<(u32, u32), GpuTexture>>>,
-    /// Target texture pool: one render target per output resolution
-    /// Keyed by (target_w, target_h). These are where scaled frames are rendered.
-    video_target_textures: Arc<Mutex<std::collections::HashMap<(u32, u32), GpuTexture>>>,
+    /// Target texture pool: one render target per output resolution and placement
+    /// These are where scaled frames are rendered.
+    video_target_textures: Arc<Mutex<std::collections::HashMap<VideoTarget, GpuTexture>>>,
+
+    /// Layout uniforms for each scaled render target, rewritten every frame
+    video_layouts: Arc<Mutex<std::collections::HashMap<VideoTarget, VideoLayout>>>,
+}
+
+/// Where a video frame is rendered to: outputs of the same size that place
+/// frames differently get their own target and readback buffers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct VideoTarget {
+    size: (u32, u32),
+    placement: crate::frame_layout::Placement,
+}
+
+/// Uniform buffer placing video frames on one render target
+#[cfg_attr(not(feature = "video"), allow(dead_code))] // only videos are placed this way
+struct VideoLayout {
+    buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
 }
 
 impl GpuRenderer {
@@ -121,6 +141,19 @@ impl GpuRenderer {
                     entries: &[bind_group_entries::uniform_buffer(0)],
                 });
 
+        // Create video scaling pipeline (frame texture + layout uniforms)
+        let video_pipeline_layout = create_pipeline_layout(
+            &context.device,
+            "Video Pipeline Layout",
+            &[&texture_bind_group_layout, &shader_uniform_layout],
+        );
+
+        let video_pipeline =
+            PipelineBuilder::new(&context.device, include_str!("shaders/video.wgsl"))
+                .with_label("Video Pipeline")
+                .with_layout(&video_pipeline_layout)
+                .build();
+
         // Create plasma shader pipeline
         let plasma_pipeline_layout = create_pipeline_layout(
             &context.device,
@@ -235,6 +268,7 @@ impl GpuRenderer {
             context,
             blit_pipeline,
             scale_pipeline,
+            video_pipeline,
             plasma_pipeline,
             waves_pipeline,
             gradient_pipeline,
@@ -252,6 +286,7 @@ impl GpuRenderer {
             video_buffer_pools: Arc::new(Mutex::new(std::collections::HashMap::new())),
             video_source_textures: Arc::new(Mutex::new(std::collections::HashMap::new())),
             video_target_textures: Arc::new(Mutex::new(std::collections::HashMap::new())),
+            video_layouts: Arc::new(Mutex::new(std::collections::HashMap::new())),
         })
     }
 
@@ -521,41 +556,40 @@ impl GpuRenderer {
     /// BGRA data is uploaded directly to GPU without CPU conversion, rendered,
     /// and read back asynchronously to avoid GPU stalls.
     ///
-    /// Returns `None` if GPU readback is not ready yet (caller should reuse previous frame).
+    /// Takes BGRA data at source resolution and renders to target resolution.
+    /// If source == target, uses blit pipeline (fast copy). Otherwise the video
+    /// pipeline places the frame per the output's placement (GPU bilinear
+    /// filtering) and fills the rest with `letterbox`, the placement's
+    /// letterbox with any dominant colour worked out
+    /// (see [`crate::frame_layout::resolve_letterbox`]).
     ///
-    /// # Arguments
-    /// * `bgra_data` - Video frame in BGRA format from GStreamer
-    /// * `width` - Frame width
-    /// * `height` - Frame height
+    /// This enables single video decode pipeline for multiple output resolutions.
     ///
     /// # Returns
     /// * `Some(Vec<u8>)` - ARGB8 buffer for Wayland if ready
     /// * `None` - GPU not ready, reuse previous frame
-    /// Render video frame with optional GPU scaling
-    ///
-    /// Takes BGRA data at source resolution and renders to target resolution.
-    /// If source == target, uses blit pipeline (fast copy).
-    /// If source != target, uses scale pipeline (GPU bilinear filtering).
-    ///
-    /// This enables single video decode pipeline for multiple output resolutions.
     pub async fn render_video_frame_bgra(
         &self,
         bgra_data: &[u8],
-        source_width: u32,
-        source_height: u32,
-        target_width: u32,
-        target_height: u32,
+        (source_width, source_height): (u32, u32),
+        (target_width, target_height): (u32, u32),
+        placement: crate::frame_layout::Placement,
+        letterbox: common::Letterbox,
     ) -> Result<Option<Vec<u8>>> {
         log::trace!(
-            "GPU rendering video frame: {}x{} -> {}x{}",
+            "GPU rendering video frame: {}x{} -> {}x{} ({:?})",
             source_width,
             source_height,
             target_width,
-            target_height
+            target_height,
+            placement.mode
         );
 
         let source_key = (source_width, source_height);
-        let target_key = (target_width, target_height);
+        let target_key = VideoTarget {
+            size: (target_width, target_height),
+            placement,
+        };
 
         // Initialize buffer pool for target resolution (output size)
         let mut buffer_pools = self.video_buffer_pools.lock().await;
@@ -618,7 +652,7 @@ impl GpuRenderer {
         );
 
         let target_texture = target_textures.entry(target_key).or_insert_with(|| {
-            let scale_info = if source_key == target_key {
+            let scale_info = if source_key == target_key.size {
                 "no scaling (blit)"
             } else {
                 "with GPU scaling"
@@ -640,11 +674,16 @@ impl GpuRenderer {
             .expect("Failed to create target texture")
         });
 
-        // Choose pipeline: blit for same size, scale for different sizes
-        let pipeline = if source_key == target_key {
-            &self.blit_pipeline
+        // Choose pipeline: blit for same size, video layout for different sizes
+        let mut layouts = self.video_layouts.lock().await;
+        let layout_bind_group = if source_key == target_key.size {
+            None
         } else {
-            &self.scale_pipeline
+            Some(self.video_layout_bind_group(&mut layouts, source_key, target_key, letterbox))
+        };
+        let pipeline = match layout_bind_group {
+            Some(_) => &self.video_pipeline,
+            None => &self.blit_pipeline,
         };
 
         // Render: source texture -> target texture (scaling happens here)
@@ -674,6 +713,9 @@ impl GpuRenderer {
 
             render_pass.set_pipeline(pipeline);
             render_pass.set_bind_group(0, &source_texture.bind_group, &[]);
+            if let Some(layout_bind_group) = layout_bind_group {
+                render_pass.set_bind_group(1, layout_bind_group, &[]);
+            }
             render_pass.draw(0..3, 0..1); // Full-screen triangle
         }
 
@@ -695,6 +737,7 @@ impl GpuRenderer {
 
         // Now drop all locks
         drop(buffer_pools);
+        drop(layouts);
         drop(source_textures);
         drop(target_textures);
 
@@ -702,6 +745,81 @@ impl GpuRenderer {
         Ok(previous_frame)
     }
 
+    /// Uniforms placing a video frame on a render target (see shaders/video.wgsl)
+    ///
+    /// Each target keeps one uniform buffer, rewritten every frame since the
+    /// source size and a dominant-colour letterbox can change.
+    fn video_layout_bind_group<'a>(
+        &self,
+        layouts: &'a mut std::collections::HashMap<VideoTarget, VideoLayout>,
+        source: (u32, u32),
+        target: VideoTarget,
+        letterbox: common::Letterbox,
+    ) -> &'a wgpu::BindGroup {
+        use crate::frame_layout::{BLUR_BRIGHTNESS, BLUR_STEP, FrameLayout};
+
+        #[repr(C)]
+        #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+        struct LayoutUniforms {
+            scale: [f32; 2],
+            offset: [f32; 2],
+            cover_scale: [f32; 2],
+            cover_offset: [f32; 2],
+            background: [f32; 4],
+            tile: u32,
+            letterbox: u32,
+            blur_step: f32,
+            blur_brightness: f32,
+        }
+
+        let layout = FrameLayout::new(target.placement.mode, source, target.size);
+        let cover = FrameLayout::new(common::ScaleMode::Fill, source, target.size);
+        let [r, g, b] = match letterbox {
+            common::Letterbox::Color(color) => color,
+            _ => [0, 0, 0],
+        };
+        let uniforms = LayoutUniforms {
+            scale: layout.scale,
+            offset: layout.offset,
+            cover_scale: cover.scale,
+            cover_offset: cover.offset,
+            // The render target is sRGB, so the shader works in linear light
+            background: [srgb_to_linear(r), srgb_to_linear(g), srgb_to_linear(b), 1.0],
+            tile: layout.tile as u32,
+            letterbox: match letterbox {
+                common::Letterbox::Blur => 1,
+                common::Letterbox::Mirror => 2,
+                _ => 0,
+            },
+            blur_step: BLUR_STEP,
+            // Darken as much as the CPU path does in sRGB terms
+            blur_brightness: BLUR_BRIGHTNESS.powf(2.2),
+        };
+
+        let layout = layouts.entry(target).or_insert_with(|| {
+            let device = &self.context.device;
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Video Layout Uniforms"),
+                size: std::mem::size_of::<LayoutUniforms>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            });
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Video Layout Bind Group"),
+                layout: &self.shader_uniform_layout,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+            VideoLayout { buffer, bind_group }
+        });
+        self.context
+            .queue
+            .write_buffer(&layout.buffer, 0, bytemuck::cast_slice(&[uniforms]));
+        &layout.bind_group
+    }
+
     /// Get bind group layout for texture operations
     #[allow(dead_code)] // Part of public API for custom pipeline creation
     pub fn texture_bind_group_layout(&self) -> &wgpu::BindGroupLayout {
@@ -752,24 +870,7 @@ impl GpuRenderer {
             _ => anyhow::bail!("Unknown shader type: {}", shader_type),
         };
 
-        // Parse colors
-        let color1 = params
-            .color1
-            .as_ref()
-            .and_then(|c| common::ShaderParams::parse_color(c))
-            .unwrap_or((1.0, 0.0, 0.0)); // Default red
-
-        let color2 = params
-            .color2
-            .as_ref()
-            .and_then(|c| common::ShaderParams::parse_color(c))
-            .unwrap_or((0.0, 0.0, 1.0)); // Default blue
-
-        let color3 = params
-            .color3
-            .as_ref()
-            .and_then(|c| common::ShaderParams::parse_color(c))
-            .unwrap_or((0.0, 1.0, 0.0)); // Default green
+        let params = crate::shader_manager::ShaderUniforms::new(params);
 
         // Create uniform buffer with shader parameters
         #[repr(C)]
@@ -793,23 +894,26 @@ impl GpuRenderer {
             count: f32, // Using f32 since WGSL requires alignment
         }
 
+        let [color1_r, color1_g, color1_b] = params.color1;
+        let [color2_r, color2_g, color2_b] = params.color2;
+        let [color3_r, color3_g, color3_b] = params.color3;
         let uniforms = ShaderUniforms {
             time,
             width: width as f32,
             height: height as f32,
-            speed: params.speed.unwrap_or(1.0),
-            color1_r: color1.0,
-            color1_g: color1.1,
-            color1_b: color1.2,
-            scale: params.scale.unwrap_or(1.0),
-            color2_r: color2.0,
-            color2_g: color2.1,
-            color2_b: color2.2,
-            intensity: params.intensity.unwrap_or(1.0),
-            color3_r: color3.0,
-            color3_g: color3.1,
-            color3_b: color3.2,
-            count: params.count.unwrap_or(100) as f32,
+            speed: params.speed,
+            color1_r,
+            color1_g,
+            color1_b,
+            scale: params.scale,
+            color2_r,
+            color2_g,
+            color2_b,
+            intensity: params.intensity,
+            color3_r,
+            color3_g,
+            color3_b,
+            count: params.count,
         };
 
         let uniform_buffer =
@@ -882,7 +986,7 @@ impl GpuRenderer {
     /// Blend two ARGB frames for GPU-accelerated transitions
     ///
     /// # Arguments
-    /// * `old_frame` - Previous frame (ARGB8)
+    /// * `old_frame` - Previous frame (ARGB8), or the colour shown before (a 1x1 texture)
     /// * `new_frame` - New frame (ARGB8)
     /// * `width` - Frame width
     /// * `height` - Frame height
@@ -893,7 +997,7 @@ impl GpuRenderer {
     /// Blended ARGB8 buffer
     pub fn blend_frames(
         &self,
-        old_frame: &[u8],
+        old_frame: &crate::transition::OldFrame,
         new_frame: &[u8],
         width: u32,
         height: u32,
@@ -908,21 +1012,18 @@ impl GpuRenderer {
         );
 
         // Convert ARGB -> RGBA for GPU
-        let mut old_rgba = vec![0u8; old_frame.len()];
-        let mut new_rgba = vec![0u8; new_frame.len()];
-
-        for i in 0..(old_frame.len() / 4) {
-            let offset = i * 4;
+        let to_rgba = |argb: &[u8]| -> Vec<u8> {
+            argb.chunks_exact(4)
+                .flat_map(|px| [px[2], px[1], px[0], px[3]])
+                .collect()
+        };
+        let new_rgba = to_rgba(new_frame);
 
-            old_rgba[offset + 0] = old_frame[offset + 2]; // R
-            old_rgba[offset + 1] = old_frame[offset + 1]; // G
-            old_rgba[offset + 2] = old_frame[offset + 0]; // B
-            old_rgba[offset + 3] = old_frame[offset + 3]; // A
-            new_rgba[offset + 0] = new_frame[offset + 2]; // R
-            new_rgba[offset + 1] = new_frame[offset + 1]; // G
-            new_rgba[offset + 2] = new_frame[offset + 0]; // B
-            new_rgba[offset + 3] = new_frame[offset + 3]; // A
-        }
+        // A colour is a 1x1 texture, stretched over the frame by the sampler
+        let (old_rgba, (old_width, old_height)) = match old_frame {
+            crate::transition::OldFrame::Frame(frame) => (to_rgba(frame), (width, height)),
+            crate::transition::OldFrame::Color(pixel) => (to_rgba(pixel), (1, 1)),
+        };
 
         // Create textures from both frames
         let old_texture = GpuTexture::from_rgba(
@@ -930,8 +1031,8 @@ impl GpuRenderer {
             &self.context.queue,
             &self.texture_bind_group_layout,
             &self.sampler,
-            width,
-            height,
+            old_width,
+            old_height,
             &old_rgba,
         )?;
 
@@ -1217,3 +1318,13 @@ impl std::fmt::Debug for GpuRenderer {
             .finish()
     }
 }
+
+/// Decode an 8-bit sRGB channel to linear light
+fn srgb_to_linear(value: u8) -> f32 {
+    let v = value as f32 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
@@ -0,0 +1,90 @@
+// Video frame scaling shader
+// Places a frame on the output per its scale mode (see frame_layout.rs) and
+// fills what it doesn't cover with the letterbox background
+
+struct Layout {
+    // Frame position = output position * scale - offset (as fractions)
+    scale: vec2<f32>,
+    offset: vec2<f32>,
+    // The same for a copy of the frame covering the output (blurred letterboxes)
+    cover_scale: vec2<f32>,
+    cover_offset: vec2<f32>,
+    // Letterbox colour (linear)
+    background: vec4<f32>,
+    tile: u32,       // 1 = repeat the frame
+    letterbox: u32,  // 0 = colour, 1 = blur, 2 = mirror
+    blur_step: f32,
+    blur_brightness: f32,
+}
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+@group(0) @binding(0) var input_texture: texture_2d<f32>;
+@group(0) @binding(1) var texture_sampler: sampler;
+@group(1) @binding(0) var<uniform> frame_layout: Layout;
+
+const BLUR_REACH: i32 = 2; // 5x5 samples
+
+// Vertex shader - full-screen triangle
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+    var output: VertexOutput;
+
+    let x = f32((vertex_index << 1u) & 2u);
+    let y = f32(vertex_index & 2u);
+
+    output.position = vec4<f32>(x * 2.0 - 1.0, 1.0 - y * 2.0, 0.0, 1.0);
+    output.tex_coords = vec2<f32>(x, y);
+
+    return output;
+}
+
+// Explicit level, as samples are taken in non-uniform control flow
+fn sample_frame(uv: vec2<f32>) -> vec4<f32> {
+    return textureSampleLevel(input_texture, texture_sampler, uv, 0.0);
+}
+
+// Reflect a position back onto the frame at its edges
+fn mirror(uv: vec2<f32>) -> vec2<f32> {
+    return 1.0 - abs(fract(uv * 0.5) * 2.0 - 1.0);
+}
+
+fn blurred(uv: vec2<f32>) -> vec4<f32> {
+    var sum = vec3<f32>(0.0);
+    for (var j = -BLUR_REACH; j <= BLUR_REACH; j++) {
+        for (var i = -BLUR_REACH; i <= BLUR_REACH; i++) {
+            let step = vec2<f32>(f32(i), f32(j)) * frame_layout.blur_step;
+            sum += sample_frame(uv + step).rgb;
+        }
+    }
+
+    let taps = f32((2 * BLUR_REACH + 1) * (2 * BLUR_REACH + 1));
+    return vec4<f32>(sum / taps * frame_layout.blur_brightness, 1.0);
+}
+
+@fragment
+fn fs_main(input: VertexOutput) -> @location(0) vec4<f32> {
+    var uv = input.tex_coords * frame_layout.scale - frame_layout.offset;
+    if frame_layout.tile == 1u {
+        uv = fract(uv);
+    }
+
+    if all(uv >= vec2<f32>(0.0)) && all(uv <= vec2<f32>(1.0)) {
+        return sample_frame(uv);
+    }
+
+    switch frame_layout.letterbox {
+        case 1u: {
+            return blurred(input.tex_coords * frame_layout.cover_scale - frame_layout.cover_offset);
+        }
+        case 2u: {
+            return sample_frame(mirror(uv));
+        }
+        default: {
+            return frame_layout.background;
+        }
+    }
+}
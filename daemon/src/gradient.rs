@@ -0,0 +1,260 @@
+//! Gradient wallpapers
+//!
+//! Renders a [`common::Gradient`] to an ARGB8888 frame at an output's exact
+//! size. Linear gradients run along their angle and are stretched so the
+//! corners reach the first and last colour (like CSS), radial ones spread from
+//! the centre to the farthest corner and conic ones sweep around the centre.
+//!
+//! A gradient across a 4K screen has far more pixels than an 8-bit channel has
+//! levels, so smooth gradients show bands. Ordered dithering spreads the
+//! rounding error in a fixed 8x8 pattern, which hides them without the noise of
+//! random dithering (and renders the same every time).
+
+use anyhow::Result;
+use common::{Gradient, GradientKind};
+
+/// 8x8 Bayer matrix, thresholds 0..64
+pub const BAYER: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+/// A colour stop with its position resolved, channels in 0.0..=255.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Stop {
+    position: f32,
+    color: [f32; 3],
+}
+
+/// Render a gradient as a `width`x`height` ARGB8888 frame (BGRA in memory)
+pub fn render(gradient: &Gradient, width: u32, height: u32) -> Result<Vec<u8>> {
+    gradient.validate()?;
+    let stops = resolve_stops(gradient);
+
+    let (w, h) = (width as f32, height as f32);
+    let angle = gradient.angle.to_radians();
+    let (sin, cos) = angle.sin_cos();
+    // Length of the linear gradient line, so the corners land on 0.0 and 1.0
+    let line_length = (w * cos.abs() + h * sin.abs()).max(f32::EPSILON);
+    // Distance from the centre to the corners, where radial gradients end
+    let radius = (w * w + h * h).sqrt().max(f32::EPSILON) / 2.0;
+
+    let position = move |x: u32, y: u32| -> f32 {
+        // Pixel centre relative to the centre of the output
+        let dx = x as f32 + 0.5 - w / 2.0;
+        let dy = y as f32 + 0.5 - h / 2.0;
+
+        match gradient.kind {
+            GradientKind::Linear => (dx * cos + dy * sin) / line_length + 0.5,
+            GradientKind::Radial => (dx * dx + dy * dy).sqrt() / radius,
+            GradientKind::Conic => ((dy.atan2(dx) - angle) / std::f32::consts::TAU).rem_euclid(1.0),
+        }
+    };
+
+    let dither = gradient.dither;
+    Ok(crate::cpu_render::render_rows(width, height, |y, row| {
+        let bayer_row = &BAYER[y as usize % 8];
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let [r, g, b] = color_at(&stops, position(x as u32, y));
+
+            // Round, or move the rounding threshold around in the Bayer pattern
+            let threshold = if dither {
+                (bayer_row[x % 8] as f32 + 0.5) / 64.0
+            } else {
+                0.5
+            };
+            let quantize = |channel: f32| (channel + threshold).floor().clamp(0.0, 255.0) as u8;
+
+            pixel.copy_from_slice(&[quantize(b), quantize(g), quantize(r), 255]);
+        }
+    }))
+}
+
+/// Fill in missing stop positions like CSS: the ends default to 0.0 and 1.0,
+/// stops in between are spread evenly, and positions never go backwards
+fn resolve_stops(gradient: &Gradient) -> Vec<Stop> {
+    let count = gradient.stops.len();
+    let mut positions: Vec<Option<f32>> = gradient.stops.iter().map(|s| s.position).collect();
+    if let Some(first) = positions.first_mut() {
+        first.get_or_insert(0.0);
+    }
+    if let Some(last) = positions.last_mut() {
+        last.get_or_insert(1.0);
+    }
+
+    // Each position is at least the one before it
+    let mut previous = 0.0f32;
+    for position in positions.iter_mut().flatten() {
+        *position = position.max(previous);
+        previous = *position;
+    }
+
+    // Spread runs of missing positions between the known ones around them
+    let mut index = 0;
+    while index < count {
+        if positions[index].is_some() {
+            index += 1;
+            continue;
+        }
+
+        let start = index - 1;
+        let end = (index..count)
+            .find(|&i| positions[i].is_some())
+            .unwrap_or(count - 1);
+        let (from, to) = (
+            positions[start].unwrap_or(0.0),
+            positions[end].unwrap_or(1.0),
+        );
+        for (i, position) in positions.iter_mut().enumerate().take(end).skip(index) {
+            let t = (i - start) as f32 / (end - start) as f32;
+            *position = Some(from + (to - from) * t);
+        }
+        index = end;
+    }
+
+    gradient
+        .stops
+        .iter()
+        .zip(positions)
+        .map(|(stop, position)| {
+            let (r, g, b) = common::ShaderParams::parse_color(&stop.color).unwrap_or_default();
+            Stop {
+                position: position.unwrap_or(0.0),
+                color: [r * 255.0, g * 255.0, b * 255.0],
+            }
+        })
+        .collect()
+}
+
+/// Colour at position `t` (clamped to the first and last stops)
+fn color_at(stops: &[Stop], t: f32) -> [f32; 3] {
+    let Some(next) = stops.iter().position(|stop| stop.position > t) else {
+        return stops.last().map_or([0.0; 3], |stop| stop.color);
+    };
+    if next == 0 {
+        return stops[0].color;
+    }
+
+    let (from, to) = (stops[next - 1], stops[next]);
+    let mix = (t - from.position) / (to.position - from.position);
+    std::array::from_fn(|i| from.color[i] + (to.color[i] - from.color[i]) * mix)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(kind: GradientKind, angle: f32, stops: &[&str], dither: bool) -> Gradient {
+        Gradient {
+            kind,
+            angle,
+            stops: stops.iter().map(|stop| stop.parse().unwrap()).collect(),
+            dither,
+        }
+    }
+
+    /// BGRA pixel at (x, y)
+    fn pixel(frame: &[u8], width: u32, x: u32, y: u32) -> [u8; 4] {
+        let offset = (y * width + x) as usize * 4;
+        frame[offset..offset + 4].try_into().unwrap()
+    }
+
+    #[test]
+    fn test_resolve_stops() {
+        let positions = |stops: &[&str]| -> Vec<f32> {
+            resolve_stops(&gradient(GradientKind::Linear, 0.0, stops, false))
+                .iter()
+                .map(|stop| (stop.position * 1000.0).round() / 1000.0)
+                .collect()
+        };
+
+        assert_eq!(positions(&["#000000", "#ffffff"]), [0.0, 1.0]);
+        assert_eq!(
+            positions(&["#000000", "#000000", "#000000", "#000000@0.9", "#ffffff"]),
+            [0.0, 0.3, 0.6, 0.9, 1.0]
+        );
+        // Stops out of order are moved up to the previous one
+        assert_eq!(
+            positions(&["#000000@0.5", "#000000@0.2", "#ffffff"]),
+            [0.5, 0.5, 1.0]
+        );
+    }
+
+    #[test]
+    fn test_linear_radial_conic() {
+        // Left to right, black to red
+        let frame = render(
+            &gradient(GradientKind::Linear, 0.0, &["#000000", "#ff0000"], false),
+            256,
+            4,
+        )
+        .unwrap();
+        assert_eq!(pixel(&frame, 256, 0, 0), [0, 0, 0, 255]);
+        assert_eq!(pixel(&frame, 256, 255, 3), [0, 0, 255, 255]);
+        assert_eq!(pixel(&frame, 256, 128, 1)[2], 128);
+
+        // Top to bottom
+        let frame = render(
+            &gradient(GradientKind::Linear, 90.0, &["#0000ff", "#00ff00"], false),
+            4,
+            64,
+        )
+        .unwrap();
+        assert!(pixel(&frame, 4, 0, 0)[0] > 240);
+        assert!(pixel(&frame, 4, 3, 63)[1] > 240);
+
+        // Radial: the centre has the first colour, the corners the last
+        let frame = render(
+            &gradient(GradientKind::Radial, 0.0, &["#ffffff", "#000000"], false),
+            64,
+            64,
+        )
+        .unwrap();
+        assert!(pixel(&frame, 64, 32, 32)[0] > 240);
+        assert!(pixel(&frame, 64, 0, 0)[0] < 10);
+
+        // Conic starting at the top: left of centre is three quarters round
+        let frame = render(
+            &gradient(GradientKind::Conic, -90.0, &["#000000", "#ffffff"], false),
+            64,
+            64,
+        )
+        .unwrap();
+        let left = pixel(&frame, 64, 0, 32)[0];
+        assert!((180..200).contains(&left), "{}", left);
+    }
+
+    #[test]
+    fn test_dither_preserves_average() {
+        // A gradient too shallow for 8 bits: 10.0 to 11.0 over the whole width
+        let shallow = |dither| {
+            let gradient = gradient(GradientKind::Linear, 0.0, &["#0a0a0a", "#0b0b0b"], dither);
+            render(&gradient, 512, 8).unwrap()
+        };
+
+        let plain = shallow(false);
+        let dithered = shallow(true);
+
+        // Without dithering the left half is one flat band
+        let band: Vec<u8> = (0..200).map(|x| pixel(&plain, 512, x, 0)[0]).collect();
+        assert!(band.iter().all(|&v| v == 10));
+
+        // With it, each 8x8 block averages to the exact value
+        let block_mean = |x0: u32| {
+            let sum: u32 = (0..8)
+                .flat_map(|y| (x0..x0 + 8).map(move |x| (x, y)))
+                .map(|(x, y)| pixel(&dithered, 512, x, y)[0] as u32)
+                .sum();
+            sum as f32 / 64.0
+        };
+        assert!((block_mean(0) - 10.0).abs() < 0.1);
+        assert!((block_mean(128) - 10.25).abs() < 0.1);
+        assert!((block_mean(384) - 10.75).abs() < 0.1);
+    }
+}
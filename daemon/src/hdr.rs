@@ -0,0 +1,295 @@
+//! High bit depth and HDR images
+//!
+//! 16-bit PNG and TIFF images and floating-point OpenEXR and Radiance HDR ones
+//! keep their precision through scaling. Floating-point images are scene
+//! referred (1.0 is "paper white", highlights go far above it), so they are
+//! exposed and tone mapped into display range first.
+//!
+//! Frames are quantized to 8 bits with ordered dithering, which keeps smooth
+//! skies free of bands. When the compositor accepts XRGB2101010 buffers, a
+//! 10-bit copy of the frame is packed too and shown instead.
+
+use common::ToneMap;
+use image::{DynamicImage, ImageBuffer, Rgba, RgbaImage};
+use rayon::prelude::*;
+use std::borrow::Cow;
+
+/// 16-bit RGBA image, the working format of high-bit-depth frames
+pub type Rgba16Image = ImageBuffer<Rgba<u16>, Vec<u16>>;
+
+/// Linear white point of the Hable curve
+const HABLE_WHITE: f32 = 11.2;
+
+/// How HDR images are brought into display range
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ToneMapping {
+    pub operator: ToneMap,
+    /// Exposure adjustment in stops (+1 doubles the brightness)
+    pub exposure: f32,
+}
+
+// Exposures are validated to be finite, so the bit patterns compare like the
+// values and tone mappings can key caches
+impl Eq for ToneMapping {}
+
+impl std::hash::Hash for ToneMapping {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.operator.hash(state);
+        self.exposure.to_bits().hash(state);
+    }
+}
+
+/// Check if an image has more than 8 bits per channel
+pub fn is_high_bit_depth(image: &DynamicImage) -> bool {
+    !matches!(
+        image,
+        DynamicImage::ImageLuma8(_)
+            | DynamicImage::ImageLumaA8(_)
+            | DynamicImage::ImageRgb8(_)
+            | DynamicImage::ImageRgba8(_)
+    )
+}
+
+/// Bring an image into display range
+///
+/// Floating-point images are exposed and tone mapped to 16 bits per channel;
+/// integer images already are in display range and are returned as they are.
+pub fn display_referred(image: &DynamicImage, mapping: ToneMapping) -> Cow<'_, DynamicImage> {
+    if !matches!(
+        image,
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_)
+    ) {
+        return Cow::Borrowed(image);
+    }
+
+    let linear = image.to_rgba32f();
+    let gain = mapping.exposure.exp2();
+    let samples: Vec<u16> = linear
+        .as_raw()
+        .par_chunks_exact(4)
+        .flat_map_iter(|pixel| {
+            let tone = |c: f32| to_u16(encode_srgb(tone_map(mapping.operator, c * gain)));
+            [
+                tone(pixel[0]),
+                tone(pixel[1]),
+                tone(pixel[2]),
+                to_u16(pixel[3]),
+            ]
+        })
+        .collect();
+
+    let mapped = Rgba16Image::from_raw(linear.width(), linear.height(), samples)
+        .expect("tone mapped image matches its size");
+    Cow::Owned(DynamicImage::ImageRgba16(mapped))
+}
+
+/// Map a linear value from 0.0 upwards into 0.0..=1.0
+fn tone_map(operator: ToneMap, x: f32) -> f32 {
+    let x = x.max(0.0);
+    let mapped = match operator {
+        ToneMap::Reinhard => x / (1.0 + x),
+        // Krzysztof Narkowicz's fit of the ACES reference rendering transform
+        ToneMap::Aces => (x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14),
+        // Scaled by 2 like in the original, so mid grey stays roughly in place
+        ToneMap::Hable => hable(2.0 * x) / hable(HABLE_WHITE),
+    };
+    mapped.clamp(0.0, 1.0)
+}
+
+/// John Hable's filmic curve
+fn hable(x: f32) -> f32 {
+    const A: f32 = 0.15; // Shoulder strength
+    const B: f32 = 0.50; // Linear strength
+    const C: f32 = 0.10; // Linear angle
+    const D: f32 = 0.20; // Toe strength
+    const E: f32 = 0.02; // Toe numerator
+    const F: f32 = 0.30; // Toe denominator
+    ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F
+}
+
+/// Apply the sRGB transfer function to a linear value
+fn encode_srgb(linear: f32) -> f32 {
+    if linear <= 0.003_130_8 {
+        linear * 12.92
+    } else {
+        1.055 * linear.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn to_u16(value: f32) -> u16 {
+    (value.clamp(0.0, 1.0) * 65535.0).round() as u16
+}
+
+/// 8-bit copy of an image; high-bit-depth images are dithered instead of rounded
+pub fn to_rgba8(image: &DynamicImage) -> RgbaImage {
+    if is_high_bit_depth(image) {
+        dither(&image.to_rgba16())
+    } else {
+        image.to_rgba8()
+    }
+}
+
+/// Quantize a 16-bit image to 8 bits, spreading the rounding error in the same
+/// 8x8 Bayer pattern as gradients
+pub fn dither(image: &Rgba16Image) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    let samples = image.as_raw();
+    let stride = width as usize * 4;
+
+    let pixels = crate::cpu_render::render_rows(width, height, |y, row| {
+        let bayer_row = &crate::gradient::BAYER[y as usize % 8];
+        let source = &samples[y as usize * stride..][..stride];
+        for (x, (pixel, sample)) in row
+            .chunks_exact_mut(4)
+            .zip(source.chunks_exact(4))
+            .enumerate()
+        {
+            let threshold = (bayer_row[x % 8] as f32 + 0.5) / 64.0;
+            for (channel, &value) in pixel.iter_mut().zip(sample) {
+                *channel = (value as f32 / 257.0 + threshold).floor().min(255.0) as u8;
+            }
+        }
+    });
+
+    RgbaImage::from_raw(width, height, pixels).expect("dithered frame matches its size")
+}
+
+/// Widen an 8-bit image to 16 bits per channel
+pub fn widen(image: &RgbaImage) -> Rgba16Image {
+    let samples = image.as_raw().iter().map(|&v| v as u16 * 257).collect();
+    Rgba16Image::from_raw(image.width(), image.height(), samples)
+        .expect("widened image matches its size")
+}
+
+/// Pack a 16-bit image as XRGB2101010 (the alpha channel is dropped, like the
+/// compositor does for opaque ARGB8888 wallpapers)
+pub fn pack_xrgb2101010(image: &Rgba16Image) -> Vec<u8> {
+    let to_10 = |c: u16| (c as u32 * 1023 + 32767) / 65535;
+    image
+        .as_raw()
+        .par_chunks_exact(4)
+        .flat_map_iter(|pixel| {
+            xrgb(to_10(pixel[0]), to_10(pixel[1]), to_10(pixel[2])).to_le_bytes()
+        })
+        .collect()
+}
+
+/// Convert an ARGB8888 frame to XRGB2101010, writing into `out` (same length)
+pub fn argb8888_to_xrgb2101010(argb: &[u8], out: &mut [u8]) {
+    let to_10 = |c: u8| (c as u32 * 1023 + 127) / 255;
+    out.par_chunks_exact_mut(4)
+        .zip(argb.par_chunks_exact(4))
+        .for_each(|(packed, pixel)| {
+            // ARGB8888 is BGRA in memory
+            packed.copy_from_slice(
+                &xrgb(to_10(pixel[2]), to_10(pixel[1]), to_10(pixel[0])).to_le_bytes(),
+            );
+        });
+}
+
+/// Convert an XRGB2101010 frame back to opaque ARGB8888
+pub fn xrgb2101010_to_argb8888(packed: &[u8]) -> Vec<u8> {
+    let to_8 = |v: u32| ((v & 0x3ff) * 255 + 511) / 1023;
+    packed
+        .par_chunks_exact(4)
+        .flat_map_iter(|pixel| {
+            let v = u32::from_le_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+            [to_8(v) as u8, to_8(v >> 10) as u8, to_8(v >> 20) as u8, 255]
+        })
+        .collect()
+}
+
+/// An XRGB2101010 pixel (padding bits set)
+fn xrgb(r: u32, g: u32, b: u32) -> u32 {
+    0xc000_0000 | (r << 20) | (g << 10) | b
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tone_map_operators() {
+        for operator in [ToneMap::Reinhard, ToneMap::Aces, ToneMap::Hable] {
+            assert!(tone_map(operator, 0.0) < 0.01, "{:?}", operator);
+            assert!(tone_map(operator, 1000.0) > 0.95, "{:?}", operator);
+
+            // Brighter in, brighter out
+            let curve: Vec<f32> = (0..64)
+                .map(|i| tone_map(operator, i as f32 / 8.0))
+                .collect();
+            assert!(curve.windows(2).all(|w| w[0] <= w[1]), "{:?}", operator);
+        }
+        assert_eq!(tone_map(ToneMap::Reinhard, 1.0), 0.5);
+        assert_eq!(tone_map(ToneMap::Aces, f32::NAN), 0.0);
+    }
+
+    #[test]
+    fn test_display_referred() {
+        let hdr = |value: f32| {
+            DynamicImage::ImageRgb32F(ImageBuffer::from_pixel(2, 2, image::Rgb([value; 3])))
+        };
+        let mapped = |value: f32, exposure: f32| {
+            let mapping = ToneMapping {
+                operator: ToneMap::Reinhard,
+                exposure,
+            };
+            display_referred(&hdr(value), mapping)
+                .to_rgba16()
+                .get_pixel(0, 0)
+                .0
+        };
+
+        // Reinhard maps 1.0 to 0.5, which sRGB encodes to about 0.735
+        let [r, g, b, a] = mapped(1.0, 0.0);
+        assert_eq!((r, g), (b, b));
+        assert!((r as f32 / 65535.0 - 0.735).abs() < 0.002, "{}", r);
+        assert_eq!(a, 65535);
+
+        // One stop up is the same as doubling the value
+        assert_eq!(mapped(1.0, 1.0), mapped(2.0, 0.0));
+        assert!(mapped(8.0, 0.0)[0] > mapped(1.0, 0.0)[0]);
+
+        // Integer images are already in display range
+        let png = DynamicImage::ImageRgba16(Rgba16Image::new(2, 2));
+        assert!(matches!(
+            display_referred(&png, ToneMapping::default()),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_dither_preserves_average() {
+        // 10.25 in 8-bit terms, between two 8-bit levels
+        let image = Rgba16Image::from_pixel(8, 8, Rgba([2634, 2634, 2634, 65535]));
+        let dithered = dither(&image);
+
+        let sum: u32 = dithered.pixels().map(|p| p[0] as u32).sum();
+        assert!((sum as f32 / 64.0 - 10.25).abs() < 0.05);
+        assert!(dithered.pixels().all(|p| p[3] == 255));
+
+        // Values an 8-bit image can hold come back unchanged
+        let exact = widen(&RgbaImage::from_pixel(8, 8, Rgba([0, 17, 128, 255])));
+        assert!(dither(&exact).pixels().all(|p| p.0 == [0, 17, 128, 255]));
+
+        // 8-bit images are simply converted
+        let plain = DynamicImage::ImageRgba8(RgbaImage::from_pixel(2, 2, Rgba([1, 2, 3, 4])));
+        assert_eq!(to_rgba8(&plain).get_pixel(1, 1).0, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_xrgb2101010() {
+        let image = Rgba16Image::from_pixel(1, 1, Rgba([65535, 32768, 0, 65535]));
+        let packed = pack_xrgb2101010(&image);
+        assert_eq!(
+            u32::from_le_bytes(packed[..].try_into().unwrap()),
+            0xc000_0000 | (1023 << 20) | (512 << 10)
+        );
+
+        // 8-bit frames survive the round trip through 10 bits
+        let argb: Vec<u8> = (0..=255u8).flat_map(|v| [v, 255 - v, v / 2, 255]).collect();
+        let mut packed = vec![0; argb.len()];
+        argb8888_to_xrgb2101010(&argb, &mut packed);
+        assert_eq!(xrgb2101010_to_argb8888(&packed), argb);
+    }
+}
@@ -0,0 +1,452 @@
+//! Decoded image cache
+//!
+//! Holds decoded wallpapers and copies of them already scaled for a particular
+//! output, so switching back to a recent wallpaper skips both decoding and
+//! scaling. Entries are keyed by path, modification time and file size (an
+//! edited file is decoded again) and the least recently used ones are evicted
+//! once the cache grows past its limit.
+
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Identifies one version of an image file
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SourceKey {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+impl SourceKey {
+    /// Build the key for a file as it is on disk now
+    pub fn for_path(path: &Path) -> Result<Self> {
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("Failed to read file metadata: {}", path.display()))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+        })
+    }
+}
+
+/// How an image is scaled for an output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameSize {
+    pub width: u32,
+    pub height: u32,
+    pub mode: common::ScaleMode,
+    /// Focal point Fill crops around (None = detected from the image)
+    pub focus: Option<common::FocalPoint>,
+    /// Background around the image in Fit and Center
+    pub letterbox: common::Letterbox,
+    /// How HDR images are brought into display range
+    pub tone_mapping: crate::hdr::ToneMapping,
+    /// XRGB2101010 rather than ARGB8888 (only made for high-bit-depth images)
+    pub ten_bit: bool,
+    /// Display profile the frame was converted to (None = sRGB)
+    pub profile: Option<u64>,
+}
+
+impl FrameSize {
+    /// Describe a frame, dropping the focal point and letterbox unless `mode` uses them
+    pub fn new(
+        width: u32,
+        height: u32,
+        mode: common::ScaleMode,
+        focus: Option<common::FocalPoint>,
+        letterbox: common::Letterbox,
+        tone_mapping: crate::hdr::ToneMapping,
+    ) -> Self {
+        let padded = matches!(mode, common::ScaleMode::Fit | common::ScaleMode::Center);
+
+        Self {
+            width,
+            height,
+            mode,
+            focus: focus.filter(|_| mode == common::ScaleMode::Fill),
+            letterbox: if padded {
+                letterbox
+            } else {
+                common::Letterbox::default()
+            },
+            tone_mapping,
+            ten_bit: false,
+            profile: None,
+        }
+    }
+
+    /// This frame converted to an output's display profile, if it has one
+    pub fn with_profile(self, profile: Option<&crate::color_profile::DisplayProfile>) -> Self {
+        Self {
+            profile: profile.map(|profile| profile.id()),
+            ..self
+        }
+    }
+
+    /// The 10-bit version of this frame
+    pub fn ten_bit(self) -> Self {
+        Self {
+            ten_bit: true,
+            ..self
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum EntryKey {
+    /// The decoded image at its original size
+    Image(SourceKey),
+    /// ARGB8888 frame scaled for an output (XRGB2101010 if `size.ten_bit` is set)
+    Scaled { source: SourceKey, size: FrameSize },
+}
+
+impl EntryKey {
+    fn source(&self) -> &SourceKey {
+        match self {
+            EntryKey::Image(source) | EntryKey::Scaled { source, .. } => source,
+        }
+    }
+}
+
+enum CachedData {
+    Image(Arc<DynamicImage>),
+    Frame(Arc<Vec<u8>>),
+}
+
+struct Entry {
+    data: CachedData,
+    bytes: usize,
+    /// Value of the cache's use counter when this entry was last read or written
+    last_used: u64,
+}
+
+/// Size-accounted LRU cache of decoded and pre-scaled images
+#[derive(Default)]
+pub struct ImageCache {
+    entries: HashMap<EntryKey, Entry>,
+    bytes: usize,
+    uses: u64,
+    /// Limit from the config (None = unlimited)
+    limit: Option<usize>,
+    /// Limit from the resource monitor's memory budget
+    budget: Option<usize>,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl ImageCache {
+    /// Create a cache holding at most `limit` bytes (None = unlimited)
+    pub fn new(limit: Option<usize>) -> Self {
+        Self {
+            limit,
+            ..Default::default()
+        }
+    }
+
+    /// Set the memory budget from the resource monitor, evicting if needed
+    pub fn set_budget(&mut self, budget: Option<usize>) {
+        self.budget = budget;
+        self.evict(None);
+    }
+
+    /// The smaller of the configured limit and the memory budget
+    pub fn effective_limit(&self) -> Option<usize> {
+        match (self.limit, self.budget) {
+            (Some(limit), Some(budget)) => Some(limit.min(budget)),
+            (limit, budget) => limit.or(budget),
+        }
+    }
+
+    /// Get a decoded image
+    pub fn image(&mut self, source: &SourceKey) -> Option<Arc<DynamicImage>> {
+        match &self.get(&EntryKey::Image(source.clone()))?.data {
+            CachedData::Image(image) => Some(image.clone()),
+            CachedData::Frame(_) => None,
+        }
+    }
+
+    /// Add a decoded image
+    pub fn insert_image(&mut self, source: SourceKey, image: Arc<DynamicImage>) {
+        let bytes = image.as_bytes().len();
+        self.insert(EntryKey::Image(source), CachedData::Image(image), bytes);
+    }
+
+    /// Get an image already scaled for an output
+    pub fn frame(&mut self, source: &SourceKey, size: FrameSize) -> Option<Arc<Vec<u8>>> {
+        let key = EntryKey::Scaled {
+            source: source.clone(),
+            size,
+        };
+
+        match &self.get(&key)?.data {
+            CachedData::Frame(frame) => Some(frame.clone()),
+            CachedData::Image(_) => None,
+        }
+    }
+
+    /// Add an ARGB8888 frame scaled for an output
+    pub fn insert_frame(&mut self, source: SourceKey, size: FrameSize, frame: Arc<Vec<u8>>) {
+        let key = EntryKey::Scaled { source, size };
+        let bytes = frame.len();
+        self.insert(key, CachedData::Frame(frame), bytes);
+    }
+
+    /// Evict least recently used entries until at least `bytes` have been freed
+    pub fn trim(&mut self, bytes: usize) {
+        let target = self.bytes.saturating_sub(bytes);
+        self.evict_to(target, None);
+    }
+
+    /// Drop everything
+    pub fn clear(&mut self) {
+        log::info!(
+            "Clearing image cache ({} entries, {} MiB)",
+            self.entries.len(),
+            self.bytes / 1024 / 1024
+        );
+        self.entries.clear();
+        self.bytes = 0;
+    }
+
+    /// Snapshot of the cache for IPC
+    pub fn status(&self) -> common::CacheStatus {
+        let mut entries: Vec<(&EntryKey, &Entry)> = self.entries.iter().collect();
+        entries.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.last_used));
+
+        common::CacheStatus {
+            bytes: self.bytes as u64,
+            limit_bytes: self.effective_limit().map(|limit| limit as u64),
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+            entries: entries
+                .into_iter()
+                .map(|(key, entry)| {
+                    let (width, height, scale) = match (key, &entry.data) {
+                        (EntryKey::Scaled { size, .. }, _) => {
+                            (size.width, size.height, Some(size.mode))
+                        }
+                        (_, CachedData::Image(image)) => (image.width(), image.height(), None),
+                        (_, CachedData::Frame(_)) => (0, 0, None),
+                    };
+
+                    common::CacheEntry {
+                        path: key.source().path.display().to_string(),
+                        width,
+                        height,
+                        bytes: entry.bytes as u64,
+                        scale,
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    fn get(&mut self, key: &EntryKey) -> Option<&Entry> {
+        self.uses += 1;
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_used = self.uses;
+                self.hits += 1;
+                Some(entry)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn insert(&mut self, key: EntryKey, data: CachedData, bytes: usize) {
+        // Older versions of the same file can't be hit again
+        let stale: Vec<EntryKey> = self
+            .entries
+            .keys()
+            .filter(|k| k.source().path == key.source().path && k.source() != key.source())
+            .cloned()
+            .collect();
+        for stale_key in stale {
+            self.remove(&stale_key);
+        }
+
+        self.remove(&key);
+        self.uses += 1;
+        self.bytes += bytes;
+        self.entries.insert(
+            key.clone(),
+            Entry {
+                data,
+                bytes,
+                last_used: self.uses,
+            },
+        );
+
+        // The new entry is about to be used, so it stays even if it alone is over the limit
+        self.evict(Some(&key));
+    }
+
+    fn remove(&mut self, key: &EntryKey) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.bytes -= entry.bytes;
+        }
+    }
+
+    /// Evict down to the effective limit
+    fn evict(&mut self, keep: Option<&EntryKey>) {
+        if let Some(limit) = self.effective_limit() {
+            self.evict_to(limit, keep);
+        }
+    }
+
+    fn evict_to(&mut self, target: usize, keep: Option<&EntryKey>) {
+        while self.bytes > target {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .filter(|(key, _)| Some(*key) != keep)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+
+            log::debug!(
+                "Evicting {} from image cache",
+                oldest.source().path.display()
+            );
+            self.remove(&oldest);
+            self.evictions += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(name: &str, len: u64) -> SourceKey {
+        SourceKey {
+            path: PathBuf::from(name),
+            modified: None,
+            len,
+        }
+    }
+
+    /// 4 bytes per pixel, so an NxN image uses N*N*4 bytes
+    fn image(size: u32) -> Arc<DynamicImage> {
+        Arc::new(DynamicImage::new_rgba8(size, size))
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let mut cache = ImageCache::new(Some(200));
+        cache.insert_image(source("a", 1), image(5)); // 100 bytes
+        cache.insert_image(source("b", 1), image(5));
+
+        // Touch "a" so "b" is the least recently used
+        assert!(cache.image(&source("a", 1)).is_some());
+        cache.insert_image(source("c", 1), image(5));
+
+        assert!(cache.image(&source("b", 1)).is_none());
+        assert!(cache.image(&source("a", 1)).is_some());
+        assert!(cache.image(&source("c", 1)).is_some());
+        assert_eq!(cache.status().bytes, 200);
+        assert_eq!(cache.status().evictions, 1);
+
+        // A single entry over the limit is kept until something else is added
+        cache.insert_image(source("big", 1), image(10));
+        assert_eq!(cache.status().entries.len(), 1);
+        assert!(cache.image(&source("big", 1)).is_some());
+    }
+
+    #[test]
+    fn test_changed_file_replaces_entries() {
+        let fill = FrameSize {
+            width: 4,
+            height: 4,
+            mode: common::ScaleMode::Fill,
+            focus: None,
+            letterbox: common::Letterbox::default(),
+            tone_mapping: crate::hdr::ToneMapping::default(),
+            ten_bit: false,
+            profile: None,
+        };
+        let fit = FrameSize {
+            mode: common::ScaleMode::Fit,
+            ..fill
+        };
+        let blurred = FrameSize {
+            letterbox: common::Letterbox::Blur,
+            ..fit
+        };
+        let focused = FrameSize {
+            focus: Some(common::FocalPoint { x: 0.2, y: 0.5 }),
+            ..fill
+        };
+        let brighter = FrameSize {
+            tone_mapping: crate::hdr::ToneMapping {
+                exposure: 1.0,
+                ..fill.tone_mapping
+            },
+            ..fill
+        };
+
+        let mut cache = ImageCache::new(None);
+        cache.insert_image(source("a", 1), image(2));
+        cache.insert_frame(source("a", 1), fill, Arc::new(vec![0; 64]));
+        assert!(cache.frame(&source("a", 1), fill).is_some());
+        assert!(cache.frame(&source("a", 1), fit).is_none());
+        assert!(cache.frame(&source("a", 1), focused).is_none());
+        assert!(cache.frame(&source("a", 1), brighter).is_none());
+        assert!(cache.frame(&source("a", 1), fill.ten_bit()).is_none());
+        assert!(
+            cache
+                .frame(
+                    &source("a", 1),
+                    FrameSize {
+                        profile: Some(1),
+                        ..fill
+                    }
+                )
+                .is_none()
+        );
+
+        cache.insert_frame(source("a", 1), fit, Arc::new(vec![0; 64]));
+        assert!(cache.frame(&source("a", 1), blurred).is_none());
+
+        // Same path with a different size on disk
+        cache.insert_image(source("a", 2), image(2));
+        assert!(cache.image(&source("a", 1)).is_none());
+        assert!(cache.frame(&source("a", 1), fill).is_none());
+        assert_eq!(cache.status().entries.len(), 1);
+    }
+
+    #[test]
+    fn test_budget_and_trim() {
+        let mut cache = ImageCache::new(Some(1000));
+        for name in ["a", "b", "c"] {
+            cache.insert_image(source(name, 1), image(5));
+        }
+        assert_eq!(cache.status().bytes, 300);
+
+        // The smaller of the two limits wins
+        cache.set_budget(Some(200));
+        assert_eq!(cache.effective_limit(), Some(200));
+        assert_eq!(cache.status().bytes, 200);
+
+        cache.trim(1);
+        assert_eq!(cache.status().bytes, 100);
+        assert!(cache.image(&source("c", 1)).is_some());
+
+        cache.clear();
+        assert_eq!(cache.status().bytes, 0);
+        assert!(cache.status().entries.is_empty());
+    }
+}
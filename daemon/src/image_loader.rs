@@ -0,0 +1,321 @@
+//! Background image decoding
+//!
+//! Decoding a large photo and scaling it to an output can take hundreds of
+//! milliseconds, which would stall video and shader frames if done on the
+//! Wayland thread. Jobs run on a small rayon pool instead and their results are
+//! collected with [`ImageLoader::poll`]; the caller decides what to cache and
+//! show, so the pool never touches Wayland objects or the image cache.
+//!
+//! Videos are loaded and prerolled in the background too (see `VideoJob`), on
+//! a pool of their own: waiting for a first frame can take seconds, and image
+//! loads shouldn't queue behind it.
+
+use crate::color_profile::DisplayProfile;
+use crate::image_cache::{FrameSize, SourceKey};
+use crate::wallpaper_manager::WallpaperManager;
+use anyhow::{Context, Result};
+use image::DynamicImage;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::mpsc;
+
+/// Number of decoder threads (decoding is mostly single-threaded per image)
+const WORKER_THREADS: usize = 2;
+
+/// Number of threads waiting for videos to preroll (GStreamer decodes on its own)
+#[cfg(feature = "video")]
+const VIDEO_THREADS: usize = 2;
+
+/// Identifies a submitted job
+pub type LoadId = u64;
+
+/// An image to decode and scale
+pub struct LoadJob {
+    pub path: PathBuf,
+    /// Version of the file being loaded (returned with the result for caching)
+    pub key: SourceKey,
+    /// Already decoded image, if it was cached (only scaling is needed then)
+    pub image: Option<Arc<DynamicImage>>,
+    /// Sizes to scale the image to
+    pub sizes: Vec<FrameSize>,
+    /// Also scale high-bit-depth images to 10-bit frames (keyed by `size.ten_bit()`)
+    pub ten_bit: bool,
+    /// Display profiles the sizes are converted to (see [`FrameSize::profile`])
+    pub profiles: Vec<DisplayProfile>,
+}
+
+/// A decoded image and its ARGB8888 (and XRGB2101010) frames
+pub struct LoadedImage {
+    pub image: Arc<DynamicImage>,
+    pub frames: Vec<(FrameSize, Arc<Vec<u8>>)>,
+}
+
+impl LoadedImage {
+    /// Get the frame scaled for a size, if the job produced one
+    pub fn frame(&self, size: FrameSize) -> Option<&Arc<Vec<u8>>> {
+        self.frames
+            .iter()
+            .find(|(frame_size, _)| *frame_size == size)
+            .map(|(_, frame)| frame)
+    }
+}
+
+/// Outcome of a finished job
+pub struct Loaded {
+    pub id: LoadId,
+    pub key: SourceKey,
+    pub result: Result<LoadedImage>,
+}
+
+/// A video to load and preroll, ready to play once its first frame is decoded
+#[cfg(feature = "video")]
+pub struct VideoJob {
+    pub path: String,
+    /// Size to decode at (the largest output's)
+    pub width: u32,
+    pub height: u32,
+    pub options: crate::video::VideoOptions,
+    /// Playback settings (FPS limit, looping, crossfade)
+    pub advanced: crate::config::AdvancedSettings,
+    #[cfg(feature = "gpu")]
+    pub gpu_renderer: Option<Arc<crate::gpu::GpuRenderer>>,
+}
+
+/// Outcome of a finished video job
+#[cfg(feature = "video")]
+pub struct LoadedVideo {
+    pub id: LoadId,
+    pub result: Result<crate::video::VideoManager>,
+}
+
+/// Worker pool decoding and scaling images off the calling thread
+pub struct ImageLoader {
+    pool: rayon::ThreadPool,
+    #[cfg(feature = "video")]
+    video_pool: rayon::ThreadPool,
+    tx: mpsc::Sender<Loaded>,
+    rx: mpsc::Receiver<Loaded>,
+    #[cfg(feature = "video")]
+    video_tx: mpsc::Sender<LoadedVideo>,
+    #[cfg(feature = "video")]
+    video_rx: mpsc::Receiver<LoadedVideo>,
+    next_id: LoadId,
+}
+
+impl ImageLoader {
+    pub fn new() -> Result<Self> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(WORKER_THREADS)
+            .thread_name(|index| format!("image-loader-{}", index))
+            .build()
+            .context("Failed to start image decoder threads")?;
+        #[cfg(feature = "video")]
+        let video_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(VIDEO_THREADS)
+            .thread_name(|index| format!("video-loader-{}", index))
+            .build()
+            .context("Failed to start video loader threads")?;
+        let (tx, rx) = mpsc::channel();
+        #[cfg(feature = "video")]
+        let (video_tx, video_rx) = mpsc::channel();
+
+        Ok(Self {
+            pool,
+            #[cfg(feature = "video")]
+            video_pool,
+            tx,
+            rx,
+            #[cfg(feature = "video")]
+            video_tx,
+            #[cfg(feature = "video")]
+            video_rx,
+            next_id: 0,
+        })
+    }
+
+    /// Queue a job, returning the id its result will carry
+    pub fn submit(&mut self, job: LoadJob) -> LoadId {
+        self.next_id += 1;
+        let id = self.next_id;
+        let tx = self.tx.clone();
+
+        self.pool.spawn(move || {
+            let start = std::time::Instant::now();
+            let key = job.key.clone();
+            let result = load(job);
+
+            if result.is_ok() {
+                log::debug!(
+                    "Background load {} finished in {:.2}ms",
+                    id,
+                    start.elapsed().as_secs_f64() * 1000.0
+                );
+            }
+
+            // The receiver is gone if the Wayland connection was dropped meanwhile
+            let _ = tx.send(Loaded { id, key, result });
+        });
+
+        id
+    }
+
+    /// Collect the jobs that have finished since the last call
+    pub fn poll(&self) -> Vec<Loaded> {
+        self.rx.try_iter().collect()
+    }
+
+    /// Queue a video job, returning the id its result will carry
+    #[cfg(feature = "video")]
+    pub fn submit_video(&mut self, job: VideoJob) -> LoadId {
+        self.next_id += 1;
+        let id = self.next_id;
+        let tx = self.video_tx.clone();
+
+        self.video_pool.spawn(move || {
+            let start = std::time::Instant::now();
+            let result = load_video(job);
+
+            if result.is_ok() {
+                log::debug!(
+                    "Background video load {} prerolled in {:.2}ms",
+                    id,
+                    start.elapsed().as_secs_f64() * 1000.0
+                );
+            }
+
+            // The receiver is gone if the Wayland connection was dropped meanwhile
+            let _ = tx.send(LoadedVideo { id, result });
+        });
+
+        id
+    }
+
+    /// Collect the video jobs that have finished since the last call
+    #[cfg(feature = "video")]
+    pub fn poll_videos(&self) -> Vec<LoadedVideo> {
+        self.video_rx.try_iter().collect()
+    }
+}
+
+/// Load a video, preroll its first frame and start playback
+#[cfg(feature = "video")]
+fn load_video(job: VideoJob) -> Result<crate::video::VideoManager> {
+    let mut video_manager = crate::video::VideoManager::load(
+        &job.path,
+        job.width,
+        job.height,
+        job.options,
+        job.advanced.max_video_fps,
+        #[cfg(feature = "gpu")]
+        job.gpu_renderer,
+    )?;
+
+    video_manager.set_loop(job.advanced.video_loop);
+    video_manager.set_crossfade(job.advanced.video_crossfade());
+    video_manager.preroll()?;
+    video_manager.play()?;
+
+    Ok(video_manager)
+}
+
+/// Decode (unless already decoded) and scale an image for each requested size
+fn load(job: LoadJob) -> Result<LoadedImage> {
+    let image = match job.image {
+        Some(image) => image,
+        None => Arc::new(WallpaperManager::decode_image(&job.path)?),
+    };
+
+    // Scaling doesn't touch the manager's cache, so a fresh one is fine here
+    let scaler = WallpaperManager::new();
+    let mut frames = Vec::new();
+    for size in job.sizes {
+        let scaled = scaler.scale_image(&image, size)?;
+        let profile = size
+            .profile
+            .and_then(|id| job.profiles.iter().find(|profile| profile.id() == id));
+
+        // 10-bit frames stay in sRGB, so outputs with a display profile don't get them
+        if job.ten_bit
+            && profile.is_none()
+            && let Some(deep) = scaler.scale_image_deep(&image, size, &scaled)?
+        {
+            frames.push((size.ten_bit(), Arc::new(deep)));
+        }
+
+        let mut frame = scaler.rgba_to_argb8888(&scaled);
+        if let Some(profile) = profile {
+            profile.apply(&mut frame)?;
+        }
+        frames.push((size, Arc::new(frame)));
+    }
+
+    Ok(LoadedImage { image, frames })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_in_background() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallpaper.png");
+        image::RgbaImage::from_pixel(8, 4, image::Rgba([255, 0, 0, 255]))
+            .save(&path)
+            .unwrap();
+
+        let size = FrameSize {
+            width: 4,
+            height: 4,
+            mode: common::ScaleMode::Stretch,
+            focus: None,
+            letterbox: common::Letterbox::default(),
+            tone_mapping: crate::hdr::ToneMapping::default(),
+            ten_bit: false,
+            profile: None,
+        };
+        let mut loader = ImageLoader::new().unwrap();
+        let id = loader.submit(LoadJob {
+            key: SourceKey::for_path(&path).unwrap(),
+            path,
+            image: None,
+            sizes: vec![size],
+            ten_bit: true,
+            profiles: Vec::new(),
+        });
+
+        let loaded = loader.rx.recv().unwrap();
+        assert_eq!(loaded.id, id);
+
+        let loaded = loaded.result.unwrap();
+        assert_eq!((loaded.image.width(), loaded.image.height()), (8, 4));
+
+        // Red as ARGB8888 in memory (BGRA)
+        let frame = loaded.frame(size).unwrap();
+        assert_eq!(frame.len(), 4 * 4 * 4);
+        assert_eq!(&frame[..4], &[0, 0, 255, 255]);
+        assert!(loaded.frame(FrameSize { width: 2, ..size }).is_none());
+
+        // 8-bit images get no 10-bit frame
+        assert!(loaded.frame(size.ten_bit()).is_none());
+    }
+
+    #[test]
+    fn test_load_error_is_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.png");
+        std::fs::write(&path, b"not an image").unwrap();
+
+        let mut loader = ImageLoader::new().unwrap();
+        loader.submit(LoadJob {
+            key: SourceKey::for_path(&path).unwrap(),
+            path,
+            image: None,
+            sizes: Vec::new(),
+            ten_bit: false,
+            profiles: Vec::new(),
+        });
+
+        assert!(loader.rx.recv().unwrap().result.is_err());
+    }
+}
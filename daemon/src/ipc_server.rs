@@ -1,15 +1,24 @@
 use anyhow::Result;
-use common::{Command, DaemonStatus, Response, WallpaperError, WallpaperStatus};
+use common::{
+    ClientMessage, Command, DaemonStatus, Reply, Response, ServerMessage, WallpaperError,
+    WallpaperStatus, Welcome,
+};
 use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::unix::{OwnedReadHalf, OwnedWriteHalf};
 use tokio::net::{UnixListener, UnixStream};
 use tokio::sync::{Mutex, mpsc};
 
-use crate::{DaemonState, WallpaperCommand};
+use crate::{DaemonState, WallpaperCommand, WallpaperRequest};
+
+/// How long to wait for the Wayland manager to apply a command
+///
+/// Generous because GIFs are converted to video before they're applied.
+const COMMAND_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
 
 pub async fn start(
     state: Arc<Mutex<DaemonState>>,
-    wallpaper_tx: mpsc::UnboundedSender<WallpaperCommand>,
+    wallpaper_tx: mpsc::UnboundedSender<WallpaperRequest>,
 ) -> Result<()> {
     let socket_path = common::get_socket_path();
 
@@ -60,15 +69,194 @@ pub async fn start(
 async fn handle_client(
     stream: UnixStream,
     state: Arc<Mutex<DaemonState>>,
-    wallpaper_tx: mpsc::UnboundedSender<WallpaperCommand>,
+    wallpaper_tx: mpsc::UnboundedSender<WallpaperRequest>,
 ) -> Result<()> {
-    let (reader, mut writer) = stream.into_split();
+    let (reader, writer) = stream.into_split();
     let mut reader = BufReader::new(reader);
+
+    // Peek at the first byte to tell framed clients from legacy line-based ones
+    let Some(&first_byte) = reader.fill_buf().await?.first() else {
+        return Ok(()); // Closed without sending anything
+    };
+
+    if common::is_framed(first_byte) {
+        handle_framed_client(reader, writer, &state, &wallpaper_tx).await
+    } else {
+        log::debug!("Client is using the legacy line protocol");
+        handle_legacy_client(reader, writer, &state, &wallpaper_tx).await
+    }
+}
+
+/// Serve a client speaking the length-prefixed protocol
+///
+/// Each request runs as its own task, so a quick command isn't held up behind
+/// an image still loading; replies are sent as they're ready and matched to
+/// their requests by `request_id`.
+async fn handle_framed_client(
+    mut reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    state: &Arc<Mutex<DaemonState>>,
+    wallpaper_tx: &mpsc::UnboundedSender<WallpaperRequest>,
+) -> Result<()> {
+    let writer = Arc::new(Mutex::new(writer));
+    let mut requests = tokio::task::JoinSet::new();
+
+    loop {
+        let mut header = [0u8; 4];
+        match reader.read_exact(&mut header).await {
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+
+        let len = common::decode_frame_len(header)?;
+        let mut payload = vec![0u8; len];
+        reader.read_exact(&mut payload).await?;
+
+        match parse_client_message(&payload) {
+            Ok(ClientMessage::Hello(hello)) => {
+                log::debug!(
+                    "Client hello: {} (protocol v{})",
+                    hello.client.as_deref().unwrap_or("unknown"),
+                    hello.protocol_version
+                );
+                match welcome(hello.protocol_version) {
+                    Ok(welcome) => send_message(&writer, &ServerMessage::Welcome(welcome)).await?,
+                    Err(e) => {
+                        log::warn!("Rejecting client: {}", e);
+                        send_message(&writer, &ServerMessage::Rejected(e)).await?;
+                        break;
+                    }
+                }
+            }
+            Ok(ClientMessage::Request(request)) => {
+                let writer = writer.clone();
+                let state = state.clone();
+                let wallpaper_tx = wallpaper_tx.clone();
+                requests.spawn(async move {
+                    let wait = !request.no_wait;
+                    let reply = ServerMessage::Reply(Reply {
+                        request_id: request.request_id,
+                        response: handle_command(request.command, &state, &wallpaper_tx, wait)
+                            .await,
+                    });
+                    send_message(&writer, &reply).await
+                });
+            }
+            Err(reply) => send_message(&writer, &ServerMessage::Reply(reply)).await?,
+        }
+
+        // Collect finished requests so a long-lived connection doesn't pile them up
+        while let Some(result) = requests.try_join_next() {
+            log_reply_result(result);
+        }
+    }
+
+    // Answer the requests still running before the connection is closed
+    while let Some(result) = requests.join_next().await {
+        log_reply_result(result);
+    }
+
+    Ok(())
+}
+
+/// Write a message to a framed client, whichever request it answers
+async fn send_message(writer: &Mutex<OwnedWriteHalf>, message: &ServerMessage) -> Result<()> {
+    let frame = common::encode_frame(message)?;
+    let mut writer = writer.lock().await;
+    writer.write_all(&frame).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Log a request task that failed to send its reply
+fn log_reply_result(result: std::result::Result<Result<()>, tokio::task::JoinError>) {
+    match result {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => log::error!("Failed to send reply: {}", e),
+        Err(e) => log::error!("Request task failed: {}", e),
+    }
+}
+
+/// Parse a framed client message, turning unknown or malformed commands into error replies
+fn parse_client_message(payload: &[u8]) -> std::result::Result<ClientMessage, Reply> {
+    let err = match serde_json::from_slice::<ClientMessage>(payload) {
+        Ok(message) => return Ok(message),
+        Err(e) => e,
+    };
+
+    // Dig the request ID and command name out of the raw JSON for a useful error
+    let raw: serde_json::Value = serde_json::from_slice(payload).unwrap_or_default();
+    let request = raw.get("Request");
+    let request_id = request
+        .and_then(|r| r.get("request_id"))
+        .and_then(|id| id.as_u64())
+        .unwrap_or(0);
+    let command_name = request
+        .and_then(|r| r.get("command"))
+        .and_then(Command::name_of_raw);
+
+    let error = match command_name {
+        Some(name) if !Command::NAMES.contains(&name) => {
+            log::warn!("Unsupported command: {}", name);
+            WallpaperError::UnsupportedCommand(name.to_string())
+        }
+        _ => {
+            log::warn!("Invalid message: {}", err);
+            WallpaperError::Ipc(format!("Invalid message: {}", err))
+        }
+    };
+
+    Err(Reply {
+        request_id,
+        response: Response::Error(error),
+    })
+}
+
+/// Build the handshake reply for a client speaking up to `client_version`, or
+/// the error refusing it if that is older than any version the daemon speaks
+fn welcome(client_version: u32) -> std::result::Result<Welcome, WallpaperError> {
+    if client_version < common::MIN_PROTOCOL_VERSION {
+        return Err(WallpaperError::UnsupportedProtocol {
+            client: client_version,
+            min: common::MIN_PROTOCOL_VERSION,
+            max: common::PROTOCOL_VERSION,
+        });
+    }
+
+    let features = [
+        ("video", cfg!(feature = "video")),
+        ("gpu", cfg!(feature = "gpu")),
+        ("fetch", cfg!(feature = "fetch")),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(name, _)| name.to_string())
+    .collect();
+
+    Ok(Welcome {
+        protocol_version: client_version.min(common::PROTOCOL_VERSION),
+        daemon_version: env!("CARGO_PKG_VERSION").to_string(),
+        commands: Command::NAMES.iter().map(|name| name.to_string()).collect(),
+        features,
+    })
+}
+
+/// Serve a client sending one bare JSON command per line (the original protocol)
+async fn handle_legacy_client(
+    mut reader: BufReader<OwnedReadHalf>,
+    mut writer: OwnedWriteHalf,
+    state: &Arc<Mutex<DaemonState>>,
+    wallpaper_tx: &mpsc::UnboundedSender<WallpaperRequest>,
+) -> Result<()> {
     let mut line = String::new();
 
     while reader.read_line(&mut line).await? > 0 {
         let response = match serde_json::from_str::<Command>(&line) {
-            Ok(command) => handle_command(command, &state, &wallpaper_tx).await,
+            // Legacy clients only understand a single Ok/Error
+            Ok(command) => handle_command(command, state, wallpaper_tx, true)
+                .await
+                .into_simple(),
             Err(e) => {
                 log::warn!("Invalid command: {}", e);
                 Response::Error(WallpaperError::Ipc(format!("Invalid command: {}", e)))
@@ -87,10 +275,15 @@ async fn handle_client(
     Ok(())
 }
 
+/// Handle a single command
+///
+/// With `wait`, wallpaper commands reply once the Wayland manager has applied
+/// them; otherwise they reply `Ok` as soon as the command is queued.
 async fn handle_command(
     command: Command,
     state: &Arc<Mutex<DaemonState>>,
-    wallpaper_tx: &mpsc::UnboundedSender<WallpaperCommand>,
+    wallpaper_tx: &mpsc::UnboundedSender<WallpaperRequest>,
+    wait: bool,
 ) -> Response {
     log::debug!("Handling command: {:?}", command);
 
@@ -124,6 +317,8 @@ async fn handle_command(
             output,
             transition,
             scale,
+            focus,
+            letterbox,
         } => {
             log::info!(
                 "Setting wallpaper: {} on output: {:?} with scale: {:?}, transition: {:?}",
@@ -133,12 +328,33 @@ async fn handle_command(
                 transition
             );
 
+            // Turn URLs into local files
+            let path = match crate::sources::resolve(path).await {
+                Ok(path) => path,
+                Err(e) => return Response::Error(e),
+            };
+
             // Validate file exists
             if !std::path::Path::new(&path).exists() {
-                return Response::Error(WallpaperError::NotFound(format!(
-                    "Wallpaper file not found: {}",
-                    path
-                )));
+                return Response::Error(WallpaperError::FileNotFound { path });
+            }
+
+            if let Err(e) = validate_wallpaper_format(&path) {
+                return Response::Error(e);
+            }
+
+            if let Err(e) = validate_output(state, output.as_deref()).await {
+                return Response::Error(e);
+            }
+
+            if let Some(focus) = focus
+                && !focus.is_valid()
+            {
+                return Response::Error(WallpaperError::invalid_argument(
+                    "focal point",
+                    format!("{},{}", focus.x, focus.y),
+                    "both coordinates must be between 0 and 1",
+                ));
             }
 
             // Send command to Wayland manager
@@ -147,15 +363,47 @@ async fn handle_command(
                 output,
                 scale: scale.unwrap_or_default(),
                 transition,
+                focus,
+                letterbox,
             };
-            if let Err(e) = wallpaper_tx.send(cmd) {
-                return Response::Error(WallpaperError::Ipc(format!(
-                    "Failed to send command to Wayland manager: {}",
-                    e
-                )));
+            dispatch(wallpaper_tx, cmd, wait).await
+        }
+
+        Command::SetImageData {
+            width,
+            height,
+            format,
+            bytes,
+            output,
+            transition,
+            scale,
+            letterbox,
+        } => {
+            log::info!(
+                "Setting wallpaper from {} bytes of {:?} image data on output: {:?}",
+                bytes.len(),
+                format,
+                output
+            );
+
+            if let Err(e) = validate_output(state, output.as_deref()).await {
+                return Response::Error(e);
             }
 
-            Response::Ok
+            let path = match crate::sources::store_image_data(width, height, format, bytes).await {
+                Ok(path) => path,
+                Err(e) => return Response::Error(e),
+            };
+
+            let cmd = WallpaperCommand::SetImage {
+                path,
+                output,
+                scale: scale.unwrap_or_default(),
+                transition,
+                focus: None,
+                letterbox,
+            };
+            dispatch(wallpaper_tx, cmd, wait).await
         }
 
         Command::SetColor { color, output } => {
@@ -163,22 +411,48 @@ async fn handle_command(
 
             // Validate color format
             if !is_valid_hex_color(&color) {
-                return Response::Error(WallpaperError::Ipc(format!(
-                    "Invalid color format: {}. Use hex format like #FF5733 or FF5733",
-                    color
-                )));
+                return Response::Error(WallpaperError::invalid_argument(
+                    "color",
+                    color,
+                    "use hex format like #FF5733 or FF5733",
+                ));
+            }
+
+            if let Err(e) = validate_output(state, output.as_deref()).await {
+                return Response::Error(e);
             }
 
             // Send command to Wayland manager
             let cmd = WallpaperCommand::SetColor { color, output };
-            if let Err(e) = wallpaper_tx.send(cmd) {
-                return Response::Error(WallpaperError::Ipc(format!(
-                    "Failed to send command to Wayland manager: {}",
-                    e
-                )));
+            dispatch(wallpaper_tx, cmd, wait).await
+        }
+
+        Command::SetGradient {
+            gradient,
+            output,
+            transition,
+        } => {
+            log::info!(
+                "Setting {:?} gradient with {} stops on output: {:?}",
+                gradient.kind,
+                gradient.stops.len(),
+                output
+            );
+
+            if let Err(e) = gradient.validate() {
+                return Response::Error(e);
             }
 
-            Response::Ok
+            if let Err(e) = validate_output(state, output.as_deref()).await {
+                return Response::Error(e);
+            }
+
+            let cmd = WallpaperCommand::SetGradient {
+                gradient,
+                output,
+                transition,
+            };
+            dispatch(wallpaper_tx, cmd, wait).await
         }
 
         Command::SetShader {
@@ -189,6 +463,14 @@ async fn handle_command(
         } => {
             log::info!("Setting shader: {} on output: {:?}", shader, output);
 
+            if crate::shader_manager::BuiltinShader::from_str(&shader).is_none() {
+                return Response::Error(WallpaperError::UnknownShader { shader });
+            }
+
+            if let Err(e) = validate_output(state, output.as_deref()).await {
+                return Response::Error(e);
+            }
+
             // Send command to Wayland manager
             let cmd = WallpaperCommand::SetShader {
                 shader,
@@ -196,60 +478,175 @@ async fn handle_command(
                 transition,
                 params,
             };
-            if let Err(e) = wallpaper_tx.send(cmd) {
-                return Response::Error(WallpaperError::Ipc(format!(
-                    "Failed to send command to Wayland manager: {}",
-                    e
-                )));
-            }
-
-            Response::Ok
+            dispatch(wallpaper_tx, cmd, wait).await
         }
 
         Command::SetOverlay {
             overlay,
             params,
             output,
+            fade,
         } => {
-            log::info!("Setting overlay: {} on output: {:?}", overlay, output);
+            log::info!(
+                "Setting overlay: {} on output: {:?} (fade: {:?})",
+                overlay,
+                output,
+                fade
+            );
 
             // Convert common::OverlayParams to internal overlay_shader::OverlayParams
             let internal_params = if let Some(p) = params {
                 crate::overlay_shader::OverlayParams {
-                    intensity: p.intensity,
                     strength: p.strength,
-                    ..Default::default()
+                    intensity: p.intensity,
+                    line_width: p.line_width,
+                    offset: p.offset,
+                    curvature: p.curvature,
+                    pixel_size: p.pixel_size,
+                    r: p.r,
+                    g: p.g,
+                    b: p.b,
                 }
             } else {
                 crate::overlay_shader::OverlayParams::default()
             };
 
+            if crate::overlay_shader::OverlayShader::from_str(&overlay, &internal_params).is_none()
+            {
+                return Response::Error(WallpaperError::UnknownOverlay { overlay });
+            }
+
+            if let Err(e) = validate_output(state, output.as_deref()).await {
+                return Response::Error(e);
+            }
+
             let cmd = WallpaperCommand::SetOverlay {
                 overlay,
                 params: internal_params,
                 output,
+                fade,
             };
 
-            if let Err(e) = wallpaper_tx.send(cmd) {
-                return Response::Error(WallpaperError::Ipc(format!(
-                    "Failed to send command: {}",
-                    e
-                )));
+            dispatch(wallpaper_tx, cmd, wait).await
+        }
+
+        Command::ClearOverlay { output, fade } => {
+            log::info!(
+                "Clearing overlay for output: {:?} (fade: {:?})",
+                output,
+                fade
+            );
+
+            if let Err(e) = validate_output(state, output.as_deref()).await {
+                return Response::Error(e);
             }
 
-            Response::Ok
+            let cmd = WallpaperCommand::ClearOverlay { output, fade };
+            dispatch(wallpaper_tx, cmd, wait).await
         }
 
-        Command::ClearOverlay { output } => {
-            log::info!("Clearing overlay for output: {:?}", output);
-            let cmd = WallpaperCommand::ClearOverlay { output };
-            if let Err(e) = wallpaper_tx.send(cmd) {
-                return Response::Error(WallpaperError::Ipc(format!(
-                    "Failed to send command: {}",
-                    e
-                )));
+        Command::Capture { output, path } => {
+            log::info!("Capturing output {:?} to {}", output, path);
+
+            let target = std::path::Path::new(&path);
+            if !target.is_absolute() {
+                return Response::Error(WallpaperError::invalid_argument(
+                    "path",
+                    path,
+                    "must be absolute",
+                ));
             }
-            Response::Ok
+            if let Some(parent) = target.parent()
+                && !parent.is_dir()
+            {
+                return Response::Error(WallpaperError::FileNotFound {
+                    path: parent.display().to_string(),
+                });
+            }
+
+            if let Err(e) = validate_output(state, output.as_deref()).await {
+                return Response::Error(e);
+            }
+
+            let cmd = WallpaperCommand::Capture { output, path };
+            dispatch(wallpaper_tx, cmd, wait).await
+        }
+
+        Command::SetVolume {
+            output,
+            volume,
+            muted,
+        } => {
+            log::info!(
+                "Setting video volume {:?} (muted: {:?}) on output: {:?}",
+                volume,
+                muted,
+                output
+            );
+
+            if let Some(volume) = volume
+                && !(0.0..=1.0).contains(&volume)
+            {
+                return Response::Error(WallpaperError::invalid_argument(
+                    "volume",
+                    volume.to_string(),
+                    "must be between 0 and 1",
+                ));
+            }
+
+            if let Err(e) = validate_output(state, output.as_deref()).await {
+                return Response::Error(e);
+            }
+
+            let cmd = WallpaperCommand::SetVolume {
+                output,
+                volume,
+                muted,
+            };
+            dispatch(wallpaper_tx, cmd, wait).await
+        }
+
+        Command::GetCacheStatus => {
+            let state = state.lock().await;
+            match &state.cache_status {
+                Some(status) => Response::Cache(status.clone()),
+                None => Response::Error(WallpaperError::Unavailable("image cache".to_string())),
+            }
+        }
+
+        Command::ClearCache => {
+            log::info!("Clearing image cache");
+            // Nothing is reported per output
+            dispatch(wallpaper_tx, WallpaperCommand::ClearCache, wait)
+                .await
+                .into_simple()
+        }
+
+        Command::GetPalette { output } => {
+            if let Err(e) = validate_output(state, output.as_deref()).await {
+                return Response::Error(e);
+            }
+
+            let state = state.lock().await;
+            let selected =
+                crate::output_selector::selected_names(output.as_deref(), &state.outputs);
+            let mut palettes: Vec<common::OutputPalette> = state
+                .palettes
+                .iter()
+                .filter(|(name, _)| output.is_none() || selected.contains(name))
+                .map(|(name, palette)| common::OutputPalette {
+                    output: name.clone(),
+                    palette: palette.clone(),
+                })
+                .collect();
+            palettes.sort_by(|a, b| a.output.cmp(&b.output));
+
+            if palettes.is_empty() {
+                return Response::Error(WallpaperError::Unavailable(
+                    "palette (no wallpaper has been sampled yet)".to_string(),
+                ));
+            }
+            Response::Palette(palettes)
         }
 
         Command::Kill => {
@@ -299,21 +696,16 @@ async fn handle_command(
                         output: None,
                         scale: common::ScaleMode::Fill,
                         transition: Some(transition_type),
+                        focus: None,
+                        letterbox: None,
                     };
 
-                    if let Err(e) = wallpaper_tx.send(cmd) {
-                        return Response::Error(WallpaperError::Ipc(format!(
-                            "Failed to send command: {}",
-                            e
-                        )));
-                    }
-
-                    Response::Ok
+                    dispatch(wallpaper_tx, cmd, wait).await
                 } else {
-                    Response::Error(WallpaperError::Ipc("Playlist is empty".to_string()))
+                    Response::Error(WallpaperError::PlaylistEmpty)
                 }
             } else {
-                Response::Error(WallpaperError::Ipc("No playlist configured".to_string()))
+                Response::Error(WallpaperError::NoPlaylist)
             }
         }
 
@@ -354,21 +746,16 @@ async fn handle_command(
                         output: None,
                         scale: common::ScaleMode::Fill,
                         transition: Some(transition_type),
+                        focus: None,
+                        letterbox: None,
                     };
 
-                    if let Err(e) = wallpaper_tx.send(cmd) {
-                        return Response::Error(WallpaperError::Ipc(format!(
-                            "Failed to send command: {}",
-                            e
-                        )));
-                    }
-
-                    Response::Ok
+                    dispatch(wallpaper_tx, cmd, wait).await
                 } else {
-                    Response::Error(WallpaperError::Ipc("Playlist is empty".to_string()))
+                    Response::Error(WallpaperError::PlaylistEmpty)
                 }
             } else {
-                Response::Error(WallpaperError::Ipc("No playlist configured".to_string()))
+                Response::Error(WallpaperError::NoPlaylist)
             }
         }
 
@@ -379,7 +766,7 @@ async fn handle_command(
                 log::info!("Toggled shuffle mode");
                 Response::Ok
             } else {
-                Response::Error(WallpaperError::Ipc("No playlist configured".to_string()))
+                Response::Error(WallpaperError::NoPlaylist)
             }
         }
 
@@ -394,19 +781,248 @@ async fn handle_command(
                     battery_percent: stats.battery_percent,
                 })
             } else {
-                Response::Error(WallpaperError::Ipc(
-                    "Resource stats not yet available".to_string(),
-                ))
+                Response::Error(WallpaperError::Unavailable("resource stats".to_string()))
             }
         }
 
-        Command::SetPerformanceMode { mode: _ } => Response::Error(WallpaperError::Ipc(
-            "Setting performance mode not yet implemented (use config file)".to_string(),
+        Command::SetPerformanceMode { mode: _ } => Response::Error(WallpaperError::NotImplemented(
+            "setting performance mode over IPC (use config file)".to_string(),
         )),
     }
 }
 
+/// Queue a command for the Wayland manager, optionally waiting for its outcome
+async fn dispatch(
+    wallpaper_tx: &mpsc::UnboundedSender<WallpaperRequest>,
+    command: WallpaperCommand,
+    wait: bool,
+) -> Response {
+    let (request, reply_rx) = if wait {
+        let (request, reply_rx) = WallpaperRequest::new(command);
+        (request, Some(reply_rx))
+    } else {
+        (WallpaperRequest::fire_and_forget(command), None)
+    };
+
+    if let Err(e) = wallpaper_tx.send(request) {
+        return Response::Error(WallpaperError::Ipc(format!(
+            "Failed to send command to Wayland manager: {}",
+            e
+        )));
+    }
+
+    let Some(reply_rx) = reply_rx else {
+        return Response::Ok;
+    };
+
+    match tokio::time::timeout(COMMAND_TIMEOUT, reply_rx).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(_)) => Response::Error(WallpaperError::Ipc(
+            "Wayland manager dropped the command (compositor reconnecting?)".to_string(),
+        )),
+        Err(_) => Response::Error(WallpaperError::Ipc(format!(
+            "Timed out after {}s waiting for the command to be applied",
+            COMMAND_TIMEOUT.as_secs()
+        ))),
+    }
+}
+
+/// Check that an output filter is a valid selector picking a connected output
+async fn validate_output(
+    state: &Arc<Mutex<DaemonState>>,
+    output: Option<&str>,
+) -> std::result::Result<(), WallpaperError> {
+    let Some(output) = output.filter(|name| *name != "all") else {
+        return Ok(());
+    };
+
+    let selector: crate::output_selector::OutputSelector =
+        output
+            .parse()
+            .map_err(|e: anyhow::Error| WallpaperError::InvalidArgument {
+                field: "output".to_string(),
+                value: output.to_string(),
+                reason: e.root_cause().to_string(),
+            })?;
+
+    let state = state.lock().await;
+
+    // Outputs may not be enumerated yet right after startup
+    if state.outputs.is_empty() || !selector.select(&state.outputs).is_empty() {
+        Ok(())
+    } else {
+        Err(WallpaperError::UnknownOutput {
+            output: output.to_string(),
+        })
+    }
+}
+
+/// Check that a wallpaper file is something the daemon can display
+fn validate_wallpaper_format(path: &str) -> std::result::Result<(), WallpaperError> {
+    use crate::wallpaper_manager::WallpaperManager;
+
+    if WallpaperManager::is_svg(path) {
+        return Ok(());
+    }
+
+    if WallpaperManager::is_video(path) || WallpaperManager::is_gif(path) {
+        if cfg!(feature = "video") {
+            return Ok(());
+        }
+        return Err(WallpaperError::FeatureDisabled {
+            feature: "video".to_string(),
+        });
+    }
+
+    let reason = match image::ImageFormat::from_path(path) {
+        Ok(format) if format.reading_enabled() => return Ok(()),
+        Ok(format) => format!("{:?} decoding is not compiled in", format),
+        Err(e) => e.to_string(),
+    };
+
+    Err(WallpaperError::UnsupportedFormat {
+        path: path.to_string(),
+        reason,
+    })
+}
+
 fn is_valid_hex_color(color: &str) -> bool {
     let color = color.trim_start_matches('#');
     (color.len() == 6 || color.len() == 8) && color.chars().all(|c| c.is_ascii_hexdigit())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::{Hello, Request};
+
+    /// Serve one end of a socket pair, returning the other end and the
+    /// commands passed on to the Wayland manager
+    fn serve() -> (UnixStream, mpsc::UnboundedReceiver<WallpaperRequest>) {
+        let (client, server) = UnixStream::pair().unwrap();
+        let (wallpaper_tx, wallpaper_rx) = mpsc::unbounded_channel();
+        let state = Arc::new(Mutex::new(DaemonState::new()));
+        tokio::spawn(async move { handle_client(server, state, wallpaper_tx).await.unwrap() });
+        (client, wallpaper_rx)
+    }
+
+    async fn send_payload(stream: &mut UnixStream, payload: &[u8]) {
+        stream
+            .write_all(&(payload.len() as u32).to_be_bytes())
+            .await
+            .unwrap();
+        stream.write_all(payload).await.unwrap();
+    }
+
+    async fn send(stream: &mut UnixStream, message: &ClientMessage) {
+        send_payload(stream, &serde_json::to_vec(message).unwrap()).await;
+    }
+
+    async fn receive(stream: &mut UnixStream) -> ServerMessage {
+        let mut header = [0u8; 4];
+        stream.read_exact(&mut header).await.unwrap();
+        let mut payload = vec![0u8; common::decode_frame_len(header).unwrap()];
+        stream.read_exact(&mut payload).await.unwrap();
+        serde_json::from_slice(&payload).unwrap()
+    }
+
+    fn request(request_id: u64, command: Command) -> ClientMessage {
+        ClientMessage::Request(Request {
+            request_id,
+            command,
+            no_wait: false,
+        })
+    }
+
+    fn hello(protocol_version: u32) -> ClientMessage {
+        ClientMessage::Hello(Hello {
+            protocol_version,
+            client: Some("test".to_string()),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_replies_out_of_order() {
+        let (mut stream, mut wallpaper_rx) = serve();
+
+        // A colour waits for the Wayland manager; the ping behind it doesn't
+        let set_color = Command::SetColor {
+            color: "#1e1e2e".to_string(),
+            output: None,
+        };
+        send(&mut stream, &request(1, set_color)).await;
+        send(&mut stream, &request(2, Command::Ping)).await;
+
+        match receive(&mut stream).await {
+            ServerMessage::Reply(reply) => {
+                assert_eq!(reply.request_id, 2);
+                assert!(matches!(reply.response, Response::Pong));
+            }
+            _ => panic!("Wrong message type"),
+        }
+
+        let applied = wallpaper_rx.recv().await.unwrap();
+        assert!(matches!(applied.command, WallpaperCommand::SetColor { .. }));
+        applied.reply.send(&Ok(Vec::new()));
+
+        match receive(&mut stream).await {
+            ServerMessage::Reply(reply) => {
+                assert_eq!(reply.request_id, 1);
+                assert!(matches!(reply.response, Response::Applied(_)));
+            }
+            _ => panic!("Wrong message type"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handshake_version_check() {
+        let (mut stream, _wallpaper_rx) = serve();
+
+        // A newer client is answered with the daemon's version
+        send(&mut stream, &hello(common::PROTOCOL_VERSION + 1)).await;
+        match receive(&mut stream).await {
+            ServerMessage::Welcome(welcome) => {
+                assert_eq!(welcome.protocol_version, common::PROTOCOL_VERSION);
+                assert!(welcome.supports(&Command::Ping));
+            }
+            _ => panic!("Wrong message type"),
+        }
+
+        // One older than the daemon speaks is refused and disconnected
+        let (mut stream, _wallpaper_rx) = serve();
+        send(&mut stream, &hello(common::MIN_PROTOCOL_VERSION - 1)).await;
+        match receive(&mut stream).await {
+            ServerMessage::Rejected(WallpaperError::UnsupportedProtocol { client, min, max }) => {
+                assert_eq!(client, common::MIN_PROTOCOL_VERSION - 1);
+                assert_eq!(min, common::MIN_PROTOCOL_VERSION);
+                assert_eq!(max, common::PROTOCOL_VERSION);
+            }
+            _ => panic!("Wrong message type"),
+        }
+        let mut rest = Vec::new();
+        stream.read_to_end(&mut rest).await.unwrap();
+        assert!(rest.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_command() {
+        let (mut stream, _wallpaper_rx) = serve();
+
+        // A command from a newer client that this daemon doesn't know
+        let raw = br#"{"Request":{"request_id":3,"command":{"SetTeleport":{"output":null}}}}"#;
+        send_payload(&mut stream, raw).await;
+
+        match receive(&mut stream).await {
+            ServerMessage::Reply(reply) => {
+                assert_eq!(reply.request_id, 3);
+                match reply.response {
+                    Response::Error(WallpaperError::UnsupportedCommand(name)) => {
+                        assert_eq!(name, "SetTeleport")
+                    }
+                    other => panic!("Unexpected response: {:?}", other),
+                }
+            }
+            _ => panic!("Wrong message type"),
+        }
+    }
+}
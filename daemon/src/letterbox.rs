@@ -0,0 +1,190 @@
+//! Letterbox backgrounds
+//!
+//! Fit leaves bars beside or above the scaled image and Center leaves a border
+//! around small images. What fills them is a [`common::Letterbox`]: a solid
+//! colour, the image's dominant colour, a blurred and darkened copy of the
+//! image scaled to cover the output, or the image's edges mirrored outwards.
+
+use common::Letterbox;
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgba, RgbaImage};
+
+/// The blurred copy is made at this fraction of the output size, then scaled up
+const BLUR_DOWNSCALE: u32 = 8;
+
+/// Blur radius at the reduced size (so about 8x this at the output size)
+const BLUR_SIGMA: f32 = 4.0;
+
+/// Brightness of the blurred copy, so the image itself stands out
+const BLUR_BRIGHTNESS: f32 = 0.5;
+
+/// Longest side of the thumbnail the dominant colour is taken from
+const DOMINANT_SAMPLE_SIZE: u32 = 64;
+
+/// Place `content` (`source` scaled) at `offset` on a `width`x`height` background
+pub fn compose(
+    source: &DynamicImage,
+    content: &RgbaImage,
+    offset: (u32, u32),
+    width: u32,
+    height: u32,
+    background: Letterbox,
+) -> RgbaImage {
+    let mut output = match background {
+        Letterbox::Color([r, g, b]) => RgbaImage::from_pixel(width, height, Rgba([r, g, b, 255])),
+        Letterbox::Dominant => {
+            let [r, g, b] = dominant_color(source);
+            RgbaImage::from_pixel(width, height, Rgba([r, g, b, 255]))
+        }
+        Letterbox::Blur => blurred(source, width, height),
+        // The mirror already contains the image itself
+        Letterbox::Mirror => return mirrored(content, offset, width, height),
+    };
+
+    image::imageops::overlay(&mut output, content, offset.0 as i64, offset.1 as i64);
+    output
+}
+
+/// Most common colour of an image (black if it is fully transparent)
+fn dominant_color(source: &DynamicImage) -> [u8; 3] {
+    let thumbnail = source
+        .thumbnail(DOMINANT_SAMPLE_SIZE, DOMINANT_SAMPLE_SIZE)
+        .to_rgba8();
+
+    // The palette works on ARGB8888 frames
+    let argb: Vec<u8> = thumbnail
+        .pixels()
+        .flat_map(|&Rgba([r, g, b, a])| [b, g, r, a])
+        .collect();
+
+    crate::palette::dominant(&argb).unwrap_or([0, 0, 0])
+}
+
+/// A blurred, darkened copy of an image scaled to cover `width`x`height`
+fn blurred(source: &DynamicImage, width: u32, height: u32) -> RgbaImage {
+    // Blurring a small copy is much cheaper and looks the same once scaled up
+    let small_width = (width / BLUR_DOWNSCALE).max(1);
+    let small_height = (height / BLUR_DOWNSCALE).max(1);
+
+    let cover = (small_width as f32 / source.width().max(1) as f32)
+        .max(small_height as f32 / source.height().max(1) as f32);
+    let covering = source.thumbnail_exact(
+        ((source.width() as f32 * cover).ceil() as u32).max(small_width),
+        ((source.height() as f32 * cover).ceil() as u32).max(small_height),
+    );
+    let small = covering
+        .crop_imm(
+            (covering.width() - small_width) / 2,
+            (covering.height() - small_height) / 2,
+            small_width,
+            small_height,
+        )
+        .to_rgba8();
+
+    let mut small = image::imageops::blur(&small, BLUR_SIGMA);
+    for pixel in small.pixels_mut() {
+        for channel in &mut pixel.0[..3] {
+            *channel = (*channel as f32 * BLUR_BRIGHTNESS).round() as u8;
+        }
+        pixel[3] = 255;
+    }
+
+    image::imageops::resize(&small, width, height, FilterType::Triangle)
+}
+
+/// The content placed at `offset`, with its edges reflected out to fill `width`x`height`
+fn mirrored(content: &RgbaImage, offset: (u32, u32), width: u32, height: u32) -> RgbaImage {
+    // Only the part of the content that lands on the output is reflected
+    let visible_width = content.width().min(width.saturating_sub(offset.0));
+    let visible_height = content.height().min(height.saturating_sub(offset.1));
+    if visible_width == 0 || visible_height == 0 {
+        return RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 255]));
+    }
+
+    let columns: Vec<u32> = (0..width)
+        .map(|x| reflect(x, offset.0, visible_width))
+        .collect();
+
+    let pixels = crate::cpu_render::render_rows(width, height, |y, row| {
+        let source_y = reflect(y, offset.1, visible_height);
+        for (pixel, &source_x) in row.chunks_exact_mut(4).zip(&columns) {
+            pixel.copy_from_slice(&content.get_pixel(source_x, source_y).0);
+        }
+    });
+
+    RgbaImage::from_raw(width, height, pixels).expect("rendered frame matches its size")
+}
+
+/// Position within a run of `len` pixels starting at `start` that `v` shows when
+/// the run is mirrored back and forth in both directions
+fn reflect(v: u32, start: u32, len: u32) -> u32 {
+    let period = 2 * len as i64;
+    let position = (v as i64 - start as i64).rem_euclid(period);
+
+    if position < len as i64 {
+        position as u32
+    } else {
+        (period - 1 - position) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x2 image with a distinct colour per pixel, placed in the middle of 6x4
+    fn quad() -> (DynamicImage, RgbaImage) {
+        let content =
+            RgbaImage::from_fn(2, 2, |x, y| Rgba([x as u8 * 100, y as u8 * 100, 50, 255]));
+        (DynamicImage::ImageRgba8(content.clone()), content)
+    }
+
+    #[test]
+    fn test_reflect() {
+        // Run of 3 pixels at 2..5
+        let mapped: Vec<u32> = (0..9).map(|v| reflect(v, 2, 3)).collect();
+        assert_eq!(mapped, [1, 0, 0, 1, 2, 2, 1, 0, 0]);
+    }
+
+    #[test]
+    fn test_color_and_mirror() {
+        let (source, content) = quad();
+
+        let solid = compose(&source, &content, (2, 1), 6, 4, Letterbox::Color([1, 2, 3]));
+        assert_eq!(solid.get_pixel(0, 0).0, [1, 2, 3, 255]);
+        assert_eq!(solid.get_pixel(3, 2), content.get_pixel(1, 1));
+
+        let mirror = compose(&source, &content, (2, 1), 6, 4, Letterbox::Mirror);
+        for (x, y, pixel) in mirror.enumerate_pixels() {
+            let expected = content.get_pixel(reflect(x, 2, 2), reflect(y, 1, 2));
+            assert_eq!(pixel, expected, "({}, {})", x, y);
+        }
+        // The edge next to the image repeats it
+        assert_eq!(mirror.get_pixel(1, 1), content.get_pixel(0, 0));
+        assert_eq!(mirror.get_pixel(4, 2), content.get_pixel(1, 1));
+    }
+
+    #[test]
+    fn test_dominant_and_blur() {
+        // Mostly blue with a red stripe
+        let mut image = RgbaImage::from_pixel(40, 20, Rgba([20, 40, 200, 255]));
+        for y in 0..20 {
+            for x in 0..8 {
+                image.put_pixel(x, y, Rgba([220, 10, 10, 255]));
+            }
+        }
+        let source = DynamicImage::ImageRgba8(image);
+        let content = source.resize_exact(20, 10, FilterType::Triangle).to_rgba8();
+
+        let dominant = compose(&source, &content, (0, 5), 20, 20, Letterbox::Dominant);
+        assert_eq!(dominant.get_pixel(10, 0).0, [20, 40, 200, 255]);
+
+        let blur = compose(&source, &content, (0, 5), 20, 20, Letterbox::Blur);
+        assert_eq!(blur.dimensions(), (20, 20));
+        // Darkened, and opaque
+        let bar = blur.get_pixel(15, 1);
+        assert!(bar[2] < 150 && bar[2] > 50, "{:?}", bar);
+        assert_eq!(bar[3], 255);
+        assert_eq!(blur.get_pixel(15, 10), content.get_pixel(15, 5));
+    }
+}
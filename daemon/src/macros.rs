@@ -112,17 +112,12 @@ macro_rules! create_shader_pipeline {
 ///
 /// # Example
 /// ```
-/// commit_buffer!(layer_surface, buffer, width, height);
+/// commit_buffer!(layer_surface, buffer);
 /// ```
 #[macro_export]
 macro_rules! commit_buffer {
-    ($layer_surface:expr, $buffer:expr, $width:expr, $height:expr) => {
-        $layer_surface
-            .wl_surface()
-            .attach(Some($buffer.buffer()), 0, 0);
-        $layer_surface
-            .wl_surface()
-            .damage_buffer(0, 0, $width as i32, $height as i32);
+    ($layer_surface:expr, $buffer:expr) => {
+        $buffer.attach_to($layer_surface.wl_surface());
         $layer_surface.wl_surface().commit();
     };
 }
@@ -172,30 +167,14 @@ macro_rules! validate_enum {
 /// update_wallpaper_state!(
 ///     state,
 ///     output_filter,
-///     WallpaperType::Image(path.to_string())
+///     WallpaperType::Color(color.to_string()),
+///     WallpaperCommand::SetColor { color: color.to_string(), output: None }
 /// );
 /// ```
 #[macro_export]
 macro_rules! update_wallpaper_state {
-    ($state:expr, $output_filter:expr, $wallpaper_type:expr) => {
-        if let Some(filter) = $output_filter {
-            if filter == "all" {
-                let output_names: Vec<String> =
-                    $state.outputs.iter().map(|o| o.name.clone()).collect();
-                for name in output_names {
-                    $state.wallpapers.insert(name, $wallpaper_type.clone());
-                }
-            } else {
-                $state
-                    .wallpapers
-                    .insert(filter.to_string(), $wallpaper_type);
-            }
-        } else {
-            let output_names: Vec<String> = $state.outputs.iter().map(|o| o.name.clone()).collect();
-            for name in output_names {
-                $state.wallpapers.insert(name, $wallpaper_type.clone());
-            }
-        }
+    ($state:expr, $output_filter:expr, $wallpaper_type:expr, $command:expr) => {
+        $state.record_wallpaper($output_filter, $wallpaper_type.clone(), &$command);
     };
 }
 
@@ -1,13 +1,35 @@
+mod assignments;
+#[cfg_attr(not(feature = "video"), allow(dead_code))] // Only videos play audio
+mod audio;
 mod buffer;
+mod capture;
+mod clock;
+mod color_profile;
 mod config;
+mod cpu_render;
+mod focal_point;
+#[cfg_attr(not(feature = "video"), allow(dead_code))]
+// Only videos use all of it; SVGs use the CPU letterboxing
+mod frame_layout;
 mod gif_converter;
+mod gradient;
+mod hdr;
+mod image_cache;
+mod image_loader;
 mod ipc_server;
+mod letterbox;
 mod macros;
+mod offscreen;
+mod output_selector;
+mod output_transform;
 mod overlay_shader;
+mod palette;
 mod playlist;
 mod resource_monitor;
 mod scheduler;
 mod shader_manager;
+mod sources;
+mod svg;
 mod transition;
 mod video;
 mod wallpaper_manager;
@@ -17,13 +39,160 @@ mod wayland;
 mod gpu;
 
 use anyhow::Result;
+use clap::{Parser, Subcommand};
 use std::sync::Arc;
-use tokio::sync::{Mutex, mpsc};
+use tokio::sync::{Mutex, mpsc, oneshot};
+
+#[derive(Parser)]
+#[command(name = "momoi")]
+#[command(about = "Wayland wallpaper daemon", long_about = None)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Render a single frame to a PNG without a compositor
+    Render(RenderArgs),
+}
+
+#[derive(clap::Args)]
+struct RenderArgs {
+    /// Frame size
+    #[arg(long, value_name = "WIDTHxHEIGHT", default_value = "1920x1080")]
+    size: String,
+
+    /// Image or SVG to render
+    #[arg(long, group = "source")]
+    image: Option<String>,
+
+    /// Built-in shader to render
+    #[arg(long, group = "source")]
+    shader: Option<String>,
+
+    /// Solid colour to render (#rrggbb[aa])
+    #[arg(long, group = "source")]
+    color: Option<String>,
+
+    /// Image scaling mode (center, fill, fit, stretch, tile)
+    #[arg(short, long, default_value = "fill")]
+    scale: String,
+
+    /// Point in time to render, in seconds
+    #[arg(short, long, default_value = "0")]
+    time: f64,
+
+    /// Overlay effect to apply
+    #[arg(long)]
+    overlay: Option<String>,
+
+    /// Transition into the source from this image, #colour or shader
+    #[arg(long, value_name = "SOURCE")]
+    from: Option<String>,
+
+    /// Transition effect used with --from
+    #[arg(long, default_value = "fade", requires = "from")]
+    transition: String,
+
+    /// Transition duration in milliseconds
+    #[arg(short, long, default_value = "1000", requires = "from")]
+    duration: u64,
+
+    /// When the transition starts, in seconds
+    #[arg(long, default_value = "0", requires = "from")]
+    transition_start: f64,
+
+    /// Output PNG path
+    #[arg(short, long)]
+    output: std::path::PathBuf,
+}
+
+/// Render one frame offscreen and save it
+fn render(args: RenderArgs) -> Result<()> {
+    use offscreen::{Scene, SceneTransition, Source};
+
+    let (width, height) = args
+        .size
+        .split_once(['x', 'X'])
+        .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+        .ok_or_else(|| anyhow::anyhow!("Invalid size '{}', expected WIDTHxHEIGHT", args.size))?;
+    let scale = match args.scale.as_str() {
+        "center" => common::ScaleMode::Center,
+        "fill" => common::ScaleMode::Fill,
+        "fit" => common::ScaleMode::Fit,
+        "stretch" => common::ScaleMode::Stretch,
+        "tile" => common::ScaleMode::Tile,
+        other => anyhow::bail!("Unknown scale mode: {}", other),
+    };
+
+    let source = if let Some(name) = &args.shader {
+        let shader = shader_manager::BuiltinShader::from_str(name).ok_or_else(|| {
+            common::WallpaperError::UnknownShader {
+                shader: name.clone(),
+            }
+        })?;
+        Source::Shader {
+            shader,
+            params: None,
+        }
+    } else if let Some(color) = &args.color {
+        let (r, g, b, a) = buffer::parse_hex_color(color)
+            .ok_or_else(|| anyhow::anyhow!("Invalid color format: {}", color))?;
+        Source::Color([r, g, b, a])
+    } else if let Some(path) = &args.image {
+        Source::Image {
+            path: path.into(),
+            scale,
+        }
+    } else {
+        anyhow::bail!("Nothing to render: pass --image, --shader or --color");
+    };
+
+    let mut scene = Scene::new(source);
+    if let Some(name) = &args.overlay {
+        scene.overlay = Some(
+            overlay_shader::OverlayShader::from_str(name, &Default::default()).ok_or_else(
+                || common::WallpaperError::UnknownOverlay {
+                    overlay: name.clone(),
+                },
+            )?,
+        );
+    }
+    if let Some(from) = &args.from {
+        scene.transition = Some(SceneTransition {
+            from: Source::parse(from, scale),
+            transition_type: transition::TransitionType::from_name(&args.transition)
+                .ok_or_else(|| anyhow::anyhow!("Unknown transition: {}", args.transition))?,
+            duration: std::time::Duration::from_millis(args.duration),
+            start: std::time::Duration::from_secs_f64(args.transition_start.max(0.0)),
+        });
+    }
+
+    let time = std::time::Duration::from_secs_f64(args.time.max(0.0));
+    let image = offscreen::OffscreenRenderer::new(width, height).render(&scene, time)?;
+    capture::save_png(&image, &args.output)?;
+
+    log::info!(
+        "Rendered {}x{} frame at {:.3}s to {}",
+        width,
+        height,
+        time.as_secs_f64(),
+        args.output.display()
+    );
+    Ok(())
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
 
+    if let Some(Commands::Render(args)) = cli.command {
+        return render(args);
+    }
+
     log::info!(
         "Starting Wayland Wallpaper Daemon v{}",
         env!("CARGO_PKG_VERSION")
@@ -158,6 +327,7 @@ async fn main() -> Result<()> {
                     &playlist_cfg.extensions,
                     playlist_cfg.interval,
                     playlist_cfg.shuffle,
+                    playlist_cfg.orientation()?,
                     None, // Global playlist
                 ) {
                     Ok(playlist) => {
@@ -245,11 +415,20 @@ pub enum WallpaperCommand {
         output: Option<String>,
         scale: common::ScaleMode,
         transition: Option<common::TransitionType>,
+        /// Focal point for Fill cropping (None = sidecar file, config or detected)
+        focus: Option<common::FocalPoint>,
+        /// Background for Fit and Center (None = config default)
+        letterbox: Option<common::Letterbox>,
     },
     SetColor {
         color: String,
         output: Option<String>,
     },
+    SetGradient {
+        gradient: common::Gradient,
+        output: Option<String>,
+        transition: Option<common::TransitionType>,
+    },
     SetShader {
         shader: String,
         output: Option<String>,
@@ -260,10 +439,117 @@ pub enum WallpaperCommand {
         overlay: String,
         params: crate::overlay_shader::OverlayParams,
         output: Option<String>,
+        fade: Option<common::OverlayFade>,
     },
     ClearOverlay {
         output: Option<String>,
+        fade: Option<common::OverlayFade>,
     },
+    Capture {
+        output: Option<String>,
+        path: String,
+    },
+    SetVolume {
+        output: Option<String>,
+        volume: Option<f32>,
+        muted: Option<bool>,
+    },
+    ClearCache,
+}
+
+/// A command queued for the Wayland manager, with a channel for its outcome
+#[derive(Debug)]
+pub struct WallpaperRequest {
+    pub command: WallpaperCommand,
+    pub reply: CommandReply,
+}
+
+impl WallpaperRequest {
+    /// Create a request whose outcome can be awaited on the returned receiver
+    pub fn new(command: WallpaperCommand) -> (Self, oneshot::Receiver<common::Response>) {
+        let (tx, rx) = oneshot::channel();
+        let request = Self {
+            command,
+            reply: CommandReply(Some(tx)),
+        };
+        (request, rx)
+    }
+
+    /// Create a request nobody waits on
+    pub fn fire_and_forget(command: WallpaperCommand) -> Self {
+        Self {
+            command,
+            reply: CommandReply(None),
+        }
+    }
+}
+
+/// Reply channel receiving the per-output outcome of a [`WallpaperRequest`]
+#[derive(Debug)]
+pub struct CommandReply(Option<oneshot::Sender<common::Response>>);
+
+impl CommandReply {
+    /// Report the outcome to the requester (if anyone is listening)
+    pub fn send(self, result: &Result<Vec<common::OutputResult>>) {
+        let Some(reply) = self.0 else {
+            return;
+        };
+
+        let response = match result {
+            Ok(results) => common::Response::Applied(results.clone()),
+            Err(e) => common::Response::Error(to_wallpaper_error(e)),
+        };
+
+        // The client may have disconnected or timed out; nothing to do then
+        let _ = reply.send(response);
+    }
+}
+
+/// Convert a daemon error into an IPC error, keeping structured errors intact
+pub fn to_wallpaper_error(error: &anyhow::Error) -> common::WallpaperError {
+    if let Some(err) = error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<common::WallpaperError>())
+    {
+        return err.clone();
+    }
+
+    let message = format!("{:#}", error);
+    if error.chain().any(is_image_error) {
+        common::WallpaperError::Image(message)
+    } else if error.chain().any(|cause| cause.is::<std::io::Error>()) {
+        common::WallpaperError::Io(message)
+    } else if error.chain().any(is_wayland_error) {
+        common::WallpaperError::Wayland(message)
+    } else if error.chain().any(is_video_error) {
+        common::WallpaperError::Video(message)
+    } else {
+        common::WallpaperError::Internal(message)
+    }
+}
+
+fn is_image_error(cause: &(dyn std::error::Error + 'static)) -> bool {
+    cause.is::<image::ImageError>() || cause.is::<resvg::usvg::Error>()
+}
+
+fn is_wayland_error(cause: &(dyn std::error::Error + 'static)) -> bool {
+    cause.is::<wayland_client::ConnectError>()
+        || cause.is::<wayland_client::DispatchError>()
+        || cause.is::<wayland_client::backend::WaylandError>()
+        || cause.is::<wayland_client::globals::GlobalError>()
+        || cause.is::<wayland_client::globals::BindError>()
+}
+
+#[cfg(feature = "video")]
+fn is_video_error(cause: &(dyn std::error::Error + 'static)) -> bool {
+    cause.is::<gstreamer::glib::Error>()
+        || cause.is::<gstreamer::glib::BoolError>()
+        || cause.is::<gstreamer::StateChangeError>()
+}
+
+#[cfg(not(feature = "video"))]
+fn is_video_error(_cause: &(dyn std::error::Error + 'static)) -> bool {
+    false
 }
 
 /// Type of wallpaper content being displayed
@@ -280,11 +566,17 @@ pub struct DaemonState {
     pub start_time: std::time::Instant,
     pub outputs: Vec<common::OutputInfo>,
     pub wallpapers: std::collections::HashMap<String, common::WallpaperType>,
+    /// Wallpapers remembered for monitors that are plugged in later
+    pub assignments: assignments::Assignments,
+    /// Colour palette of what each output is showing
+    pub palettes: std::collections::HashMap<String, common::Palette>,
     pub config: Option<config::Config>,
     pub playlist: Option<playlist::PlaylistState>,
     pub scheduler: Option<scheduler::SchedulerState>,
     pub performance_mode: String,
     pub resource_stats: Option<resource_monitor::ResourceStats>,
+    /// Image cache contents, published by the Wayland thread
+    pub cache_status: Option<common::CacheStatus>,
 }
 
 impl DaemonState {
@@ -294,12 +586,30 @@ impl DaemonState {
             start_time: std::time::Instant::now(),
             outputs: Vec::new(),
             wallpapers: std::collections::HashMap::new(),
+            assignments: assignments::Assignments::default(),
+            palettes: std::collections::HashMap::new(),
             config: None,
             playlist: None,
             scheduler: None,
             performance_mode: "balanced".to_string(),
             resource_stats: None,
+            cache_status: None,
+        }
+    }
+
+    /// Record a wallpaper set on the outputs an output filter picks, and the
+    /// command that set it (without its output or transition)
+    pub fn record_wallpaper(
+        &mut self,
+        output_filter: Option<&str>,
+        wallpaper: common::WallpaperType,
+        command: &WallpaperCommand,
+    ) {
+        for name in output_selector::selected_names(output_filter, &self.outputs) {
+            self.wallpapers.insert(name, wallpaper.clone());
         }
+        self.assignments
+            .record(output_filter, command, &self.outputs);
     }
 
     pub fn uptime_secs(&self) -> u64 {
@@ -0,0 +1,249 @@
+//! Output selectors
+//!
+//! Connector names like `DP-1` change when a laptop is docked or a cable is
+//! moved, so commands and `[[output]]` sections pick outputs with selectors:
+//!
+//! - `all`, or `all-but:<selector>` for every output the selector doesn't pick
+//! - `primary`: the output at the origin of the layout (else the first one)
+//! - `landscape` / `portrait`: outputs in that orientation
+//! - `name:`, `description:`, `make:`, `model:` or `serial:` followed by a glob
+//! - anything else is a glob matched against the name and the description
+//!
+//! Globs (`*`, `?`, `[...]`) ignore case, so `model:*u2720q*` matches too.
+
+use crate::output_transform::Orientation;
+use anyhow::{Context, Result};
+use common::OutputInfo;
+use glob::{MatchOptions, Pattern};
+use std::str::FromStr;
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: false,
+    require_literal_leading_dot: false,
+};
+
+/// Which outputs a command or config section applies to
+#[derive(Debug, Clone)]
+pub enum OutputSelector {
+    All,
+    Primary,
+    Orientation(Orientation),
+    AllBut(Box<OutputSelector>),
+    Field(Field, Pattern),
+    /// Glob matched against the name and the description
+    Pattern(Pattern),
+}
+
+/// Output metadata a selector can match
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Description,
+    Make,
+    Model,
+    Serial,
+}
+
+impl FromStr for OutputSelector {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let pattern = |glob: &str| {
+            Pattern::new(glob).with_context(|| format!("Invalid output selector: {}", s))
+        };
+
+        match s.to_lowercase().as_str() {
+            "all" => return Ok(OutputSelector::All),
+            "primary" => return Ok(OutputSelector::Primary),
+            "landscape" => return Ok(OutputSelector::Orientation(Orientation::Landscape)),
+            "portrait" => return Ok(OutputSelector::Orientation(Orientation::Portrait)),
+            _ => {}
+        }
+
+        let Some((prefix, rest)) = s.split_once(':') else {
+            return Ok(OutputSelector::Pattern(pattern(s)?));
+        };
+
+        let field = match prefix.to_lowercase().as_str() {
+            "all-but" => return Ok(OutputSelector::AllBut(Box::new(rest.parse()?))),
+            "name" => Field::Name,
+            "description" | "desc" => Field::Description,
+            "make" => Field::Make,
+            "model" => Field::Model,
+            "serial" => Field::Serial,
+            // Not a known prefix, so the colon is part of the glob
+            _ => return Ok(OutputSelector::Pattern(pattern(s)?)),
+        };
+        Ok(OutputSelector::Field(field, pattern(rest)?))
+    }
+}
+
+impl OutputSelector {
+    /// Check if an output is selected, given every connected output
+    pub fn matches(&self, output: &OutputInfo, outputs: &[OutputInfo]) -> bool {
+        match self {
+            OutputSelector::All => true,
+            OutputSelector::Primary => primary(outputs).is_some_and(|p| p.name == output.name),
+            OutputSelector::Orientation(orientation) => {
+                Orientation::of(output.width, output.height) == *orientation
+            }
+            OutputSelector::AllBut(selector) => !selector.matches(output, outputs),
+            OutputSelector::Field(field, pattern) => field
+                .value(output)
+                .is_some_and(|value| pattern.matches_with(value, MATCH_OPTIONS)),
+            OutputSelector::Pattern(pattern) => [Field::Name, Field::Description]
+                .iter()
+                .filter_map(|field| field.value(output))
+                .any(|value| pattern.matches_with(value, MATCH_OPTIONS)),
+        }
+    }
+
+    /// The outputs this selector picks
+    pub fn select<'a>(&self, outputs: &'a [OutputInfo]) -> Vec<&'a OutputInfo> {
+        outputs
+            .iter()
+            .filter(|output| self.matches(output, outputs))
+            .collect()
+    }
+}
+
+impl Field {
+    fn value(self, output: &OutputInfo) -> Option<&str> {
+        match self {
+            Field::Name => Some(&output.name),
+            Field::Description => output.description.as_deref(),
+            Field::Make => Some(&output.make),
+            Field::Model => Some(&output.model),
+            Field::Serial => output.serial.as_deref(),
+        }
+    }
+}
+
+/// The output at the layout's origin, or the first one if none is there
+fn primary(outputs: &[OutputInfo]) -> Option<&OutputInfo> {
+    outputs
+        .iter()
+        .find(|output| output.position == Some((0, 0)))
+        .or(outputs.first())
+}
+
+/// Names of the outputs a command's output filter picks (all of them without
+/// one; none if it doesn't parse)
+pub fn selected_names(filter: Option<&str>, outputs: &[OutputInfo]) -> Vec<String> {
+    let selector = match filter.map(str::parse).transpose() {
+        Ok(selector) => selector.unwrap_or(OutputSelector::All),
+        Err(e) => {
+            log::warn!("{:#}", e);
+            return Vec::new();
+        }
+    };
+
+    selector
+        .select(outputs)
+        .into_iter()
+        .map(|output| output.name.clone())
+        .collect()
+}
+
+/// Find the serial number in an output description
+///
+/// wl_output has no serial, but wlroots-based compositors describe outputs as
+/// "make model serial (connector)", so it's what's left between the model and
+/// the connector.
+pub fn serial_from_description(description: &str, make: &str, model: &str) -> Option<String> {
+    let rest = description.strip_prefix(make)?.trim_start();
+    let rest = rest.strip_prefix(model)?.trim();
+    let rest = match rest.rfind(" (") {
+        Some(index) if rest.ends_with(')') => &rest[..index],
+        _ if rest.starts_with('(') && rest.ends_with(')') => "",
+        _ => rest,
+    };
+
+    let serial = rest.trim();
+    (!serial.is_empty() && !serial.eq_ignore_ascii_case("unknown")).then(|| serial.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(name: &str, (width, height): (u32, u32), position: (i32, i32)) -> OutputInfo {
+        OutputInfo {
+            name: name.to_string(),
+            width,
+            height,
+            position: Some(position),
+            ..Default::default()
+        }
+    }
+
+    fn outputs() -> Vec<OutputInfo> {
+        let mut laptop = output("eDP-1", (1920, 1200), (2560, 0));
+        laptop.make = "BOE".to_string();
+        laptop.model = "0x0BCA".to_string();
+
+        let mut dell = output("DP-3", (2560, 1440), (0, 0));
+        dell.make = "Dell Inc.".to_string();
+        dell.model = "DELL U2720Q".to_string();
+        dell.serial = Some("8XYZ123".to_string());
+        dell.description = Some("Dell Inc. DELL U2720Q 8XYZ123 (DP-3)".to_string());
+
+        let side = output("HDMI-A-1", (1080, 1920), (-1080, 0));
+        vec![laptop, dell, side]
+    }
+
+    fn names(selector: &str) -> Vec<String> {
+        selected_names(Some(selector), &outputs())
+    }
+
+    #[test]
+    fn test_selectors() {
+        assert_eq!(names("DP-3"), ["DP-3"]);
+        assert_eq!(names("dp-*"), ["DP-3"]);
+        assert_eq!(names("*Dell*"), ["DP-3"]);
+        assert_eq!(names("make:dell*"), ["DP-3"]);
+        assert_eq!(names("model:*U2720Q"), ["DP-3"]);
+        assert_eq!(names("serial:8XYZ123"), ["DP-3"]);
+        assert_eq!(names("name:HDMI-*"), ["HDMI-A-1"]);
+        // Outputs without a serial or description don't match those
+        assert_eq!(names("serial:*"), ["DP-3"]);
+
+        assert_eq!(names("primary"), ["DP-3"]);
+        assert_eq!(names("portrait"), ["HDMI-A-1"]);
+        assert_eq!(names("landscape"), ["eDP-1", "DP-3"]);
+        assert_eq!(names("all-but:eDP-1"), ["DP-3", "HDMI-A-1"]);
+        assert_eq!(names("all-but:portrait"), ["eDP-1", "DP-3"]);
+        assert_eq!(names("all").len(), 3);
+        assert_eq!(selected_names(None, &outputs()).len(), 3);
+
+        // Unknown prefixes are part of the glob
+        assert!(names("foo:bar").is_empty());
+        assert!("[".parse::<OutputSelector>().is_err());
+        assert!(names("make:[").is_empty());
+    }
+
+    #[test]
+    fn test_primary_without_origin() {
+        let outputs = vec![output("DP-1", (1920, 1080), (1920, 0))];
+        assert_eq!(selected_names(Some("primary"), &outputs), ["DP-1"]);
+        assert!(selected_names(Some("primary"), &[]).is_empty());
+    }
+
+    #[test]
+    fn test_serial_from_description() {
+        let serial = |description| serial_from_description(description, "Dell Inc.", "DELL U2720Q");
+        assert_eq!(
+            serial("Dell Inc. DELL U2720Q 8XYZ123 (DP-3)"),
+            Some("8XYZ123".to_string())
+        );
+        assert_eq!(
+            serial("Dell Inc. DELL U2720Q 8XYZ123"),
+            Some("8XYZ123".to_string())
+        );
+        assert_eq!(serial("Dell Inc. DELL U2720Q (DP-3)"), None);
+        assert_eq!(serial("Dell Inc. DELL U2720Q Unknown (DP-3)"), None);
+        assert_eq!(serial("Built-in display"), None);
+    }
+}
@@ -0,0 +1,217 @@
+//! Rotated and flipped outputs
+//!
+//! Wallpapers are rendered the way the surface is laid out, which is portrait
+//! on a monitor turned on its side. When the compositor reports that the
+//! surface is on a rotated output, frames are turned into the output's own
+//! orientation as they're written to a buffer, and the buffer transform tells
+//! the compositor so; it can then show them without rotating every frame.
+//!
+//! Orientations also pick wallpapers: outputs can have a portrait wallpaper,
+//! and playlists can skip images that don't suit their monitor.
+
+use std::str::FromStr;
+use wayland_client::protocol::wl_output::Transform;
+
+/// Whether something is wider or taller
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+}
+
+impl Orientation {
+    /// Orientation of an area (squares count as landscape)
+    pub fn of(width: u32, height: u32) -> Self {
+        if height > width {
+            Orientation::Portrait
+        } else {
+            Orientation::Landscape
+        }
+    }
+
+    /// Check if an image of this size suits the orientation (squares suit both)
+    pub fn fits(self, width: u32, height: u32) -> bool {
+        match self {
+            Orientation::Landscape => width >= height,
+            Orientation::Portrait => height >= width,
+        }
+    }
+}
+
+impl FromStr for Orientation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "landscape" => Ok(Orientation::Landscape),
+            "portrait" => Ok(Orientation::Portrait),
+            _ => anyhow::bail!(
+                "Invalid orientation: {} (expected landscape or portrait)",
+                s
+            ),
+        }
+    }
+}
+
+/// Size of a buffer holding a `width`x`height` frame under a transform
+pub fn buffer_size(width: u32, height: u32, transform: Transform) -> (u32, u32) {
+    if swaps_axes(transform) {
+        (height, width)
+    } else {
+        (width, height)
+    }
+}
+
+/// Turn an ARGB8888 frame from surface to buffer orientation
+pub fn to_buffer(frame: &[u8], width: u32, height: u32, transform: Transform) -> Vec<u8> {
+    remap(frame, (width, height), transform)
+}
+
+/// Turn buffer contents back into a `width`x`height` frame in surface orientation
+pub fn from_buffer(data: &[u8], width: u32, height: u32, transform: Transform) -> Vec<u8> {
+    let (buffer_width, buffer_height) = buffer_size(width, height, transform);
+    remap(data, (buffer_width, buffer_height), inverse(transform))
+}
+
+/// Name of a transform as shown to users (e.g. "flipped-90")
+pub fn transform_name(transform: Transform) -> &'static str {
+    match transform {
+        Transform::_90 => "90",
+        Transform::_180 => "180",
+        Transform::_270 => "270",
+        Transform::Flipped => "flipped",
+        Transform::Flipped90 => "flipped-90",
+        Transform::Flipped180 => "flipped-180",
+        Transform::Flipped270 => "flipped-270",
+        _ => "normal",
+    }
+}
+
+fn swaps_axes(transform: Transform) -> bool {
+    matches!(
+        transform,
+        Transform::_90 | Transform::_270 | Transform::Flipped90 | Transform::Flipped270
+    )
+}
+
+/// The transform undoing another one
+fn inverse(transform: Transform) -> Transform {
+    match transform {
+        Transform::_90 => Transform::_270,
+        Transform::_270 => Transform::_90,
+        // Flips and half turns undo themselves
+        other => other,
+    }
+}
+
+/// Where a pixel of a `width`x`height` frame lands in the transformed buffer
+fn buffer_coords(
+    transform: Transform,
+    (width, height): (u32, u32),
+    (x, y): (u32, u32),
+) -> (u32, u32) {
+    let (right, bottom) = (width - 1 - x, height - 1 - y);
+    match transform {
+        Transform::_90 => (y, right),
+        Transform::_180 => (right, bottom),
+        Transform::_270 => (bottom, x),
+        Transform::Flipped => (right, y),
+        Transform::Flipped90 => (y, x),
+        Transform::Flipped180 => (x, bottom),
+        Transform::Flipped270 => (bottom, right),
+        _ => (x, y),
+    }
+}
+
+/// Transform an ARGB8888 frame, gathering each output row in parallel
+fn remap(source: &[u8], (width, height): (u32, u32), transform: Transform) -> Vec<u8> {
+    if transform == Transform::Normal {
+        return source.to_vec();
+    }
+
+    let (out_width, out_height) = buffer_size(width, height, transform);
+    let back = inverse(transform);
+    crate::cpu_render::render_rows(out_width, out_height, |y, row| {
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let (source_x, source_y) = buffer_coords(back, (out_width, out_height), (x as u32, y));
+            let index = (source_y as usize * width as usize + source_x as usize) * 4;
+            pixel.copy_from_slice(&source[index..index + 4]);
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [Transform; 8] = [
+        Transform::Normal,
+        Transform::_90,
+        Transform::_180,
+        Transform::_270,
+        Transform::Flipped,
+        Transform::Flipped90,
+        Transform::Flipped180,
+        Transform::Flipped270,
+    ];
+
+    /// A 3x2 frame whose pixels are numbered 0..6 in their blue channel
+    fn numbered() -> Vec<u8> {
+        (0..6u8).flat_map(|i| [i, 0, 0, 255]).collect()
+    }
+
+    fn blues(frame: &[u8]) -> Vec<u8> {
+        frame.chunks_exact(4).map(|pixel| pixel[0]).collect()
+    }
+
+    #[test]
+    fn test_to_buffer() {
+        // 0 1 2
+        // 3 4 5
+        let frame = numbered();
+        let buffer = |transform| blues(&to_buffer(&frame, 3, 2, transform));
+
+        assert_eq!(buffer(Transform::Normal), [0, 1, 2, 3, 4, 5]);
+        // The buffer holds the frame turned a quarter counter-clockwise
+        assert_eq!(buffer(Transform::_90), [2, 5, 1, 4, 0, 3]);
+        assert_eq!(buffer(Transform::_180), [5, 4, 3, 2, 1, 0]);
+        assert_eq!(buffer(Transform::_270), [3, 0, 4, 1, 5, 2]);
+        assert_eq!(buffer(Transform::Flipped), [2, 1, 0, 5, 4, 3]);
+        assert_eq!(buffer(Transform::Flipped90), [0, 3, 1, 4, 2, 5]);
+
+        assert_eq!(buffer_size(3, 2, Transform::_90), (2, 3));
+        assert_eq!(buffer_size(3, 2, Transform::Flipped180), (3, 2));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let frame = numbered();
+        for transform in ALL {
+            let buffer = to_buffer(&frame, 3, 2, transform);
+            assert_eq!(
+                from_buffer(&buffer, 3, 2, transform),
+                frame,
+                "{:?}",
+                transform
+            );
+        }
+    }
+
+    #[test]
+    fn test_orientation() {
+        assert_eq!(Orientation::of(2560, 1440), Orientation::Landscape);
+        assert_eq!(Orientation::of(1440, 2560), Orientation::Portrait);
+        assert_eq!(Orientation::of(1000, 1000), Orientation::Landscape);
+
+        assert!(Orientation::Portrait.fits(1080, 1920));
+        assert!(!Orientation::Portrait.fits(1920, 1080));
+        assert!(Orientation::Portrait.fits(500, 500));
+        assert!(Orientation::Landscape.fits(500, 500));
+
+        assert_eq!(
+            "Portrait".parse::<Orientation>().unwrap(),
+            Orientation::Portrait
+        );
+        assert!("sideways".parse::<Orientation>().is_err());
+    }
+}
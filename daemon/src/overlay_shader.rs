@@ -1,6 +1,8 @@
 use crate::log_params_once;
+use crate::transition::EasingFunction;
 use anyhow::Result;
-use std::time::Instant;
+use rayon::prelude::*;
+use std::time::{Duration, Instant};
 
 /// Overlay shader types that render on top of existing wallpapers
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -63,6 +65,88 @@ impl OverlayShader {
         }
     }
 
+    /// Check whether two overlays are the same effect (ignoring parameters)
+    pub fn same_kind(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    /// Interpolate parameters towards `other` (t = 0.0 is `self`, 1.0 is `other`).
+    ///
+    /// Overlays of different kinds can't be interpolated, so `other` is returned as-is.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let mix = |a: f32, b: f32| a + (b - a) * t;
+
+        match (*self, *other) {
+            (OverlayShader::Vignette { strength: a }, OverlayShader::Vignette { strength: b }) => {
+                OverlayShader::Vignette {
+                    strength: mix(a, b),
+                }
+            }
+            (
+                OverlayShader::Scanlines {
+                    intensity: ai,
+                    line_width: aw,
+                },
+                OverlayShader::Scanlines {
+                    intensity: bi,
+                    line_width: bw,
+                },
+            ) => OverlayShader::Scanlines {
+                intensity: mix(ai, bi),
+                line_width: mix(aw, bw),
+            },
+            (
+                OverlayShader::FilmGrain { intensity: a },
+                OverlayShader::FilmGrain { intensity: b },
+            ) => OverlayShader::FilmGrain {
+                intensity: mix(a, b),
+            },
+            (
+                OverlayShader::ChromaticAberration { offset: a },
+                OverlayShader::ChromaticAberration { offset: b },
+            ) => OverlayShader::ChromaticAberration { offset: mix(a, b) },
+            (
+                OverlayShader::CRT {
+                    curvature: ac,
+                    scanline_intensity: ai,
+                },
+                OverlayShader::CRT {
+                    curvature: bc,
+                    scanline_intensity: bi,
+                },
+            ) => OverlayShader::CRT {
+                curvature: mix(ac, bc),
+                scanline_intensity: mix(ai, bi),
+            },
+            (
+                OverlayShader::Pixelate { pixel_size: a },
+                OverlayShader::Pixelate { pixel_size: b },
+            ) => OverlayShader::Pixelate {
+                pixel_size: mix(a as f32, b as f32).round().max(1.0) as u32,
+            },
+            (
+                OverlayShader::ColorTint {
+                    r: ar,
+                    g: ag,
+                    b: ab,
+                    strength: a_strength,
+                },
+                OverlayShader::ColorTint {
+                    r: br,
+                    g: bg,
+                    b: bb,
+                    strength: b_strength,
+                },
+            ) => OverlayShader::ColorTint {
+                r: mix(ar, br),
+                g: mix(ag, bg),
+                b: mix(ab, bb),
+                strength: mix(a_strength, b_strength),
+            },
+            _ => *other,
+        }
+    }
+
     pub fn name(&self) -> &str {
         match self {
             OverlayShader::Vignette { .. } => "vignette",
@@ -150,11 +234,49 @@ pub struct OverlayParams {
     pub b: Option<f32>,
 }
 
+/// An in-progress fade or parameter change
+#[derive(Debug, Clone, Copy)]
+struct OverlayAnimation {
+    /// Overlay parameters at the start of the animation
+    from: OverlayShader,
+    /// Opacity at the start of the animation
+    from_opacity: f32,
+    /// Opacity at the end of the animation
+    to_opacity: f32,
+    start_time: Instant,
+    duration: Duration,
+    easing: EasingFunction,
+}
+
+impl OverlayAnimation {
+    /// Get the eased progress (0.0 to 1.0) at `now`
+    fn progress(&self, now: Instant) -> f32 {
+        let elapsed = now.saturating_duration_since(self.start_time);
+        if elapsed >= self.duration {
+            1.0
+        } else {
+            self.easing
+                .apply(elapsed.as_secs_f32() / self.duration.as_secs_f32())
+        }
+    }
+
+    fn is_complete(&self, now: Instant) -> bool {
+        now.saturating_duration_since(self.start_time) >= self.duration
+    }
+}
+
 /// Overlay shader manager
 pub struct OverlayManager {
+    /// Target overlay (the final parameters once any animation completes)
     overlay: OverlayShader,
+    /// Target opacity of the effect (0.0 = invisible, 1.0 = fully applied)
+    opacity: f32,
+    /// Active fade or parameter interpolation
+    animation: Option<OverlayAnimation>,
     time: Instant,
     frame: u64,
+    /// Time source for effects and animations
+    clock: crate::clock::Clock,
 }
 
 impl OverlayManager {
@@ -162,9 +284,40 @@ impl OverlayManager {
     pub fn new(overlay: OverlayShader) -> Self {
         OverlayManager {
             overlay,
+            opacity: 1.0,
+            animation: None,
             time: Instant::now(),
             frame: 0,
+            clock: crate::clock::Clock::system(),
+        }
+    }
+
+    /// Create an overlay manager that fades the effect in from nothing
+    pub fn with_fade_in(
+        overlay: OverlayShader,
+        duration: Duration,
+        easing: EasingFunction,
+    ) -> Self {
+        let mut manager = Self::new(overlay);
+        manager.animation = Some(OverlayAnimation {
+            from: overlay,
+            from_opacity: 0.0,
+            to_opacity: 1.0,
+            start_time: manager.clock.now(),
+            duration,
+            easing,
+        });
+        manager
+    }
+
+    /// Drive effects and animations from a different clock (restarting them at its current time)
+    pub fn with_clock(mut self, clock: crate::clock::Clock) -> Self {
+        self.time = clock.now();
+        if let Some(animation) = &mut self.animation {
+            animation.start_time = clock.now();
         }
+        self.clock = clock;
+        self
     }
 
     /// Get reference to the overlay shader
@@ -172,16 +325,116 @@ impl OverlayManager {
         &self.overlay
     }
 
+    /// Animate from the currently displayed state to a new overlay.
+    ///
+    /// Parameters are interpolated when the new overlay is the same effect;
+    /// a different effect replaces the current one and fades in.
+    pub fn animate_to(
+        &mut self,
+        overlay: OverlayShader,
+        duration: Duration,
+        easing: EasingFunction,
+    ) {
+        let (current, current_opacity) = self.current_state();
+
+        let (from, from_opacity) = if current.same_kind(&overlay) {
+            (current, current_opacity)
+        } else {
+            (overlay, 0.0)
+        };
+
+        self.overlay = overlay;
+        self.opacity = 1.0;
+        self.animation = Some(OverlayAnimation {
+            from,
+            from_opacity,
+            to_opacity: 1.0,
+            start_time: self.clock.now(),
+            duration,
+            easing,
+        });
+    }
+
+    /// Fade the effect out from the currently displayed state
+    pub fn fade_out(&mut self, duration: Duration, easing: EasingFunction) {
+        let (current, current_opacity) = self.current_state();
+
+        self.opacity = 0.0;
+        self.animation = Some(OverlayAnimation {
+            from: current,
+            from_opacity: current_opacity,
+            to_opacity: 0.0,
+            start_time: self.clock.now(),
+            duration,
+            easing,
+        });
+    }
+
+    /// Check if a fade or parameter change is still pending
+    ///
+    /// Stays true until the final frame has been applied, so static wallpapers
+    /// get redrawn with the end state.
+    pub fn is_animating(&self) -> bool {
+        self.animation.is_some()
+    }
+
+    /// End an animation whose time is up, whether or not a frame was drawn with it
+    ///
+    /// Outputs with nothing to redraw (e.g. a solid colour) never apply the
+    /// final frame, so their fades are settled this way instead.
+    pub fn settle(&mut self) {
+        if self
+            .animation
+            .is_some_and(|anim| anim.is_complete(self.clock.now()))
+        {
+            log::debug!(
+                "Overlay '{}' animation complete (opacity: {:.2})",
+                self.overlay.name(),
+                self.opacity
+            );
+            self.animation = None;
+        }
+    }
+
+    /// Check if the overlay has completely faded out and can be removed
+    pub fn is_faded_out(&self) -> bool {
+        self.animation.is_none() && self.opacity <= 0.0
+    }
+
+    /// Get the overlay parameters and opacity to display right now
+    fn current_state(&self) -> (OverlayShader, f32) {
+        match &self.animation {
+            Some(anim) => {
+                let t = anim.progress(self.clock.now());
+                (
+                    anim.from.lerp(&self.overlay, t),
+                    anim.from_opacity + (anim.to_opacity - anim.from_opacity) * t,
+                )
+            }
+            None => (self.overlay, self.opacity),
+        }
+    }
+
     /// Get elapsed time since overlay was created
     #[allow(dead_code)] // Part of public API for time-based overlay effects
     pub fn elapsed_time(&self) -> f32 {
-        self.time.elapsed().as_secs_f32()
+        self.clock.elapsed(self.time).as_secs_f32()
     }
 
     /// Apply overlay effect to existing ARGB buffer
     pub fn apply_overlay(&mut self, buffer: &mut [u8], width: u32, height: u32) -> Result<()> {
         self.frame += 1;
-        let time = self.time.elapsed().as_secs_f32();
+        let time = self.clock.elapsed(self.time).as_secs_f32();
+
+        let (overlay, opacity) = self.current_state();
+        self.settle();
+
+        if opacity <= 0.0 {
+            return Ok(());
+        }
+
+        // Keep the untouched frame around so a partially faded effect can be mixed back in
+        let original = (opacity < 1.0).then(|| buffer.to_vec());
 
         // Log first few frames to see parameters
         if self.frame <= 3 {
@@ -196,7 +449,7 @@ impl OverlayManager {
             );
         }
 
-        match self.overlay {
+        match overlay {
             OverlayShader::Vignette { strength } => {
                 log_params_once!(self.frame, "Vignette", "strength" => strength);
                 self.apply_vignette(buffer, width, height, strength);
@@ -206,15 +459,15 @@ impl OverlayManager {
                 line_width,
             } => {
                 log_params_once!(self.frame, "Scanlines", "intensity" => intensity, "line_width" => line_width);
-                self.apply_scanlines(buffer, width, height, intensity, line_width);
+                self.apply_scanlines(buffer, width, intensity, line_width);
             }
             OverlayShader::FilmGrain { intensity } => {
                 log_params_once!(self.frame, "FilmGrain", "intensity" => intensity);
-                self.apply_film_grain(buffer, width, height, intensity, time);
+                self.apply_film_grain(buffer, width, intensity, time);
             }
             OverlayShader::ChromaticAberration { offset } => {
                 log_params_once!(self.frame, "ChromaticAberration", "offset" => offset);
-                self.apply_chromatic_aberration(buffer, width, height, offset);
+                self.apply_chromatic_aberration(buffer, width, offset);
             }
             OverlayShader::CRT {
                 curvature,
@@ -225,110 +478,92 @@ impl OverlayManager {
             }
             OverlayShader::Pixelate { pixel_size } => {
                 log_params_once!(self.frame, "Pixelate", "pixel_size" => pixel_size);
-                self.apply_pixelate(buffer, width, height, pixel_size);
+                self.apply_pixelate(buffer, width, pixel_size);
             }
             OverlayShader::ColorTint { r, g, b, strength } => {
                 log_params_once!(self.frame, "ColorTint", "r" => r, "g" => g, "b" => b, "strength" => strength);
-                self.apply_color_tint(buffer, width, height, r, g, b, strength);
+                self.apply_color_tint(buffer, width, r, g, b, strength);
             }
         }
 
+        if let Some(original) = original {
+            Self::mix_with_original(buffer, &original, opacity);
+        }
+
         Ok(())
     }
 
+    /// Blend the processed buffer back towards the original by `opacity`
+    fn mix_with_original(buffer: &mut [u8], original: &[u8], opacity: f32) {
+        crate::cpu_render::mix(buffer, original, 1.0 - opacity);
+    }
+
     /// Apply vignette effect (darken edges)
     fn apply_vignette(&self, buffer: &mut [u8], width: u32, height: u32, strength: f32) {
         let center_x = width as f32 / 2.0;
         let center_y = height as f32 / 2.0;
         let max_dist = (center_x * center_x + center_y * center_y).sqrt();
 
-        for y in 0..height {
-            for x in 0..width {
-                let idx = ((y * width + x) * 4) as usize;
-
+        crate::cpu_render::for_each_row(buffer, width, |y, row| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
                 let dx = x as f32 - center_x;
                 let dy = y as f32 - center_y;
                 let dist = (dx * dx + dy * dy).sqrt();
                 let vignette = 1.0 - (dist / max_dist * strength).min(1.0);
 
-                buffer[idx] = (buffer[idx] as f32 * vignette) as u8; // B
-                buffer[idx + 1] = (buffer[idx + 1] as f32 * vignette) as u8; // G
-                buffer[idx + 2] = (buffer[idx + 2] as f32 * vignette) as u8; // R
+                pixel[0] = (pixel[0] as f32 * vignette) as u8; // B
+                pixel[1] = (pixel[1] as f32 * vignette) as u8; // G
+                pixel[2] = (pixel[2] as f32 * vignette) as u8; // R
             }
-        }
+        });
     }
 
     /// Apply scanlines effect
-    fn apply_scanlines(
-        &self,
-        buffer: &mut [u8],
-        width: u32,
-        height: u32,
-        intensity: f32,
-        line_width: f32,
-    ) {
-        for y in 0..height {
+    fn apply_scanlines(&self, buffer: &mut [u8], width: u32, intensity: f32, line_width: f32) {
+        crate::cpu_render::for_each_row(buffer, width, |y, row| {
             let scanline = ((y as f32 / line_width).sin() * 0.5 + 0.5) * intensity;
             let darken = 1.0 - scanline;
 
-            for x in 0..width {
-                let idx = ((y * width + x) * 4) as usize;
-
-                buffer[idx] = (buffer[idx] as f32 * darken) as u8;
-                buffer[idx + 1] = (buffer[idx + 1] as f32 * darken) as u8;
-                buffer[idx + 2] = (buffer[idx + 2] as f32 * darken) as u8;
+            for pixel in row.chunks_exact_mut(4) {
+                pixel[0] = (pixel[0] as f32 * darken) as u8;
+                pixel[1] = (pixel[1] as f32 * darken) as u8;
+                pixel[2] = (pixel[2] as f32 * darken) as u8;
             }
-        }
+        });
     }
 
     /// Apply film grain effect
-    fn apply_film_grain(
-        &self,
-        buffer: &mut [u8],
-        width: u32,
-        height: u32,
-        intensity: f32,
-        time: f32,
-    ) {
-        for y in 0..height {
-            for x in 0..width {
-                let idx = ((y * width + x) * 4) as usize;
-
+    fn apply_film_grain(&self, buffer: &mut [u8], width: u32, intensity: f32, time: f32) {
+        crate::cpu_render::for_each_row(buffer, width, |y, row| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
                 // Pseudo-random noise
                 let seed = (x as f32 * 12.9898 + y as f32 * 78.233 + time * 43758.5453).sin();
                 let noise = (seed.fract() - 0.5) * intensity * 255.0;
 
-                buffer[idx] = (buffer[idx] as f32 + noise).clamp(0.0, 255.0) as u8;
-                buffer[idx + 1] = (buffer[idx + 1] as f32 + noise).clamp(0.0, 255.0) as u8;
-                buffer[idx + 2] = (buffer[idx + 2] as f32 + noise).clamp(0.0, 255.0) as u8;
+                pixel[0] = (pixel[0] as f32 + noise).clamp(0.0, 255.0) as u8;
+                pixel[1] = (pixel[1] as f32 + noise).clamp(0.0, 255.0) as u8;
+                pixel[2] = (pixel[2] as f32 + noise).clamp(0.0, 255.0) as u8;
             }
-        }
+        });
     }
 
     /// Apply chromatic aberration (RGB split)
-    fn apply_chromatic_aberration(&self, buffer: &mut [u8], width: u32, height: u32, offset: f32) {
-        let mut new_buffer = buffer.to_vec();
+    fn apply_chromatic_aberration(&self, buffer: &mut [u8], width: u32, offset: f32) {
+        let original = buffer.to_vec();
         let offset_i = offset as i32;
 
-        for y in 0..height {
-            for x in 0..width {
-                let idx = ((y * width + x) * 4) as usize;
-
+        crate::cpu_render::for_each_row_with(buffer, &original, width, |_, row, source| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
                 // Sample red from offset position
-                let rx = (x as i32 + offset_i).clamp(0, width as i32 - 1) as u32;
-                let r_idx = ((y * width + rx) * 4) as usize;
+                let rx = (x as i32 + offset_i).clamp(0, width as i32 - 1) as usize;
 
                 // Sample blue from opposite offset
-                let bx = (x as i32 - offset_i).clamp(0, width as i32 - 1) as u32;
-                let b_idx = ((y * width + bx) * 4) as usize;
+                let bx = (x as i32 - offset_i).clamp(0, width as i32 - 1) as usize;
 
-                new_buffer[idx] = buffer[b_idx]; // B (shifted left)
-                new_buffer[idx + 1] = buffer[idx + 1]; // G (unchanged)
-                new_buffer[idx + 2] = buffer[r_idx + 2]; // R (shifted right)
+                pixel[0] = source[bx * 4]; // B (shifted left)
+                pixel[2] = source[rx * 4 + 2]; // R (shifted right)
             }
-        }
-
-        buffer.copy_from_slice(&new_buffer);
+        });
     }
 
     /// Apply CRT effect (combines scanlines and slight curvature simulation)
@@ -341,69 +576,70 @@ impl OverlayManager {
         scanline_intensity: f32,
     ) {
         // Apply scanlines
-        self.apply_scanlines(buffer, width, height, scanline_intensity, 2.0);
+        self.apply_scanlines(buffer, width, scanline_intensity, 2.0);
 
         // Add slight vignette
         self.apply_vignette(buffer, width, height, 0.3);
 
         // Add subtle RGB separation at edges
         let center_x = width as f32 / 2.0;
-        for y in 0..height {
-            for x in 0..width {
-                let idx = ((y * width + x) * 4) as usize;
-
+        crate::cpu_render::for_each_row(buffer, width, |_, row| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
                 let edge_dist = ((x as f32 - center_x).abs() / center_x).powf(2.0);
                 if edge_dist > 0.7 {
                     // Slight color shift at edges
                     let shift = ((edge_dist - 0.7) * 50.0) as u8;
-                    buffer[idx] = buffer[idx].saturating_sub(shift);
-                    buffer[idx + 2] = buffer[idx + 2].saturating_add(shift);
+                    pixel[0] = pixel[0].saturating_sub(shift);
+                    pixel[2] = pixel[2].saturating_add(shift);
                 }
             }
-        }
+        });
     }
 
     /// Apply pixelate effect
-    fn apply_pixelate(&self, buffer: &mut [u8], width: u32, height: u32, pixel_size: u32) {
-        let mut new_buffer = buffer.to_vec();
-
-        for block_y in (0..height).step_by(pixel_size as usize) {
-            for block_x in (0..width).step_by(pixel_size as usize) {
-                // Calculate average color of block
-                let mut avg_b = 0u32;
-                let mut avg_g = 0u32;
-                let mut avg_r = 0u32;
-                let mut count = 0u32;
-
-                for y in block_y..(block_y + pixel_size).min(height) {
-                    for x in block_x..(block_x + pixel_size).min(width) {
-                        let idx = ((y * width + x) * 4) as usize;
-                        avg_b += buffer[idx] as u32;
-                        avg_g += buffer[idx + 1] as u32;
-                        avg_r += buffer[idx + 2] as u32;
-                        count += 1;
+    fn apply_pixelate(&self, buffer: &mut [u8], width: u32, pixel_size: u32) {
+        let stride = width as usize * 4;
+        let pixel_size = pixel_size.max(1) as usize;
+        if stride == 0 {
+            return;
+        }
+
+        // Blocks in different block rows are independent, so each row of blocks is one work item
+        buffer
+            .par_chunks_mut(stride * pixel_size)
+            .for_each(|block_rows| {
+                for block_x in (0..width as usize).step_by(pixel_size) {
+                    let block_end = (block_x + pixel_size).min(width as usize);
+
+                    // Calculate average color of block
+                    let mut avg_b = 0u32;
+                    let mut avg_g = 0u32;
+                    let mut avg_r = 0u32;
+                    let mut count = 0u32;
+
+                    for row in block_rows.chunks(stride) {
+                        for pixel in row[block_x * 4..block_end * 4].chunks_exact(4) {
+                            avg_b += pixel[0] as u32;
+                            avg_g += pixel[1] as u32;
+                            avg_r += pixel[2] as u32;
+                            count += 1;
+                        }
                     }
-                }
 
-                if count > 0 {
                     avg_b /= count;
                     avg_g /= count;
                     avg_r /= count;
 
                     // Fill block with average color
-                    for y in block_y..(block_y + pixel_size).min(height) {
-                        for x in block_x..(block_x + pixel_size).min(width) {
-                            let idx = ((y * width + x) * 4) as usize;
-                            new_buffer[idx] = avg_b as u8;
-                            new_buffer[idx + 1] = avg_g as u8;
-                            new_buffer[idx + 2] = avg_r as u8;
+                    for row in block_rows.chunks_mut(stride) {
+                        for pixel in row[block_x * 4..block_end * 4].chunks_exact_mut(4) {
+                            pixel[0] = avg_b as u8;
+                            pixel[1] = avg_g as u8;
+                            pixel[2] = avg_r as u8;
                         }
                     }
                 }
-            }
-        }
-
-        buffer.copy_from_slice(&new_buffer);
+            });
     }
 
     /// Apply color tint overlay
@@ -411,24 +647,109 @@ impl OverlayManager {
         &self,
         buffer: &mut [u8],
         width: u32,
-        height: u32,
         r: f32,
         g: f32,
         b: f32,
         strength: f32,
     ) {
-        for y in 0..height {
-            for x in 0..width {
-                let idx = ((y * width + x) * 4) as usize;
+        crate::cpu_render::for_each_row(buffer, width, |_, row| {
+            for pixel in row.chunks_exact_mut(4) {
+                let orig_b = pixel[0] as f32;
+                let orig_g = pixel[1] as f32;
+                let orig_r = pixel[2] as f32;
+
+                pixel[0] = (orig_b * (1.0 - strength) + orig_b * b * strength) as u8;
+                pixel[1] = (orig_g * (1.0 - strength) + orig_g * g * strength) as u8;
+                pixel[2] = (orig_r * (1.0 - strength) + orig_r * r * strength) as u8;
+            }
+        });
+    }
+}
 
-                let orig_b = buffer[idx] as f32;
-                let orig_g = buffer[idx + 1] as f32;
-                let orig_r = buffer[idx + 2] as f32;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-                buffer[idx] = (orig_b * (1.0 - strength) + orig_b * b * strength) as u8;
-                buffer[idx + 1] = (orig_g * (1.0 - strength) + orig_g * g * strength) as u8;
-                buffer[idx + 2] = (orig_r * (1.0 - strength) + orig_r * r * strength) as u8;
-            }
+    #[test]
+    fn test_lerp_same_kind() {
+        let from = OverlayShader::Vignette { strength: 0.2 };
+        let to = OverlayShader::Vignette { strength: 0.8 };
+
+        match from.lerp(&to, 0.5) {
+            OverlayShader::Vignette { strength } => assert!((strength - 0.5).abs() < 1e-6),
+            other => panic!("Unexpected overlay: {:?}", other),
         }
+        assert_eq!(from.lerp(&to, 1.0), to);
+    }
+
+    #[test]
+    fn test_lerp_different_kind() {
+        let from = OverlayShader::Vignette { strength: 0.2 };
+        let to = OverlayShader::FilmGrain { intensity: 0.1 };
+
+        assert!(!from.same_kind(&to));
+        assert_eq!(from.lerp(&to, 0.3), to);
+    }
+
+    #[test]
+    fn test_zero_duration_fade_out() {
+        let mut manager = OverlayManager::new(OverlayShader::Vignette { strength: 0.7 });
+        manager.fade_out(Duration::ZERO, EasingFunction::Linear);
+        assert!(manager.is_animating());
+
+        // The final (invisible) frame leaves the buffer untouched
+        let mut buffer = vec![200u8; 4 * 4 * 4];
+        manager.apply_overlay(&mut buffer, 4, 4).unwrap();
+        assert!(buffer.iter().all(|&b| b == 200));
+        assert!(manager.is_faded_out());
+    }
+
+    #[test]
+    fn test_settle_without_redraw() {
+        // An overlay on a colour output is never applied to a frame
+        let clock = crate::clock::Clock::manual();
+        let mut manager = OverlayManager::new(OverlayShader::Vignette { strength: 0.7 })
+            .with_clock(clock.clone());
+        manager.fade_out(Duration::from_millis(300), EasingFunction::Linear);
+
+        clock.advance(Duration::from_millis(100));
+        manager.settle();
+        assert!(manager.is_animating());
+
+        clock.advance(Duration::from_millis(200));
+        manager.settle();
+        assert!(!manager.is_animating());
+        assert!(manager.is_faded_out());
+    }
+
+    #[test]
+    fn test_animate_to_interpolates_parameters() {
+        let mut manager = OverlayManager::new(OverlayShader::Vignette { strength: 0.2 });
+        manager.animate_to(
+            OverlayShader::Vignette { strength: 0.8 },
+            Duration::from_secs(60),
+            EasingFunction::Linear,
+        );
+
+        let (current, opacity) = manager.current_state();
+        match current {
+            OverlayShader::Vignette { strength } => assert!(strength < 0.3),
+            other => panic!("Unexpected overlay: {:?}", other),
+        }
+        assert_eq!(opacity, 1.0);
+        assert!(!manager.is_faded_out());
+    }
+
+    #[test]
+    fn test_pixelate_averages_blocks() {
+        let manager = OverlayManager::new(OverlayShader::Pixelate { pixel_size: 2 });
+
+        // 3x3 frame, so the right and bottom blocks are partial
+        let mut buffer: Vec<u8> = (0..9).flat_map(|i| [i * 10, 0, 0, 255]).collect();
+        manager.apply_pixelate(&mut buffer, 3, 2);
+
+        let blue: Vec<u8> = buffer.chunks(4).map(|pixel| pixel[0]).collect();
+        assert_eq!(blue, vec![20, 20, 35, 20, 20, 35, 65, 65, 80]);
+        assert!(buffer.chunks(4).all(|pixel| pixel[3] == 255));
     }
 }
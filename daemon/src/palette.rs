@@ -0,0 +1,386 @@
+//! Colour palette extraction
+//!
+//! Finds the dominant colours of a frame (median cut, refined with k-means) and
+//! picks background, foreground and accent colours from them, so desktop themes
+//! can follow the wallpaper. Palettes can be rendered through user templates (`{{color0}}`,
+//! `{{background.strip}}`, ...) into theme files.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// At most this many pixels are sampled from a frame
+const MAX_SAMPLES: usize = 16_384;
+
+/// Number of k-means passes after median cut
+const KMEANS_ITERATIONS: usize = 8;
+
+/// Colours clustered when looking for the single dominant one
+const DOMINANT_CLUSTERS: usize = 6;
+
+/// Minimum WCAG contrast ratio between foreground and background
+const MIN_CONTRAST: f32 = 7.0;
+
+type Rgb = [u8; 3];
+
+/// Extract a palette of up to `count` colours from an ARGB8888 frame (BGRA in memory)
+///
+/// Returns None if the frame has no opaque pixels.
+pub fn extract(argb: &[u8], count: usize) -> Option<common::Palette> {
+    let samples = sample(argb);
+    let seeds = median_cut(samples.clone(), count.max(1));
+    let colors = kmeans(&samples, seeds);
+    let (background, foreground, accent) = pick_roles(&colors)?;
+
+    Some(common::Palette {
+        colors: colors.iter().map(|&(color, _)| hex(color)).collect(),
+        background: hex(background),
+        foreground: hex(foreground),
+        accent: hex(accent),
+    })
+}
+
+/// Find the most common colour of an ARGB8888 frame (BGRA in memory)
+///
+/// Returns None if the frame has no opaque pixels.
+pub fn dominant(argb: &[u8]) -> Option<[u8; 3]> {
+    let samples = sample(argb);
+    let seeds = median_cut(samples.clone(), DOMINANT_CLUSTERS);
+    kmeans(&samples, seeds).first().map(|&(color, _)| color)
+}
+
+/// Take up to [`MAX_SAMPLES`] evenly spaced opaque pixels of a frame as RGB
+fn sample(argb: &[u8]) -> Vec<Rgb> {
+    let pixel_count = argb.len() / 4;
+    let step = pixel_count.div_ceil(MAX_SAMPLES).max(1);
+
+    argb.chunks_exact(4)
+        .step_by(step)
+        .filter(|px| px[3] > 0)
+        .map(|px| [px[2], px[1], px[0]])
+        .collect()
+}
+
+/// Split the samples into up to `count` boxes, returning each box's mean colour
+fn median_cut(samples: Vec<Rgb>, count: usize) -> Vec<Rgb> {
+    if samples.is_empty() {
+        return Vec::new();
+    }
+
+    let mut boxes = vec![samples];
+    while boxes.len() < count {
+        // Split the box with the widest channel range, favouring larger boxes
+        let Some((index, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.len() > 1)
+            .map(|(i, b)| {
+                let (channel, range) = widest_channel(b);
+                (i, channel, range as usize * b.len())
+            })
+            .filter(|&(_, _, score)| score > 0)
+            .max_by_key(|&(_, _, score)| score)
+            .map(|(i, channel, _)| (i, channel))
+        else {
+            break;
+        };
+
+        let mut colors = boxes.swap_remove(index);
+        colors.sort_unstable_by_key(|c| c[channel]);
+        let upper = colors.split_off(colors.len() / 2);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes.iter().map(|b| mean(b.iter())).collect()
+}
+
+/// Move the seed colours to the centres of the samples nearest to them, returning
+/// each colour and how many samples it covers, most common first
+fn kmeans(samples: &[Rgb], mut centers: Vec<Rgb>) -> Vec<(Rgb, usize)> {
+    let mut assignment = vec![0; samples.len()];
+
+    for _ in 0..KMEANS_ITERATIONS {
+        for (sample, cluster) in samples.iter().zip(&mut assignment) {
+            *cluster = nearest(&centers, *sample);
+        }
+
+        let moved: Vec<Rgb> = (0..centers.len())
+            .map(|i| {
+                let members = samples
+                    .iter()
+                    .zip(&assignment)
+                    .filter(|&(_, &cluster)| cluster == i)
+                    .map(|(sample, _)| sample);
+                mean(members)
+            })
+            .collect();
+
+        if moved == centers {
+            break;
+        }
+        centers = moved;
+    }
+
+    let mut colors: Vec<(Rgb, usize)> = centers
+        .iter()
+        .enumerate()
+        .map(|(i, &color)| (color, assignment.iter().filter(|&&c| c == i).count()))
+        .filter(|&(_, population)| population > 0)
+        .collect();
+
+    // Most common first, ties broken by colour so the order is stable
+    colors.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    colors
+}
+
+/// Index of the centre closest to a colour
+fn nearest(centers: &[Rgb], color: Rgb) -> usize {
+    let distance = |c: &Rgb| -> u32 {
+        (0..3)
+            .map(|i| (c[i] as i32 - color[i] as i32).pow(2) as u32)
+            .sum()
+    };
+
+    (0..centers.len())
+        .min_by_key(|&i| distance(&centers[i]))
+        .unwrap_or(0)
+}
+
+/// Rounded mean of some colours (black if there are none)
+fn mean<'a>(colors: impl Iterator<Item = &'a Rgb>) -> Rgb {
+    let mut sum = [0u64; 3];
+    let mut n = 0;
+    for color in colors {
+        for (total, &channel) in sum.iter_mut().zip(color) {
+            *total += channel as u64;
+        }
+        n += 1;
+    }
+
+    if n == 0 {
+        return [0; 3];
+    }
+    sum.map(|total| ((total + n / 2) / n) as u8)
+}
+
+/// The channel with the largest spread of values, and that spread
+fn widest_channel(colors: &[Rgb]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let (min, max) = colors.iter().fold((u8::MAX, u8::MIN), |(min, max), c| {
+                (min.min(c[channel]), max.max(c[channel]))
+            });
+            (channel, max - min)
+        })
+        .max_by_key(|&(channel, range)| (range, std::cmp::Reverse(channel)))
+        .unwrap_or((0, 0))
+}
+
+/// Choose background, foreground and accent colours from the dominant colours
+fn pick_roles(colors: &[(Rgb, usize)]) -> Option<(Rgb, Rgb, Rgb)> {
+    let darkest = colors
+        .iter()
+        .map(|&(color, _)| color)
+        .min_by(|a, b| luminance(*a).total_cmp(&luminance(*b)))?;
+    let lightest = colors
+        .iter()
+        .map(|&(color, _)| color)
+        .max_by(|a, b| luminance(*a).total_cmp(&luminance(*b)))?;
+
+    // Keep the background dark, and push the foreground towards white until readable
+    let background = mix(
+        darkest,
+        [0, 0, 0],
+        (luminance(darkest) - 0.05).max(0.0) * 2.0,
+    );
+    let mut foreground = lightest;
+    for step in 0..=10 {
+        foreground = mix(lightest, [255, 255, 255], step as f32 / 10.0);
+        if contrast(foreground, background) >= MIN_CONTRAST {
+            break;
+        }
+    }
+
+    // The most vivid colour, ignoring tiny specks
+    let total: usize = colors.iter().map(|&(_, n)| n).sum();
+    let accent = colors
+        .iter()
+        .filter(|&&(_, n)| n * 50 >= total)
+        .map(|&(color, _)| color)
+        .max_by(|a, b| vividness(*a).total_cmp(&vividness(*b)))
+        .unwrap_or(colors[0].0);
+
+    Some((background, foreground, accent))
+}
+
+/// Relative luminance (WCAG)
+fn luminance(color: Rgb) -> f32 {
+    let linear = color.map(|c| {
+        let c = c as f32 / 255.0;
+        if c <= 0.03928 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    });
+    0.2126 * linear[0] + 0.7152 * linear[1] + 0.0722 * linear[2]
+}
+
+/// WCAG contrast ratio between two colours
+fn contrast(a: Rgb, b: Rgb) -> f32 {
+    let (la, lb) = (luminance(a), luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// Saturation weighted by brightness, so near-black colours don't count as vivid
+fn vividness(color: Rgb) -> f32 {
+    let max = *color.iter().max().unwrap() as f32;
+    let min = *color.iter().min().unwrap() as f32;
+    if max == 0.0 {
+        return 0.0;
+    }
+    (max - min) / max * (max / 255.0)
+}
+
+/// Linear blend from `a` towards `b` (`t` clamped to 0..=1)
+fn mix(a: Rgb, b: Rgb, t: f32) -> Rgb {
+    let t = t.clamp(0.0, 1.0);
+    [0, 1, 2].map(|i| (a[i] as f32 + (b[i] as f32 - a[i] as f32) * t).round() as u8)
+}
+
+fn hex([r, g, b]: Rgb) -> String {
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+/// Fill in a template with palette colours.
+///
+/// `{{background}}`, `{{foreground}}`, `{{accent}}` and `{{colorN}}` become
+/// `#rrggbb`; a `.strip` suffix drops the `#` and `.rgb` gives `r,g,b`.
+/// Unknown placeholders are left as they are.
+pub fn render_template(template: &str, palette: &common::Palette) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let after = &rest[start + 2..];
+        let Some(end) = after.find("}}") else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+
+        match lookup(after[..end].trim(), palette) {
+            Some(value) => output.push_str(&value),
+            None => output.push_str(&rest[start..start + 2 + end + 2]),
+        }
+        rest = &after[end + 2..];
+    }
+    output.push_str(rest);
+
+    output
+}
+
+/// Resolve a template placeholder like `color3.strip`
+fn lookup(name: &str, palette: &common::Palette) -> Option<String> {
+    let (name, format) = name.split_once('.').unwrap_or((name, ""));
+
+    let color = match name {
+        "background" => &palette.background,
+        "foreground" => &palette.foreground,
+        "accent" => &palette.accent,
+        _ => {
+            let index: usize = name.strip_prefix("color")?.parse().ok()?;
+            palette.colors.get(index)?
+        }
+    };
+
+    let (r, g, b, _) = crate::buffer::parse_hex_color(color)?;
+    match format {
+        "" => Some(color.clone()),
+        "strip" => Some(color.trim_start_matches('#').to_string()),
+        "rgb" => Some(format!("{},{},{}", r, g, b)),
+        _ => None,
+    }
+}
+
+/// Render a template file to its target
+pub fn export(template: &Path, target: &Path, palette: &common::Palette) -> Result<()> {
+    let source = std::fs::read_to_string(template)
+        .with_context(|| format!("Failed to read palette template: {}", template.display()))?;
+
+    if let Some(parent) = target.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+
+    std::fs::write(target, render_template(&source, palette))
+        .with_context(|| format!("Failed to write palette file: {}", target.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// ARGB8888 frame with `n` pixels of each colour
+    fn frame(colors: &[(Rgb, usize)]) -> Vec<u8> {
+        colors
+            .iter()
+            .flat_map(|&([r, g, b], n)| [b, g, r, 255].repeat(n))
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_dominant_colors() {
+        let argb = frame(&[
+            ([20, 30, 120], 600),
+            ([230, 80, 20], 300),
+            ([250, 250, 240], 100),
+        ]);
+        let palette = extract(&argb, 3).unwrap();
+
+        assert_eq!(palette.colors, ["#141e78", "#e65014", "#fafaf0"]);
+        assert_eq!(palette.accent, "#e65014");
+        assert!(luminance(parse(&palette.background)) < 0.05);
+        assert!(contrast(parse(&palette.foreground), parse(&palette.background)) >= MIN_CONTRAST);
+
+        // Fewer distinct colours than requested
+        assert_eq!(
+            extract(&frame(&[([9, 9, 9], 10)]), 8).unwrap().colors.len(),
+            1
+        );
+
+        // Nothing opaque to sample
+        assert!(extract(&[0, 0, 0, 0], 8).is_none());
+
+        assert_eq!(dominant(&argb), Some([20, 30, 120]));
+        assert_eq!(dominant(&[0, 0, 0, 0]), None);
+    }
+
+    #[test]
+    fn test_render_template() {
+        let palette = common::Palette {
+            colors: vec!["#102030".to_string(), "#ff8800".to_string()],
+            background: "#000000".to_string(),
+            foreground: "#ffffff".to_string(),
+            accent: "#ff8800".to_string(),
+        };
+
+        assert_eq!(
+            render_template(
+                "bg={{background}} fg={{ foreground.strip }} c1={{color1.rgb}}",
+                &palette
+            ),
+            "bg=#000000 fg=ffffff c1=255,136,0"
+        );
+
+        // Unknown names and braces that aren't placeholders pass through
+        assert_eq!(
+            render_template("{{color9}} {{nope}} a { b } {{", &palette),
+            "{{color9}} {{nope}} a { b } {{"
+        );
+    }
+
+    fn parse(color: &str) -> Rgb {
+        let (r, g, b, _) = crate::buffer::parse_hex_color(color).unwrap();
+        [r, g, b]
+    }
+}
@@ -1,3 +1,4 @@
+use crate::output_transform::Orientation;
 use anyhow::Result;
 use glob::glob;
 use rand::rng;
@@ -31,15 +32,28 @@ pub struct PlaylistState {
 }
 
 impl PlaylistState {
-    /// Create a new playlist from sources
+    /// Create a new playlist from sources, keeping only images that suit
+    /// `orientation` if one is given
     pub fn new(
         sources: &[String],
         extensions: &[String],
         interval_secs: u64,
         shuffle: bool,
+        orientation: Option<Orientation>,
         output_name: Option<String>,
     ) -> Result<Self> {
-        let wallpapers = Self::load_wallpapers_from_sources(sources, extensions)?;
+        let mut wallpapers = Self::load_wallpapers_from_sources(sources, extensions)?;
+
+        if let Some(orientation) = orientation {
+            let total = wallpapers.len();
+            wallpapers.retain(|path| Self::suits(path, orientation));
+            log::info!(
+                "Kept {} of {} wallpapers suiting {:?} outputs",
+                wallpapers.len(),
+                total,
+                orientation
+            );
+        }
 
         if wallpapers.is_empty() {
             anyhow::bail!("No wallpapers found in playlist sources");
@@ -141,6 +155,15 @@ impl PlaylistState {
         false
     }
 
+    /// Check if a wallpaper suits an orientation (files whose size can't be
+    /// read without decoding them, like videos and SVGs, suit both)
+    fn suits(path: &Path, orientation: Orientation) -> bool {
+        match crate::color_profile::upright_dimensions(path) {
+            Some((width, height)) => orientation.fits(width, height),
+            None => true,
+        }
+    }
+
     /// Generate a new shuffle order
     fn generate_shuffle_order(&mut self) {
         let mut rng = rng();
@@ -185,6 +208,27 @@ impl PlaylistState {
         self.current()
     }
 
+    /// Get the wallpaper `next()` will move to, without moving
+    ///
+    /// Returns None at the end of a shuffle cycle, where a new order is drawn.
+    pub fn peek_next(&self) -> Option<&Path> {
+        if self.wallpapers.is_empty() {
+            return None;
+        }
+
+        let next_index = (self.current_index + 1) % self.wallpapers.len();
+        let index = if self.shuffle {
+            if next_index == 0 {
+                return None;
+            }
+            *self.shuffle_order.get(next_index)?
+        } else {
+            next_index
+        };
+
+        self.wallpapers.get(index).map(|p| p.as_path())
+    }
+
     /// Move to the previous wallpaper
     pub fn prev(&mut self) -> Option<&Path> {
         if self.wallpapers.is_empty() {
@@ -307,4 +351,58 @@ mod tests {
         playlist.prev();
         assert_eq!(playlist.current(), Some(Path::new("/tmp/3.jpg")));
     }
+
+    #[test]
+    fn test_peek_next() {
+        let mut playlist = PlaylistState {
+            wallpapers: vec![PathBuf::from("/tmp/1.jpg"), PathBuf::from("/tmp/2.jpg")],
+            current_index: 0,
+            shuffle_order: vec![1, 0],
+            shuffle: true,
+            last_rotation: Instant::now(),
+            interval: Duration::from_secs(300),
+            output_name: None,
+        };
+
+        assert_eq!(playlist.peek_next(), Some(Path::new("/tmp/1.jpg")));
+        assert_eq!(playlist.next(), Some(Path::new("/tmp/1.jpg")));
+
+        // A new shuffle order is drawn when the cycle ends
+        assert_eq!(playlist.peek_next(), None);
+
+        playlist.shuffle = false;
+        playlist.current_index = 1;
+        assert_eq!(playlist.peek_next(), Some(Path::new("/tmp/1.jpg")));
+        assert_eq!(playlist.current(), Some(Path::new("/tmp/2.jpg")));
+    }
+
+    #[test]
+    fn test_orientation_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        image::RgbImage::new(4, 2)
+            .save(dir.path().join("wide.png"))
+            .unwrap();
+        image::RgbImage::new(2, 4)
+            .save(dir.path().join("tall.png"))
+            .unwrap();
+        std::fs::write(dir.path().join("clip.mp4"), b"not decoded").unwrap();
+
+        let sources = vec![dir.path().to_string_lossy().to_string()];
+        let extensions = vec!["png".to_string(), "mp4".to_string()];
+        let playlist = |orientation| {
+            PlaylistState::new(&sources, &extensions, 300, false, orientation, None)
+                .unwrap()
+                .wallpapers
+        };
+
+        assert_eq!(playlist(None).len(), 3);
+        assert_eq!(
+            playlist(Some(Orientation::Portrait)),
+            vec![dir.path().join("clip.mp4"), dir.path().join("tall.png")]
+        );
+        assert_eq!(
+            playlist(Some(Orientation::Landscape)),
+            vec![dir.path().join("clip.mp4"), dir.path().join("wide.png")]
+        );
+    }
 }
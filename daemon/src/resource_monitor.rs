@@ -53,7 +53,6 @@ impl PerformanceMode {
     }
 
     /// Get memory limit in MB for frame caches
-    #[allow(dead_code)] // Used by memory management features
     pub fn memory_limit_mb(&self) -> usize {
         match self {
             PerformanceMode::Performance => 500,
@@ -239,7 +238,6 @@ impl ResourceMonitor {
     }
 
     /// Check if we're over memory limit
-    #[allow(dead_code)] // Used by memory management features
     pub fn is_over_memory_limit(&self, current_bytes: u64) -> bool {
         if !self.config.enforce_memory_limits || self.config.max_memory_mb == 0 {
             return false;
@@ -250,7 +248,6 @@ impl ResourceMonitor {
     }
 
     /// Get current memory limit for the active performance mode
-    #[allow(dead_code)] // Used by memory management features
     pub fn current_memory_limit_mb(&self) -> usize {
         if self.config.max_memory_mb > 0 {
             self.config.max_memory_mb.min(self.mode.memory_limit_mb())
@@ -258,4 +255,11 @@ impl ResourceMonitor {
             self.mode.memory_limit_mb()
         }
     }
+
+    /// Memory budget for image caches in bytes, if memory limits are enforced
+    pub fn cache_budget_bytes(&self) -> Option<usize> {
+        self.config
+            .enforce_memory_limits
+            .then(|| self.current_memory_limit_mb() * 1024 * 1024)
+    }
 }
@@ -95,6 +95,8 @@ pub struct ShaderManager {
     target_fps: u32,
     /// Last frame time
     last_frame: Instant,
+    /// Time source for the animation
+    clock: crate::clock::Clock,
     /// Optional GPU renderer for accelerated rendering
     #[cfg(feature = "gpu")]
     gpu_renderer: Option<std::sync::Arc<crate::gpu::GpuRenderer>>,
@@ -116,11 +118,20 @@ impl ShaderManager {
             start_time: Instant::now(),
             target_fps: 30, // Default to 30fps for shaders
             last_frame: Instant::now(),
+            clock: crate::clock::Clock::system(),
             #[cfg(feature = "gpu")]
             gpu_renderer,
         }
     }
 
+    /// Drive the animation from a different clock (restarts it at the clock's current time)
+    pub fn with_clock(mut self, clock: crate::clock::Clock) -> Self {
+        self.start_time = clock.now();
+        self.last_frame = clock.now();
+        self.clock = clock;
+        self
+    }
+
     /// Set target frame rate
     #[allow(dead_code)] // Part of public API for FPS control
     pub fn set_fps(&mut self, fps: u32) {
@@ -130,17 +141,17 @@ impl ShaderManager {
     /// Check if it's time to render next frame
     pub fn should_render(&self) -> bool {
         let frame_duration = std::time::Duration::from_millis(1000 / self.target_fps as u64);
-        self.last_frame.elapsed() >= frame_duration
+        self.clock.elapsed(self.last_frame) >= frame_duration
     }
 
     /// Render current frame to ARGB buffer
     pub fn render_frame(&mut self, width: u32, height: u32) -> Result<Vec<u8>> {
         // Update context
         self.context.resolution = (width, height);
-        let elapsed = self.start_time.elapsed().as_secs_f32();
+        let elapsed = self.clock.elapsed(self.start_time).as_secs_f32();
         self.context.time = elapsed;
         self.context.frame += 1;
-        self.last_frame = Instant::now();
+        self.last_frame = self.clock.now();
 
         // Try GPU rendering first if available
         #[cfg(feature = "gpu")]
@@ -186,8 +197,8 @@ impl ShaderManager {
             BuiltinShader::Matrix => self.render_matrix(width, height),
             BuiltinShader::Gradient => self.render_gradient(width, height),
             BuiltinShader::Starfield => self.render_starfield(width, height),
-            BuiltinShader::Raymarching => self.render_fallback(width, height, "Raymarching"),
-            BuiltinShader::Tunnel => self.render_fallback(width, height, "Tunnel"),
+            BuiltinShader::Raymarching => self.render_raymarching(width, height),
+            BuiltinShader::Tunnel => self.render_tunnel(width, height),
         };
         log::info!(
             "CPU shader '{:?}' rendered {}x{} in {:.2}ms",
@@ -200,42 +211,16 @@ impl ShaderManager {
         Ok(buffer)
     }
 
-    /// Fallback for GPU-only shaders (too complex for CPU)
-    fn render_fallback(&self, width: u32, height: u32, name: &str) -> Vec<u8> {
-        let mut buffer = vec![0u8; (width * height * 4) as usize];
-
-        // Simple gradient as fallback
-        for y in 0..height {
-            for x in 0..width {
-                let idx = ((y * width + x) * 4) as usize;
-                let intensity = ((x + y) as f32 / (width + height) as f32 * 255.0) as u8;
-
-                buffer[idx] = intensity / 2; // B
-                buffer[idx + 1] = intensity / 2; // G
-                buffer[idx + 2] = intensity / 2; // R
-                buffer[idx + 3] = 255; // A
-            }
-        }
-
-        log::warn!(
-            "{} shader requires GPU acceleration, showing simple fallback",
-            name
-        );
-        buffer
-    }
-
     /// Render plasma effect
     fn render_plasma(&self, width: u32, height: u32) -> Vec<u8> {
-        let mut buffer = vec![0u8; (width * height * 4) as usize];
         let time = self.context.time;
 
-        for y in 0..height {
-            for x in 0..width {
-                let idx = ((y * width + x) * 4) as usize;
+        crate::cpu_render::render_rows(width, height, |y, row| {
+            let ny = y as f32 / height as f32;
 
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
                 // Normalized coordinates
                 let nx = x as f32 / width as f32;
-                let ny = y as f32 / height as f32;
 
                 // Plasma effect using sine waves
                 let v1 = (nx * 10.0 + time).sin();
@@ -250,55 +235,45 @@ impl ShaderManager {
                 let g = (((value + 2.0).sin() * 0.5 + 0.5) * 255.0) as u8;
                 let b = (((value + 4.0).sin() * 0.5 + 0.5) * 255.0) as u8;
 
-                buffer[idx] = b; // B
-                buffer[idx + 1] = g; // G
-                buffer[idx + 2] = r; // R
-                buffer[idx + 3] = 255; // A
+                pixel.copy_from_slice(&[b, g, r, 255]);
             }
-        }
-
-        buffer
+        })
     }
 
     /// Render wave effect
     fn render_waves(&self, width: u32, height: u32) -> Vec<u8> {
-        let mut buffer = vec![0u8; (width * height * 4) as usize];
         let time = self.context.time;
 
-        for y in 0..height {
-            for x in 0..width {
-                let idx = ((y * width + x) * 4) as usize;
+        crate::cpu_render::render_rows(width, height, |y, row| {
+            let ny = y as f32 / height as f32;
+            let wave2 = (ny * 15.0 - time * 1.5).sin();
 
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
                 let nx = x as f32 / width as f32;
-                let ny = y as f32 / height as f32;
 
                 // Wave patterns
                 let wave1 = (nx * 20.0 + time * 2.0).sin();
-                let wave2 = (ny * 15.0 - time * 1.5).sin();
                 let value = (wave1 + wave2) / 2.0;
 
                 // Blue to cyan gradient based on wave
                 let intensity = (value * 0.5 + 0.5) * 255.0;
-                buffer[idx] = intensity as u8; // B
-                buffer[idx + 1] = (intensity * 0.7) as u8; // G
-                buffer[idx + 2] = (intensity * 0.3) as u8; // R
-                buffer[idx + 3] = 255;
+                pixel.copy_from_slice(&[
+                    intensity as u8,         // B
+                    (intensity * 0.7) as u8, // G
+                    (intensity * 0.3) as u8, // R
+                    255,
+                ]);
             }
-        }
-
-        buffer
+        })
     }
 
     /// Render matrix rain effect
     fn render_matrix(&self, width: u32, height: u32) -> Vec<u8> {
-        let mut buffer = vec![0u8; (width * height * 4) as usize];
         let time = self.context.time;
 
         // Simple vertical green lines falling
-        for y in 0..height {
-            for x in 0..width {
-                let idx = ((y * width + x) * 4) as usize;
-
+        crate::cpu_render::render_rows(width, height, |y, row| {
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
                 // Create vertical streams
                 let column_seed = (x as f32 * 0.1).sin() * 1000.0;
                 let fall_speed = 100.0 + column_seed;
@@ -311,44 +286,34 @@ impl ShaderManager {
                     0.0
                 };
 
-                buffer[idx] = 0; // B
-                buffer[idx + 1] = brightness as u8; // G
-                buffer[idx + 2] = 0; // R
-                buffer[idx + 3] = 255;
+                pixel.copy_from_slice(&[0, brightness as u8, 0, 255]);
             }
-        }
-
-        buffer
+        })
     }
 
     /// Render gradient animation
     fn render_gradient(&self, width: u32, height: u32) -> Vec<u8> {
-        let mut buffer = vec![0u8; (width * height * 4) as usize];
         let time = self.context.time;
 
-        for y in 0..height {
-            for x in 0..width {
-                let idx = ((y * width + x) * 4) as usize;
+        // Rotating gradient
+        let angle = time * 0.5;
+        let (sin_a, cos_a) = angle.sin_cos();
+
+        crate::cpu_render::render_rows(width, height, |y, row| {
+            let ny = y as f32 / height as f32;
 
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
                 let nx = x as f32 / width as f32;
-                let ny = y as f32 / height as f32;
 
-                // Rotating gradient
-                let angle = time * 0.5;
-                let gradient = (nx * angle.cos() + ny * angle.sin() + time * 0.3).sin() * 0.5 + 0.5;
+                let gradient = (nx * cos_a + ny * sin_a + time * 0.3).sin() * 0.5 + 0.5;
 
                 let r = (gradient * 255.0) as u8;
                 let g = ((1.0 - gradient) * 255.0) as u8;
                 let b = ((nx + ny) * 0.5 * 255.0) as u8;
 
-                buffer[idx] = b;
-                buffer[idx + 1] = g;
-                buffer[idx + 2] = r;
-                buffer[idx + 3] = 255;
+                pixel.copy_from_slice(&[b, g, r, 255]);
             }
-        }
-
-        buffer
+        })
     }
 
     /// Render starfield effect
@@ -390,6 +355,125 @@ impl ShaderManager {
         buffer
     }
 
+    /// Render raymarched spheres (software version of `raymarching.wgsl`)
+    fn render_raymarching(&self, width: u32, height: u32) -> Vec<u8> {
+        let uniforms = ShaderUniforms::new(&self.params);
+        let scene = RaymarchScene {
+            anim_time: self.context.time * uniforms.speed,
+            scale: uniforms.scale,
+        };
+        let anim_time = scene.anim_time;
+        let aspect = width as f32 / height as f32;
+
+        // Camera setup
+        let cam_pos = Vec3::new(
+            (anim_time * 0.3).cos() * 8.0,
+            (anim_time * 0.2).sin() * 2.0 + 2.0,
+            (anim_time * 0.3).sin() * 8.0,
+        );
+        let cam_forward = (Vec3::ZERO - cam_pos).normalize();
+        let cam_right = Vec3::new(0.0, 1.0, 0.0).cross(cam_forward).normalize();
+        let cam_up = cam_forward.cross(cam_right);
+
+        let color1 = Vec3::from(uniforms.color1);
+        let color2 = Vec3::from(uniforms.color2);
+        let background = Vec3::from(uniforms.color3) * 0.15;
+        let light_pos = Vec3::new(5.0, 10.0, 5.0);
+
+        crate::cpu_render::render_rows(width, height, |y, row| {
+            let uv_y = (y as f32 + 0.5) / height as f32 * 2.0 - 1.0;
+
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let uv_x = ((x as f32 + 0.5) / width as f32 * 2.0 - 1.0) * aspect;
+                let ray_dir = (cam_forward + cam_right * uv_x + cam_up * uv_y).normalize();
+
+                let depth = scene.raymarch(cam_pos, ray_dir);
+                let mut color = background;
+
+                if depth > 0.0 {
+                    let hit_pos = cam_pos + ray_dir * depth;
+                    let normal = scene.normal(hit_pos);
+
+                    // Lighting
+                    let light_dir = (light_pos - hit_pos).normalize();
+                    let diffuse = normal.dot(light_dir).max(0.0);
+
+                    // Specular
+                    let view_dir = (cam_pos - hit_pos).normalize();
+                    let reflect_dir = (-light_dir).reflect(normal);
+                    let specular = view_dir.dot(reflect_dir).max(0.0).powf(32.0);
+
+                    let base_color =
+                        color1.mix(color2, (hit_pos.x * 2.0 + anim_time).sin() * 0.5 + 0.5);
+
+                    // Ambient occlusion approximation
+                    let ao = 1.0 - (1.0 / (1.0 + depth * 0.1));
+
+                    color = base_color * ((0.3 + diffuse * 0.7) * ao * uniforms.intensity)
+                        + Vec3::splat(specular);
+
+                    // Fog
+                    let fog_amount = 1.0 - (-depth * 0.05).exp();
+                    color = color.mix(background, fog_amount);
+                }
+
+                write_pixel(pixel, color);
+            }
+        })
+    }
+
+    /// Render the tunnel vortex (software version of `tunnel.wgsl`)
+    fn render_tunnel(&self, width: u32, height: u32) -> Vec<u8> {
+        let uniforms = ShaderUniforms::new(&self.params);
+        let anim_time = self.context.time * uniforms.speed;
+        let aspect = width as f32 / height as f32;
+
+        let base_color1 = Vec3::from(uniforms.color1);
+        let base_color2 = Vec3::from(uniforms.color2);
+        let accent_color = Vec3::from(uniforms.color3);
+        let checker_size = (uniforms.count * 0.1).max(2.0);
+        let ray_count = (uniforms.count * 0.08).max(4.0);
+
+        crate::cpu_render::render_rows(width, height, |y, row| {
+            let uv_y = (y as f32 + 0.5) / height as f32 - 0.5;
+
+            for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                let uv_x = ((x as f32 + 0.5) / width as f32 - 0.5) * aspect;
+
+                // Polar coordinates
+                let angle = uv_y.atan2(uv_x);
+                let radius = (uv_x * uv_x + uv_y * uv_y).sqrt();
+
+                // Tunnel depth
+                let depth = 1.0 / (radius + 0.1);
+                let z = depth * uniforms.scale + anim_time * 2.0;
+
+                // Checker pattern (WGSL's `%` truncates like Rust's, so this can be -1)
+                let tunnel_u = angle / (std::f32::consts::PI * 2.0) + anim_time * 0.5;
+                let checker_u = (tunnel_u * checker_size).floor();
+                let checker_v = (z * checker_size).floor();
+                let checker = (checker_u + checker_v) % 2.0;
+
+                // Color based on depth
+                let color1 = base_color1 * (0.5 + 0.5 * (z * 2.0).sin());
+                let color2 = base_color2 * (0.5 + 0.5 * (z * 1.5).cos());
+                let mut color = color1.mix(color2, checker);
+
+                // Glow at the center
+                let glow = 1.0 / (radius * 5.0 + 1.0);
+                color = color + accent_color * (glow * 0.5);
+
+                // Rotating rays
+                let rays = (angle * ray_count + anim_time * 3.0).sin() * 0.5 + 0.5;
+                color = color + accent_color * (rays * 0.2 * (1.0 - radius));
+
+                // Vignette
+                let vignette = 1.0 - radius.powf(2.0);
+                write_pixel(pixel, color * (vignette * uniforms.intensity));
+            }
+        })
+    }
+
     /// Get current shader type
     #[allow(dead_code)] // Part of public API for shader queries
     pub fn shader(&self) -> BuiltinShader {
@@ -401,10 +485,295 @@ impl ShaderManager {
     pub fn set_shader(&mut self, shader: BuiltinShader) {
         if self.shader != shader {
             self.shader = shader;
-            self.start_time = Instant::now();
+            self.start_time = self.clock.now();
             self.context.time = 0.0;
             self.context.frame = 0;
             log::info!("Switched to shader: {}", shader.name());
         }
     }
 }
+
+/// Shader parameters as the WGSL uniforms see them, with the defaults applied
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ShaderUniforms {
+    pub speed: f32,
+    pub scale: f32,
+    pub intensity: f32,
+    pub count: f32,
+    pub color1: [f32; 3],
+    pub color2: [f32; 3],
+    pub color3: [f32; 3],
+}
+
+impl ShaderUniforms {
+    pub fn new(params: &common::ShaderParams) -> Self {
+        let color = |hex: &Option<String>, default: [f32; 3]| {
+            hex.as_deref()
+                .and_then(common::ShaderParams::parse_color)
+                .map_or(default, |(r, g, b)| [r, g, b])
+        };
+
+        ShaderUniforms {
+            speed: params.speed.unwrap_or(1.0),
+            scale: params.scale.unwrap_or(1.0),
+            intensity: params.intensity.unwrap_or(1.0),
+            count: params.count.unwrap_or(100) as f32,
+            color1: color(&params.color1, [1.0, 0.0, 0.0]), // Red
+            color2: color(&params.color2, [0.0, 0.0, 1.0]), // Blue
+            color3: color(&params.color3, [0.0, 1.0, 0.0]), // Green
+        }
+    }
+}
+
+/// Store a linear colour the way the GPU's sRGB render target does, as ARGB8888
+fn write_pixel(pixel: &mut [u8], color: Vec3) {
+    let encode = |c: f32| {
+        let c = c.clamp(0.0, 1.0);
+        let srgb = if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+        (srgb * 255.0).round() as u8
+    };
+
+    pixel.copy_from_slice(&[encode(color.z), encode(color.y), encode(color.x), 255]);
+}
+
+/// Signed distance field scene of `raymarching.wgsl`
+struct RaymarchScene {
+    anim_time: f32,
+    scale: f32,
+}
+
+impl RaymarchScene {
+    /// Distance from `p` to the nearest surface
+    fn distance(&self, p: Vec3) -> f32 {
+        let t = self.anim_time;
+
+        // Animated sphere position
+        let sphere_pos = Vec3::new((t * 0.5).sin() * 2.0, (t * 0.7).cos() * 1.5, 0.0);
+
+        let sphere1 = (p - sphere_pos).length() - 1.5 * self.scale;
+        let sphere2 = (p + sphere_pos * 0.5).length() - self.scale;
+        let sphere3 = (p - Vec3::new(0.0, t.sin() * 2.0, 2.0)).length() - 0.8 * self.scale;
+
+        // Ground plane
+        let ground = p.y + 3.0;
+
+        sphere1.min(sphere2).min(sphere3).min(ground)
+    }
+
+    /// Surface normal from the distance gradient
+    fn normal(&self, p: Vec3) -> Vec3 {
+        let epsilon = 0.001;
+        let dx = Vec3::new(epsilon, 0.0, 0.0);
+        let dy = Vec3::new(0.0, epsilon, 0.0);
+        let dz = Vec3::new(0.0, 0.0, epsilon);
+
+        Vec3::new(
+            self.distance(p + dx) - self.distance(p - dx),
+            self.distance(p + dy) - self.distance(p - dy),
+            self.distance(p + dz) - self.distance(p - dz),
+        )
+        .normalize()
+    }
+
+    /// Depth at which the ray hits a surface, or -1.0 for a miss
+    fn raymarch(&self, origin: Vec3, dir: Vec3) -> f32 {
+        let mut depth = 0.0;
+
+        for _ in 0..100 {
+            let dist = self.distance(origin + dir * depth);
+            if dist < 0.001 {
+                return depth;
+            }
+
+            depth += dist;
+            if depth > 100.0 {
+                return -1.0;
+            }
+        }
+
+        -1.0
+    }
+}
+
+/// Minimal `vec3<f32>` for porting WGSL shaders
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Vec3 {
+    x: f32,
+    y: f32,
+    z: f32,
+}
+
+impl Vec3 {
+    const ZERO: Vec3 = Vec3::splat(0.0);
+
+    const fn new(x: f32, y: f32, z: f32) -> Self {
+        Vec3 { x, y, z }
+    }
+
+    const fn splat(v: f32) -> Self {
+        Vec3::new(v, v, v)
+    }
+
+    fn dot(self, other: Vec3) -> f32 {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+
+    fn cross(self, other: Vec3) -> Vec3 {
+        Vec3::new(
+            self.y * other.z - self.z * other.y,
+            self.z * other.x - self.x * other.z,
+            self.x * other.y - self.y * other.x,
+        )
+    }
+
+    fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    fn normalize(self) -> Vec3 {
+        self * (1.0 / self.length())
+    }
+
+    /// WGSL `reflect(self, normal)`
+    fn reflect(self, normal: Vec3) -> Vec3 {
+        self - normal * (2.0 * normal.dot(self))
+    }
+
+    /// WGSL `mix(self, other, t)`
+    fn mix(self, other: Vec3, t: f32) -> Vec3 {
+        self + (other - self) * t
+    }
+}
+
+impl From<[f32; 3]> for Vec3 {
+    fn from([x, y, z]: [f32; 3]) -> Self {
+        Vec3::new(x, y, z)
+    }
+}
+
+impl std::ops::Add for Vec3 {
+    type Output = Vec3;
+
+    fn add(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+    }
+}
+
+impl std::ops::Sub for Vec3 {
+    type Output = Vec3;
+
+    fn sub(self, other: Vec3) -> Vec3 {
+        Vec3::new(self.x - other.x, self.y - other.y, self.z - other.z)
+    }
+}
+
+impl std::ops::Mul<f32> for Vec3 {
+    type Output = Vec3;
+
+    fn mul(self, factor: f32) -> Vec3 {
+        Vec3::new(self.x * factor, self.y * factor, self.z * factor)
+    }
+}
+
+impl std::ops::Neg for Vec3 {
+    type Output = Vec3;
+
+    fn neg(self) -> Vec3 {
+        Vec3::new(-self.x, -self.y, -self.z)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Render `shader` on the CPU at a fixed point in its animation
+    fn render_cpu(shader: BuiltinShader, width: u32, height: u32, time: f32) -> Vec<u8> {
+        let clock = crate::clock::Clock::manual();
+        let mut manager = ShaderManager::new(
+            shader,
+            width,
+            height,
+            None,
+            #[cfg(feature = "gpu")]
+            None,
+        )
+        .with_clock(clock.clone());
+        clock.advance(Duration::from_secs_f32(time));
+        manager.render_frame(width, height).unwrap()
+    }
+
+    #[test]
+    fn test_cpu_raymarching_and_tunnel() {
+        let (width, height) = (64, 36);
+
+        // Corner pixels of the tunnel are outside the vignette and clamp to black
+        let tunnel = render_cpu(BuiltinShader::Tunnel, width, height, 1.0);
+        assert_eq!(&tunnel[..4], &[0, 0, 0, 255]);
+        // The center glows with the accent colour (green by default)
+        let center = ((height / 2 * width + width / 2) * 4) as usize;
+        assert!(tunnel[center + 1] > tunnel[center] && tunnel[center + 1] > tunnel[center + 2]);
+
+        // Some rays hit the spheres or ground, the rest show the dim background
+        let raymarching = render_cpu(BuiltinShader::Raymarching, width, height, 2.5);
+        let background = [0, 108, 0, 255];
+        let pixels = raymarching.chunks_exact(4);
+        assert!(pixels.clone().any(|pixel| pixel == background));
+        assert!(pixels.clone().any(|pixel| pixel != background));
+        assert!(pixels.clone().all(|pixel| pixel[3] == 255));
+    }
+
+    #[cfg(feature = "gpu")]
+    #[test]
+    fn test_cpu_matches_gpu() {
+        // Needs an adapter, which may be a software one like lavapipe
+        let gpu = match pollster::block_on(crate::gpu::GpuRenderer::new()) {
+            Ok(gpu) => gpu,
+            Err(e) => {
+                eprintln!("Skipping GPU comparison: {}", e);
+                return;
+            }
+        };
+
+        let (width, height) = (160, 90);
+        let params = common::ShaderParams::default();
+
+        for shader in [BuiltinShader::Raymarching, BuiltinShader::Tunnel] {
+            for time in [0.0, 1.5, 7.25] {
+                let cpu = render_cpu(shader, width, height, time);
+                let gpu = gpu
+                    .render_shader(shader.name(), width, height, time, &params)
+                    .unwrap();
+                assert_eq!(cpu.len(), gpu.len());
+
+                // Edges of checkers and silhouettes may land on different pixels
+                // with the GPU's float precision, so allow a few outliers
+                let mut total = 0u64;
+                let mut outliers = 0;
+                for (a, b) in cpu.chunks_exact(4).zip(gpu.chunks_exact(4)) {
+                    let diff = (0..3).map(|c| a[c].abs_diff(b[c])).max().unwrap();
+                    total += diff as u64;
+                    if diff > 16 {
+                        outliers += 1;
+                    }
+                }
+
+                let pixels = (width * height) as u64;
+                let mean = total as f64 / pixels as f64;
+                assert!(
+                    mean < 3.0 && outliers * 50 < pixels,
+                    "{:?} at {}s differs: mean {:.2}, {} outliers",
+                    shader,
+                    time,
+                    mean,
+                    outliers
+                );
+            }
+        }
+    }
+}
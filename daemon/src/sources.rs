@@ -0,0 +1,492 @@
+//! Wallpaper sources other than local files.
+//!
+//! `file://` URLs, `http(s)://` URLs and image data sent over IPC are all turned
+//! into files in the cache directory, so the rest of the daemon only deals with
+//! local paths (and wallpapers survive reconnection like any other file).
+
+use common::{PixelFormat, WallpaperError};
+use std::path::{Path, PathBuf};
+
+type Result<T> = std::result::Result<T, WallpaperError>;
+
+/// Largest file accepted from a remote URL
+#[cfg(feature = "fetch")]
+const MAX_DOWNLOAD_BYTES: u64 = 256 * 1024 * 1024;
+
+/// How long a download may take in total
+#[cfg(feature = "fetch")]
+const FETCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Size the cache directory is pruned back to, least recently used files first
+const MAX_CACHE_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Directory holding fetched and uploaded wallpapers
+pub fn cache_dir() -> Result<PathBuf> {
+    let dir = dirs::cache_dir()
+        .ok_or_else(|| WallpaperError::Io("Failed to get cache directory".to_string()))?
+        .join("momoi")
+        .join("sources");
+
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Check whether a wallpaper path is a remote URL
+pub fn is_remote(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+/// Resolve a wallpaper path or URL to a local path.
+///
+/// Plain paths are returned unchanged, `file://` URLs are decoded and remote
+/// URLs are downloaded into the cache directory.
+pub async fn resolve(path: String) -> Result<String> {
+    if let Some(rest) = path.strip_prefix("file://") {
+        return file_url_to_path(rest);
+    }
+
+    if !is_remote(&path) {
+        return Ok(path);
+    }
+
+    let dir = cache_dir()?;
+    let fetched = tokio::task::spawn_blocking(move || fetch_url(&path, &dir))
+        .await
+        .map_err(|e| WallpaperError::Io(format!("Download task failed: {}", e)))??;
+
+    Ok(fetched.to_string_lossy().into_owned())
+}
+
+/// Store image data received over IPC in the cache directory and return its path
+pub async fn store_image_data(
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    bytes: Vec<u8>,
+) -> Result<String> {
+    let dir = cache_dir()?;
+    let stored =
+        tokio::task::spawn_blocking(move || write_image_data(&dir, width, height, format, &bytes))
+            .await
+            .map_err(|e| WallpaperError::Io(format!("Image data task failed: {}", e)))??;
+
+    Ok(stored.to_string_lossy().into_owned())
+}
+
+/// Convert the part of a `file://` URL after the scheme to a path
+fn file_url_to_path(rest: &str) -> Result<String> {
+    // Only local files: file:///path or file://localhost/path
+    let path = rest.strip_prefix("localhost").unwrap_or(rest);
+    if !path.starts_with('/') {
+        return Err(WallpaperError::invalid_argument(
+            "path",
+            format!("file://{}", rest),
+            "only local file:// URLs with an absolute path are supported",
+        ));
+    }
+
+    let path = percent_decode(path).ok_or_else(|| {
+        WallpaperError::invalid_argument(
+            "path",
+            format!("file://{}", rest),
+            "invalid percent-encoding",
+        )
+    })?;
+
+    if !Path::new(&path).exists() {
+        return Err(WallpaperError::FileNotFound { path });
+    }
+
+    Ok(path)
+}
+
+/// Decode `%XX` escapes in a URL path
+fn percent_decode(input: &str) -> Option<String> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(decoded).ok()
+}
+
+/// Download a URL into `dir`, named after its contents
+#[cfg(feature = "fetch")]
+fn fetch_url(url: &str, dir: &Path) -> Result<PathBuf> {
+    let fetch_error = |reason: String| WallpaperError::FetchFailed {
+        url: url.to_string(),
+        reason,
+    };
+
+    log::info!("Fetching wallpaper from {}", url);
+
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(FETCH_TIMEOUT))
+        .build()
+        .into();
+
+    let mut response = agent
+        .get(url)
+        .call()
+        .map_err(|e| fetch_error(e.to_string()))?;
+    let bytes = response
+        .body_mut()
+        .with_config()
+        .limit(MAX_DOWNLOAD_BYTES)
+        .read_to_vec()
+        .map_err(|e| fetch_error(e.to_string()))?;
+
+    // Prefer the real contents over whatever the URL claims to be
+    let extension = match image::guess_format(&bytes) {
+        Ok(format) => format.extensions_str()[0].to_string(),
+        Err(_) => url_extension(url).ok_or_else(|| WallpaperError::UnsupportedFormat {
+            path: url.to_string(),
+            reason: "could not detect the file type".to_string(),
+        })?,
+    };
+
+    let path = dir.join(format!("{}.{}", content_hash(&bytes), extension));
+    write_atomically(dir, &path, &bytes)?;
+
+    log::info!(
+        "Fetched {} ({} bytes) to {}",
+        url,
+        bytes.len(),
+        path.display()
+    );
+    Ok(path)
+}
+
+#[cfg(not(feature = "fetch"))]
+fn fetch_url(_url: &str, _dir: &Path) -> Result<PathBuf> {
+    Err(WallpaperError::FeatureDisabled {
+        feature: "fetch".to_string(),
+    })
+}
+
+/// Get the file extension from a URL path, ignoring any query or fragment
+#[cfg(feature = "fetch")]
+fn url_extension(url: &str) -> Option<String> {
+    let (_, rest) = url.split_once("://")?;
+    let (_, path) = rest.split_once('/')?;
+    let path = path.split(['?', '#']).next()?;
+    let file_name = path.rsplit('/').next()?;
+    let (_, extension) = file_name.rsplit_once('.')?;
+
+    (!extension.is_empty() && extension.chars().all(|c| c.is_ascii_alphanumeric()))
+        .then(|| extension.to_ascii_lowercase())
+}
+
+/// Write image data into `dir`, named after its contents
+fn write_image_data(
+    dir: &Path,
+    width: u32,
+    height: u32,
+    format: PixelFormat,
+    bytes: &[u8],
+) -> Result<PathBuf> {
+    let Some(bytes_per_pixel) = format.bytes_per_pixel() else {
+        // Encoded files are stored as-is
+        let image_format = image::guess_format(bytes)
+            .ok()
+            .filter(|f| f.reading_enabled())
+            .ok_or_else(|| WallpaperError::UnsupportedFormat {
+                path: "<image data>".to_string(),
+                reason: "not a supported image file".to_string(),
+            })?;
+
+        let path = dir.join(format!(
+            "{}.{}",
+            content_hash(bytes),
+            image_format.extensions_str()[0]
+        ));
+        write_atomically(dir, &path, bytes)?;
+        return Ok(path);
+    };
+
+    let expected = width as usize * height as usize * bytes_per_pixel;
+    if width == 0 || height == 0 || bytes.len() != expected {
+        return Err(WallpaperError::invalid_argument(
+            "image data",
+            format!("{}x{} {:?}", width, height, format),
+            format!("expected {} bytes, got {}", expected, bytes.len()),
+        ));
+    }
+
+    let image: image::DynamicImage = match format {
+        PixelFormat::Rgba8 => image::RgbaImage::from_raw(width, height, bytes.to_vec())
+            .map(image::DynamicImage::ImageRgba8),
+        PixelFormat::Bgra8 => {
+            let mut rgba = bytes.to_vec();
+            for pixel in rgba.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+            image::RgbaImage::from_raw(width, height, rgba).map(image::DynamicImage::ImageRgba8)
+        }
+        PixelFormat::Rgb8 => image::RgbImage::from_raw(width, height, bytes.to_vec())
+            .map(image::DynamicImage::ImageRgb8),
+        PixelFormat::Gray8 => image::GrayImage::from_raw(width, height, bytes.to_vec())
+            .map(image::DynamicImage::ImageLuma8),
+        PixelFormat::Encoded => None,
+    }
+    .ok_or_else(|| WallpaperError::Image("Failed to build image from raw data".to_string()))?;
+
+    // Store raw pixels losslessly so the wallpaper can be reloaded later
+    let mut png = Vec::new();
+    image
+        .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .map_err(|e| WallpaperError::Image(e.to_string()))?;
+
+    let path = dir.join(format!("{}.png", content_hash(bytes)));
+    write_atomically(dir, &path, &png)?;
+    Ok(path)
+}
+
+/// SHA-256 of file contents, for a stable cache file name
+fn content_hash(bytes: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, bytes)
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Write a file via a temporary file so readers never see partial contents,
+/// then prune the cache
+///
+/// Files are named after their contents, so an existing one already holds
+/// them and is only marked as recently used.
+fn write_atomically(dir: &Path, path: &Path, bytes: &[u8]) -> Result<()> {
+    use std::io::Write;
+
+    if path.exists() {
+        std::fs::File::options()
+            .write(true)
+            .open(path)?
+            .set_modified(std::time::SystemTime::now())?;
+    } else {
+        let mut file = tempfile::NamedTempFile::new_in(dir)?;
+        file.write_all(bytes)?;
+        file.persist(path)
+            .map_err(|e| WallpaperError::from(e.error))?;
+    }
+
+    prune(dir, path, MAX_CACHE_BYTES);
+    Ok(())
+}
+
+/// Delete the least recently written files in `dir` until it holds at most
+/// `limit` bytes, keeping `keep`
+fn prune(dir: &Path, keep: &Path, limit: u64) {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!("Failed to list cache directory {}: {}", dir.display(), e);
+            return;
+        }
+    };
+
+    let mut files: Vec<_> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let modified = metadata.modified().ok()?;
+            metadata
+                .is_file()
+                .then(|| (modified, metadata.len(), entry.path()))
+        })
+        .collect();
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+
+    // Oldest first
+    files.sort();
+    for (_, len, path) in files {
+        if total <= limit {
+            break;
+        }
+        if path == keep {
+            continue;
+        }
+
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                total -= len;
+                log::debug!("Pruned cached wallpaper {}", path.display());
+            }
+            Err(e) => log::warn!("Failed to prune {}: {}", path.display(), e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn png_bytes() -> Vec<u8> {
+        let image = image::RgbaImage::from_pixel(4, 3, image::Rgba([10, 20, 30, 255]));
+        let mut png = Vec::new();
+        image::DynamicImage::ImageRgba8(image)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    #[test]
+    fn test_file_urls() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("my wallpaper.png");
+        std::fs::write(&file, png_bytes()).unwrap();
+
+        let url = format!(
+            "{}/my%20wallpaper.png",
+            dir.path().display().to_string().trim_end_matches('/')
+        );
+        assert_eq!(file_url_to_path(&url).unwrap(), file.display().to_string());
+
+        let missing = file_url_to_path("/nonexistent/wall.png").unwrap_err();
+        assert_eq!(missing.code(), common::ErrorCode::FileNotFound);
+
+        // Remote hosts are not local files
+        assert!(file_url_to_path("server/share/wall.png").is_err());
+        assert_eq!(percent_decode("%zz"), None);
+    }
+
+    #[test]
+    fn test_raw_image_data() {
+        let dir = tempfile::tempdir().unwrap();
+
+        // 2x1 BGRA: blue then red
+        let bgra = [255, 0, 0, 255, 0, 0, 255, 255];
+        let path = write_image_data(dir.path(), 2, 1, PixelFormat::Bgra8, &bgra).unwrap();
+        assert_eq!(path.extension().unwrap(), "png");
+
+        let image = image::open(&path).unwrap().to_rgba8();
+        assert_eq!(image.get_pixel(0, 0).0, [0, 0, 255, 255]);
+        assert_eq!(image.get_pixel(1, 0).0, [255, 0, 0, 255]);
+
+        // Same data maps to the same file
+        let again = write_image_data(dir.path(), 2, 1, PixelFormat::Bgra8, &bgra).unwrap();
+        assert_eq!(path, again);
+
+        // Length must match the dimensions
+        let err = write_image_data(dir.path(), 3, 1, PixelFormat::Bgra8, &bgra).unwrap_err();
+        assert_eq!(err.code(), common::ErrorCode::InvalidArgument);
+    }
+
+    #[test]
+    fn test_encoded_image_data() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let path = write_image_data(dir.path(), 0, 0, PixelFormat::Encoded, &png_bytes()).unwrap();
+        assert_eq!(path.extension().unwrap(), "png");
+        assert_eq!(image::open(&path).unwrap().width(), 4);
+
+        let err =
+            write_image_data(dir.path(), 0, 0, PixelFormat::Encoded, b"not an image").unwrap_err();
+        assert_eq!(err.code(), common::ErrorCode::UnsupportedFormat);
+    }
+
+    #[test]
+    fn test_cache_pruning() {
+        assert_eq!(
+            content_hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        // Three 10-byte files written a minute apart
+        let dir = tempfile::tempdir().unwrap();
+        let start = std::time::SystemTime::now() - std::time::Duration::from_secs(600);
+        let paths: Vec<_> = (0..3)
+            .map(|i| {
+                let path = dir.path().join(format!("{}.png", i));
+                std::fs::write(&path, [0; 10]).unwrap();
+                std::fs::File::options()
+                    .write(true)
+                    .open(&path)
+                    .unwrap()
+                    .set_modified(start + std::time::Duration::from_secs(60 * i))
+                    .unwrap();
+                path
+            })
+            .collect();
+
+        // The oldest file goes, unless it's the one being kept
+        prune(dir.path(), &paths[2], 20);
+        assert!(!paths[0].exists() && paths[1].exists() && paths[2].exists());
+        prune(dir.path(), &paths[1], 10);
+        assert!(paths[1].exists() && !paths[2].exists());
+    }
+
+    #[cfg(feature = "fetch")]
+    #[test]
+    fn test_fetch_from_local_server() {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let body = png_bytes();
+
+        // Serve the PNG once, then a 404
+        let server_body = body.clone();
+        let server = std::thread::spawn(move || {
+            for status in ["200 OK", "404 Not Found"] {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line != "\r\n" {
+                    line.clear();
+                }
+
+                let body: &[u8] = if status.starts_with("200") {
+                    &server_body
+                } else {
+                    b""
+                };
+                let mut stream = reader.into_inner();
+                write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(body).unwrap();
+            }
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+
+        // No extension in the URL: the type comes from the contents
+        let url = format!("http://127.0.0.1:{}/wallpaper?size=large", port);
+        let path = fetch_url(&url, dir.path()).unwrap();
+        assert_eq!(path.extension().unwrap(), "png");
+        assert_eq!(std::fs::read(&path).unwrap(), body);
+
+        let err = fetch_url(&url, dir.path()).unwrap_err();
+        assert_eq!(err.code(), common::ErrorCode::FetchFailed);
+
+        server.join().unwrap();
+    }
+
+    #[cfg(feature = "fetch")]
+    #[test]
+    fn test_url_extension() {
+        assert_eq!(
+            url_extension("https://example.com/a/clip.MP4?t=1"),
+            Some("mp4".to_string())
+        );
+        assert_eq!(url_extension("https://example.com/image"), None);
+        assert_eq!(url_extension("https://example.com"), None);
+    }
+}
@@ -0,0 +1,387 @@
+//! SVG wallpaper rendering
+//!
+//! SVGs are rasterized with resvg directly at each output's size, so they stay
+//! sharp at any resolution instead of being scaled from a bitmap. Colours can be
+//! substituted from the config before parsing, which lets one SVG be recoloured
+//! to match a theme.
+
+use anyhow::{Context, Result};
+use image::RgbaImage;
+use resvg::{tiny_skia, usvg};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Render an SVG file at exactly `width`x`height` using the given scale mode,
+/// with `letterbox` around Fit and Center content.
+///
+/// `colors` maps CSS variable names (used as `var(--name)`) or literal hex
+/// colours (`#rrggbb`) to replacement colours.
+pub fn render(
+    path: &Path,
+    width: u32,
+    height: u32,
+    mode: common::ScaleMode,
+    letterbox: common::Letterbox,
+    colors: &BTreeMap<String, String>,
+    fontdb: Arc<usvg::fontdb::Database>,
+) -> Result<RgbaImage> {
+    let data =
+        std::fs::read(path).with_context(|| format!("Failed to read SVG: {}", path.display()))?;
+
+    // .svgz is gzip-compressed, and substitution needs the text
+    let data = if data.starts_with(&[0x1f, 0x8b]) {
+        usvg::decompress_svgz(&data)
+            .with_context(|| format!("Failed to decompress SVG: {}", path.display()))?
+    } else {
+        data
+    };
+    let data = substitute_colors(&String::from_utf8_lossy(&data), colors);
+
+    let options = usvg::Options {
+        resources_dir: path.parent().map(Path::to_path_buf),
+        fontdb,
+        ..Default::default()
+    };
+
+    let tree = usvg::Tree::from_str(&data, &options)
+        .with_context(|| format!("Failed to parse SVG: {}", path.display()))?;
+
+    log::debug!(
+        "Rendering SVG {} ({}x{}) at {}x{} ({:?})",
+        path.display(),
+        tree.size().width(),
+        tree.size().height(),
+        width,
+        height,
+        mode
+    );
+
+    render_tree(&tree, width, height, mode, letterbox)
+}
+
+/// Rasterize a parsed SVG at the target size, padding Fit and Center with the letterbox
+fn render_tree(
+    tree: &usvg::Tree,
+    width: u32,
+    height: u32,
+    mode: common::ScaleMode,
+    letterbox: common::Letterbox,
+) -> Result<RgbaImage> {
+    let (svg_width, svg_height) = (tree.size().width(), tree.size().height());
+    let (target_width, target_height) = (width as f32, height as f32);
+
+    let background = match letterbox {
+        common::Letterbox::Color([r, g, b]) => tiny_skia::Color::from_rgba8(r, g, b, 255),
+        // Letterboxes taken from the content are laid out around a bitmap of it, like images
+        _ if matches!(mode, common::ScaleMode::Fit | common::ScaleMode::Center) => {
+            let content = match mode {
+                common::ScaleMode::Fit => {
+                    let scale = (target_width / svg_width).min(target_height / svg_height);
+                    (
+                        ((svg_width * scale).round() as u32).clamp(1, width),
+                        ((svg_height * scale).round() as u32).clamp(1, height),
+                    )
+                }
+                _ => (
+                    (svg_width.ceil() as u32).clamp(1, width),
+                    (svg_height.ceil() as u32).clamp(1, height),
+                ),
+            };
+            return render_letterboxed(tree, content, (width, height), mode, letterbox);
+        }
+        _ => tiny_skia::Color::BLACK,
+    };
+
+    let mut pixmap = tiny_skia::Pixmap::new(width, height).context("Invalid output size")?;
+    pixmap.fill(background);
+
+    // Centre content scaled by (sx, sy) on the output
+    let centered = |sx: f32, sy: f32| {
+        tiny_skia::Transform::from_row(
+            sx,
+            0.0,
+            0.0,
+            sy,
+            (target_width - svg_width * sx) / 2.0,
+            (target_height - svg_height * sy) / 2.0,
+        )
+    };
+
+    match mode {
+        common::ScaleMode::Fill => {
+            let scale = (target_width / svg_width).max(target_height / svg_height);
+            resvg::render(tree, centered(scale, scale), &mut pixmap.as_mut());
+        }
+        common::ScaleMode::Fit => {
+            let scale = (target_width / svg_width).min(target_height / svg_height);
+            resvg::render(tree, centered(scale, scale), &mut pixmap.as_mut());
+        }
+        common::ScaleMode::Stretch => {
+            let transform = tiny_skia::Transform::from_scale(
+                target_width / svg_width,
+                target_height / svg_height,
+            );
+            resvg::render(tree, transform, &mut pixmap.as_mut());
+        }
+        common::ScaleMode::Center => {
+            resvg::render(tree, centered(1.0, 1.0), &mut pixmap.as_mut());
+        }
+        common::ScaleMode::Tile => {
+            let tile_width = (svg_width.ceil() as u32).max(1);
+            let tile_height = (svg_height.ceil() as u32).max(1);
+            let mut tile = tiny_skia::Pixmap::new(tile_width, tile_height)
+                .context("SVG is too large to tile")?;
+            resvg::render(tree, tiny_skia::Transform::identity(), &mut tile.as_mut());
+
+            for y in (0..height).step_by(tile_height as usize) {
+                for x in (0..width).step_by(tile_width as usize) {
+                    pixmap.draw_pixmap(
+                        x as i32,
+                        y as i32,
+                        tile.as_ref(),
+                        &tiny_skia::PixmapPaint::default(),
+                        tiny_skia::Transform::identity(),
+                        None,
+                    );
+                }
+            }
+        }
+    }
+
+    // The background is opaque, so premultiplied and straight alpha are identical
+    RgbaImage::from_raw(width, height, pixmap.take()).context("Failed to create SVG image buffer")
+}
+
+/// Rasterize Fit or Center content at its `content` size on the output, then
+/// place it with the same letterbox code as images
+fn render_letterboxed(
+    tree: &usvg::Tree,
+    content: (u32, u32),
+    (width, height): (u32, u32),
+    mode: common::ScaleMode,
+    letterbox: common::Letterbox,
+) -> Result<RgbaImage> {
+    let frame = render_tree(
+        tree,
+        content.0,
+        content.1,
+        mode,
+        common::Letterbox::default(),
+    )?;
+
+    // The frame layout works on BGRA
+    let mut frame = frame.into_raw();
+    swap_red_blue(&mut frame);
+    let placement = crate::frame_layout::Placement { mode, letterbox };
+    let mut frame = crate::frame_layout::scale_frame(&frame, content, (width, height), placement);
+    swap_red_blue(&mut frame);
+
+    RgbaImage::from_raw(width, height, frame).context("Failed to create SVG image buffer")
+}
+
+fn swap_red_blue(pixels: &mut [u8]) {
+    for pixel in pixels.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+}
+
+/// Apply colour substitutions to SVG source.
+///
+/// Keys starting with `#` replace that literal colour (case-insensitive); other
+/// keys resolve `var(--key)` references. Unknown variables fall back to the
+/// default given in `var(--key, default)`, since usvg doesn't support them.
+pub fn substitute_colors(svg: &str, colors: &BTreeMap<String, String>) -> String {
+    let mut output = String::with_capacity(svg.len());
+    let mut rest = svg;
+
+    while let Some(start) = rest.find("var(") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 4..];
+
+        // Find the matching closing parenthesis (fallbacks may contain rgb(...))
+        let mut depth = 1;
+        let end = after.char_indices().find_map(|(i, c)| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            (depth == 0).then_some(i)
+        });
+
+        let Some(end) = end else {
+            output.push_str(&rest[start..]);
+            rest = "";
+            break;
+        };
+
+        let inner = &after[..end];
+        let (name, fallback) = match inner.split_once(',') {
+            Some((name, fallback)) => (name.trim(), Some(fallback.trim())),
+            None => (inner.trim(), None),
+        };
+
+        match name.strip_prefix("--").and_then(|n| colors.get(n)) {
+            Some(color) => output.push_str(color),
+            None => match fallback {
+                Some(fallback) => output.push_str(fallback),
+                None => output.push_str(&rest[start..start + 4 + end + 1]),
+            },
+        }
+
+        rest = &after[end + 1..];
+    }
+    output.push_str(rest);
+
+    for (from, to) in colors.iter().filter(|(key, _)| key.starts_with('#')) {
+        output = replace_hex_color(&output, from, to);
+    }
+
+    output
+}
+
+/// Replace a hex colour, ignoring case and longer colours it is a prefix of
+fn replace_hex_color(svg: &str, from: &str, to: &str) -> String {
+    let lower = svg.to_ascii_lowercase();
+    let from = from.to_ascii_lowercase();
+    let mut output = String::with_capacity(svg.len());
+    let mut last = 0;
+
+    for (index, _) in lower.match_indices(&from) {
+        let end = index + from.len();
+        let continues = lower[end..]
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_hexdigit());
+
+        if index >= last && !continues {
+            output.push_str(&svg[last..index]);
+            output.push_str(to);
+            last = end;
+        }
+    }
+    output.push_str(&svg[last..]);
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SQUARE: &str = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10">
+        <rect width="10" height="10" fill="var(--accent, #0000ff)"/>
+    </svg>"##;
+
+    fn parse(svg: &str) -> usvg::Tree {
+        usvg::Tree::from_str(svg, &usvg::Options::default()).unwrap()
+    }
+
+    #[test]
+    fn test_substitute_colors() {
+        let mut colors = BTreeMap::new();
+
+        // Unknown variables use their fallback
+        assert!(substitute_colors(SQUARE, &colors).contains(r##"fill="#0000ff""##));
+
+        colors.insert("accent".to_string(), "#ff0000".to_string());
+        assert!(substitute_colors(SQUARE, &colors).contains(r##"fill="#ff0000""##));
+
+        // Literal colours are replaced case-insensitively, but not inside longer ones
+        let mut literal = BTreeMap::new();
+        literal.insert("#abc".to_string(), "#123".to_string());
+        assert_eq!(
+            substitute_colors("fill:#ABC;stroke:#abcdef", &literal),
+            "fill:#123;stroke:#abcdef"
+        );
+
+        // Nested parentheses in fallbacks
+        assert_eq!(
+            substitute_colors("fill:var(--bg, rgb(1, 2, 3))", &BTreeMap::new()),
+            "fill:rgb(1, 2, 3)"
+        );
+    }
+
+    #[test]
+    fn test_render_sizes() {
+        let tree = parse(&substitute_colors(SQUARE, &BTreeMap::new()));
+
+        // Rendered at the exact output size in every mode
+        for mode in [
+            common::ScaleMode::Fill,
+            common::ScaleMode::Fit,
+            common::ScaleMode::Stretch,
+            common::ScaleMode::Center,
+            common::ScaleMode::Tile,
+        ] {
+            let image = render_tree(&tree, 40, 20, mode, common::Letterbox::default()).unwrap();
+            assert_eq!(image.dimensions(), (40, 20));
+        }
+    }
+
+    #[test]
+    fn test_render_modes() {
+        let tree = parse(&substitute_colors(SQUARE, &BTreeMap::new()));
+        let blue = [0, 0, 255, 255];
+        let black = [0, 0, 0, 255];
+
+        // Fill covers the whole output
+        let fill = render_tree(
+            &tree,
+            40,
+            20,
+            common::ScaleMode::Fill,
+            common::Letterbox::default(),
+        )
+        .unwrap();
+        assert_eq!(fill.get_pixel(0, 0).0, blue);
+        assert_eq!(fill.get_pixel(39, 19).0, blue);
+
+        // Fit letterboxes the sides
+        let fit = render_tree(
+            &tree,
+            40,
+            20,
+            common::ScaleMode::Fit,
+            common::Letterbox::default(),
+        )
+        .unwrap();
+        assert_eq!(fit.get_pixel(0, 10).0, black);
+        assert_eq!(fit.get_pixel(20, 10).0, blue);
+
+        // Center keeps the native size
+        let center = render_tree(
+            &tree,
+            40,
+            20,
+            common::ScaleMode::Center,
+            common::Letterbox::default(),
+        )
+        .unwrap();
+        assert_eq!(center.get_pixel(20, 10).0, blue);
+        assert_eq!(center.get_pixel(10, 10).0, black);
+
+        // The letterbox colour pads Fit, and mirrored content pads it blue
+        let red = render_tree(
+            &tree,
+            40,
+            20,
+            common::ScaleMode::Fit,
+            common::Letterbox::Color([255, 0, 0]),
+        )
+        .unwrap();
+        assert_eq!(red.get_pixel(0, 10).0, [255, 0, 0, 255]);
+        assert_eq!(red.get_pixel(20, 10).0, blue);
+        let mirror = render_tree(
+            &tree,
+            40,
+            20,
+            common::ScaleMode::Fit,
+            common::Letterbox::Mirror,
+        )
+        .unwrap();
+        assert_eq!(mirror.dimensions(), (40, 20));
+        assert_eq!(mirror.get_pixel(0, 10).0, blue);
+    }
+}
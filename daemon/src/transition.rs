@@ -26,6 +26,24 @@ pub enum TransitionType {
     Random,
 }
 
+impl TransitionType {
+    /// Look up a transition by its config/CLI name (`random` is excluded, as it isn't reproducible)
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "none" => Some(Self::None),
+            "fade" => Some(Self::Fade),
+            "wipe-left" => Some(Self::WipeLeft),
+            "wipe-right" => Some(Self::WipeRight),
+            "wipe-top" => Some(Self::WipeTop),
+            "wipe-bottom" => Some(Self::WipeBottom),
+            "wipe-angle" => Some(Self::WipeAngle(45.0)),
+            "center" => Some(Self::Center),
+            "outer" => Some(Self::Outer),
+            _ => None,
+        }
+    }
+}
+
 impl Default for TransitionType {
     fn default() -> Self {
         Self::Fade
@@ -88,6 +106,17 @@ impl Default for EasingFunction {
     }
 }
 
+impl From<common::Easing> for EasingFunction {
+    fn from(e: common::Easing) -> Self {
+        match e {
+            common::Easing::Linear => Self::Linear,
+            common::Easing::EaseIn => Self::EaseIn,
+            common::Easing::EaseOut => Self::EaseOut,
+            common::Easing::EaseInOut => Self::EaseInOut,
+        }
+    }
+}
+
 impl EasingFunction {
     /// Apply easing to a linear progress value (0.0 to 1.0)
     pub fn apply(&self, t: f32) -> f32 {
@@ -106,6 +135,16 @@ impl EasingFunction {
     }
 }
 
+/// What a transition starts from
+pub enum OldFrame {
+    /// A full ARGB8888 frame
+    Frame(Vec<u8>),
+    /// A solid colour (one ARGB8888 pixel, BGRA in memory), as shown by a
+    /// single-pixel buffer; blends paint it straight into the new frame
+    /// instead of expanding it to a full one
+    Color([u8; 4]),
+}
+
 /// Manages a transition between two wallpapers
 pub struct Transition {
     /// Type of transition effect
@@ -116,11 +155,13 @@ pub struct Transition {
     duration: Duration,
     /// When the transition started
     start_time: Instant,
-    /// Old wallpaper frame data (ARGB8888)
-    old_frame: Vec<u8>,
+    /// Old wallpaper frame (or the colour that was shown)
+    old_frame: OldFrame,
     /// Dimensions of the frames
     width: u32,
     height: u32,
+    /// Time source for the transition
+    clock: crate::clock::Clock,
     /// Optional GPU renderer for accelerated transitions
     #[cfg(feature = "gpu")]
     gpu_renderer: Option<std::sync::Arc<crate::gpu::GpuRenderer>>,
@@ -131,7 +172,7 @@ impl Transition {
     pub fn new(
         transition_type: TransitionType,
         duration: Duration,
-        old_frame: Vec<u8>,
+        old_frame: OldFrame,
         width: u32,
         height: u32,
         #[cfg(feature = "gpu")] gpu_renderer: Option<std::sync::Arc<crate::gpu::GpuRenderer>>,
@@ -144,6 +185,7 @@ impl Transition {
             old_frame,
             width,
             height,
+            clock: crate::clock::Clock::system(),
             #[cfg(feature = "gpu")]
             gpu_renderer,
         }
@@ -151,7 +193,7 @@ impl Transition {
 
     /// Get the current progress (0.0 to 1.0)
     fn raw_progress(&self) -> f32 {
-        let elapsed = self.start_time.elapsed();
+        let elapsed = self.clock.elapsed(self.start_time);
         if elapsed >= self.duration {
             1.0
         } else {
@@ -166,7 +208,7 @@ impl Transition {
 
     /// Check if the transition is complete
     pub fn is_complete(&self) -> bool {
-        self.start_time.elapsed() >= self.duration
+        self.clock.elapsed(self.start_time) >= self.duration
     }
 
     /// Blend old and new frames based on current progress
@@ -232,28 +274,59 @@ impl Transition {
 
     /// Fade transition: alpha blend between old and new
     fn blend_fade(&self, new_frame: &[u8], progress: f32) -> Vec<u8> {
-        let mut result = Vec::with_capacity(self.old_frame.len());
-
-        // ARGB8888 format: 4 bytes per pixel
-        for i in (0..self.old_frame.len()).step_by(4) {
-            let old_b = self.old_frame[i] as f32;
-            let old_g = self.old_frame[i + 1] as f32;
-            let old_r = self.old_frame[i + 2] as f32;
-            let old_a = self.old_frame[i + 3] as f32;
-
-            let new_b = new_frame[i] as f32;
-            let new_g = new_frame[i + 1] as f32;
-            let new_r = new_frame[i + 2] as f32;
-            let new_a = new_frame[i + 3] as f32;
-
-            // Linear interpolation
-            result.push((old_b + (new_b - old_b) * progress) as u8);
-            result.push((old_g + (new_g - old_g) * progress) as u8);
-            result.push((old_r + (new_r - old_r) * progress) as u8);
-            result.push((old_a + (new_a - old_a) * progress) as u8);
+        match &self.old_frame {
+            OldFrame::Frame(old_frame) => {
+                let mut result = old_frame.clone();
+                crate::cpu_render::mix(&mut result, new_frame, progress);
+                result
+            }
+            OldFrame::Color(color) => {
+                let mut result = new_frame.to_vec();
+                crate::cpu_render::mix_color(&mut result, *color, 1.0 - progress);
+                result
+            }
         }
+    }
 
-        result
+    /// Copy the pixels of `new_frame` for which `show_new(x, y)` holds over the old frame
+    fn blend_pixels<F>(&self, new_frame: &[u8], show_new: F) -> Vec<u8>
+    where
+        F: Fn(usize, usize) -> bool + Sync,
+    {
+        match &self.old_frame {
+            OldFrame::Frame(old_frame) => {
+                let mut result = old_frame.clone();
+                crate::cpu_render::for_each_row_with(
+                    &mut result,
+                    new_frame,
+                    self.width,
+                    |y, row, new_row| {
+                        for (x, (pixel, new_pixel)) in row
+                            .chunks_exact_mut(4)
+                            .zip(new_row.chunks_exact(4))
+                            .enumerate()
+                        {
+                            if show_new(x, y as usize) {
+                                pixel.copy_from_slice(new_pixel);
+                            }
+                        }
+                    },
+                );
+                result
+            }
+            // Paint the colour where the new frame doesn't show yet
+            OldFrame::Color(color) => {
+                let mut result = new_frame.to_vec();
+                crate::cpu_render::for_each_row(&mut result, self.width, |y, row| {
+                    for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+                        if !show_new(x, y as usize) {
+                            pixel.copy_from_slice(color);
+                        }
+                    }
+                });
+                result
+            }
+        }
     }
 
     /// Horizontal wipe transition
@@ -263,9 +336,6 @@ impl Transition {
         progress: f32,
         right_to_left: bool,
     ) -> Vec<u8> {
-        let mut result = self.old_frame.clone();
-        let stride = self.width as usize * 4; // 4 bytes per pixel
-
         // Calculate the transition boundary (in pixels)
         let boundary = if right_to_left {
             self.width as f32 * (1.0 - progress)
@@ -273,63 +343,64 @@ impl Transition {
             self.width as f32 * progress
         };
 
-        for y in 0..self.height as usize {
-            let row_start = y * stride;
-            for x in 0..self.width as usize {
-                let pixel_start = row_start + x * 4;
-
-                // Determine if this pixel should show new or old frame
-                let show_new = if right_to_left {
-                    x as f32 >= boundary
-                } else {
-                    (x as f32) < boundary
-                };
-
-                if show_new {
-                    result[pixel_start..pixel_start + 4]
-                        .copy_from_slice(&new_frame[pixel_start..pixel_start + 4]);
-                }
+        self.blend_pixels(new_frame, |x, _| {
+            // Determine if this pixel should show new or old frame
+            if right_to_left {
+                x as f32 >= boundary
+            } else {
+                (x as f32) < boundary
             }
-        }
-
-        result
+        })
     }
 
     /// Vertical wipe transition
     fn blend_wipe_vertical(&self, new_frame: &[u8], progress: f32, bottom_to_top: bool) -> Vec<u8> {
-        let mut result = self.old_frame.clone();
-        let stride = self.width as usize * 4;
-
         // Calculate the transition boundary (in rows)
         let boundary = if bottom_to_top {
             self.height as f32 * (1.0 - progress)
         } else {
             self.height as f32 * progress
         };
-
-        for y in 0..self.height as usize {
-            // Determine if this row should show new or old frame
-            let show_new = if bottom_to_top {
+        // Determine if a row should show new or old frame
+        let show_new = |y: u32| {
+            if bottom_to_top {
                 y as f32 >= boundary
             } else {
                 (y as f32) < boundary
-            };
+            }
+        };
 
-            if show_new {
-                let row_start = y * stride;
-                result[row_start..row_start + stride]
-                    .copy_from_slice(&new_frame[row_start..row_start + stride]);
+        match &self.old_frame {
+            OldFrame::Frame(old_frame) => {
+                let mut result = old_frame.clone();
+                crate::cpu_render::for_each_row_with(
+                    &mut result,
+                    new_frame,
+                    self.width,
+                    |y, row, new_row| {
+                        if show_new(y) {
+                            row.copy_from_slice(new_row);
+                        }
+                    },
+                );
+                result
+            }
+            OldFrame::Color(color) => {
+                let mut result = new_frame.to_vec();
+                crate::cpu_render::for_each_row(&mut result, self.width, |y, row| {
+                    if !show_new(y) {
+                        for pixel in row.chunks_exact_mut(4) {
+                            pixel.copy_from_slice(color);
+                        }
+                    }
+                });
+                result
             }
         }
-
-        result
     }
 
     /// Diagonal wipe transition at a custom angle
     fn blend_wipe_angle(&self, new_frame: &[u8], progress: f32, angle: f32) -> Vec<u8> {
-        let mut result = self.old_frame.clone();
-        let stride = self.width as usize * 4;
-
         // Convert angle to radians
         let angle_rad = angle.to_radians();
         let cos_a = angle_rad.cos();
@@ -339,30 +410,17 @@ impl Transition {
         let max_dist = self.width as f32 * cos_a.abs() + self.height as f32 * sin_a.abs();
         let boundary = max_dist * progress;
 
-        for y in 0..self.height as usize {
-            let row_start = y * stride;
-            for x in 0..self.width as usize {
-                let pixel_start = row_start + x * 4;
+        self.blend_pixels(new_frame, |x, y| {
+            // Calculate distance along the angle direction
+            let dist = x as f32 * cos_a + y as f32 * sin_a;
 
-                // Calculate distance along the angle direction
-                let dist = x as f32 * cos_a + y as f32 * sin_a;
-
-                // Determine if this pixel should show new or old frame
-                if dist < boundary {
-                    result[pixel_start..pixel_start + 4]
-                        .copy_from_slice(&new_frame[pixel_start..pixel_start + 4]);
-                }
-            }
-        }
-
-        result
+            // Determine if this pixel should show new or old frame
+            dist < boundary
+        })
     }
 
     /// Center expand transition (expand from center outward)
     fn blend_center(&self, new_frame: &[u8], progress: f32) -> Vec<u8> {
-        let mut result = self.old_frame.clone();
-        let stride = self.width as usize * 4;
-
         // Calculate center point
         let center_x = self.width as f32 / 2.0;
         let center_y = self.height as f32 / 2.0;
@@ -371,32 +429,19 @@ impl Transition {
         let max_radius = (center_x * center_x + center_y * center_y).sqrt();
         let current_radius = max_radius * progress;
 
-        for y in 0..self.height as usize {
-            let row_start = y * stride;
-            for x in 0..self.width as usize {
-                let pixel_start = row_start + x * 4;
+        self.blend_pixels(new_frame, |x, y| {
+            // Calculate distance from center
+            let dx = x as f32 - center_x;
+            let dy = y as f32 - center_y;
+            let dist = (dx * dx + dy * dy).sqrt();
 
-                // Calculate distance from center
-                let dx = x as f32 - center_x;
-                let dy = y as f32 - center_y;
-                let dist = (dx * dx + dy * dy).sqrt();
-
-                // Show new frame if within current radius
-                if dist < current_radius {
-                    result[pixel_start..pixel_start + 4]
-                        .copy_from_slice(&new_frame[pixel_start..pixel_start + 4]);
-                }
-            }
-        }
-
-        result
+            // Show new frame if within current radius
+            dist < current_radius
+        })
     }
 
     /// Outer shrink transition (shrink from edges inward)
     fn blend_outer(&self, new_frame: &[u8], progress: f32) -> Vec<u8> {
-        let mut result = self.old_frame.clone();
-        let stride = self.width as usize * 4;
-
         // Calculate center point
         let center_x = self.width as f32 / 2.0;
         let center_y = self.height as f32 / 2.0;
@@ -405,25 +450,22 @@ impl Transition {
         let max_radius = (center_x * center_x + center_y * center_y).sqrt();
         let current_radius = max_radius * (1.0 - progress);
 
-        for y in 0..self.height as usize {
-            let row_start = y * stride;
-            for x in 0..self.width as usize {
-                let pixel_start = row_start + x * 4;
+        self.blend_pixels(new_frame, |x, y| {
+            // Calculate distance from center
+            let dx = x as f32 - center_x;
+            let dy = y as f32 - center_y;
+            let dist = (dx * dx + dy * dy).sqrt();
 
-                // Calculate distance from center
-                let dx = x as f32 - center_x;
-                let dy = y as f32 - center_y;
-                let dist = (dx * dx + dy * dy).sqrt();
-
-                // Show new frame if outside current radius
-                if dist > current_radius {
-                    result[pixel_start..pixel_start + 4]
-                        .copy_from_slice(&new_frame[pixel_start..pixel_start + 4]);
-                }
-            }
-        }
+            // Show new frame if outside current radius
+            dist > current_radius
+        })
+    }
 
-        result
+    /// Drive the transition from a different clock (restarting it at the clock's current time)
+    pub fn with_clock(mut self, clock: crate::clock::Clock) -> Self {
+        self.start_time = clock.now();
+        self.clock = clock;
+        self
     }
 
     /// Set the easing function
@@ -457,7 +499,7 @@ mod tests {
         let transition = Transition::new(
             TransitionType::Fade,
             Duration::from_millis(100),
-            old_frame,
+            OldFrame::Frame(old_frame),
             10,
             10,
             #[cfg(feature = "gpu")]
@@ -467,4 +509,67 @@ mod tests {
         assert!(transition.progress() >= 0.0);
         assert!(transition.progress() <= 1.0);
     }
+
+    #[test]
+    fn test_cpu_blends() {
+        // 40 rows so the frame spans several row bands
+        let (width, height) = (8u32, 40u32);
+        let old_frame = vec![0u8; (width * height * 4) as usize];
+        let new_frame = vec![255u8; old_frame.len()];
+        let transition = |transition_type| {
+            Transition::new(
+                transition_type,
+                Duration::from_secs(1),
+                OldFrame::Frame(old_frame.clone()),
+                width,
+                height,
+                #[cfg(feature = "gpu")]
+                None,
+            )
+        };
+        let row = |frame: &[u8], y: u32| frame[(y * width * 4) as usize];
+
+        let fade = transition(TransitionType::Fade).blend_frames_cpu(&new_frame, 0.5);
+        assert!(fade.iter().all(|&v| v == 127));
+
+        // The top 30% of rows show the new frame
+        let wipe = transition(TransitionType::WipeTop).blend_frames_cpu(&new_frame, 0.3);
+        assert_eq!((row(&wipe, 11), row(&wipe, 12)), (255, 0));
+
+        // The left half of every row shows the new frame
+        let wipe = transition(TransitionType::WipeLeft).blend_frames_cpu(&new_frame, 0.5);
+        for y in [0, height - 1] {
+            let start = (y * width * 4) as usize;
+            assert_eq!(wipe[start + 3 * 4], 255);
+            assert_eq!(wipe[start + 4 * 4], 0);
+        }
+
+        // The middle shows the new frame first, the corners last
+        let center = transition(TransitionType::Center).blend_frames_cpu(&new_frame, 0.5);
+        assert_eq!(center[((20 * width + 4) * 4) as usize], 255);
+        assert_eq!(center[0], 0);
+
+        // A colour blends like a frame filled with it
+        let from_color = |transition_type| {
+            Transition::new(
+                transition_type,
+                Duration::from_secs(1),
+                OldFrame::Color([0; 4]),
+                width,
+                height,
+                #[cfg(feature = "gpu")]
+                None,
+            )
+        };
+        assert_eq!(
+            from_color(TransitionType::Fade).blend_frames_cpu(&new_frame, 0.5),
+            fade
+        );
+        for transition_type in [TransitionType::WipeTop, TransitionType::Center] {
+            assert_eq!(
+                from_color(transition_type).blend_frames_cpu(&new_frame, 0.3),
+                transition(transition_type).blend_frames_cpu(&new_frame, 0.3)
+            );
+        }
+    }
 }
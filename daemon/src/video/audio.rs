@@ -0,0 +1,110 @@
+//! Audio branch of the video pipeline
+//!
+//! The demuxer's audio stream is only decoded once the video is to be heard:
+//! until then its pad stays unlinked and no audio sink is opened. The branch
+//! is linked when the pad appears if the video already has a volume, or later
+//! when it is first given one, and is then muted rather than removed.
+
+use anyhow::{Context, Result};
+use gstreamer as gst;
+use gstreamer::prelude::*;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// Name of the demuxer in the pipeline description
+pub const DEMUXER: &str = "demux";
+
+/// Name of the volume element in the audio branch
+const VOLUME: &str = "audio_volume";
+
+/// Decoding and playback of the audio stream
+const AUDIO_BRANCH: &str =
+    "queue ! decodebin ! audioconvert ! audioresample ! volume name=audio_volume ! autoaudiosink";
+
+/// Plays a video's audio stream at a volume
+pub struct AudioBranch {
+    pipeline: gst::Pipeline,
+
+    /// Volume (f32 bits), shared with the demuxer's pad-added handler
+    volume: Arc<AtomicU32>,
+}
+
+impl AudioBranch {
+    /// Set up audio for a pipeline that hasn't started yet
+    pub fn new(pipeline: &gst::Pipeline, volume: f32) -> Self {
+        let shared = Arc::new(AtomicU32::new(volume.to_bits()));
+
+        if let Some(demuxer) = pipeline.by_name(DEMUXER) {
+            let volume = Arc::clone(&shared);
+            demuxer.connect_pad_added(move |demuxer, pad| {
+                let volume = f32::from_bits(volume.load(Ordering::Relaxed));
+                if volume > 0.0
+                    && is_audio(pad)
+                    && let Some(pipeline) = demuxer.parent().and_downcast::<gst::Bin>()
+                    && let Err(e) = link(&pipeline, pad, volume)
+                {
+                    log::warn!("Failed to play video audio: {:#}", e);
+                }
+            });
+        }
+
+        Self {
+            pipeline: pipeline.clone(),
+            volume: shared,
+        }
+    }
+
+    /// Play the audio at `volume` (0 mutes it)
+    pub fn set_volume(&self, volume: f32) {
+        if self.volume.swap(volume.to_bits(), Ordering::Relaxed) == volume.to_bits() {
+            return;
+        }
+
+        if let Some(element) = self.pipeline.by_name(VOLUME) {
+            element.set_property("volume", volume as f64);
+            element.set_property("mute", volume == 0.0);
+            return;
+        }
+
+        // Not heard before: link the audio stream the demuxer already exposed
+        let Some(demuxer) = self.pipeline.by_name(DEMUXER) else {
+            return;
+        };
+        if volume > 0.0
+            && let Some(pad) = demuxer
+                .src_pads()
+                .into_iter()
+                .find(|pad| !pad.is_linked() && is_audio(pad))
+            && let Err(e) = link(self.pipeline.upcast_ref(), &pad, volume)
+        {
+            log::warn!("Failed to play video audio: {:#}", e);
+        }
+    }
+}
+
+/// Check if a demuxer pad carries audio
+fn is_audio(pad: &gst::Pad) -> bool {
+    pad.current_caps()
+        .unwrap_or_else(|| pad.query_caps(None))
+        .structure(0)
+        .is_some_and(|structure| structure.name().starts_with("audio/"))
+}
+
+/// Add an audio branch to the pipeline and link the demuxer's audio pad to it
+fn link(pipeline: &gst::Bin, pad: &gst::Pad, volume: f32) -> Result<()> {
+    let branch = gst::parse::bin_from_description(AUDIO_BRANCH, true)
+        .context("Failed to create audio branch")?;
+    if let Some(element) = branch.by_name(VOLUME) {
+        element.set_property("volume", volume as f64);
+    }
+
+    pipeline.add(&branch)?;
+    let sink = branch
+        .static_pad("sink")
+        .context("Audio branch has no sink pad")?;
+    pad.link(&sink)?;
+    branch.sync_state_with_parent()?;
+
+    log::info!("Playing video audio at {:.0}%", volume * 100.0);
+    Ok(())
+}
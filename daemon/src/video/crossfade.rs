@@ -0,0 +1,173 @@
+//! Crossfading the end of a looping video into its start
+//!
+//! Clips that weren't cut to loop jump from their last frame back to their
+//! first. The opening of the clip is kept as it plays the first time; over the
+//! last moments of each pass the end is blended frame by frame with the
+//! opening, and the next pass resumes just after it, so the opening isn't
+//! shown twice.
+
+use super::frames::VideoFrame;
+use std::time::Duration;
+
+/// Most memory the kept opening may take; the crossfade is shortened to fit
+const MAX_OPENING_BYTES: usize = 256 * 1024 * 1024;
+
+/// Crossfades the end of a clip into its opening
+#[derive(Default)]
+pub struct Crossfade {
+    /// Length of the crossfade (zero turns it off)
+    duration: Duration,
+
+    /// Frames of the clip's opening, by position
+    opening: Vec<(Duration, VideoFrame)>,
+
+    /// How much of the clip the opening covers, once it's all kept
+    covered: Option<Duration>,
+}
+
+impl Crossfade {
+    /// Change the length of the crossfade
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
+    /// Keep a frame shown at `position` if it's part of the opening
+    pub fn record(&mut self, frame: &VideoFrame, position: Duration) {
+        if self.duration.is_zero() || self.covered.is_some() {
+            return;
+        }
+        match self.opening.last() {
+            // The same frame, shown again
+            Some((last, _)) if *last == position => return,
+            // A clip shorter than the crossfade looped; all of it is kept
+            Some((last, _)) if *last > position => {
+                self.covered = Some(*last);
+                return;
+            }
+            _ => {}
+        }
+
+        let kept: usize = self.opening.iter().map(|(_, f)| f.data.len()).sum();
+        if position >= self.duration || kept + frame.data.len() > MAX_OPENING_BYTES {
+            if position < self.duration {
+                log::debug!(
+                    "Video crossfade shortened to {:?} to keep its frames in memory",
+                    position
+                );
+            }
+            // Nothing was kept if playback started past the opening
+            self.covered = (!self.opening.is_empty()).then_some(position.min(self.duration));
+            return;
+        }
+
+        self.opening.push((position, frame.clone()));
+    }
+
+    /// Length of the crossfade in a clip `length` long, and where later
+    /// passes start (zero until the opening has been kept)
+    pub fn length(&self, length: Duration) -> Duration {
+        self.covered
+            .map_or(Duration::ZERO, |covered| covered.min(length / 2))
+    }
+
+    /// Blend a frame `position` into a clip `length` long with the opening frame it dissolves into
+    pub fn apply(&self, frame: &mut VideoFrame, position: Duration, length: Duration) {
+        let fade = self.length(length);
+        let t = weight(position, length, fade);
+        if t <= 0.0 {
+            return;
+        }
+
+        // The opening frame as far into the clip as this one is into the crossfade
+        let offset = position.saturating_sub(length - fade);
+        let index = self
+            .opening
+            .partition_point(|(start, _)| *start <= offset)
+            .saturating_sub(1);
+        let Some((_, opening)) = self.opening.get(index) else {
+            return;
+        };
+
+        // A decoder renegotiating mid-clip can change the frame size
+        if (opening.width, opening.height) != (frame.width, frame.height) {
+            return;
+        }
+
+        crate::cpu_render::mix(&mut frame.data, &opening.data, t);
+    }
+}
+
+/// How far through the crossfade a position is
+///
+/// 0 until the last `fade` of the clip, rising to 1 at its end.
+pub fn weight(position: Duration, length: Duration, fade: Duration) -> f32 {
+    if fade.is_zero() {
+        return 0.0;
+    }
+
+    let remaining = length.saturating_sub(position);
+    (1.0 - remaining.as_secs_f32() / fade.as_secs_f32()).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(value: u8) -> VideoFrame {
+        VideoFrame {
+            data: vec![value; 2 * 2 * 4],
+            width: 2,
+            height: 2,
+        }
+    }
+
+    #[test]
+    fn test_weight() {
+        let secs = Duration::from_secs_f32;
+        let (length, fade) = (secs(10.0), secs(1.0));
+
+        assert_eq!(weight(secs(0.0), length, fade), 0.0);
+        assert_eq!(weight(secs(9.0), length, fade), 0.0);
+        assert!((weight(secs(9.5), length, fade) - 0.5).abs() < 1e-4);
+        assert_eq!(weight(secs(10.0), length, fade), 1.0);
+        assert_eq!(weight(secs(12.0), length, fade), 1.0);
+        assert_eq!(weight(secs(9.5), length, Duration::ZERO), 0.0);
+    }
+
+    #[test]
+    fn test_crossfade() {
+        let secs = Duration::from_secs_f32;
+        let length = secs(10.0);
+        let mut crossfade = Crossfade::default();
+        crossfade.set_duration(secs(1.0));
+
+        // Nothing to blend with until the whole opening is kept
+        crossfade.record(&frame(0), secs(0.0));
+        crossfade.record(&frame(100), secs(0.5));
+        let mut last = frame(200);
+        crossfade.apply(&mut last, length, length);
+        assert_eq!(last.data, frame(200).data);
+        assert_eq!(crossfade.length(length), Duration::ZERO);
+
+        crossfade.record(&frame(50), secs(1.0));
+        assert_eq!(crossfade.length(length), secs(1.0));
+        // At most half of a short clip
+        assert_eq!(crossfade.length(secs(1.0)), secs(0.5));
+
+        // Before the crossfade, and halfway through it with the opening frame half a second in
+        let mut end = frame(200);
+        crossfade.apply(&mut end, secs(8.0), length);
+        assert_eq!(end.data, frame(200).data);
+        crossfade.apply(&mut end, secs(9.5), length);
+        assert_eq!(end.data, frame(150).data);
+
+        // Frames of another size are left alone
+        let mut other = VideoFrame {
+            data: vec![200; 4],
+            width: 1,
+            height: 1,
+        };
+        crossfade.apply(&mut other, length, length);
+        assert_eq!(other.data, vec![200; 4]);
+    }
+}
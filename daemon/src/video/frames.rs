@@ -10,10 +10,18 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
+/// A decoded frame (BGRA) and its size
+#[derive(Clone)]
+pub struct VideoFrame {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
 /// Manages video frame data and state
 pub struct FrameHandler {
     /// Current frame data (BGRA from GStreamer)
-    pub(super) current_frame: Arc<Mutex<Option<Vec<u8>>>>,
+    pub(super) current_frame: Arc<Mutex<Option<VideoFrame>>>,
 
     /// Cached rendered frame (for async GPU readback fallback)
     pub(super) cached_frame: Arc<Mutex<Option<Vec<u8>>>>,
@@ -39,7 +47,7 @@ impl FrameHandler {
     }
 
     /// Get clone of current_frame for GStreamer callback
-    pub fn current_frame_handle(&self) -> Arc<Mutex<Option<Vec<u8>>>> {
+    pub fn current_frame_handle(&self) -> Arc<Mutex<Option<VideoFrame>>> {
         Arc::clone(&self.current_frame)
     }
 
@@ -64,11 +72,19 @@ impl FrameHandler {
         self.new_frame_available.store(false, Ordering::Release);
     }
 
-    /// Get current frame data (BGRA format)
-    pub fn current_frame_bgra(&self) -> Option<Vec<u8>> {
+    /// Get current frame (BGRA format)
+    pub fn current_frame(&self) -> Option<VideoFrame> {
         self.current_frame.lock().ok()?.clone()
     }
 
+    /// Replace the current frame (e.g. with a prerolled one) and flag it as new
+    pub fn set_current_frame(&self, frame: VideoFrame) {
+        if let Ok(mut current) = self.current_frame.lock() {
+            *current = Some(frame);
+            self.new_frame_available.store(true, Ordering::Release);
+        }
+    }
+
     /// Get or set cached rendered frame
     pub fn get_cached_frame(&self) -> Option<Vec<u8>> {
         self.cached_frame.lock().ok()?.clone()
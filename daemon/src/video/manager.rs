@@ -3,7 +3,11 @@
 //! This module integrates pipeline setup, frame handling, and statistics
 //! to provide a high-level interface for video wallpaper playback.
 
-use super::{frames::FrameHandler, pipeline, stats::VideoStats};
+use super::{
+    VideoOptions, audio::AudioBranch, crossfade::Crossfade, frames::FrameHandler, pipeline,
+    stats::VideoStats,
+};
+use crate::frame_layout::Placement;
 use anyhow::{Context, Result};
 use gstreamer as gst;
 use gstreamer::prelude::*;
@@ -12,6 +16,12 @@ use std::path::Path;
 use std::sync::atomic::Ordering;
 use std::time::{Duration, Instant};
 
+/// Frames between refreshes of a dominant-colour letterbox
+const DOMINANT_REFRESH_FRAMES: u64 = 30;
+
+/// How long to wait for a video to decode its first frame
+const PREROLL_TIMEOUT: gst::ClockTime = gst::ClockTime::from_seconds(5);
+
 /// Manages video playback for wallpapers
 pub struct VideoManager {
     /// GStreamer pipeline
@@ -51,6 +61,18 @@ pub struct VideoManager {
     /// Loop the video
     should_loop: bool,
 
+    /// Crossfade of the clip's end into its start when looping
+    crossfade: Crossfade,
+
+    /// Audio playback (only decoded while heard)
+    audio: AudioBranch,
+
+    /// How frames are placed when no output asks otherwise
+    placement: Placement,
+
+    /// Dominant colour of a recent frame, and the frame count when it was taken
+    dominant: Option<([u8; 3], u64)>,
+
     /// Optional GPU renderer for hardware-accelerated video display
     #[cfg(feature = "gpu")]
     gpu_renderer: Option<std::sync::Arc<crate::gpu::GpuRenderer>>,
@@ -64,16 +86,14 @@ impl VideoManager {
     /// * `path` - Path to the video file
     /// * `target_width` - Decode width (typically max output resolution)
     /// * `target_height` - Decode height
-    /// * `scale_mode` - Scaling mode (currently unused, GPU handles scaling)
-    /// * `muted` - Whether to mute audio (currently unused)
+    /// * `options` - Scale mode, letterbox and audio volume
     /// * `target_fps` - Target FPS limit from configuration
     /// * `gpu_renderer` - Optional GPU renderer for hardware acceleration
     pub fn load(
         path: impl AsRef<Path>,
         target_width: u32,
         target_height: u32,
-        _scale_mode: common::ScaleMode,
-        _muted: bool,
+        options: VideoOptions,
         target_fps: u32,
         #[cfg(feature = "gpu")] gpu_renderer: Option<std::sync::Arc<crate::gpu::GpuRenderer>>,
     ) -> Result<Self> {
@@ -83,11 +103,14 @@ impl VideoManager {
         let path = path.as_ref();
         log::info!("Loading video: {}", path.display());
 
-        // Build pipeline
-        let (pipeline, app_sink) = pipeline::build_pipeline(path, target_width, target_height)?;
+        // Build pipeline (stretched frames may as well be decoded at the output size)
+        let decode_size =
+            (options.scale == common::ScaleMode::Stretch).then_some((target_width, target_height));
+        let (pipeline, app_sink) = pipeline::build_pipeline(path, decode_size)?;
 
         // Configure appsink
         pipeline::configure_app_sink(&app_sink);
+        let audio = AudioBranch::new(&pipeline, options.volume);
 
         // Create frame handler and stats
         let frames = FrameHandler::new();
@@ -136,12 +159,40 @@ impl VideoManager {
             height: target_height,
             is_playing: false,
             should_loop: true,
+            crossfade: Crossfade::default(),
+            audio,
+            placement: options.placement(),
+            dominant: None,
             target_fps,
             #[cfg(feature = "gpu")]
             gpu_renderer,
         })
     }
 
+    /// Decode the first frame without starting playback
+    ///
+    /// The frame can be rendered straight away, so the video can be shown (or
+    /// transitioned to) before it plays. Looping clips are also set up to play
+    /// as a segment, so they loop without flushing the pipeline.
+    pub fn preroll(&mut self) -> Result<()> {
+        self.pipeline
+            .set_state(gst::State::Paused)
+            .context("Failed to set pipeline to Paused state")?;
+        let (result, ..) = self.pipeline.state(PREROLL_TIMEOUT);
+        result.context("Video did not preroll")?;
+
+        if self.should_loop {
+            self.seek_to_start(gst::SeekFlags::FLUSH)?;
+        }
+
+        let frame = pipeline::pull_preroll(&self.app_sink, PREROLL_TIMEOUT)
+            .context("Video prerolled without a frame")?;
+        log::debug!("Prerolled {}x{} video frame", frame.width, frame.height);
+        self.crossfade.record(&frame, Duration::ZERO);
+        self.frames.set_current_frame(frame);
+        Ok(())
+    }
+
     /// Start video playback
     pub fn play(&mut self) -> Result<()> {
         log::info!("Starting video playback");
@@ -163,12 +214,44 @@ impl VideoManager {
         Ok(())
     }
 
+    /// Seek back to the start of the clip, playing it as a segment
+    ///
+    /// Segment seeks end with a SegmentDone message instead of EOS, and the
+    /// next (non-flushing) one queues the start of the clip behind the end.
+    /// Once the crossfade has shown the opening, loops start after it.
+    fn seek_to_start(&self, flags: gst::SeekFlags) -> Result<()> {
+        let start = self
+            .clip_position()
+            .map_or(Duration::ZERO, |(_, length)| self.crossfade.length(length));
+
+        self.pipeline
+            .seek(
+                1.0,
+                flags | gst::SeekFlags::SEGMENT | gst::SeekFlags::ACCURATE,
+                gst::SeekType::Set,
+                gst::ClockTime::from_nseconds(start.as_nanos() as u64),
+                gst::SeekType::None,
+                gst::ClockTime::NONE,
+            )
+            .context("Failed to seek to the start of the video")
+    }
+
+    /// Playback position and length of the clip
+    fn clip_position(&self) -> Option<(Duration, Duration)> {
+        let position = self.pipeline.query_position::<gst::ClockTime>()?;
+        let length = self.pipeline.query_duration::<gst::ClockTime>()?;
+        Some((
+            Duration::from_nanos(position.nseconds()),
+            Duration::from_nanos(length.nseconds()),
+        ))
+    }
+
     /// Get raw BGRA frame data from GStreamer (for shared VideoManager usage)
     /// Returns decoded video frame at the VideoManager's resolution
     /// Outputs can then scale/convert this to their specific resolutions
     #[allow(dead_code)] // Alternative API for raw frame access
     pub fn current_frame_bgra(&self) -> Option<Vec<u8>> {
-        self.frames.current_frame_bgra()
+        self.frames.current_frame().map(|frame| frame.data)
     }
 
     /// Get the decode resolution (width, height) of this VideoManager
@@ -181,20 +264,32 @@ impl VideoManager {
     /// If GPU rendering is enabled, uses async GPU path with frame caching
     #[allow(dead_code)] // Alternative API, current_frame_data_scaled is the primary method
     pub fn current_frame_data(&mut self) -> Option<Vec<u8>> {
-        self.current_frame_data_scaled(self.width, self.height)
+        self.current_frame_data_scaled(self.width, self.height, self.placement)
     }
 
     /// Get current frame data scaled to target resolution (for multi-resolution support)
-    /// This allows a single VideoManager (decode at max res) to serve multiple outputs at different resolutions
+    /// This allows a single VideoManager (decode at max res) to serve multiple outputs at
+    /// different resolutions, each placing frames its own way
     pub fn current_frame_data_scaled(
         &mut self,
         target_width: u32,
         target_height: u32,
+        placement: Placement,
     ) -> Option<Vec<u8>> {
         #[cfg(feature = "profiling")]
         let render_request_time = Instant::now();
 
-        let bgra_data = self.frames.current_frame_bgra()?;
+        let mut frame = self.frames.current_frame()?;
+        if self.should_loop
+            && let Some((position, length)) = self.clip_position()
+        {
+            self.crossfade.record(&frame, position);
+            self.crossfade.apply(&mut frame, position, length);
+        }
+
+        let source = (frame.width, frame.height);
+        let target = (target_width, target_height);
+        let letterbox = self.letterbox(placement.letterbox, &frame.data);
 
         // Get GStreamer frame delivery time for profiling
         #[cfg(feature = "profiling")]
@@ -214,14 +309,8 @@ impl VideoManager {
                 // Use futures::executor::block_on since we're in a blocking context
                 // Pass source (decode) and target (output) resolutions for GPU scaling
                 let result = futures::executor::block_on(async {
-                    gpu.render_video_frame_bgra(
-                        &bgra_data,
-                        self.width,
-                        self.height,
-                        target_width,
-                        target_height,
-                    )
-                    .await
+                    gpu.render_video_frame_bgra(&frame.data, source, target, placement, letterbox)
+                        .await
                 });
 
                 match result {
@@ -234,15 +323,14 @@ impl VideoManager {
 
                             // Detailed profiling every 60 frames
                             if self.stats.frames_rendered.is_multiple_of(60) {
-                                let scale_info =
-                                    if self.width == target_width && self.height == target_height {
-                                        "no scaling".to_string()
-                                    } else {
-                                        format!(
-                                            "scaled {}x{} -> {}x{}",
-                                            self.width, self.height, target_width, target_height
-                                        )
-                                    };
+                                let scale_info = if source == target {
+                                    "no scaling".to_string()
+                                } else {
+                                    format!(
+                                        "scaled {}x{} -> {}x{}",
+                                        source.0, source.1, target_width, target_height
+                                    )
+                                };
 
                                 log::info!(
                                     "[PROFILE] Frame {}: GStreamer→Render={:.2}ms, GPU={:.2}ms ({}), Total={:.2}ms",
@@ -301,7 +389,45 @@ impl VideoManager {
 
         // CPU path: BGRA from GStreamer is already in correct format for Wayland
         log::trace!("Using CPU video rendering path");
-        Some(bgra_data)
+        Some(crate::frame_layout::scale_frame(
+            &frame.data,
+            source,
+            target,
+            Placement {
+                letterbox,
+                ..placement
+            },
+        ))
+    }
+
+    /// `letterbox` with the dominant colour worked out
+    fn letterbox(&mut self, letterbox: common::Letterbox, frame: &[u8]) -> common::Letterbox {
+        if letterbox != common::Letterbox::Dominant {
+            return letterbox;
+        }
+
+        let rendered = self.stats.frames_rendered;
+        let color = match self.dominant {
+            Some((color, taken)) if rendered.saturating_sub(taken) < DOMINANT_REFRESH_FRAMES => {
+                color
+            }
+            _ => {
+                let color = crate::frame_layout::dominant_color(frame);
+                self.dominant = Some((color, rendered));
+                color
+            }
+        };
+        common::Letterbox::Color(color)
+    }
+
+    /// Play the audio at `volume` (0 mutes it)
+    pub fn set_volume(&self, volume: f32) {
+        self.audio.set_volume(volume);
+    }
+
+    /// Crossfade the end of a looping clip into its start over `duration`
+    pub fn set_crossfade(&mut self, duration: Duration) {
+        self.crossfade.set_duration(duration);
     }
 
     /// Check if a new frame is available and should be displayed
@@ -332,18 +458,26 @@ impl VideoManager {
             );
         }
 
-        // Check for EOS (end of stream) for looping
+        // Check for the end of the clip for looping
         // Drain ALL messages from the bus to prevent memory leak
         if let Some(bus) = self.pipeline.bus() {
             while let Some(msg) = bus.pop() {
                 match msg.view() {
+                    gst::MessageView::SegmentDone(_) => {
+                        // Queue the start behind the end, so playback runs on without a gap
+                        log::debug!("Video segment done, looping...");
+                        if let Err(e) = self.seek_to_start(gst::SeekFlags::empty()) {
+                            log::warn!("{:#}", e);
+                        }
+                    }
+
                     gst::MessageView::Eos(_) => {
                         if self.should_loop {
+                            // Only when segment seeks didn't take (a flushing loop is a hitch)
                             log::debug!("Video reached EOS, looping...");
-                            let _ = self.pipeline.seek_simple(
-                                gst::SeekFlags::FLUSH | gst::SeekFlags::KEY_UNIT,
-                                gst::ClockTime::from_seconds(0),
-                            );
+                            if let Err(e) = self.seek_to_start(gst::SeekFlags::FLUSH) {
+                                log::warn!("{:#}", e);
+                            }
                         } else {
                             log::info!("Video playback finished");
                             self.is_playing = false;
@@ -446,8 +580,7 @@ impl VideoManager {
         self.is_playing
     }
 
-    /// Set whether video should loop
-    #[allow(dead_code)] // Part of public API for loop control
+    /// Set whether video should loop (before prerolling, which sets up the loop)
     pub fn set_loop(&mut self, should_loop: bool) {
         self.should_loop = should_loop;
     }
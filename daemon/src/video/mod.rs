@@ -5,6 +5,8 @@
 //!
 //! - `pipeline`: GStreamer pipeline setup and configuration
 //! - `frames`: Frame data handling and processing
+//! - `crossfade`: Crossfading the end of a looping clip into its start
+//! - `audio`: The audio branch, added once a video is to be heard
 //! - `stats`: Performance statistics and metrics tracking
 //! - `manager`: Main VideoManager that coordinates everything
 //!
@@ -17,6 +19,10 @@
 //! 4. GPU renderer scales BGRA to target resolution(s)
 //! 5. Async GPU readback provides frames to Wayland compositor
 //!
+//! Videos are prerolled (their first frame decoded while paused) before they
+//! play, so there is always a frame to show, and loop with segment seeks,
+//! which queue the start of the clip without flushing the pipeline.
+//!
 //! # Performance
 //!
 //! - Hardware decode: Minimal CPU usage
@@ -24,6 +30,10 @@
 //! - Resolution caching: Each unique resolution rendered once
 //! - Double buffering: No GPU stalls during readback
 
+#[cfg(feature = "video")]
+mod audio;
+#[cfg(feature = "video")]
+mod crossfade;
 #[cfg(feature = "video")]
 mod frames;
 #[cfg(feature = "video")]
@@ -36,6 +46,32 @@ mod stats;
 #[cfg(feature = "video")]
 pub use manager::VideoManager;
 
+use crate::frame_layout::Placement;
+
+/// How a video is decoded, placed on outputs and heard
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(not(feature = "video"), allow(dead_code))] // Only loaded with the video feature
+pub struct VideoOptions {
+    /// Only Stretch decodes at the output size; other modes keep the video's
+    /// own size and aspect
+    pub scale: common::ScaleMode,
+    /// Background around frames that don't cover the output
+    pub letterbox: common::Letterbox,
+    /// Audio volume (0.0 to 1.0); at 0 the audio isn't decoded
+    pub volume: f32,
+}
+
+#[cfg_attr(not(feature = "video"), allow(dead_code))]
+impl VideoOptions {
+    /// Where frames go on an output
+    pub fn placement(&self) -> Placement {
+        Placement {
+            mode: self.scale,
+            letterbox: self.letterbox,
+        }
+    }
+}
+
 // Re-export for backward compatibility during migration
 #[cfg(not(feature = "video"))]
 pub use manager_stub::VideoManager;
@@ -54,12 +90,14 @@ mod manager_stub {
             _path: impl AsRef<Path>,
             _target_width: u32,
             _target_height: u32,
-            _scale_mode: common::ScaleMode,
-            _muted: bool,
+            _options: super::VideoOptions,
             _target_fps: u32,
             #[cfg(feature = "gpu")] _gpu_renderer: Option<std::sync::Arc<crate::gpu::GpuRenderer>>,
         ) -> Result<Self> {
-            anyhow::bail!("Video support not compiled in")
+            Err(common::WallpaperError::FeatureDisabled {
+                feature: "video".to_string(),
+            }
+            .into())
         }
 
         pub fn play(&mut self) -> Result<()> {
@@ -70,6 +108,10 @@ mod manager_stub {
             Ok(())
         }
 
+        pub fn preroll(&mut self) -> Result<()> {
+            Ok(())
+        }
+
         pub fn current_frame_data(&self) -> Option<Vec<u8>> {
             None
         }
@@ -88,6 +130,10 @@ mod manager_stub {
 
         pub fn set_loop(&mut self, _should_loop: bool) {}
 
+        pub fn set_crossfade(&mut self, _duration: Duration) {}
+
+        pub fn set_volume(&self, _volume: f32) {}
+
         pub fn frame_duration(&self) -> Duration {
             Duration::from_millis(16)
         }
@@ -3,10 +3,14 @@
 //! This module handles the creation and configuration of GStreamer pipelines
 //! for hardware-accelerated video decoding.
 
-use anyhow::{Context, Result};
+use super::frames::VideoFrame;
+use anyhow::Result;
+use common::WallpaperError;
 use gstreamer as gst;
 use gstreamer::prelude::*;
 use gstreamer_app as gst_app;
+use gstreamer_video as gst_video;
+use gstreamer_video::prelude::*;
 use std::path::Path;
 use std::sync::atomic::AtomicU64;
 use std::sync::{Arc, Mutex};
@@ -28,48 +32,74 @@ pub fn initialize_gstreamer() {
 /// - `vah264dec`: Hardware H.264 decoder (outputs NV12)
 /// - `vapostproc`: Hardware scaling + color conversion to BGRA
 ///
+/// The demuxer's audio stream is left for [`super::audio::AudioBranch`].
+///
 /// # Arguments
 ///
 /// * `path` - Path to the video file
-/// * `target_width` - Target width for decoded frames
-/// * `target_height` - Target height for decoded frames
+/// * `size` - Size to decode frames at, or None for the video's own size
+///   (with square pixels, so frames keep their aspect ratio for scaling)
 ///
 /// # Returns
 ///
 /// Tuple of (pipeline, app_sink) where app_sink can receive decoded frames
 pub fn build_pipeline(
     path: impl AsRef<Path>,
-    target_width: u32,
-    target_height: u32,
+    size: Option<(u32, u32)>,
 ) -> Result<(gst::Pipeline, gst_app::AppSink)> {
     let path = path.as_ref();
     log::info!("Creating GStreamer pipeline for: {}", path.display());
 
+    let caps = match size {
+        Some((width, height)) => format!("width={},height={}", width, height),
+        None => "pixel-aspect-ratio=1/1".to_string(),
+    };
+
     // Hardware-accelerated pipeline
     let pipeline_str = format!(
-        "filesrc location={} ! qtdemux ! h264parse ! vah264dec ! vapostproc ! video/x-raw,format=BGRA,width={},height={} ! appsink name=sink",
+        "filesrc location={} ! qtdemux name={} ! h264parse ! vah264dec ! vapostproc ! video/x-raw,format=BGRA,{} ! appsink name=sink",
         path.display(),
-        target_width,
-        target_height
+        super::audio::DEMUXER,
+        caps
     );
 
     log::debug!("GStreamer pipeline: {}", pipeline_str);
 
     let pipeline = gst::parse::launch(&pipeline_str)
-        .context("Failed to create GStreamer pipeline")?
+        .map_err(|e| WallpaperError::Gstreamer {
+            element: failed_element(&e),
+            message: e.to_string(),
+        })?
         .dynamic_cast::<gst::Pipeline>()
         .map_err(|_| anyhow::anyhow!("Pipeline is not a gst::Pipeline"))?;
 
     // Get the appsink element
     let app_sink = pipeline
         .by_name("sink")
-        .context("Failed to get appsink from pipeline")?
+        .ok_or_else(|| WallpaperError::Gstreamer {
+            element: "appsink".to_string(),
+            message: "not found in pipeline".to_string(),
+        })?
         .dynamic_cast::<gst_app::AppSink>()
         .map_err(|_| anyhow::anyhow!("sink is not an AppSink"))?;
 
     Ok((pipeline, app_sink))
 }
 
+/// Get the element a pipeline description failed on
+///
+/// GStreamer reports missing plugins as `no element "vah264dec"`; other parse
+/// errors are attributed to the pipeline as a whole.
+fn failed_element(error: &gst::glib::Error) -> String {
+    if error.kind::<gst::ParseError>() == Some(gst::ParseError::NoSuchElement)
+        && let Some(name) = error.message().split('"').nth(1)
+    {
+        return name.to_string();
+    }
+
+    "pipeline".to_string()
+}
+
 /// Configure AppSink for low-latency video delivery
 ///
 /// Settings optimized for wallpaper video playback:
@@ -88,7 +118,7 @@ pub fn configure_app_sink(app_sink: &gst_app::AppSink) {
 /// Callback receives frames from GStreamer and stores them for rendering
 pub fn setup_frame_callback(
     app_sink: &gst_app::AppSink,
-    current_frame: Arc<Mutex<Option<Vec<u8>>>>,
+    current_frame: Arc<Mutex<Option<VideoFrame>>>,
     new_frame_flag: Arc<std::sync::atomic::AtomicBool>,
     frames_dropped: Arc<AtomicU64>,
     #[cfg(feature = "profiling")] gstreamer_frame_time: Arc<Mutex<Option<Instant>>>,
@@ -100,14 +130,7 @@ pub fn setup_frame_callback(
                 let frame_arrival = Instant::now();
 
                 let sample = sink.pull_sample().map_err(|_| gst::FlowError::Eos)?;
-                let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
-
-                // Map buffer to read pixel data
-                let map = buffer.map_readable().map_err(|_| gst::FlowError::Error)?;
-                let data = map.as_slice();
-
-                // Convert BGRA to ARGB8888 (just copy on little-endian)
-                let argb_data = data.to_vec();
+                let video_frame = sample_frame(&sample)?;
 
                 // Store the frame
                 if let Ok(mut frame) = current_frame.lock() {
@@ -117,7 +140,7 @@ pub fn setup_frame_callback(
                         log::trace!("Video frame dropped (previous frame not consumed in time)");
                     }
 
-                    *frame = Some(argb_data);
+                    *frame = Some(video_frame);
 
                     // Set flag to indicate new frame is available
                     new_frame_flag.store(true, std::sync::atomic::Ordering::Release);
@@ -137,6 +160,46 @@ pub fn setup_frame_callback(
     );
 }
 
+/// Copy the frame out of a sample from the AppSink
+fn sample_frame(sample: &gst::Sample) -> Result<VideoFrame, gst::FlowError> {
+    let buffer = sample.buffer().ok_or(gst::FlowError::Error)?;
+
+    // Frames are the video's own size unless a size was asked for
+    let info = sample
+        .caps()
+        .and_then(|caps| gst_video::VideoInfo::from_caps(caps).ok())
+        .ok_or(gst::FlowError::NotNegotiated)?;
+
+    // Rows can be padded past the width, so copy them one at a time
+    let frame = gst_video::VideoFrameRef::from_buffer_ref_readable(buffer, &info)
+        .map_err(|_| gst::FlowError::Error)?;
+    let stride = frame.plane_stride()[0] as usize;
+    let plane = frame.plane_data(0).map_err(|_| gst::FlowError::Error)?;
+    let row_bytes = frame.width() as usize * 4;
+    let height = frame.height() as usize;
+    if stride < row_bytes || plane.len() < stride * height.saturating_sub(1) + row_bytes {
+        return Err(gst::FlowError::Error);
+    }
+
+    // Convert BGRA to ARGB8888 (just copy on little-endian)
+    let mut data = Vec::with_capacity(row_bytes * height);
+    for row in plane.chunks(stride).take(height) {
+        data.extend_from_slice(&row[..row_bytes]);
+    }
+
+    Ok(VideoFrame {
+        data,
+        width: frame.width(),
+        height: frame.height(),
+    })
+}
+
+/// Get the frame a paused pipeline prerolled, waiting up to `timeout` for it
+pub fn pull_preroll(app_sink: &gst_app::AppSink, timeout: gst::ClockTime) -> Option<VideoFrame> {
+    let sample = app_sink.try_pull_preroll(timeout)?;
+    sample_frame(&sample).ok()
+}
+
 /// Detect video FPS from pipeline
 pub fn detect_fps(pipeline: &gst::Pipeline) -> Option<f64> {
     // Try to get FPS from the pipeline
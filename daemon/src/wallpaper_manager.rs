@@ -1,23 +1,28 @@
+use crate::image_cache::{FrameSize, ImageCache, SourceKey};
 use anyhow::{Context, Result};
 use image::{DynamicImage, ImageBuffer, Rgba};
 use std::path::Path;
+use std::sync::Arc;
 
 /// Manages wallpaper loading, caching, and processing
 pub struct WallpaperManager {
-    cache: std::collections::HashMap<String, CachedImage>,
-}
-
-/// A cached image with metadata
-#[allow(dead_code)] // Field 'path' kept for future cache management features
-struct CachedImage {
-    image: DynamicImage,
-    path: String,
+    /// Decoded and pre-scaled images
+    cache: ImageCache,
+    /// System fonts for SVG text (loaded on first SVG render)
+    fontdb: Option<std::sync::Arc<resvg::usvg::fontdb::Database>>,
 }
 
 impl WallpaperManager {
+    /// Create a manager with an unbounded image cache
     pub fn new() -> Self {
+        Self::with_cache_limit(None)
+    }
+
+    /// Create a manager whose image cache holds at most `limit` bytes
+    pub fn with_cache_limit(limit: Option<usize>) -> Self {
         Self {
-            cache: std::collections::HashMap::new(),
+            cache: ImageCache::new(limit),
+            fontdb: None,
         }
     }
 
@@ -49,51 +54,137 @@ impl WallpaperManager {
         }
     }
 
+    /// Check if a file is an SVG (rendered at output size instead of decoded)
+    pub fn is_svg(path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+
+        if let Some(ext) = path.extension() {
+            let ext_lower = ext.to_string_lossy().to_lowercase();
+            matches!(ext_lower.as_str(), "svg" | "svgz")
+        } else {
+            false
+        }
+    }
+
+    /// Render an SVG file at the target size (see [`crate::svg::render`])
+    pub fn render_svg(
+        &mut self,
+        path: impl AsRef<Path>,
+        target_width: u32,
+        target_height: u32,
+        mode: common::ScaleMode,
+        letterbox: common::Letterbox,
+        colors: &std::collections::BTreeMap<String, String>,
+    ) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
+        let fontdb = self
+            .fontdb
+            .get_or_insert_with(|| {
+                let mut fontdb = resvg::usvg::fontdb::Database::new();
+                fontdb.load_system_fonts();
+                log::debug!("Loaded {} font faces for SVG text", fontdb.len());
+                std::sync::Arc::new(fontdb)
+            })
+            .clone();
+
+        crate::svg::render(
+            path.as_ref(),
+            target_width,
+            target_height,
+            mode,
+            letterbox,
+            colors,
+            fontdb,
+        )
+    }
+
     /// Load an image from a file path
-    pub fn load_image(&mut self, path: impl AsRef<Path>) -> Result<&DynamicImage> {
+    pub fn load_image(&mut self, path: impl AsRef<Path>) -> Result<Arc<DynamicImage>> {
         let path = path.as_ref();
-        let path_str = path.to_string_lossy().to_string();
+        let key = SourceKey::for_path(path)
+            .with_context(|| format!("Failed to load image: {}", path.display()))?;
 
         // Check cache first
-        if !self.cache.contains_key(&path_str) {
-            log::info!("Loading image: {}", path.display());
-
-            let image =
-                image::open(path).context(format!("Failed to load image: {}", path.display()))?;
-
-            log::info!(
-                "Loaded image: {}x{} ({})",
-                image.width(),
-                image.height(),
-                path.display()
-            );
-
-            self.cache.insert(
-                path_str.clone(),
-                CachedImage {
-                    image,
-                    path: path_str.clone(),
-                },
-            );
+        if let Some(image) = self.cache.image(&key) {
+            return Ok(image);
         }
 
-        Ok(&self.cache.get(&path_str).unwrap().image)
+        let image = Arc::new(Self::decode_image(path)?);
+        self.cache.insert_image(key, image.clone());
+        Ok(image)
+    }
+
+    /// Decode an image file without caching it (used by the background loader)
+    ///
+    /// The image is turned upright by its EXIF orientation and converted from
+    /// its embedded colour profile to sRGB.
+    pub fn decode_image(path: &Path) -> Result<DynamicImage> {
+        log::info!("Loading image: {}", path.display());
+
+        let image = crate::color_profile::decode(path)
+            .context(format!("Failed to load image: {}", path.display()))?;
+
+        log::info!(
+            "Loaded image: {}x{} ({})",
+            image.width(),
+            image.height(),
+            path.display()
+        );
+
+        Ok(image)
+    }
+
+    /// Get an image already scaled to an output's size (ARGB8888)
+    pub fn scaled_frame(
+        &mut self,
+        path: impl AsRef<Path>,
+        size: FrameSize,
+    ) -> Option<Arc<Vec<u8>>> {
+        let key = SourceKey::for_path(path.as_ref()).ok()?;
+        self.cache.frame(&key, size)
+    }
+
+    /// Keep an image scaled to an output's size (ARGB8888) for reuse
+    pub fn store_scaled_frame(
+        &mut self,
+        path: impl AsRef<Path>,
+        size: FrameSize,
+        frame: Arc<Vec<u8>>,
+    ) {
+        if let Ok(key) = SourceKey::for_path(path.as_ref()) {
+            self.cache.insert_frame(key, size, frame);
+        }
     }
 
-    /// Scale/fit an image to the target dimensions
+    /// Scale/fit an image to a frame's dimensions
+    ///
+    /// Fill crops around the frame's focal point, or the one detected in the
+    /// image if None. Fit and Center pad with the frame's letterbox background.
+    /// HDR images are tone mapped first, and high-bit-depth ones are dithered
+    /// down to 8 bits. The frame stays in sRGB; callers convert it to the
+    /// size's display profile.
     pub fn scale_image(
         &self,
         image: &DynamicImage,
-        target_width: u32,
-        target_height: u32,
-        mode: common::ScaleMode,
+        size: FrameSize,
     ) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
-        // let (img_width, img_height) = (image.width(), image.height());
+        let FrameSize {
+            width: target_width,
+            height: target_height,
+            mode,
+            focus,
+            letterbox,
+            tone_mapping,
+            ten_bit: _,
+            profile: _,
+        } = size;
+        let image = &*crate::hdr::display_referred(image, tone_mapping);
 
         match mode {
-            common::ScaleMode::Center => self.center_image(image, target_width, target_height),
-            common::ScaleMode::Fill => self.fill_image(image, target_width, target_height),
-            common::ScaleMode::Fit => self.fit_image(image, target_width, target_height),
+            common::ScaleMode::Center => {
+                self.center_image(image, target_width, target_height, letterbox)
+            }
+            common::ScaleMode::Fill => self.fill_image(image, target_width, target_height, focus),
+            common::ScaleMode::Fit => self.fit_image(image, target_width, target_height, letterbox),
             common::ScaleMode::Stretch => self.stretch_image(image, target_width, target_height),
             common::ScaleMode::Tile => self.tile_image(image, target_width, target_height),
         }
@@ -105,49 +196,40 @@ impl WallpaperManager {
         image: &DynamicImage,
         target_width: u32,
         target_height: u32,
+        letterbox: common::Letterbox,
     ) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
-        let mut output = ImageBuffer::from_pixel(target_width, target_height, Rgba([0, 0, 0, 255]));
-
-        let rgba_image = image.to_rgba8();
+        let rgba_image = crate::hdr::to_rgba8(image);
         let (img_width, img_height) = rgba_image.dimensions();
 
         let x_offset = (target_width.saturating_sub(img_width)) / 2;
         let y_offset = (target_height.saturating_sub(img_height)) / 2;
 
-        image::imageops::overlay(&mut output, &rgba_image, x_offset as i64, y_offset as i64);
-
-        Ok(output)
+        Ok(crate::letterbox::compose(
+            image,
+            &rgba_image,
+            (x_offset, y_offset),
+            target_width,
+            target_height,
+            letterbox,
+        ))
     }
 
-    /// Scale to fill entire output (may crop)
+    /// Scale to fill entire output (may crop around the focal point)
     fn fill_image(
         &self,
         image: &DynamicImage,
         target_width: u32,
         target_height: u32,
+        focus: Option<common::FocalPoint>,
     ) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
-        let (img_width, img_height) = (image.width(), image.height());
-        let target_ratio = target_width as f32 / target_height as f32;
-        let img_ratio = img_width as f32 / img_height as f32;
-
-        let (scale_width, scale_height) = if target_ratio > img_ratio {
-            // Target is wider, scale to width
-            let scale = target_width as f32 / img_width as f32;
-            (target_width, (img_height as f32 * scale) as u32)
-        } else {
-            // Target is taller, scale to height
-            let scale = target_height as f32 / img_height as f32;
-            ((img_width as f32 * scale) as u32, target_height)
-        };
+        let ((scale_width, scale_height), (x_offset, y_offset)) =
+            fill_layout(image, target_width, target_height, focus);
 
         // Resize image
         let resized = self.resize_image_fast(image, scale_width, scale_height)?;
 
         // Crop to target size if needed
         if scale_width != target_width || scale_height != target_height {
-            let x_offset = (scale_width.saturating_sub(target_width)) / 2;
-            let y_offset = (scale_height.saturating_sub(target_height)) / 2;
-
             Ok(
                 image::imageops::crop_imm(
                     &resized,
@@ -169,56 +251,32 @@ impl WallpaperManager {
         image: &DynamicImage,
         target_width: u32,
         target_height: u32,
+        letterbox: common::Letterbox,
     ) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
-        let (img_width, img_height) = (image.width(), image.height());
-        let target_ratio = target_width as f32 / target_height as f32;
-        let img_ratio = img_width as f32 / img_height as f32;
-
-        log::debug!(
-            "Fit mode: image {}x{} (ratio {:.2}), target {}x{} (ratio {:.2})",
-            img_width,
-            img_height,
-            img_ratio,
-            target_width,
-            target_height,
-            target_ratio
-        );
-
-        let (scale_width, scale_height) = if target_ratio > img_ratio {
-            // Target is wider than image, scale to height
-            let scale = target_height as f32 / img_height as f32;
-            let scaled = ((img_width as f32 * scale) as u32, target_height);
-            log::debug!(
-                "Target wider: scaling to height, result: {}x{}",
-                scaled.0,
-                scaled.1
-            );
-            scaled
-        } else {
-            // Target is taller than image (or same), scale to width
-            let scale = target_width as f32 / img_width as f32;
-            let scaled = (target_width, (img_height as f32 * scale) as u32);
-            log::debug!(
-                "Target taller: scaling to width, result: {}x{}",
-                scaled.0,
-                scaled.1
-            );
-            scaled
-        };
+        let (scale_width, scale_height) = fit_size(image, target_width, target_height);
 
         // Resize image
         let resized = self.resize_image_fast(image, scale_width, scale_height)?;
 
-        // Center on black background
-        let mut output = ImageBuffer::from_pixel(target_width, target_height, Rgba([0, 0, 0, 255]));
+        // Center on the letterbox background
         let x_offset = (target_width.saturating_sub(scale_width)) / 2;
         let y_offset = (target_height.saturating_sub(scale_height)) / 2;
 
-        log::debug!("Centering at offset ({}, {})", x_offset, y_offset);
-
-        image::imageops::overlay(&mut output, &resized, x_offset as i64, y_offset as i64);
+        log::debug!(
+            "Centering at offset ({}, {}) on {:?}",
+            x_offset,
+            y_offset,
+            letterbox
+        );
 
-        Ok(output)
+        Ok(crate::letterbox::compose(
+            image,
+            &resized,
+            (x_offset, y_offset),
+            target_width,
+            target_height,
+            letterbox,
+        ))
     }
 
     /// Stretch to fill output
@@ -239,7 +297,7 @@ impl WallpaperManager {
         target_height: u32,
     ) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
         let mut output = ImageBuffer::from_pixel(target_width, target_height, Rgba([0, 0, 0, 255]));
-        let rgba_image = image.to_rgba8();
+        let rgba_image = crate::hdr::to_rgba8(image);
         let (img_width, img_height) = rgba_image.dimensions();
 
         let tiles_x = target_width.div_ceil(img_width);
@@ -263,41 +321,117 @@ impl WallpaperManager {
         target_width: u32,
         target_height: u32,
     ) -> Result<ImageBuffer<Rgba<u8>, Vec<u8>>> {
-        use fast_image_resize as fr;
+        // Scale high-bit-depth images at full precision, then dither down
+        if crate::hdr::is_high_bit_depth(image) {
+            let resized =
+                self.resize_image_deep(&image.to_rgba16(), target_width, target_height)?;
+            return Ok(crate::hdr::dither(&resized));
+        }
 
         let src_image = image.to_rgba8();
-        let (src_width, src_height) = src_image.dimensions();
-
-        // Create source image for fast_image_resize
-        let src = fr::images::Image::from_vec_u8(
-            TryInto::try_into(src_width)?,
-            TryInto::try_into(src_height)?,
+        let dimensions = src_image.dimensions();
+        let resized = resize_pixels(
             src_image.into_raw(),
-            fr::PixelType::U8x4,
-        )
-        .context("Failed to create source image")?;
+            dimensions,
+            (target_width, target_height),
+            fast_image_resize::PixelType::U8x4,
+        )?;
 
-        // Create destination image
-        let mut dst = fr::images::Image::new(
-            TryInto::try_into(target_width)?,
-            TryInto::try_into(target_height)?,
-            fr::PixelType::U8x4,
-        );
+        // Convert back to ImageBuffer
+        ImageBuffer::from_raw(target_width, target_height, resized)
+            .context("Failed to create output image buffer")
+    }
+
+    /// Resize a 16-bit image with fast_image_resize
+    fn resize_image_deep(
+        &self,
+        image: &crate::hdr::Rgba16Image,
+        target_width: u32,
+        target_height: u32,
+    ) -> Result<crate::hdr::Rgba16Image> {
+        let bytes = image
+            .as_raw()
+            .iter()
+            .flat_map(|v| v.to_ne_bytes())
+            .collect();
+        let resized = resize_pixels(
+            bytes,
+            image.dimensions(),
+            (target_width, target_height),
+            fast_image_resize::PixelType::U16x4,
+        )?;
+
+        let samples = resized
+            .chunks_exact(2)
+            .map(|v| u16::from_ne_bytes([v[0], v[1]]))
+            .collect();
+        ImageBuffer::from_raw(target_width, target_height, samples)
+            .context("Failed to create output image buffer")
+    }
+
+    /// Scale a high-bit-depth image to a frame's dimensions as XRGB2101010
+    ///
+    /// `frame` is the same frame from [`Self::scale_image`]: the image is placed
+    /// exactly like on it, and the letterbox around it keeps its 8 bits. Returns
+    /// None for 8-bit images, which gain nothing from a 10-bit frame.
+    pub fn scale_image_deep(
+        &self,
+        image: &DynamicImage,
+        size: FrameSize,
+        frame: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    ) -> Result<Option<Vec<u8>>> {
+        let image = crate::hdr::display_referred(image, size.tone_mapping);
+        if !crate::hdr::is_high_bit_depth(&image) {
+            return Ok(None);
+        }
 
-        // Resize
-        let mut resizer = fr::Resizer::new();
-        resizer
-            .resize(
-                &src,
-                &mut dst,
-                &fr::ResizeOptions::new()
-                    .resize_alg(fr::ResizeAlg::Convolution(fr::FilterType::Lanczos3)),
+        let (target_width, target_height) = (size.width, size.height);
+        let content = image.to_rgba16();
+        let centered = |width: u32, height: u32| {
+            (
+                (target_width.saturating_sub(width) / 2) as i64,
+                (target_height.saturating_sub(height) / 2) as i64,
             )
-            .context("Failed to resize image")?;
+        };
 
-        // Convert back to ImageBuffer
-        ImageBuffer::from_raw(target_width, target_height, dst.into_vec())
-            .context("Failed to create output image buffer")
+        let deep = match size.mode {
+            common::ScaleMode::Stretch => {
+                self.resize_image_deep(&content, target_width, target_height)?
+            }
+            common::ScaleMode::Fill => {
+                let ((scale_width, scale_height), (x_offset, y_offset)) =
+                    fill_layout(&image, target_width, target_height, size.focus);
+                let resized = self.resize_image_deep(&content, scale_width, scale_height)?;
+                image::imageops::crop_imm(&resized, x_offset, y_offset, target_width, target_height)
+                    .to_image()
+            }
+            common::ScaleMode::Fit => {
+                let (scale_width, scale_height) = fit_size(&image, target_width, target_height);
+                let resized = self.resize_image_deep(&content, scale_width, scale_height)?;
+                let (x, y) = centered(scale_width, scale_height);
+                let mut deep = crate::hdr::widen(frame);
+                image::imageops::overlay(&mut deep, &resized, x, y);
+                deep
+            }
+            common::ScaleMode::Center => {
+                let (x, y) = centered(content.width(), content.height());
+                let mut deep = crate::hdr::widen(frame);
+                image::imageops::overlay(&mut deep, &content, x, y);
+                deep
+            }
+            common::ScaleMode::Tile => {
+                let mut deep =
+                    ImageBuffer::from_pixel(target_width, target_height, Rgba([0, 0, 0, u16::MAX]));
+                for y in (0..target_height).step_by(content.height() as usize) {
+                    for x in (0..target_width).step_by(content.width() as usize) {
+                        image::imageops::overlay(&mut deep, &content, x as i64, y as i64);
+                    }
+                }
+                deep
+            }
+        };
+
+        Ok(Some(crate::hdr::pack_xrgb2101010(&deep)))
     }
 
     /// Convert RGBA image to ARGB8888 format for Wayland
@@ -318,19 +452,135 @@ impl WallpaperManager {
     }
 
     /// Clear the image cache
-    #[allow(dead_code)] // Part of public API for cache management
     pub fn clear_cache(&mut self) {
-        log::info!("Clearing image cache ({} entries)", self.cache.len());
         self.cache.clear();
     }
 
-    /// Get cache size
-    #[allow(dead_code)] // Part of public API for cache introspection
-    pub fn cache_size(&self) -> usize {
-        self.cache.len()
+    /// Get the image cache, e.g. to change its limits
+    pub fn cache_mut(&mut self) -> &mut ImageCache {
+        &mut self.cache
+    }
+
+    /// Get the image cache contents and memory use
+    pub fn cache_status(&self) -> common::CacheStatus {
+        self.cache.status()
     }
 }
 
+/// Size to scale an image to so it covers a target, and where to crop it
+///
+/// Crops around `focus`, or the focal point detected in the image if None.
+fn fill_layout(
+    image: &DynamicImage,
+    target_width: u32,
+    target_height: u32,
+    focus: Option<common::FocalPoint>,
+) -> ((u32, u32), (u32, u32)) {
+    let (img_width, img_height) = (image.width(), image.height());
+    let target_ratio = target_width as f32 / target_height as f32;
+    let img_ratio = img_width as f32 / img_height as f32;
+
+    let (scale_width, scale_height) = if target_ratio > img_ratio {
+        // Target is wider, scale to width
+        let scale = target_width as f32 / img_width as f32;
+        (target_width, (img_height as f32 * scale) as u32)
+    } else {
+        // Target is taller, scale to height
+        let scale = target_height as f32 / img_height as f32;
+        ((img_width as f32 * scale) as u32, target_height)
+    };
+
+    if scale_width == target_width && scale_height == target_height {
+        return ((scale_width, scale_height), (0, 0));
+    }
+
+    let focus = focus.unwrap_or_else(|| {
+        let detected = crate::focal_point::detect(image);
+        log::debug!(
+            "Detected focal point ({:.2}, {:.2})",
+            detected.x,
+            detected.y
+        );
+        detected
+    });
+    let offset = crate::focal_point::crop_offset(
+        (scale_width, scale_height),
+        (target_width, target_height),
+        focus,
+    );
+
+    ((scale_width, scale_height), offset)
+}
+
+/// Size to scale an image to so it fits within a target
+fn fit_size(image: &DynamicImage, target_width: u32, target_height: u32) -> (u32, u32) {
+    let (img_width, img_height) = (image.width(), image.height());
+    let target_ratio = target_width as f32 / target_height as f32;
+    let img_ratio = img_width as f32 / img_height as f32;
+
+    log::debug!(
+        "Fit mode: image {}x{} (ratio {:.2}), target {}x{} (ratio {:.2})",
+        img_width,
+        img_height,
+        img_ratio,
+        target_width,
+        target_height,
+        target_ratio
+    );
+
+    if target_ratio > img_ratio {
+        // Target is wider than image, scale to height
+        let scale = target_height as f32 / img_height as f32;
+        let scaled = ((img_width as f32 * scale) as u32, target_height);
+        log::debug!(
+            "Target wider: scaling to height, result: {}x{}",
+            scaled.0,
+            scaled.1
+        );
+        scaled
+    } else {
+        // Target is taller than image (or same), scale to width
+        let scale = target_width as f32 / img_width as f32;
+        let scaled = (target_width, (img_height as f32 * scale) as u32);
+        log::debug!(
+            "Target taller: scaling to width, result: {}x{}",
+            scaled.0,
+            scaled.1
+        );
+        scaled
+    }
+}
+
+/// Resize raw pixels of the given type with fast_image_resize
+fn resize_pixels(
+    pixels: Vec<u8>,
+    (src_width, src_height): (u32, u32),
+    (target_width, target_height): (u32, u32),
+    pixel_type: fast_image_resize::PixelType,
+) -> Result<Vec<u8>> {
+    use fast_image_resize as fr;
+
+    // Create source image for fast_image_resize
+    let src = fr::images::Image::from_vec_u8(src_width, src_height, pixels, pixel_type)
+        .context("Failed to create source image")?;
+
+    // Create destination image
+    let mut dst = fr::images::Image::new(target_width, target_height, pixel_type);
+
+    // Resize
+    let mut resizer = fr::Resizer::new();
+    resizer
+        .resize(
+            &src,
+            &mut dst,
+            &fr::ResizeOptions::new()
+                .resize_alg(fr::ResizeAlg::Convolution(fr::FilterType::Lanczos3)),
+        )
+        .context("Failed to resize image")?;
+
+    Ok(dst.into_vec())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -354,4 +604,114 @@ mod tests {
         assert_eq!(argb[2], 255); // R
         assert_eq!(argb[3], 255); // A
     }
+
+    #[test]
+    fn test_fit_pads_with_letterbox() {
+        let manager = WallpaperManager::new();
+        let image = DynamicImage::ImageRgba8(ImageBuffer::from_pixel(4, 2, Rgba([200, 0, 0, 255])));
+        let letterbox = common::Letterbox::Color([0, 0, 90]);
+
+        let fit = FrameSize::new(
+            8,
+            8,
+            common::ScaleMode::Fit,
+            None,
+            letterbox,
+            Default::default(),
+        );
+        let scaled = manager.scale_image(&image, fit).unwrap();
+        assert_eq!(scaled.dimensions(), (8, 8));
+        assert_eq!(scaled.get_pixel(4, 0), &Rgba([0, 0, 90, 255]));
+        assert_eq!(scaled.get_pixel(4, 4), &Rgba([200, 0, 0, 255]));
+
+        let center = FrameSize::new(
+            6,
+            4,
+            common::ScaleMode::Center,
+            None,
+            letterbox,
+            Default::default(),
+        );
+        let scaled = manager.scale_image(&image, center).unwrap();
+        assert_eq!(scaled.get_pixel(0, 0), &Rgba([0, 0, 90, 255]));
+        assert_eq!(scaled.get_pixel(1, 1), &Rgba([200, 0, 0, 255]));
+
+        // Fill covers the output, so the letterbox isn't part of its key
+        let fill = FrameSize::new(
+            8,
+            8,
+            common::ScaleMode::Fill,
+            None,
+            letterbox,
+            Default::default(),
+        );
+        assert_eq!(fill.letterbox, common::Letterbox::default());
+    }
+
+    #[test]
+    fn test_high_bit_depth_frames() {
+        let manager = WallpaperManager::new();
+        let size = |mode| {
+            FrameSize::new(
+                8,
+                4,
+                mode,
+                None,
+                common::Letterbox::Color([0, 0, 90]),
+                crate::hdr::ToneMapping::default(),
+            )
+        };
+
+        // A 16-bit grey between two 8-bit levels is dithered, not rounded
+        let grey = DynamicImage::ImageRgba16(ImageBuffer::from_pixel(
+            8,
+            8,
+            Rgba([2634, 2634, 2634, 65535]),
+        ));
+        let dithered = manager
+            .scale_image(
+                &grey,
+                FrameSize {
+                    height: 8,
+                    ..size(common::ScaleMode::Stretch)
+                },
+            )
+            .unwrap();
+        let sum: u32 = dithered.pixels().map(|p| p[0] as u32).sum();
+        assert!((sum as f32 / 64.0 - 10.25).abs() < 0.1, "{}", sum);
+
+        let scaled = manager
+            .scale_image(&grey, size(common::ScaleMode::Fit))
+            .unwrap();
+        assert_eq!(scaled.get_pixel(0, 0), &Rgba([0, 0, 90, 255]));
+
+        // The 10-bit frame keeps the grey's precision and the letterbox around it
+        let deep = manager
+            .scale_image_deep(&grey, size(common::ScaleMode::Fit), &scaled)
+            .unwrap()
+            .unwrap();
+        let pixel = |x: usize| u32::from_le_bytes(deep[x * 4..x * 4 + 4].try_into().unwrap());
+        assert_eq!(pixel(8 * 2 + 4) & 0x3ff, 41);
+        assert_eq!(pixel(0) & 0x3ff, (90 * 1023 + 127) / 255);
+        assert_eq!(deep.len(), 8 * 4 * 4);
+
+        // HDR images are tone mapped, 8-bit ones get no 10-bit frame
+        let hdr = DynamicImage::ImageRgb32F(ImageBuffer::from_pixel(4, 4, image::Rgb([4.0; 3])));
+        let scaled = manager
+            .scale_image(&hdr, size(common::ScaleMode::Fill))
+            .unwrap();
+        let value = scaled.get_pixel(3, 2)[0];
+        assert!(value > 200 && value < 255, "{}", value);
+
+        let plain = DynamicImage::ImageRgba8(ImageBuffer::from_pixel(4, 4, Rgba([1, 2, 3, 255])));
+        let scaled = manager
+            .scale_image(&plain, size(common::ScaleMode::Stretch))
+            .unwrap();
+        assert!(
+            manager
+                .scale_image_deep(&plain, size(common::ScaleMode::Stretch), &scaled)
+                .unwrap()
+                .is_none()
+        );
+    }
 }
@@ -0,0 +1,134 @@
+//! Volume of video wallpapers' audio
+//!
+//! Each video is played at the volume mixed from the outputs showing it (see
+//! [`crate::audio`]), turned down while other applications play audio and
+//! muted while the session is idle. Idleness comes from the compositor through
+//! `ext_idle_notify_v1`; without it videos are never muted for being idle.
+
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use wayland_client::{
+    Connection, Dispatch, QueueHandle, globals::GlobalList, protocol::wl_seat::WlSeat,
+};
+use wayland_protocols::ext::idle_notify::v1::client::{
+    ext_idle_notification_v1::{self, ExtIdleNotificationV1},
+    ext_idle_notifier_v1::ExtIdleNotifierV1,
+};
+
+use super::WallpaperDaemon;
+use crate::DaemonState;
+use crate::audio::{Ducking, Volume};
+use crate::config::AdvancedSettings;
+
+/// Volume outputs start with
+pub(super) fn configured_volume(state: &Arc<Mutex<DaemonState>>) -> Volume {
+    advanced_setting(state, AdvancedSettings::video_volume)
+}
+
+/// What currently turns video audio down
+pub(super) fn ducking(app_data: &WallpaperDaemon) -> Ducking {
+    Ducking {
+        other_audio: app_data.other_audio.is_playing(),
+        idle: app_data.idle,
+        duck_level: advanced_setting(&app_data.state, |a| a.video_duck_volume),
+    }
+}
+
+/// Apply each video the volume of the outputs showing it
+pub(super) fn update_video_volumes(app_data: &mut WallpaperDaemon) {
+    // Other applications' audio only matters while a video can be heard
+    if app_data
+        .outputs
+        .iter()
+        .any(|o| o.video.is_some() && o.volume.audible() > 0.0)
+    {
+        app_data.other_audio.refresh();
+    }
+
+    let ducking = ducking(app_data);
+    for (video, video_manager) in &app_data.video_managers {
+        let volumes = app_data
+            .outputs
+            .iter()
+            .filter(|o| o.video.as_ref() == Some(video))
+            .map(|o| o.volume);
+        let volume = crate::audio::mix(volumes, ducking);
+
+        // Setting an unchanged volume does nothing, so a busy manager can wait
+        if let Ok(video_manager) = video_manager.try_lock() {
+            video_manager.set_volume(volume);
+        }
+    }
+}
+
+/// Ask the compositor to tell when the session goes idle
+///
+/// Does nothing if `video_idle_mute_secs` is 0, or the compositor lacks the
+/// idle-notify protocol or a seat.
+pub(super) fn watch_idle(
+    app_data: &WallpaperDaemon,
+    globals: &GlobalList,
+    qh: &QueueHandle<WallpaperDaemon>,
+) {
+    let timeout_secs = advanced_setting(&app_data.state, |a| a.video_idle_mute_secs);
+    if timeout_secs == 0 {
+        return;
+    }
+
+    let notifier = globals.bind::<ExtIdleNotifierV1, _, _>(qh, 1..=1, ());
+    let seat = globals.bind::<WlSeat, _, _>(qh, 1..=1, ());
+    match (notifier, seat) {
+        (Ok(notifier), Ok(seat)) => {
+            // The notification lives as long as the connection
+            notifier.get_idle_notification(timeout_secs.saturating_mul(1000), &seat, qh, ());
+            log::info!(
+                "Video audio will be muted after {}s of inactivity",
+                timeout_secs
+            );
+        }
+        (notifier, seat) => log::info!(
+            "Video audio won't be muted when idle (idle notifier: {}, seat: {})",
+            notifier.is_ok(),
+            seat.is_ok()
+        ),
+    }
+}
+
+/// Read an advanced setting from the config, or its default
+fn advanced_setting<T>(
+    state: &Arc<Mutex<DaemonState>>,
+    setting: impl FnOnce(&AdvancedSettings) -> T,
+) -> T {
+    let state = state.try_lock().ok();
+    match state.as_ref().and_then(|state| state.config.as_ref()) {
+        Some(config) => setting(&config.advanced),
+        None => setting(&AdvancedSettings::default()),
+    }
+}
+
+impl Dispatch<ExtIdleNotificationV1, ()> for WallpaperDaemon {
+    fn event(
+        state: &mut Self,
+        _notification: &ExtIdleNotificationV1,
+        event: ext_idle_notification_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            ext_idle_notification_v1::Event::Idled => {
+                log::debug!("Session idle, muting video audio");
+                state.idle = true;
+            }
+            ext_idle_notification_v1::Event::Resumed => {
+                log::debug!("Session active again");
+                state.idle = false;
+            }
+            _ => {}
+        }
+    }
+}
+
+wayland_client::delegate_noop!(WallpaperDaemon: ExtIdleNotifierV1);
+// Only needed to ask about the seat's idleness
+wayland_client::delegate_noop!(WallpaperDaemon: ignore WlSeat);
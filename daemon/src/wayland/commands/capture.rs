@@ -0,0 +1,98 @@
+//! Frame capture handler.
+//!
+//! Reads back what each output is showing from its committed shm buffer, which
+//! already contains scaling, overlays and transition blending (GPU-rendered
+//! frames are copied into it as well), and saves it as a PNG.
+
+use super::super::utils::{output_name, output_result, select_outputs};
+use super::super::{OutputData, WallpaperDaemon};
+use crate::capture::CapturedFrame;
+use anyhow::{Context, Result};
+
+pub(in crate::wayland) fn capture_outputs(
+    app_data: &mut WallpaperDaemon,
+    output_filter: Option<&str>,
+    path: &str,
+) -> Result<Vec<common::OutputResult>> {
+    let mut results = Vec::new();
+    let mut frames = Vec::new();
+    // Outputs without a known position are laid out left to right
+    let mut next_x = 0;
+
+    let selected = select_outputs(&app_data.output_state, &app_data.outputs, output_filter)?;
+    let stitch = output_filter.is_none_or(|filter| filter == "all") || selected.len() > 1;
+    for output_data in &app_data.outputs {
+        if !output_data.configured {
+            continue;
+        }
+
+        // Check if this output matches the filter
+        if !selected.contains(&output_data.output) {
+            continue;
+        }
+
+        let name = output_name(&app_data.output_state, &output_data.output);
+        let (x, y) = app_data
+            .output_state
+            .info(&output_data.output)
+            .and_then(|info| info.logical_position)
+            .unwrap_or((next_x, 0));
+
+        match read_frame(output_data) {
+            Ok(image) => {
+                next_x = next_x.max(x + image.width() as i32);
+                frames.push(CapturedFrame { x, y, image });
+                results.push(output_result(name, Ok(())));
+            }
+            Err(e) => results.push(output_result(name, Err(e))),
+        }
+    }
+
+    let image = if stitch {
+        crate::capture::stitch(&frames)
+    } else {
+        frames.pop().map(|frame| frame.image)
+    };
+
+    let Some(image) = image else {
+        if results.is_empty() {
+            return Err(common::WallpaperError::Unavailable(
+                "no configured outputs to capture".to_string(),
+            )
+            .into());
+        }
+        // Every matching output failed; the results say why
+        return Ok(results);
+    };
+
+    crate::capture::save_png(&image, std::path::Path::new(path))?;
+
+    log::info!(
+        "Saved {}x{} capture of {} output(s) to {}",
+        image.width(),
+        image.height(),
+        results.iter().filter(|r| r.error.is_none()).count(),
+        path
+    );
+
+    Ok(results)
+}
+
+/// Read the frame currently attached to an output's surface
+fn read_frame(output_data: &OutputData) -> Result<image::RgbaImage> {
+    // A single-pixel colour has no full-size buffer to read
+    if let Some((r, g, b, a)) = output_data.single_pixel_color() {
+        return Ok(image::RgbaImage::from_pixel(
+            output_data.width,
+            output_data.height,
+            image::Rgba([r, g, b, a]),
+        ));
+    }
+
+    let buffer = output_data
+        .buffer
+        .as_ref()
+        .context("Nothing has been displayed on this output yet")?;
+
+    crate::capture::argb8888_to_rgba(&buffer.read_data()?, buffer.width(), buffer.height())
+}
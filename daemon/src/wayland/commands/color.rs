@@ -3,9 +3,11 @@
 //! Handles solid color wallpapers with support for:
 //! - Hex color parsing (#RRGGBB, #RRGGBBAA)
 //! - Output filtering (specific output or all)
-//! - Direct buffer filling (no GPU required)
+//! - Single-pixel buffers scaled by the compositor, when it supports them
+//! - Direct buffer filling otherwise (no GPU required)
 
-use super::super::WallpaperDaemon;
+use super::super::utils::{output_name, output_result, select_outputs};
+use super::super::{OutputData, WallpaperDaemon};
 use anyhow::Result;
 use smithay_client_toolkit::shell::WaylandSurface;
 use wayland_client::QueueHandle;
@@ -15,7 +17,7 @@ pub(in crate::wayland) fn set_color_wallpaper(
     color: &str,
     output_filter: Option<&str>,
     qh: &QueueHandle<WallpaperDaemon>,
-) -> Result<()> {
+) -> Result<Vec<common::OutputResult>> {
     log::info!(
         "Setting color wallpaper: {} for output: {:?}",
         color,
@@ -27,78 +29,91 @@ pub(in crate::wayland) fn set_color_wallpaper(
         .ok_or_else(|| anyhow::anyhow!("Invalid color format: {}", color))?;
 
     // Apply to matching outputs
+    let mut results = Vec::new();
+    let selected = select_outputs(&app_data.output_state, &app_data.outputs, output_filter)?;
     for output_data in &mut app_data.outputs {
         if !output_data.configured {
             continue;
         }
 
         // Check if this output matches the filter
-        if let Some(filter) = output_filter
-            && let Some(info) = app_data.output_state.info(&output_data.output)
-            && let Some(name) = &info.name
-            && name != filter
-            && filter != "all"
-        {
+        if !selected.contains(&output_data.output) {
             continue;
         }
 
-        let width = output_data.width;
-        let height = output_data.height;
-
-        if width == 0 || height == 0 {
+        if output_data.width == 0 || output_data.height == 0 {
             continue;
         }
 
-        // Create buffer and fill with color
-        let mut buffer = crate::buffer::ShmBuffer::new(app_data.shm.wl_shm(), width, height, qh)?;
+        let name = output_name(&app_data.output_state, &output_data.output);
+        let result = fill_output(
+            app_data.shm.wl_shm(),
+            app_data.single_pixel.as_ref(),
+            output_data,
+            (r, g, b, a),
+            qh,
+        );
+        results.push(output_result(name, result));
+    }
 
-        buffer.fill_color(r, g, b, a);
+    // Update shared state
+    if let Ok(mut state) = app_data.state.try_lock() {
+        let wallpaper_type = common::WallpaperType::Color(color.to_string());
+        let command = crate::WallpaperCommand::SetColor {
+            color: color.to_string(),
+            output: None,
+        };
+        state.record_wallpaper(output_filter, wallpaper_type, &command);
+    }
 
-        // Attach and commit
-        if let Some(layer_surface) = &output_data.layer_surface {
-            layer_surface
-                .wl_surface()
-                .attach(Some(buffer.buffer()), 0, 0);
+    Ok(results)
+}
 
-            layer_surface
-                .wl_surface()
-                .damage_buffer(0, 0, width as i32, height as i32);
+/// Fill a single output with a solid color
+///
+/// Uses a single-pixel buffer when the compositor supports it, else an SHM buffer.
+pub(in crate::wayland) fn fill_output(
+    shm: &wayland_client::protocol::wl_shm::WlShm,
+    single_pixel: Option<&super::super::single_pixel::SinglePixel>,
+    output_data: &mut OutputData,
+    (r, g, b, a): (u8, u8, u8, u8),
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Result<()> {
+    let width = output_data.width;
+    let height = output_data.height;
+
+    output_data.base_frame = None;
+    output_data.deep_frame = None;
+    output_data.scaled_frame = None;
+    output_data.svg_source = None;
+    output_data.gradient_source = None;
+
+    if let Some(single_pixel) = single_pixel {
+        single_pixel.show(output_data, (r, g, b, a), qh)?;
+        log::info!(
+            "Applied color to output {}x{} (single-pixel buffer)",
+            width,
+            height
+        );
+        return Ok(());
+    }
 
-            layer_surface.wl_surface().commit();
-        }
+    // Create buffer and fill with color
+    let mut buffer = output_data.new_buffer(shm, width, height, qh)?;
 
-        // Mark buffer as busy (compositor is using it)
-        // Just replace buffer directly
-        // Swap buffer (moves old buffer to pool)
-        output_data.buffer = Some(buffer);
+    buffer.fill_color(r, g, b, a);
 
-        log::info!("Applied color to output {}x{}", width, height);
+    // Attach and commit
+    if let Some(layer_surface) = &output_data.layer_surface {
+        buffer.attach_to(layer_surface.wl_surface());
+        layer_surface.wl_surface().commit();
     }
 
-    // Update shared state
-    if let Ok(mut state) = app_data.state.try_lock() {
-        let wallpaper_type = common::WallpaperType::Color(color.to_string());
-
-        if let Some(filter) = output_filter {
-            if filter == "all" {
-                let output_names: Vec<String> =
-                    state.outputs.iter().map(|o| o.name.clone()).collect();
-
-                for name in output_names {
-                    state.wallpapers.insert(name, wallpaper_type.clone());
-                }
-            } else {
-                state.wallpapers.insert(filter.to_string(), wallpaper_type);
-            }
-        } else {
-            // Apply to all outputs
-            let output_names: Vec<String> = state.outputs.iter().map(|o| o.name.clone()).collect();
-
-            for name in output_names {
-                state.wallpapers.insert(name, wallpaper_type.clone());
-            }
-        }
-    }
+    // Mark buffer as busy (compositor is using it)
+    // Just replace buffer directly
+    // Swap buffer (moves old buffer to pool)
+    output_data.set_buffer(buffer);
 
+    log::info!("Applied color to output {}x{}", width, height);
     Ok(())
 }
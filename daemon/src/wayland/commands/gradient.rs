@@ -0,0 +1,110 @@
+//! Gradient wallpaper handler.
+//!
+//! Handles linear, radial and conic gradient wallpapers with support for:
+//! - Rendering once at each output's exact resolution (again when it's resized)
+//! - Ordered dithering against banding
+//! - Transitions and overlays, like still images
+
+use super::super::utils::{output_name, output_result, select_outputs};
+use super::super::{OutputData, StaticFrame, WallpaperDaemon};
+use anyhow::Result;
+use wayland_client::QueueHandle;
+
+pub(in crate::wayland) fn set_gradient_wallpaper(
+    app_data: &mut WallpaperDaemon,
+    gradient: &common::Gradient,
+    output_filter: Option<&str>,
+    transition: Option<common::TransitionType>,
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Result<Vec<common::OutputResult>> {
+    log::info!(
+        "Setting {:?} gradient wallpaper ({} stops) for output: {:?}",
+        gradient.kind,
+        gradient.stops.len(),
+        output_filter
+    );
+    gradient.validate()?;
+
+    // Apply to matching outputs
+    let mut results = Vec::new();
+    let selected = select_outputs(&app_data.output_state, &app_data.outputs, output_filter)?;
+    for output_data in &mut app_data.outputs {
+        if !output_data.configured {
+            continue;
+        }
+
+        // Check if this output matches the filter
+        if !selected.contains(&output_data.output) {
+            continue;
+        }
+
+        if output_data.width == 0 || output_data.height == 0 {
+            continue;
+        }
+
+        // Clear any old managers (they would draw over the gradient)
+        output_data.shader_manager = None;
+        #[cfg(feature = "video")]
+        {
+            output_data.video = None;
+        }
+
+        let name = output_name(&app_data.output_state, &output_data.output);
+        let result = render_gradient_to_output(
+            app_data.shm.wl_shm(),
+            output_data,
+            gradient,
+            transition.as_ref(),
+            qh,
+        );
+        results.push(output_result(name, result));
+    }
+
+    // Update shared state
+    if let Ok(mut state) = app_data.state.try_lock() {
+        let wallpaper_type = common::WallpaperType::Gradient(gradient.clone());
+        let command = crate::WallpaperCommand::SetGradient {
+            gradient: gradient.clone(),
+            output: None,
+            transition: None,
+        };
+        state.record_wallpaper(output_filter, wallpaper_type, &command);
+    }
+
+    Ok(results)
+}
+
+/// Render a gradient at a single output's size and display it (or start a transition to it)
+///
+/// Also used to re-render the output's gradient after it is resized.
+pub(in crate::wayland) fn render_gradient_to_output(
+    shm: &wayland_client::protocol::wl_shm::WlShm,
+    output_data: &mut OutputData,
+    gradient: &common::Gradient,
+    transition: Option<&common::TransitionType>,
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Result<()> {
+    let width = output_data.width;
+    let height = output_data.height;
+
+    let start = std::time::Instant::now();
+    let argb_data = crate::gradient::render(gradient, width, height)?;
+
+    log::info!(
+        "Gradient rendering: {}x{} in {:.2}ms",
+        width,
+        height,
+        start.elapsed().as_secs_f64() * 1000.0
+    );
+
+    super::image::show_frame(
+        shm,
+        output_data,
+        StaticFrame::new(argb_data),
+        transition,
+        qh,
+    )?;
+    output_data.svg_source = None;
+    output_data.gradient_source = Some(gradient.clone());
+    Ok(())
+}
@@ -3,24 +3,55 @@
 //! Handles static image wallpapers (PNG, JPG, etc.) with support for:
 //! - GIF detection and conversion to WebM for efficient playback
 //! - Video file detection (delegates to video handler)
-//! - GPU-accelerated scaling with CPU fallback
+//! - SVG rendering at each output's exact size
+//! - Decoding and scaling in the background, or instantly from the cache
+//! - 10-bit frames of high-bit-depth and HDR images, where supported
+//! - Rescaling on the Wayland thread for outputs resized mid-load
 //! - Transitions between wallpapers
 //! - Overlay shader application
 
-use super::super::WallpaperDaemon;
+use super::super::utils::{output_name, output_result, select_outputs};
+use super::super::{ImageLoad, OutputData, StaticFrame, SvgSource, WallpaperDaemon};
+use super::CommandStatus;
 use crate::apply_overlay_or_warn;
+use crate::image_cache::FrameSize;
+use crate::image_loader::{LoadId, LoadedImage};
+use crate::wallpaper_manager::WallpaperManager;
 use anyhow::Result;
 use smithay_client_toolkit::shell::WaylandSurface;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
 use wayland_client::QueueHandle;
+use wayland_client::protocol::{wl_output, wl_shm};
+
+/// How an image is placed on each output
+#[derive(Debug, Clone, Copy)]
+pub(in crate::wayland) struct ImagePlacement {
+    pub(in crate::wayland) scale: common::ScaleMode,
+    /// Point Fill crops around (None = from a sidecar, the config or the image)
+    pub(in crate::wayland) focus: Option<common::FocalPoint>,
+    /// Background for Fit and Center (None = the config's)
+    pub(in crate::wayland) letterbox: Option<common::Letterbox>,
+}
 
 pub(in crate::wayland) fn set_image_wallpaper(
     app_data: &mut WallpaperDaemon,
     path: &str,
     output_filter: Option<&str>,
-    scale: common::ScaleMode,
+    placement: ImagePlacement,
     transition: Option<common::TransitionType>,
     qh: &QueueHandle<WallpaperDaemon>,
-) -> Result<()> {
+) -> Result<CommandStatus> {
+    let ImagePlacement {
+        scale,
+        focus,
+        letterbox,
+    } = placement;
+    let letterbox = letterbox.unwrap_or_else(|| default_letterbox(&app_data.state));
+    let tone_mapping = tone_mapping(&app_data.state);
+    let ten_bit = ten_bit_frames(app_data);
+
     log::info!(
         "Setting image wallpaper: {} for output: {:?} with transition: {:?}",
         path,
@@ -38,7 +69,10 @@ pub(in crate::wayland) fn set_image_wallpaper(
             app_data,
             webm_path.to_str().unwrap(),
             output_filter,
-            scale,
+            crate::frame_layout::Placement {
+                mode: scale,
+                letterbox,
+            },
             transition,
             qh,
         );
@@ -47,220 +81,581 @@ pub(in crate::wayland) fn set_image_wallpaper(
     // Check if this is a video
     if crate::wallpaper_manager::WallpaperManager::is_video(path) {
         log::info!("Detected video file, loading with VideoManager");
-        return super::set_video_wallpaper(app_data, path, output_filter, scale, transition, qh);
+        return super::set_video_wallpaper(
+            app_data,
+            path,
+            output_filter,
+            crate::frame_layout::Placement {
+                mode: scale,
+                letterbox,
+            },
+            transition,
+            qh,
+        );
     }
 
-    // Load and clone the image (so we don't hold a borrow to wallpaper_manager)
-    let image = app_data.wallpaper_manager.load_image(path)?.clone();
-
-    // Apply to matching outputs
-    for output_data in &mut app_data.outputs {
+    // Find the outputs to apply to
+    let mut targets = Vec::new();
+    let selected = select_outputs(&app_data.output_state, &app_data.outputs, output_filter)?;
+    for (index, output_data) in app_data.outputs.iter().enumerate() {
         if !output_data.configured {
             continue;
         }
 
         // Check if this output matches the filter
-        if let Some(filter) = output_filter
-            && let Some(info) = app_data.output_state.info(&output_data.output)
-            && let Some(name) = &info.name
-            && name != filter
-            && filter != "all"
-        {
+        if !selected.contains(&output_data.output) {
             continue;
         }
 
-        let width = output_data.width;
-        let height = output_data.height;
-
-        if width == 0 || height == 0 {
+        if output_data.width == 0 || output_data.height == 0 {
             continue;
         }
 
-        // Scale image to fit output
-        // Try GPU acceleration first, fall back to CPU if unavailable
-        let argb_data = {
-            #[cfg(feature = "gpu")]
-            {
-                if let Some(ref gpu) = output_data.gpu_renderer {
-                    let start = std::time::Instant::now();
-                    log::debug!("Using GPU acceleration for image scaling");
-
-                    // Convert DynamicImage to RGBA
-                    let rgba_image = image.to_rgba8();
-                    let (src_width, src_height) = rgba_image.dimensions();
-
-                    match gpu.render_image(
-                        rgba_image.as_raw(),
-                        src_width,
-                        src_height,
-                        width,
-                        height,
-                    ) {
-                        Ok(data) => {
-                            let elapsed = start.elapsed();
-
-                            log::info!(
-                                "GPU rendering: {}x{} -> {}x{} in {:.2}ms",
-                                src_width,
-                                src_height,
-                                width,
-                                height,
-                                elapsed.as_secs_f64() * 1000.0
-                            );
-
-                            data
-                        }
-
-                        Err(e) => {
-                            log::warn!("GPU rendering failed: {}, falling back to CPU", e);
-
-                            // Fallback to CPU
-                            let start_cpu = std::time::Instant::now();
-                            let scaled = app_data
-                                .wallpaper_manager
-                                .scale_image(&image, width, height, scale)?;
-                            let result = app_data.wallpaper_manager.rgba_to_argb8888(&scaled);
-                            let elapsed = start_cpu.elapsed();
-
-                            log::info!(
-                                "CPU rendering (fallback): {}x{} in {:.2}ms",
-                                width,
-                                height,
-                                elapsed.as_secs_f64() * 1000.0
-                            );
-
-                            result
-                        }
-                    }
-                } else {
-                    // No GPU, use CPU
-                    let start = std::time::Instant::now();
-                    let scaled = app_data
-                        .wallpaper_manager
-                        .scale_image(&image, width, height, scale)?;
-                    let result = app_data.wallpaper_manager.rgba_to_argb8888(&scaled);
-                    let elapsed = start.elapsed();
-
-                    log::info!(
-                        "CPU rendering: {}x{} in {:.2}ms",
-                        width,
-                        height,
-                        elapsed.as_secs_f64() * 1000.0
-                    );
-
-                    result
-                }
-            }
-
-            #[cfg(not(feature = "gpu"))]
-            {
-                // GPU feature disabled, use CPU
-                let scaled = app_data
-                    .wallpaper_manager
-                    .scale_image(&image, width, height, scale)?;
+        targets.push(index);
+    }
 
-                app_data.wallpaper_manager.rgba_to_argb8888(&scaled)
-            }
+    // Still images are decoded and scaled in the background, unless every
+    // output's frame is already cached (e.g. a preloaded playlist entry)
+    if !WallpaperManager::is_svg(path) {
+        let focus = focus.or_else(|| focal_point(&app_data.state, Path::new(path)));
+        let frames: Option<Vec<_>> = targets
+            .iter()
+            .map(|&index| {
+                let output_data = &app_data.outputs[index];
+                let size = FrameSize::new(
+                    output_data.width,
+                    output_data.height,
+                    scale,
+                    focus,
+                    letterbox,
+                    tone_mapping,
+                )
+                .with_profile(output_data.color_profile.as_ref());
+                let frame = app_data.wallpaper_manager.scaled_frame(path, size)?;
+                // 8-bit images have no 10-bit frame, so its absence needs no load
+                let deep = ten_bit
+                    .then(|| {
+                        app_data
+                            .wallpaper_manager
+                            .scaled_frame(path, size.ten_bit())
+                    })
+                    .flatten();
+                Some((frame, deep))
+            })
+            .collect();
+
+        let Some(frames) = frames else {
+            let load = ImageLoad {
+                path: path.to_string(),
+                preload: false,
+                output_filter: output_filter.map(str::to_string),
+                scale,
+                focus,
+                letterbox,
+                tone_mapping,
+                ten_bit,
+                transition,
+                reply: None,
+            };
+            let id = super::super::image_loads::start_load(app_data, load, &targets)?;
+            return Ok(CommandStatus::Loading(id));
         };
 
-        // Handle transition if requested
-        if let Some(ref trans_config) = transition
-            && trans_config.duration_ms() > 0
-            // Capture current frame as "old frame" for transition
-            && let Some(buffer) = &output_data.buffer
-            && let Ok(old_frame_data) = buffer.read_data()
-        {
-            // Start transition
-            let transition_type = crate::transition::TransitionType::from(trans_config);
-            let duration = std::time::Duration::from_millis(trans_config.duration_ms() as u64);
-
-            output_data.transition = Some(crate::transition::Transition::new(
-                transition_type,
-                duration,
-                old_frame_data,
-                width,
-                height,
-                #[cfg(feature = "gpu")]
-                output_data.gpu_renderer.clone(),
-            ));
-
-            // Store new wallpaper as pending
-            output_data.pending_wallpaper_data = Some(argb_data);
-
+        let mut results = Vec::new();
+        for (index, (frame, deep)) in targets.into_iter().zip(frames) {
+            let output_data = &mut app_data.outputs[index];
+            let name = output_name(&app_data.output_state, &output_data.output);
             log::info!(
-                "Starting {:?} transition ({}ms) for output {}x{}",
-                transition_type,
-                trans_config.duration_ms(),
-                width,
-                height
+                "Using cached {}x{} frame for {}",
+                output_data.width,
+                output_data.height,
+                path
             );
-
-            // Don't update buffer yet - transition will handle it
-            continue;
+            output_data.svg_source = None;
+            output_data.gradient_source = None;
+            let frame = StaticFrame {
+                argb: frame.to_vec(),
+                deep,
+                scaled: None,
+            };
+            let result = show_frame(
+                app_data.shm.wl_shm(),
+                output_data,
+                frame,
+                transition.as_ref(),
+                qh,
+            );
+            results.push(output_result(name, result));
         }
 
-        // No transition or transition setup failed - apply immediately
-        // Apply overlay if present
-        let mut final_data = argb_data;
+        let placement = ImagePlacement {
+            scale,
+            focus,
+            letterbox: Some(letterbox),
+        };
+        record_image_wallpaper(&app_data.state, path, output_filter, placement);
+        return Ok(CommandStatus::Applied(results));
+    }
 
-        apply_overlay_or_warn!(
-            super::super::overlay::apply_overlay_to_frame,
+    // SVGs are rendered per output at its exact size
+    let colors = svg_colors(&app_data.state);
+    let mut results = Vec::new();
+    for index in targets {
+        let output_data = &mut app_data.outputs[index];
+        let name = output_name(&app_data.output_state, &output_data.output);
+        let result = render_svg_to_output(
+            &mut app_data.wallpaper_manager,
+            app_data.shm.wl_shm(),
             output_data,
-            &mut final_data,
-            width,
-            height,
-            "image"
+            &SvgSource {
+                path: path.to_string(),
+                scale,
+                letterbox,
+            },
+            &colors,
+            transition.as_ref(),
+            qh,
         );
+        results.push(output_result(name, result));
+    }
+
+    let placement = ImagePlacement {
+        scale,
+        focus,
+        letterbox: Some(letterbox),
+    };
+    record_image_wallpaper(&app_data.state, path, output_filter, placement);
+    Ok(CommandStatus::Applied(results))
+}
+
+/// Show a finished background load on the outputs still waiting for it
+pub(in crate::wayland) fn show_loaded_image(
+    app_data: &mut WallpaperDaemon,
+    id: LoadId,
+    load: &ImageLoad,
+    loaded: &LoadedImage,
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Vec<common::OutputResult> {
+    let mut results = Vec::new();
+    for output_data in &mut app_data.outputs {
+        // Outputs that got another wallpaper meanwhile no longer wait for this one
+        if output_data.pending_load != Some(id) {
+            continue;
+        }
+        output_data.pending_load = None;
+
+        let name = output_name(&app_data.output_state, &output_data.output);
+        let size = FrameSize::new(
+            output_data.width,
+            output_data.height,
+            load.scale,
+            load.focus,
+            load.letterbox,
+            load.tone_mapping,
+        )
+        .with_profile(output_data.color_profile.as_ref());
+        let result = match loaded.frame(size) {
+            Some(frame) => {
+                output_data.svg_source = None;
+                output_data.gradient_source = None;
+                let frame = StaticFrame {
+                    argb: frame.to_vec(),
+                    deep: loaded.frame(size.ten_bit()).cloned(),
+                    scaled: None,
+                };
+                show_frame(
+                    app_data.shm.wl_shm(),
+                    output_data,
+                    frame,
+                    load.transition.as_ref(),
+                    qh,
+                )
+            }
+            // The output was resized while the image was loading
+            None => apply_image_to_output(
+                &mut app_data.wallpaper_manager,
+                app_data.shm.wl_shm(),
+                output_data,
+                load,
+                &loaded.image,
+                size,
+                qh,
+            ),
+        };
+        results.push(output_result(name, result));
+    }
 
-        // Create or update buffer
-        let mut buffer = crate::buffer::ShmBuffer::new(&app_data.shm.wl_shm(), width, height, qh)?;
-        buffer.write_image_data(&final_data)?;
+    if !results.is_empty() {
+        let placement = ImagePlacement {
+            scale: load.scale,
+            focus: load.focus,
+            letterbox: Some(load.letterbox),
+        };
+        record_image_wallpaper(
+            &app_data.state,
+            &load.path,
+            load.output_filter.as_deref(),
+            placement,
+        );
+    }
 
-        // Attach and commit
-        if let Some(layer_surface) = &output_data.layer_surface {
-            layer_surface
-                .wl_surface()
-                .attach(Some(buffer.buffer()), 0, 0);
+    results
+}
 
-            layer_surface
-                .wl_surface()
-                .damage_buffer(0, 0, width as i32, height as i32);
+/// Record an image as the wallpaper of the outputs matching the filter
+fn record_image_wallpaper(
+    state: &std::sync::Arc<tokio::sync::Mutex<crate::DaemonState>>,
+    path: &str,
+    output_filter: Option<&str>,
+    placement: ImagePlacement,
+) {
+    if let Ok(mut state) = state.try_lock() {
+        let wallpaper_type = common::WallpaperType::Image(path.to_string());
+        let command = crate::WallpaperCommand::SetImage {
+            path: path.to_string(),
+            output: None,
+            scale: placement.scale,
+            transition: None,
+            focus: placement.focus,
+            letterbox: placement.letterbox,
+        };
+        state.record_wallpaper(output_filter, wallpaper_type, &command);
+    }
+}
 
-            layer_surface.wl_surface().commit();
-        }
+/// Get the focal point given for an image by a sidecar file or the config
+pub(in crate::wayland) fn focal_point(
+    state: &std::sync::Arc<tokio::sync::Mutex<crate::DaemonState>>,
+    path: &Path,
+) -> Option<common::FocalPoint> {
+    let config_points = state
+        .try_lock()
+        .ok()
+        .and_then(|state| state.config.as_ref().map(|c| c.focal_points.clone()))
+        .unwrap_or_default();
+
+    crate::focal_point::configured(path, &config_points)
+}
 
-        // Mark buffer as busy (compositor is using it)
-        // Just replace buffer directly
-        // Swap buffer (moves old buffer to pool)
-        output_data.buffer = Some(buffer);
+/// Get the default letterbox background from the config
+pub(in crate::wayland) fn default_letterbox(
+    state: &std::sync::Arc<tokio::sync::Mutex<crate::DaemonState>>,
+) -> common::Letterbox {
+    state
+        .try_lock()
+        .ok()
+        .and_then(|state| state.config.as_ref().map(|c| c.letterbox()))
+        .unwrap_or_default()
+}
 
-        log::info!("Applied wallpaper to output {}x{}", width, height);
+/// Get how HDR images are tone mapped from the config
+pub(in crate::wayland) fn tone_mapping(
+    state: &std::sync::Arc<tokio::sync::Mutex<crate::DaemonState>>,
+) -> crate::hdr::ToneMapping {
+    state
+        .try_lock()
+        .ok()
+        .and_then(|state| state.config.as_ref().map(|c| c.tone_mapping()))
+        .unwrap_or_default()
+}
+
+/// Check if high-bit-depth images should also get 10-bit frames: the config
+/// allows them and the compositor accepts XRGB2101010 buffers
+pub(in crate::wayland) fn ten_bit_frames(app_data: &WallpaperDaemon) -> bool {
+    let enabled = app_data
+        .state
+        .try_lock()
+        .ok()
+        .and_then(|state| state.config.as_ref().map(|c| c.hdr.ten_bit))
+        .unwrap_or(true);
+
+    enabled
+        && app_data
+            .shm
+            .formats()
+            .contains(&wl_shm::Format::Xrgb2101010)
+}
+
+/// Get the SVG colour substitutions from the config
+pub(in crate::wayland) fn svg_colors(
+    state: &std::sync::Arc<tokio::sync::Mutex<crate::DaemonState>>,
+) -> BTreeMap<String, String> {
+    state
+        .try_lock()
+        .ok()
+        .and_then(|state| state.config.as_ref().map(|c| c.svg.colors.clone()))
+        .unwrap_or_default()
+}
+
+/// Render an SVG at a single output's size and display it (or start a transition to it)
+///
+/// Also used to re-render the output's SVG after it is resized.
+pub(in crate::wayland) fn render_svg_to_output(
+    wallpaper_manager: &mut WallpaperManager,
+    shm: &wayland_client::protocol::wl_shm::WlShm,
+    output_data: &mut OutputData,
+    svg: &SvgSource,
+    colors: &BTreeMap<String, String>,
+    transition: Option<&common::TransitionType>,
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Result<()> {
+    let width = output_data.width;
+    let height = output_data.height;
+    let buffer_scale = output_data.buffer_scale;
+
+    let start = std::time::Instant::now();
+    let rendered =
+        wallpaper_manager.render_svg(&svg.path, width, height, svg.scale, svg.letterbox, colors)?;
+    let argb_data = wallpaper_manager.rgba_to_argb8888(&rendered);
+
+    // HiDPI outputs also get a copy rasterized at their buffer scale
+    let scaled = if buffer_scale > 1 {
+        let rendered = wallpaper_manager.render_svg(
+            &svg.path,
+            width * buffer_scale,
+            height * buffer_scale,
+            svg.scale,
+            svg.letterbox,
+            colors,
+        )?;
+        Some(Arc::new(wallpaper_manager.rgba_to_argb8888(&rendered)))
+    } else {
+        None
+    };
+
+    log::info!(
+        "SVG rendering: {}x{} (scale {}) in {:.2}ms",
+        width,
+        height,
+        buffer_scale,
+        start.elapsed().as_secs_f64() * 1000.0
+    );
+
+    let frame = StaticFrame {
+        argb: argb_data,
+        deep: None,
+        scaled,
+    };
+    show_frame(shm, output_data, frame, transition, qh)?;
+    output_data.svg_source = Some(svg.clone());
+    output_data.gradient_source = None;
+    Ok(())
+}
+
+/// Scale a loaded image to a single output and display it (or start a transition to it)
+fn apply_image_to_output(
+    wallpaper_manager: &mut WallpaperManager,
+    shm: &wayland_client::protocol::wl_shm::WlShm,
+    output_data: &mut OutputData,
+    load: &ImageLoad,
+    image: &::image::DynamicImage,
+    size: FrameSize,
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Result<()> {
+    let (width, height) = (size.width, size.height);
+    let path = load.path.as_str();
+    let transition = load.transition.as_ref();
+
+    // Reuse the copy scaled for this size last time, if it's still cached
+    if let Some(frame) = wallpaper_manager.scaled_frame(path, size) {
+        log::info!("Using cached {}x{} frame for {}", width, height, path);
+        let deep = load
+            .ten_bit
+            .then(|| wallpaper_manager.scaled_frame(path, size.ten_bit()))
+            .flatten();
+        let frame = StaticFrame {
+            argb: frame.to_vec(),
+            deep,
+            scaled: None,
+        };
+        show_frame(shm, output_data, frame, transition, qh)?;
+        output_data.svg_source = None;
+        output_data.gradient_source = None;
+        return Ok(());
     }
 
-    // Update shared state
-    if let Ok(mut state) = app_data.state.try_lock() {
-        let wallpaper_type = common::WallpaperType::Image(path.to_string());
+    // Scale on the CPU like the background loader, which places the image by
+    // its scale mode, focal point and letterbox (the GPU can only stretch it)
+    // 10-bit frames stay in sRGB, so outputs with a display profile don't get them
+    let ten_bit = load.ten_bit && output_data.color_profile.is_none();
+    let start = std::time::Instant::now();
+    let scaled = wallpaper_manager.scale_image(image, size)?;
+    let deep = match ten_bit {
+        true => wallpaper_manager.scale_image_deep(image, size, &scaled)?,
+        false => None,
+    };
+    let mut argb_data = wallpaper_manager.rgba_to_argb8888(&scaled);
+    let deep = deep.map(Arc::new);
 
-        if let Some(filter) = output_filter {
-            if filter == "all" {
-                let output_names: Vec<String> =
-                    state.outputs.iter().map(|o| o.name.clone()).collect();
+    log::info!(
+        "CPU rendering: {}x{} in {:.2}ms",
+        width,
+        height,
+        start.elapsed().as_secs_f64() * 1000.0
+    );
 
-                for name in output_names {
-                    state.wallpapers.insert(name, wallpaper_type.clone());
-                }
-            } else {
-                state.wallpapers.insert(filter.to_string(), wallpaper_type);
-            }
-        } else {
-            // Apply to all outputs
-            let output_names: Vec<String> = state.outputs.iter().map(|o| o.name.clone()).collect();
+    if let Some(profile) = &output_data.color_profile {
+        profile.apply(&mut argb_data)?;
+    }
 
-            for name in output_names {
-                state.wallpapers.insert(name, wallpaper_type.clone());
-            }
+    wallpaper_manager.store_scaled_frame(path, size, Arc::new(argb_data.clone()));
+    if let Some(deep) = &deep {
+        wallpaper_manager.store_scaled_frame(path, size.ten_bit(), deep.clone());
+    }
+    show_frame(
+        shm,
+        output_data,
+        StaticFrame {
+            argb: argb_data,
+            deep,
+            scaled: None,
+        },
+        transition,
+        qh,
+    )?;
+    output_data.svg_source = None;
+    output_data.gradient_source = None;
+    Ok(())
+}
+
+/// Display a rendered frame on an output, transitioning from the current frame if requested
+///
+/// The frame's 10-bit or HiDPI copy, if it has one, is shown instead of it
+/// unless an overlay is drawn over the frame.
+pub(super) fn show_frame(
+    shm: &wayland_client::protocol::wl_shm::WlShm,
+    output_data: &mut OutputData,
+    frame: StaticFrame,
+    transition: Option<&common::TransitionType>,
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Result<()> {
+    let width = output_data.width;
+    let height = output_data.height;
+    let argb_data = frame.argb;
+    output_data.deep_frame = frame.deep;
+    output_data.scaled_frame = frame.scaled;
+
+    // Handle transition if requested
+    if let Some(trans_config) = transition
+        && trans_config.duration_ms() > 0
+        // Capture current frame as "old frame" for transition
+        && let Ok(Some(old_frame_data)) = output_data.read_frame()
+    {
+        // Start transition
+        let transition_type = crate::transition::TransitionType::from(trans_config);
+        let duration = std::time::Duration::from_millis(trans_config.duration_ms() as u64);
+
+        output_data.transition = Some(crate::transition::Transition::new(
+            transition_type,
+            duration,
+            old_frame_data,
+            width,
+            height,
+            #[cfg(feature = "gpu")]
+            output_data.gpu_renderer.clone(),
+        ));
+
+        // Store new wallpaper as pending
+        output_data.pending_wallpaper_data = Some(argb_data);
+
+        log::info!(
+            "Starting {:?} transition ({}ms) for output {}x{}",
+            transition_type,
+            trans_config.duration_ms(),
+            width,
+            height
+        );
+
+        // Don't update buffer yet - transition will handle it
+        return Ok(());
+    }
+
+    // No transition or transition setup failed - apply immediately
+    // Apply overlay if present
+    output_data.base_frame = Some(argb_data.clone());
+    let mut final_data = argb_data;
+
+    apply_overlay_or_warn!(
+        super::super::overlay::apply_overlay_to_frame,
+        output_data,
+        &mut final_data,
+        width,
+        height,
+        "image"
+    );
+
+    // Create or update buffer
+    let buffer = match (&output_data.scaled_frame, &output_data.deep_frame) {
+        (Some(scaled), _) if output_data.overlay_manager.is_none() => scaled_frame_buffer(
+            shm,
+            (width, height),
+            output_data.buffer_scale,
+            scaled,
+            output_data.buffer_transform,
+            qh,
+        )?,
+        (_, Some(deep)) if output_data.overlay_manager.is_none() => {
+            deep_frame_buffer(shm, (width, height), deep, output_data.buffer_transform, qh)?
         }
+        _ => {
+            let mut buffer = output_data.new_buffer(shm, width, height, qh)?;
+            buffer.write_image_data(&final_data)?;
+            buffer
+        }
+    };
+
+    // Attach and commit
+    if let Some(layer_surface) = &output_data.layer_surface {
+        buffer.attach_to(layer_surface.wl_surface());
+        layer_surface.wl_surface().commit();
     }
 
+    // Mark buffer as busy (compositor is using it)
+    // Just replace buffer directly
+    // Swap buffer (moves old buffer to pool)
+    output_data.set_buffer(buffer);
+
+    log::info!("Applied wallpaper to output {}x{}", width, height);
     Ok(())
 }
+
+/// Create a 10-bit buffer holding an XRGB2101010 frame
+pub(in crate::wayland) fn deep_frame_buffer(
+    shm: &wayland_client::protocol::wl_shm::WlShm,
+    (width, height): (u32, u32),
+    deep: &[u8],
+    transform: wl_output::Transform,
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Result<crate::buffer::ShmBuffer> {
+    let mut buffer = crate::buffer::ShmBuffer::with_format(
+        shm,
+        width,
+        height,
+        (wl_shm::Format::Xrgb2101010, transform),
+        qh,
+    )?;
+    buffer.write_native_data(deep)?;
+    Ok(buffer)
+}
+
+/// Create a buffer showing a frame rendered at `scale` times the output's
+/// `width`x`height`
+pub(in crate::wayland) fn scaled_frame_buffer(
+    shm: &wayland_client::protocol::wl_shm::WlShm,
+    (width, height): (u32, u32),
+    scale: u32,
+    frame: &[u8],
+    transform: wl_output::Transform,
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Result<crate::buffer::ShmBuffer> {
+    let mut buffer = crate::buffer::ShmBuffer::with_format(
+        shm,
+        width * scale,
+        height * scale,
+        (wl_shm::Format::Argb8888, transform),
+        qh,
+    )?
+    .with_scale(scale);
+    buffer.write_image_data(frame)?;
+    Ok(buffer)
+}
@@ -5,43 +5,97 @@
 //! - **video**: Video wallpapers with hardware decoding
 //! - **shader**: Procedural shader wallpapers (plasma, waves, etc.)
 //! - **color**: Solid color wallpapers
+//! - **capture**: Saving the displayed frame to a PNG
+//! - **volume**: Volume of video audio per output
 //!
 //! Each submodule handles the specifics of loading, rendering, and applying
-//! its wallpaper type to Wayland outputs.
+//! its wallpaper type to Wayland outputs, and reports the outcome per output.
 
 use super::WallpaperDaemon;
 use crate::WallpaperCommand;
 use anyhow::Result;
 use wayland_client::QueueHandle;
 
+mod capture;
 mod color;
+mod gradient;
 mod image;
 mod shader;
 mod video;
+mod volume;
 
-pub(in crate::wayland) use color::set_color_wallpaper;
-pub(in crate::wayland) use image::set_image_wallpaper;
+pub(in crate::wayland) use capture::capture_outputs;
+pub(in crate::wayland) use color::{fill_output, set_color_wallpaper};
+pub(in crate::wayland) use gradient::{render_gradient_to_output, set_gradient_wallpaper};
+pub(in crate::wayland) use image::{
+    ImagePlacement, deep_frame_buffer, default_letterbox, focal_point, render_svg_to_output,
+    scaled_frame_buffer, set_image_wallpaper, show_loaded_image, svg_colors, ten_bit_frames,
+    tone_mapping,
+};
 pub(in crate::wayland) use shader::set_shader_wallpaper;
 pub(in crate::wayland) use video::set_video_wallpaper;
+#[cfg(feature = "video")]
+pub(in crate::wayland) use video::show_loaded_video;
+pub(in crate::wayland) use volume::set_volume;
+
+/// What became of a wallpaper command
+pub(super) enum CommandStatus {
+    /// Applied, with the outcome for each matching output
+    Applied(Vec<common::OutputResult>),
+    /// Waiting for the image to be decoded (or the video prerolled) in the background
+    Loading(crate::image_loader::LoadId),
+}
 
 /// Main command handler dispatcher
+///
+/// Returns the outcome for each matching output, or the background load an
+/// image or video is waiting on; an `Err` means the command failed as a whole (e.g. the
+/// image file doesn't exist).
 pub(super) fn handle_wallpaper_command(
     app_data: &mut WallpaperDaemon,
     cmd: WallpaperCommand,
     qh: &QueueHandle<WallpaperDaemon>,
-) -> Result<()> {
-    match cmd {
+) -> Result<CommandStatus> {
+    let changes_content = matches!(
+        cmd,
+        WallpaperCommand::SetImage { .. }
+            | WallpaperCommand::SetColor { .. }
+            | WallpaperCommand::SetGradient { .. }
+            | WallpaperCommand::SetShader { .. }
+    );
+
+    let results = match cmd {
         WallpaperCommand::SetImage {
             path,
             output,
             scale,
             transition,
-        } => set_image_wallpaper(app_data, &path, output.as_deref(), scale, transition, qh),
+            focus,
+            letterbox,
+        } => {
+            let output = output.as_deref();
+            let placement = ImagePlacement {
+                scale,
+                focus,
+                letterbox,
+            };
+            match set_image_wallpaper(app_data, &path, output, placement, transition, qh)? {
+                CommandStatus::Applied(results) => Ok(results),
+                // Palettes are scheduled once the load has been shown
+                loading => return Ok(loading),
+            }
+        }
 
         WallpaperCommand::SetColor { color, output } => {
             set_color_wallpaper(app_data, &color, output.as_deref(), qh)
         }
 
+        WallpaperCommand::SetGradient {
+            gradient,
+            output,
+            transition,
+        } => set_gradient_wallpaper(app_data, &gradient, output.as_deref(), transition, qh),
+
         WallpaperCommand::SetShader {
             shader,
             output,
@@ -53,18 +107,44 @@ pub(super) fn handle_wallpaper_command(
             overlay,
             params,
             output,
+            fade,
         } => super::overlay::set_overlay_shader(
             &mut app_data.outputs,
             &app_data.output_state,
             &overlay,
             params,
             output.as_deref(),
+            fade,
         ),
 
-        WallpaperCommand::ClearOverlay { output } => super::overlay::clear_overlay_shader(
+        WallpaperCommand::ClearOverlay { output, fade } => super::overlay::clear_overlay_shader(
             &mut app_data.outputs,
             &app_data.output_state,
             output.as_deref(),
+            fade,
         ),
+
+        WallpaperCommand::Capture { output, path } => {
+            capture_outputs(app_data, output.as_deref(), &path)
+        }
+
+        WallpaperCommand::SetVolume {
+            output,
+            volume,
+            muted,
+        } => set_volume(app_data, output.as_deref(), volume, muted),
+
+        WallpaperCommand::ClearCache => {
+            app_data.wallpaper_manager.clear_cache();
+            Ok(Vec::new())
+        }
+    }?;
+
+    if changes_content {
+        super::palette::schedule_palette_updates(app_data, &results);
+        // A load still in flight for these outputs would overwrite the new wallpaper
+        super::image_loads::supersede_loads(app_data, &results);
     }
+
+    Ok(CommandStatus::Applied(results))
 }
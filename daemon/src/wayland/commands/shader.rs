@@ -7,6 +7,7 @@
 //! - GPU-accelerated rendering
 
 use super::super::WallpaperDaemon;
+use super::super::utils::{output_name, output_result, select_outputs};
 use anyhow::Result;
 use wayland_client::QueueHandle;
 
@@ -17,7 +18,7 @@ pub(in crate::wayland) fn set_shader_wallpaper(
     _transition: Option<common::TransitionType>,
     mut params: Option<common::ShaderParams>,
     _qh: &QueueHandle<WallpaperDaemon>,
-) -> Result<()> {
+) -> Result<Vec<common::OutputResult>> {
     log::info!(
         "Setting shader wallpaper: {} for output: {:?}",
         shader_name,
@@ -49,26 +50,21 @@ pub(in crate::wayland) fn set_shader_wallpaper(
     // Parse shader type
 
     let shader = crate::shader_manager::BuiltinShader::from_str(shader_name).ok_or_else(|| {
-        anyhow::anyhow!(
-            "Unknown shader: {}. Available: plasma, waves, matrix, gradient, starfield",
-            shader_name
-        )
+        common::WallpaperError::UnknownShader {
+            shader: shader_name.to_string(),
+        }
     })?;
 
     // Apply to matching outputs
-
+    let mut results = Vec::new();
+    let selected = select_outputs(&app_data.output_state, &app_data.outputs, output_filter)?;
     for output_data in &mut app_data.outputs {
         if !output_data.configured {
             continue;
         }
 
         // Check if this output matches the filter
-        if let Some(filter) = output_filter
-            && let Some(info) = app_data.output_state.info(&output_data.output)
-            && let Some(name) = &info.name
-            && name != filter
-            && filter != "all"
-        {
+        if !selected.contains(&output_data.output) {
             continue;
         }
 
@@ -90,6 +86,11 @@ pub(in crate::wayland) fn set_shader_wallpaper(
         );
 
         output_data.shader_manager = Some(shader_mgr);
+        output_data.base_frame = None;
+        output_data.deep_frame = None;
+        output_data.scaled_frame = None;
+        output_data.svg_source = None;
+        output_data.gradient_source = None;
 
         log::info!(
             "Applied shader '{}' to output {}x{}",
@@ -97,32 +98,23 @@ pub(in crate::wayland) fn set_shader_wallpaper(
             width,
             height
         );
+
+        let name = output_name(&app_data.output_state, &output_data.output);
+        results.push(output_result(name, Ok(())));
     }
 
     // Update shared state
     if let Ok(mut state) = app_data.state.try_lock() {
         let wallpaper_type = common::WallpaperType::Shader(shader_name.to_string());
-
-        if let Some(filter) = output_filter {
-            if filter == "all" {
-                let output_names: Vec<String> =
-                    state.outputs.iter().map(|o| o.name.clone()).collect();
-
-                for name in output_names {
-                    state.wallpapers.insert(name, wallpaper_type.clone());
-                }
-            } else {
-                state.wallpapers.insert(filter.to_string(), wallpaper_type);
-            }
-        } else {
-            // Apply to all outputs
-            let output_names: Vec<String> = state.outputs.iter().map(|o| o.name.clone()).collect();
-
-            for name in output_names {
-                state.wallpapers.insert(name, wallpaper_type.clone());
-            }
-        }
+        // Presets are recorded resolved, so the shader comes back as it looks now
+        let command = crate::WallpaperCommand::SetShader {
+            shader: shader_name.to_string(),
+            output: None,
+            transition: None,
+            params,
+        };
+        state.record_wallpaper(output_filter, wallpaper_type, &command);
     }
 
-    Ok(())
+    Ok(results)
 }
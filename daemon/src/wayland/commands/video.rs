@@ -6,9 +6,17 @@
 //! - GPU-accelerated scaling for different output resolutions
 //! - Configurable target FPS limiting
 //! - Multi-output synchronization
+//! - Prerolling in the background, then transitioning into the first frame,
+//!   so outputs never go blank
 
 use super::super::WallpaperDaemon;
-use crate::config::default_max_video_fps;
+#[cfg(feature = "video")]
+use super::super::utils::{output_name, output_result, select_outputs};
+#[cfg(feature = "video")]
+use super::super::{VideoKey, VideoLoad};
+use super::CommandStatus;
+#[cfg(feature = "video")]
+use crate::image_loader::LoadId;
 use anyhow::Result;
 use wayland_client::QueueHandle;
 
@@ -17,19 +25,63 @@ pub(in crate::wayland) fn set_video_wallpaper(
     app_data: &mut WallpaperDaemon,
     path: &str,
     output_filter: Option<&str>,
-    scale: common::ScaleMode,
-    _transition: Option<common::TransitionType>,
-    _qh: &QueueHandle<WallpaperDaemon>,
-) -> Result<()> {
-    // TODO: Implement transitions for videos
-    // For now, we just apply immediately
+    placement: crate::frame_layout::Placement,
+    transition: Option<common::TransitionType>,
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Result<CommandStatus> {
     log::info!(
-        "Setting video wallpaper: {} for output: {:?}",
+        "Setting video wallpaper: {} for output: {:?} with transition: {:?}",
         path,
-        output_filter
+        output_filter,
+        transition
     );
 
-    // Calculate max resolution needed BEFORE iterating (to avoid borrow issues)
+    // Find the outputs to apply to
+    let selected = select_outputs(&app_data.output_state, &app_data.outputs, output_filter)?;
+    let targets: Vec<usize> = app_data
+        .outputs
+        .iter()
+        .enumerate()
+        .filter(|(_, output_data)| {
+            output_data.configured
+                && selected.contains(&output_data.output)
+                && output_data.width > 0
+                && output_data.height > 0
+        })
+        .map(|(index, _)| index)
+        .collect();
+
+    // Single VideoManager per video path with GPU scaling
+    // Decode once, GPU scales and places frames for each output
+    // (frames decoded for Stretch can't be placed another way, so those
+    // are decoded apart)
+    let load = VideoLoad {
+        key: VideoKey {
+            path: path.to_string(),
+            stretched: placement.mode == common::ScaleMode::Stretch,
+        },
+        placement,
+        output_filter: output_filter.map(str::to_string),
+        transition,
+        reply: None,
+    };
+
+    if app_data.video_managers.contains_key(&load.key) {
+        log::info!("Reusing existing VideoManager for {}", path);
+        return Ok(CommandStatus::Applied(show_video(
+            app_data, &load, &targets, qh,
+        )));
+    }
+
+    // Playback settings (FPS limit, looping) from config
+    let advanced = app_data
+        .state
+        .try_lock()
+        .ok()
+        .and_then(|state| state.config.as_ref().map(|c| c.advanced.clone()))
+        .unwrap_or_default();
+
+    // Decode at the largest output's size, GPU scales it down for the others
     let (max_width, max_height) = app_data
         .outputs
         .iter()
@@ -38,122 +90,150 @@ pub(in crate::wayland) fn set_video_wallpaper(
         .max()
         .unwrap_or((1920, 1080)); // Default if no configured outputs
 
-    // Apply to matching outputs
-    for output_data in &mut app_data.outputs {
-        if !output_data.configured {
-            continue;
-        }
+    // Volume of a video loaded for them (the audio isn't decoded while muted)
+    let volume = crate::audio::mix(
+        targets.iter().map(|&index| app_data.outputs[index].volume),
+        super::super::audio::ducking(app_data),
+    );
 
-        // Check if this output matches the filter
-        if let Some(filter) = output_filter
-            && let Some(info) = app_data.output_state.info(&output_data.output)
-            && let Some(name) = &info.name
-            && name != filter
-            && filter != "all"
-        {
-            continue;
+    log::info!(
+        "Creating shared VideoManager for {} (decode at {}x{}, will GPU scale to all outputs)",
+        path,
+        max_width,
+        max_height
+    );
+
+    // Prerolling waits for the first frame to be decoded, so it happens in the
+    // background; the outputs keep their wallpaper until it's ready
+    let id = app_data
+        .image_loader
+        .submit_video(crate::image_loader::VideoJob {
+            path: path.to_string(),
+            width: max_width,
+            height: max_height,
+            options: crate::video::VideoOptions {
+                scale: placement.mode,
+                letterbox: placement.letterbox,
+                volume,
+            },
+            advanced,
+            #[cfg(feature = "gpu")]
+            gpu_renderer: app_data.gpu_renderer.clone(),
+        });
+    for &index in &targets {
+        app_data.outputs[index].pending_load = Some(id);
+    }
+    app_data.video_loads.insert(id, load);
+
+    Ok(CommandStatus::Loading(id))
+}
+
+/// Play a video that finished prerolling on the outputs still waiting for it
+#[cfg(feature = "video")]
+pub(in crate::wayland) fn show_loaded_video(
+    app_data: &mut WallpaperDaemon,
+    id: LoadId,
+    load: &VideoLoad,
+    video_manager: crate::video::VideoManager,
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Vec<common::OutputResult> {
+    // Outputs that got another wallpaper meanwhile no longer wait for this one
+    let mut targets = Vec::new();
+    for (index, output_data) in app_data.outputs.iter_mut().enumerate() {
+        if output_data.pending_load == Some(id) {
+            output_data.pending_load = None;
+            targets.push(index);
         }
+    }
 
+    // Store in shared HashMap (keyed by path and stretch), unless the same
+    // video finished loading for other outputs first
+    app_data
+        .video_managers
+        .entry(load.key.clone())
+        .or_insert_with(|| std::sync::Arc::new(tokio::sync::Mutex::new(video_manager)));
+
+    show_video(app_data, load, &targets, qh)
+}
+
+/// Play a loaded video on the given outputs
+#[cfg(feature = "video")]
+fn show_video(
+    app_data: &mut WallpaperDaemon,
+    load: &VideoLoad,
+    targets: &[usize],
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Vec<common::OutputResult> {
+    let mut results = Vec::new();
+    for &index in targets {
+        let output_data = &mut app_data.outputs[index];
+        let name = output_name(&app_data.output_state, &output_data.output);
         let width = output_data.width;
         let height = output_data.height;
 
-        if width == 0 || height == 0 {
-            continue;
-        }
-
         // Clear any old managers (can't have both video and shader)
         output_data.shader_manager = None;
-
-        // Get target FPS from config
-        let target_fps = if let Ok(state_guard) = app_data.state.try_lock() {
-            state_guard
-                .config
-                .as_ref()
-                .map(|c| c.advanced.max_video_fps)
-                .unwrap_or(default_max_video_fps())
-        } else {
-            default_max_video_fps()
-        };
-
-        // Single VideoManager per video path with GPU scaling
-        // Decode at maximum resolution needed, GPU scales down for smaller outputs
-        let path_key = path.to_string();
-
-        if !app_data.video_managers.contains_key(&path_key) {
-            // Calculate max resolution needed across ALL configured outputs
-
-            log::info!(
-                "Creating shared VideoManager for {} (decode at {}x{}, will GPU scale to all outputs)",
-                path,
-                max_width,
-                max_height
-            );
-
-            let mut video_manager = crate::video::VideoManager::load(
-                path,
-                max_width,
-                max_height,
-                scale,
-                true,
-                target_fps,
-                #[cfg(feature = "gpu")]
-                app_data.gpu_renderer.clone(),
-            )?;
-
-            // Start playback
-
-            video_manager.play()?;
-
-            // Store in shared HashMap (keyed by path only)
-
-            app_data.video_managers.insert(
-                path_key.clone(),
-                std::sync::Arc::new(tokio::sync::Mutex::new(video_manager)),
-            );
-        } else {
-            log::info!(
-                "Reusing existing VideoManager for {} (GPU will scale to {}x{})",
-                path,
-                width,
-                height
-            );
+        output_data.base_frame = None;
+        output_data.deep_frame = None;
+        output_data.scaled_frame = None;
+        output_data.svg_source = None;
+        output_data.gradient_source = None;
+
+        // Show (or transition to) the first frame now rather than when the
+        // next frame is decoded; a new video has it from prerolling
+        let first_frame = app_data.video_managers[&load.key]
+            .blocking_lock()
+            .current_frame_data_scaled(width, height, load.placement);
+        if let Some(frame) = first_frame
+            && let Err(e) = super::image::show_frame(
+                app_data.shm.wl_shm(),
+                output_data,
+                super::super::StaticFrame::new(frame),
+                load.transition.as_ref(),
+                qh,
+            )
+        {
+            log::warn!("Failed to show first video frame: {}", e);
         }
+        // (video frames are redrawn as they're decoded, not kept as a static frame)
+        output_data.base_frame = None;
 
-        // Set video path reference for this output
-
-        output_data.video_path = Some(path_key);
+        // Set video reference and placement for this output
+        output_data.video = Some(load.key.clone());
+        output_data.video_placement = load.placement;
 
         log::info!("Set video wallpaper for output {}x{}", width, height);
+        results.push(output_result(name, Ok(())));
     }
 
-    // Update shared state
-
-    if let Ok(mut state) = app_data.state.try_lock() {
-        let wallpaper_type = common::WallpaperType::Video(path.to_string());
-
-        if let Some(filter) = output_filter {
-            if filter == "all" {
-                let output_names: Vec<String> =
-                    state.outputs.iter().map(|o| o.name.clone()).collect();
-
-                for name in output_names {
-                    state.wallpapers.insert(name, wallpaper_type.clone());
-                }
-            } else {
-                state.wallpapers.insert(filter.to_string(), wallpaper_type);
-            }
-        } else {
-            // Apply to all outputs
-
-            let output_names: Vec<String> = state.outputs.iter().map(|o| o.name.clone()).collect();
+    // Stop decoding videos no output shows any more
+    let shown: std::collections::HashSet<&VideoKey> = app_data
+        .outputs
+        .iter()
+        .filter_map(|output_data| output_data.video.as_ref())
+        .collect();
+    app_data
+        .video_managers
+        .retain(|video, _| shown.contains(video));
 
-            for name in output_names {
-                state.wallpapers.insert(name, wallpaper_type.clone());
-            }
-        }
+    // Update shared state
+    if !results.is_empty()
+        && let Ok(mut state) = app_data.state.try_lock()
+    {
+        let path = &load.key.path;
+        let wallpaper_type = common::WallpaperType::Video(path.clone());
+        let command = crate::WallpaperCommand::SetImage {
+            path: path.clone(),
+            output: None,
+            scale: load.placement.mode,
+            transition: None,
+            focus: None,
+            letterbox: Some(load.placement.letterbox),
+        };
+        state.record_wallpaper(load.output_filter.as_deref(), wallpaper_type, &command);
     }
 
-    Ok(())
+    results
 }
 
 #[cfg(not(feature = "video"))]
@@ -161,9 +241,12 @@ pub(in crate::wayland) fn set_video_wallpaper(
     _app_data: &mut WallpaperDaemon,
     _path: &str,
     _output_filter: Option<&str>,
-    _scale: common::ScaleMode,
+    _placement: crate::frame_layout::Placement,
     _transition: Option<common::TransitionType>,
     _qh: &QueueHandle<WallpaperDaemon>,
-) -> Result<()> {
-    anyhow::bail!("Video support not compiled in. Build with --features video")
+) -> Result<CommandStatus> {
+    Err(common::WallpaperError::FeatureDisabled {
+        feature: "video".to_string(),
+    }
+    .into())
 }
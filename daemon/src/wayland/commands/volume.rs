@@ -0,0 +1,68 @@
+//! Video audio volume handler.
+//!
+//! Sets the volume and mute switch of outputs; the videos they show pick the
+//! change up on the next pass of the event loop. Set for all outputs, it's
+//! also what outputs plugged in later start with.
+
+use super::super::WallpaperDaemon;
+#[cfg(feature = "video")]
+use super::super::utils::{output_name, output_result, select_outputs};
+use anyhow::Result;
+
+#[cfg(feature = "video")]
+pub(in crate::wayland) fn set_volume(
+    app_data: &mut WallpaperDaemon,
+    output_filter: Option<&str>,
+    volume: Option<f32>,
+    muted: Option<bool>,
+) -> Result<Vec<common::OutputResult>> {
+    // An explicit `all` sets the default as well, like a missing filter
+    let all_outputs = output_filter.is_none_or(|filter| {
+        matches!(
+            filter.parse(),
+            Ok(crate::output_selector::OutputSelector::All)
+        )
+    });
+    if all_outputs {
+        if let Some(level) = volume {
+            app_data.volume.level = level;
+        }
+        if let Some(muted) = muted {
+            app_data.volume.muted = muted;
+        }
+    }
+
+    let mut results = Vec::new();
+    let selected = select_outputs(&app_data.output_state, &app_data.outputs, output_filter)?;
+    for output_data in &mut app_data.outputs {
+        if !output_data.configured || !selected.contains(&output_data.output) {
+            continue;
+        }
+
+        if let Some(level) = volume {
+            output_data.volume.level = level;
+        }
+        if let Some(muted) = muted {
+            output_data.volume.muted = muted;
+        }
+
+        let name = output_name(&app_data.output_state, &output_data.output);
+        log::info!("Video volume of {} is now {:?}", name, output_data.volume);
+        results.push(output_result(name, Ok(())));
+    }
+
+    Ok(results)
+}
+
+#[cfg(not(feature = "video"))]
+pub(in crate::wayland) fn set_volume(
+    _app_data: &mut WallpaperDaemon,
+    _output_filter: Option<&str>,
+    _volume: Option<f32>,
+    _muted: Option<bool>,
+) -> Result<Vec<common::OutputResult>> {
+    Err(common::WallpaperError::FeatureDisabled {
+        feature: "video".to_string(),
+    }
+    .into())
+}
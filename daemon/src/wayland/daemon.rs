@@ -20,16 +20,17 @@ use std::sync::Arc;
 use tokio::sync::{Mutex, mpsc};
 use wayland_client::{Connection, globals::registry_queue_init};
 
+use super::commands::CommandStatus;
 use crate::log_and_continue;
 use crate::wallpaper_manager::WallpaperManager;
-use crate::{DaemonState, WallpaperCommand};
+use crate::{DaemonState, WallpaperRequest};
 
 /// Main entry point for the Wayland manager.
 ///
 /// Runs in a blocking task and handles reconnection automatically.
 pub async fn run(
     state: Arc<Mutex<DaemonState>>,
-    wallpaper_rx: mpsc::UnboundedReceiver<WallpaperCommand>,
+    wallpaper_rx: mpsc::UnboundedReceiver<WallpaperRequest>,
 ) -> Result<()> {
     log::info!("Connecting to Wayland compositor...");
 
@@ -59,27 +60,36 @@ pub async fn run(
 /// Ok on normal exit, Err on fatal error or broken pipe (reconnection will retry)
 fn run_wayland_blocking(
     state: Arc<Mutex<DaemonState>>,
-    wallpaper_rx: &mut mpsc::UnboundedReceiver<WallpaperCommand>,
+    wallpaper_rx: &mut mpsc::UnboundedReceiver<WallpaperRequest>,
 ) -> Result<()> {
     log::info!("run_wayland_blocking - Starting new Wayland connection");
 
-    // Initialize resource monitor with config from state
-    let resource_config = match state.try_lock() {
+    // Initialize resource monitor and image cache limit with config from state
+    let (resource_config, cache_limit_mb) = match state.try_lock() {
         Ok(state_lock) => {
             if let Some(ref config) = state_lock.config {
-                crate::resource_monitor::ResourceConfig {
-                    auto_battery_mode: config.advanced.auto_battery_mode,
-                    enforce_memory_limits: config.advanced.enforce_memory_limits,
-                    max_memory_mb: config.advanced.max_memory_mb,
-                    cpu_threshold: config.advanced.cpu_threshold,
-                }
+                (
+                    crate::resource_monitor::ResourceConfig {
+                        auto_battery_mode: config.advanced.auto_battery_mode,
+                        enforce_memory_limits: config.advanced.enforce_memory_limits,
+                        max_memory_mb: config.advanced.max_memory_mb,
+                        cpu_threshold: config.advanced.cpu_threshold,
+                    },
+                    config.advanced.cache_limit_mb,
+                )
             } else {
-                crate::resource_monitor::ResourceConfig::default()
+                (
+                    crate::resource_monitor::ResourceConfig::default(),
+                    crate::config::AdvancedSettings::default().cache_limit_mb,
+                )
             }
         }
         Err(_) => {
             log::warn!("State lock unavailable during reconnection; using defaults");
-            crate::resource_monitor::ResourceConfig::default()
+            (
+                crate::resource_monitor::ResourceConfig::default(),
+                crate::config::AdvancedSettings::default().cache_limit_mb,
+            )
         }
     };
 
@@ -120,25 +130,52 @@ fn run_wayland_blocking(
     let (globals, mut event_queue) = registry_queue_init(&conn)?;
     let qh = event_queue.handle();
 
+    #[cfg(feature = "video")]
+    let volume = super::audio::configured_volume(&state);
+
     let mut app_data = WallpaperDaemon {
         registry_state: RegistryState::new(&globals),
         compositor_state: CompositorState::bind(&globals, &qh)?,
         layer_shell: LayerShell::bind(&globals, &qh)?,
         output_state: OutputState::new(&globals, &qh),
         shm: Shm::bind(&globals, &qh)?,
+        single_pixel: super::single_pixel::SinglePixel::bind(&globals, &qh),
         outputs: Vec::new(),
-        wallpaper_manager: WallpaperManager::new(),
+        wallpaper_manager: {
+            // 0 means unlimited
+            let limit = (cache_limit_mb > 0).then_some(cache_limit_mb as usize * 1024 * 1024);
+            let mut manager = WallpaperManager::with_cache_limit(limit);
+            manager
+                .cache_mut()
+                .set_budget(resource_monitor.cache_budget_bytes());
+            manager
+        },
         state,
         exit: false,
         resource_monitor,
+        image_loader: crate::image_loader::ImageLoader::new()?,
+        image_loads: std::collections::HashMap::new(),
+        preloaded: None,
+        started: false,
         #[cfg(feature = "gpu")]
         gpu_renderer,
         #[cfg(feature = "video")]
         video_managers: std::collections::HashMap::new(),
+        #[cfg(feature = "video")]
+        video_loads: std::collections::HashMap::new(),
+        #[cfg(feature = "video")]
+        other_audio: crate::audio::OtherAudio::new(),
+        #[cfg(feature = "video")]
+        idle: false,
+        #[cfg(feature = "video")]
+        volume,
     };
 
     log::info!("Connected to Wayland compositor");
 
+    #[cfg(feature = "video")]
+    super::audio::watch_idle(&app_data, &globals, &qh);
+
     // Initial roundtrip to get outputs and create layer surfaces
     // Note: new_output callback will be triggered and create surfaces automatically
     event_queue.roundtrip(&mut app_data)?;
@@ -166,6 +203,7 @@ fn run_wayland_blocking(
     // Apply initial wallpapers from configuration
     log::info!("Applying initial configuration...");
     super::event_loop::apply_initial_config(&mut app_data, &qh)?;
+    app_data.started = true;
 
     // Event loop with command processing
     loop {
@@ -183,19 +221,44 @@ fn run_wayland_blocking(
         }
 
         // Check for wallpaper commands
-        if let Ok(cmd) = wallpaper_rx.try_recv() {
-            log_and_continue!(
-                super::commands::handle_wallpaper_command(&mut app_data, cmd, &qh),
-                "handle wallpaper command"
-            );
+        if let Ok(WallpaperRequest { command, reply }) = wallpaper_rx.try_recv() {
+            match super::commands::handle_wallpaper_command(&mut app_data, command, &qh) {
+                Ok(CommandStatus::Applied(results)) => reply.send(&Ok(results)),
+                // Answered once the image has been decoded
+                Ok(CommandStatus::Loading(id)) => {
+                    super::image_loads::reply_when_loaded(&mut app_data, id, reply)
+                }
+                Err(e) => {
+                    log::error!("Failed to handle wallpaper command: {:#}", e);
+                    reply.send(&Err(e));
+                }
+            }
+            super::event_loop::publish_cache_status(&app_data);
         }
 
+        // Show images that finished decoding in the background
+        log_and_continue!(
+            super::image_loads::update_image_loads(&mut app_data, &qh),
+            "update image loads"
+        );
+
+        // Show videos whose first frame was prerolled in the background
+        #[cfg(feature = "video")]
+        log_and_continue!(
+            super::video_loads::update_video_loads(&mut app_data, &qh),
+            "update video loads"
+        );
+
         // Update video frames (GIFs are converted to video)
         log_and_continue!(
             super::frame_updates::update_video_frames(&mut app_data, &qh),
             "update video frames"
         );
 
+        // Play video audio at the outputs' volumes
+        #[cfg(feature = "video")]
+        super::audio::update_video_volumes(&mut app_data);
+
         // Update shader frames
         log_and_continue!(
             super::frame_updates::update_shader_frames(&mut app_data, &qh),
@@ -208,12 +271,30 @@ fn run_wayland_blocking(
             "update transitions"
         );
 
+        // Update overlay fades on static wallpapers
+        log_and_continue!(
+            super::overlay::update_overlay_animations(&mut app_data, &qh),
+            "update overlay animations"
+        );
+
+        // Sample palettes of outputs whose wallpaper changed
+        log_and_continue!(
+            super::palette::update_palettes(&mut app_data),
+            "update palettes"
+        );
+
         // Check playlist rotation
         log_and_continue!(
             super::event_loop::check_playlist_rotation(&mut app_data, &qh),
             "check playlist rotation"
         );
 
+        // Decode the next playlist entry ahead of its rotation
+        log_and_continue!(
+            super::image_loads::preload_next_wallpaper(&mut app_data),
+            "preload next wallpaper"
+        );
+
         // Check schedule
         log_and_continue!(
             super::event_loop::check_schedule(&mut app_data, &qh),
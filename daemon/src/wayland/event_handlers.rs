@@ -16,26 +16,88 @@ use wayland_client::{
 };
 
 use super::WallpaperDaemon;
+use crate::output_transform::Orientation;
 
 impl CompositorHandler for WallpaperDaemon {
     fn scale_factor_changed(
         &mut self,
         _conn: &Connection,
-        _qh: &QueueHandle<Self>,
-        _surface: &wl_surface::WlSurface,
-        _new_factor: i32,
+        qh: &QueueHandle<Self>,
+        surface: &wl_surface::WlSurface,
+        new_factor: i32,
     ) {
-        // Handle scale factor changes
+        let Some(output_data) = self.outputs.iter_mut().find(|o| {
+            o.layer_surface
+                .as_ref()
+                .is_some_and(|layer| layer.wl_surface() == surface)
+        }) else {
+            return;
+        };
+
+        let new_scale = new_factor.max(1) as u32;
+        if output_data.buffer_scale == new_scale {
+            return;
+        }
+
+        log::info!(
+            "Output scale changed: {} -> {}",
+            output_data.buffer_scale,
+            new_scale
+        );
+        output_data.buffer_scale = new_scale;
+
+        // Re-rasterize SVG wallpapers at the new scale; other frames stay at
+        // the output's logical size
+        if let Some(svg) = output_data.svg_source.clone()
+            && output_data.configured
+            && output_data.width > 0
+            && output_data.height > 0
+        {
+            let colors = super::commands::svg_colors(&self.state);
+            match super::commands::render_svg_to_output(
+                &mut self.wallpaper_manager,
+                self.shm.wl_shm(),
+                output_data,
+                &svg,
+                &colors,
+                None,
+                qh,
+            ) {
+                Ok(()) => log::info!("Re-rendered SVG wallpaper at scale {}", new_scale),
+                Err(e) => log::error!("Failed to re-render SVG wallpaper: {:#}", e),
+            }
+        }
     }
 
     fn transform_changed(
         &mut self,
         _conn: &Connection,
-        _qh: &QueueHandle<Self>,
-        _surface: &wl_surface::WlSurface,
-        _new_transform: wl_output::Transform,
+        qh: &QueueHandle<Self>,
+        surface: &wl_surface::WlSurface,
+        new_transform: wl_output::Transform,
     ) {
-        // Handle transform changes
+        let Some(output_data) = self.outputs.iter_mut().find(|o| {
+            o.layer_surface
+                .as_ref()
+                .is_some_and(|layer| layer.wl_surface() == surface)
+        }) else {
+            return;
+        };
+
+        if output_data.buffer_transform == new_transform {
+            return;
+        }
+
+        log::info!(
+            "Output transform changed: {:?} -> {:?}",
+            output_data.buffer_transform,
+            new_transform
+        );
+        output_data.buffer_transform = new_transform;
+
+        if let Err(e) = super::outputs::redraw_transformed(self.shm.wl_shm(), output_data, qh) {
+            log::error!("Failed to redraw output in its new transform: {:#}", e);
+        }
     }
 
     fn frame(
@@ -99,15 +161,14 @@ impl OutputHandler for WallpaperDaemon {
                 info.scale_factor,
             );
 
+            let Some(output_data) = self.outputs.iter().find(|o| o.output == output) else {
+                log::debug!("Output updated before its layer surface was created");
+                return;
+            };
+
             // Update our shared state with output info
             if let Ok(mut state) = self.state.try_lock() {
-                let output_info = common::OutputInfo {
-                    name: info.name.clone().unwrap_or_else(|| "Unknown".to_string()),
-                    width: info.logical_size.map(|(w, _)| w as u32).unwrap_or(0),
-                    height: info.logical_size.map(|(_, h)| h as u32).unwrap_or(0),
-                    scale: info.scale_factor as f64,
-                    refresh_rate: None,
-                };
+                let output_info = super::utils::output_info(&self.output_state, output_data);
 
                 // Update or add output info
                 if let Some(existing) = state
@@ -137,6 +198,7 @@ impl OutputHandler for WallpaperDaemon {
     ) {
         log::info!("Output destroyed");
         self.outputs.retain(|o| o.output != output);
+        super::outputs::sync_outputs_to_shared_state(self);
     }
 }
 
@@ -157,6 +219,14 @@ impl LayerShellHandler for WallpaperDaemon {
         let (width, height) = configure.new_size;
         log::info!("Layer surface configured: {}x{}", width, height);
 
+        // Outputs turned between landscape and portrait switch to the
+        // wallpaper configured for their new orientation
+        let reoriented = orientation_wallpaper(self, layer, (width, height));
+        // Outputs plugged in while running get their wallpaper right away
+        let pending = reoriented
+            .or_else(|| hotplug_wallpaper(self, layer, (width, height)))
+            .or_else(|| resized_wallpaper(self, layer, (width, height)));
+
         // Find the output data for this layer surface
         let output_data = self
             .outputs
@@ -164,6 +234,9 @@ impl LayerShellHandler for WallpaperDaemon {
             .find(|o| o.layer_surface.as_ref() == Some(layer));
 
         if let Some(output_data) = output_data {
+            let resized = output_data.configured
+                && (output_data.width, output_data.height) != (width, height);
+
             output_data.width = width;
             output_data.height = height;
             output_data.configured = true;
@@ -174,22 +247,89 @@ impl LayerShellHandler for WallpaperDaemon {
                 output_data.gpu_renderer = self.gpu_renderer.clone();
             }
 
-            // Create a buffer and render a default dark gray color
-            if width > 0 && height > 0 {
-                match crate::buffer::ShmBuffer::new(self.shm.wl_shm(), width, height, qh) {
-                    Ok(mut buffer) => {
-                        // Fill with dark gray (#1e1e1e)
-                        buffer.fill_color(0x1e, 0x1e, 0x1e, 0xff);
+            super::single_pixel::resize_viewport(output_data);
 
-                        // Attach buffer and commit
-                        layer.wl_surface().attach(Some(buffer.buffer()), 0, 0);
-                        layer.wl_surface().commit();
+            // A single-pixel colour only needs the viewport to follow the new size
+            if width > 0 && height > 0 && output_data.single_pixel_color().is_some() {
+                layer.wl_surface().commit();
+                return;
+            }
 
-                        // Mark buffer as busy (compositor is using it)
-                        output_data.buffer = Some(buffer);
+            if let Some(cmd) = pending {
+                // Cover the old frame while the new wallpaper loads
+                if let Err(e) = super::commands::fill_output(
+                    self.shm.wl_shm(),
+                    self.single_pixel.as_ref(),
+                    output_data,
+                    (0x1e, 0x1e, 0x1e, 0xff),
+                    qh,
+                ) {
+                    log::error!("Failed to create buffer: {}", e);
+                }
+
+                // Selectors and recorded wallpapers need the output's new size
+                super::outputs::sync_outputs_to_shared_state(self);
+                if let Err(e) = super::commands::handle_wallpaper_command(self, cmd, qh) {
+                    log::error!("Failed to apply wallpaper to configured output: {:#}", e);
+                }
+                return;
+            }
 
-                        log::info!("Rendered default color to output");
+            // Re-render SVG wallpapers at the new size instead of scaling a bitmap
+            if resized
+                && width > 0
+                && height > 0
+                && let Some(svg) = output_data.svg_source.clone()
+            {
+                let colors = super::commands::svg_colors(&self.state);
+                match super::commands::render_svg_to_output(
+                    &mut self.wallpaper_manager,
+                    self.shm.wl_shm(),
+                    output_data,
+                    &svg,
+                    &colors,
+                    None,
+                    qh,
+                ) {
+                    Ok(()) => {
+                        log::info!("Re-rendered SVG wallpaper at {}x{}", width, height);
+                        return;
                     }
+                    Err(e) => log::error!("Failed to re-render SVG wallpaper: {:#}", e),
+                }
+            }
+
+            // Gradients are likewise rendered at the output's exact size
+            if resized
+                && width > 0
+                && height > 0
+                && let Some(gradient) = output_data.gradient_source.clone()
+            {
+                match super::commands::render_gradient_to_output(
+                    self.shm.wl_shm(),
+                    output_data,
+                    &gradient,
+                    None,
+                    qh,
+                ) {
+                    Ok(()) => {
+                        log::info!("Re-rendered gradient wallpaper at {}x{}", width, height);
+                        return;
+                    }
+                    Err(e) => log::error!("Failed to re-render gradient wallpaper: {:#}", e),
+                }
+            }
+
+            // Render a default dark gray color (#1e1e1e)
+            if width > 0 && height > 0 {
+                match super::commands::fill_output(
+                    self.shm.wl_shm(),
+                    self.single_pixel.as_ref(),
+                    output_data,
+                    (0x1e, 0x1e, 0x1e, 0xff),
+                    qh,
+                ) {
+                    Ok(()) => log::info!("Rendered default color to output"),
                     Err(e) => {
                         log::error!("Failed to create buffer: {}", e);
                         layer.wl_surface().commit();
@@ -202,6 +342,139 @@ impl LayerShellHandler for WallpaperDaemon {
     }
 }
 
+/// Command showing an output's portrait or landscape wallpaper, if it's
+/// turned to a new orientation and its config has a separate one for portrait
+fn orientation_wallpaper(
+    app_data: &WallpaperDaemon,
+    layer: &LayerSurface,
+    (width, height): (u32, u32),
+) -> Option<crate::WallpaperCommand> {
+    let index = app_data
+        .outputs
+        .iter()
+        .position(|o| o.layer_surface.as_ref() == Some(layer))?;
+    let output_data = &app_data.outputs[index];
+    let orientation = Orientation::of(width, height);
+    if !output_data.configured
+        || width == 0
+        || height == 0
+        || Orientation::of(output_data.width, output_data.height) == orientation
+    {
+        return None;
+    }
+
+    // Selectors see the output at its new size
+    let mut infos = super::utils::output_infos(&app_data.output_state, &app_data.outputs);
+    infos[index].width = width;
+    infos[index].height = height;
+
+    let state = app_data.state.try_lock().ok()?;
+    let output_cfg = state
+        .config
+        .as_ref()?
+        .get_output_config(&infos[index], &infos)?;
+    output_cfg.portrait_wallpaper.as_ref()?;
+    super::utils::configured_wallpaper(output_cfg, &infos[index].name, orientation)
+}
+
+/// Command showing the wallpaper of an output plugged in while the daemon runs
+///
+/// That's the wallpaper its `[[output]]` section gives it, else the one last
+/// set on the same monitor, else the one last set on every output.
+fn hotplug_wallpaper(
+    app_data: &WallpaperDaemon,
+    layer: &LayerSurface,
+    (width, height): (u32, u32),
+) -> Option<crate::WallpaperCommand> {
+    let index = app_data
+        .outputs
+        .iter()
+        .position(|o| o.layer_surface.as_ref() == Some(layer))?;
+    if !app_data.started || app_data.outputs[index].configured || width == 0 || height == 0 {
+        return None;
+    }
+
+    let mut infos = super::utils::output_infos(&app_data.output_state, &app_data.outputs);
+    infos[index].width = width;
+    infos[index].height = height;
+    let info = &infos[index];
+
+    let state = app_data.state.try_lock().ok()?;
+    let configured = state
+        .config
+        .as_ref()
+        .and_then(|config| config.get_output_config(info, &infos))
+        .and_then(|output_cfg| {
+            super::utils::configured_wallpaper(
+                output_cfg,
+                &info.name,
+                Orientation::of(width, height),
+            )
+        });
+    if configured.is_some() {
+        return configured;
+    }
+
+    let command = state.assignments.recall(info)?;
+    log::info!(
+        "Restoring wallpaper of monitor {} on {}",
+        crate::assignments::monitor_id(info),
+        info.name
+    );
+    Some(super::utils::replay_command(command, &info.name))
+}
+
+/// Command showing the wallpaper last set on an output again at its new size
+///
+/// Images, videos and shaders were laid out for the old size (rotating an
+/// output swaps it); SVGs, gradients and single-pixel colours are redrawn
+/// without one.
+fn resized_wallpaper(
+    app_data: &WallpaperDaemon,
+    layer: &LayerSurface,
+    (width, height): (u32, u32),
+) -> Option<crate::WallpaperCommand> {
+    let index = app_data
+        .outputs
+        .iter()
+        .position(|o| o.layer_surface.as_ref() == Some(layer))?;
+    let output_data = &app_data.outputs[index];
+    if !output_data.configured
+        || width == 0
+        || height == 0
+        || (output_data.width, output_data.height) == (width, height)
+        || output_data.svg_source.is_some()
+        || output_data.gradient_source.is_some()
+        || output_data.single_pixel_color().is_some()
+    {
+        return None;
+    }
+
+    let mut infos = super::utils::output_infos(&app_data.output_state, &app_data.outputs);
+    infos[index].width = width;
+    infos[index].height = height;
+    let info = &infos[index];
+
+    let state = app_data.state.try_lock().ok()?;
+    let command = state.assignments.recall(info)?;
+    log::info!(
+        "Re-applying wallpaper of {} at {}x{}",
+        info.name,
+        width,
+        height
+    );
+
+    // The old frame is the old size, so there's nothing to transition from
+    let mut command = super::utils::replay_command(command, &info.name);
+    match &mut command {
+        crate::WallpaperCommand::SetImage { transition, .. }
+        | crate::WallpaperCommand::SetGradient { transition, .. }
+        | crate::WallpaperCommand::SetShader { transition, .. } => *transition = None,
+        _ => {}
+    }
+    Some(command)
+}
+
 impl ProvidesRegistryState for WallpaperDaemon {
     fn registry(&mut self) -> &mut smithay_client_toolkit::registry::RegistryState {
         &mut self.registry_state
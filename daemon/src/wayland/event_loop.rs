@@ -8,6 +8,7 @@
 //! - Initial configuration application
 
 use super::types::WallpaperDaemon;
+use crate::output_transform::Orientation;
 use anyhow::Result;
 use wayland_client::QueueHandle;
 
@@ -25,9 +26,14 @@ pub(super) fn get_next_frame_delay(app_data: &WallpaperDaemon) -> std::time::Dur
     // Start with a high value, we'll find the minimum needed
     let mut min_delay = Duration::from_millis(100);
 
-    // Check for active transitions (need 60fps updates)
+    // Check for active transitions and overlay fades (need 60fps updates)
     for output_data in &app_data.outputs {
-        if output_data.transition.is_some() {
+        let overlay_animating = output_data
+            .overlay_manager
+            .as_ref()
+            .is_some_and(|mgr| mgr.is_animating());
+
+        if output_data.transition.is_some() || overlay_animating {
             // Transition active, update at 60fps
             let transition_rate = Duration::from_millis(16);
             if transition_rate < min_delay {
@@ -36,6 +42,15 @@ pub(super) fn get_next_frame_delay(app_data: &WallpaperDaemon) -> std::time::Dur
         }
     }
 
+    // Pick up background image loads promptly
+    if !app_data.image_loads.is_empty() {
+        min_delay = min_delay.min(Duration::from_millis(16));
+    }
+    #[cfg(feature = "video")]
+    if !app_data.video_loads.is_empty() {
+        min_delay = min_delay.min(Duration::from_millis(16));
+    }
+
     // Videos (including converted GIFs) produce frames asynchronously, poll at their actual frame rate
     // Check shared video managers (new architecture with GPU scaling)
     #[cfg(feature = "video")]
@@ -128,6 +143,8 @@ pub(super) fn check_playlist_rotation(
             output: None, // Apply to all outputs
             scale: common::ScaleMode::Fill,
             transition: Some(transition_type),
+            focus: None,
+            letterbox: None,
         };
 
         super::commands::handle_wallpaper_command(app_data, cmd, qh)?;
@@ -180,6 +197,8 @@ pub(super) fn check_schedule(
             output: None, // Apply to all outputs
             scale: common::ScaleMode::Fill,
             transition: Some(transition_type),
+            focus: None,
+            letterbox: None,
         };
 
         super::commands::handle_wallpaper_command(app_data, cmd, qh)?;
@@ -188,6 +207,13 @@ pub(super) fn check_schedule(
     Ok(())
 }
 
+/// Publish the image cache contents to shared state for IPC queries.
+pub(super) fn publish_cache_status(app_data: &WallpaperDaemon) {
+    if let Ok(mut state) = app_data.state.try_lock() {
+        state.cache_status = Some(app_data.wallpaper_manager.cache_status());
+    }
+}
+
 /// Check and update resource monitor.
 ///
 /// Periodically updates resource usage statistics (CPU, memory, battery)
@@ -202,10 +228,28 @@ pub(super) fn check_resources(app_data: &mut WallpaperDaemon) -> Result<()> {
     let stats = app_data.resource_monitor.update()?;
     let mode = app_data.resource_monitor.mode();
 
+    // Keep the image cache within the memory budget for the current mode,
+    // and shrink it by the overage if the daemon is over its memory limit
+    let cache = app_data.wallpaper_manager.cache_mut();
+    cache.set_budget(app_data.resource_monitor.cache_budget_bytes());
+    if app_data
+        .resource_monitor
+        .is_over_memory_limit(stats.memory_bytes)
+    {
+        let limit_bytes = app_data.resource_monitor.current_memory_limit_mb() as u64 * 1024 * 1024;
+        let over = stats.memory_bytes.saturating_sub(limit_bytes) as usize;
+        log::warn!(
+            "Memory use ({} MiB) over limit, trimming image cache",
+            stats.memory_bytes / 1024 / 1024
+        );
+        cache.trim(over);
+    }
+
     // Update shared state with latest stats
     if let Ok(mut state) = app_data.state.try_lock() {
         state.resource_stats = Some(stats);
         state.performance_mode = format!("{:?}", mode);
+        state.cache_status = Some(app_data.wallpaper_manager.cache_status());
     }
 
     // Log buffer pool statistics to monitor for memory leaks
@@ -262,27 +306,15 @@ pub(super) fn apply_initial_config(
     let mut commands = Vec::new();
 
     // Check if we have per-output wallpapers configured
-    for output_cfg in &config.output {
-        if let Some(ref wallpaper_path) = output_cfg.wallpaper {
-            log::info!(
-                "Preparing initial wallpaper for {}: {}",
-                output_cfg.name,
-                wallpaper_path
-            );
-
-            let transition_type = common::TransitionType::Fade {
-                duration_ms: output_cfg.duration as u32,
-            };
-
-            let scale_mode = super::utils::parse_scale_mode(&output_cfg.scale);
-
-            let cmd = crate::WallpaperCommand::SetImage {
-                path: wallpaper_path.clone(),
-                output: Some(output_cfg.name.clone()),
-                scale: scale_mode,
-                transition: Some(transition_type),
-            };
-
+    let infos = super::utils::output_infos(&app_data.output_state, &app_data.outputs);
+    for info in &infos {
+        if let Some(output_cfg) = config.get_output_config(info, &infos)
+            && let Some(cmd) = super::utils::configured_wallpaper(
+                output_cfg,
+                &info.name,
+                Orientation::of(info.width, info.height),
+            )
+        {
             commands.push(cmd);
         }
     }
@@ -300,6 +332,8 @@ pub(super) fn apply_initial_config(
             output: None,
             scale: common::ScaleMode::Fill,
             transition: Some(common::TransitionType::Fade { duration_ms: 500 }),
+            focus: None,
+            letterbox: None,
         };
 
         commands.push(cmd);
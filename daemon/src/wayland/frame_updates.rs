@@ -1,8 +1,10 @@
 use super::{FrameUpdate, WallpaperDaemon};
 use crate::apply_overlay_or_warn;
+#[cfg(feature = "video")]
+use crate::frame_layout::Placement;
 use anyhow::Result;
 use smithay_client_toolkit::shell::WaylandSurface;
-use wayland_client::QueueHandle;
+use wayland_client::{QueueHandle, protocol::wl_shm};
 
 #[cfg(feature = "video")]
 pub(super) fn update_video_frames(
@@ -16,29 +18,31 @@ pub(super) fn update_video_frames(
     let mut updates: Vec<FrameUpdate> = Vec::new();
 
     // Collect output info first to avoid borrow checker issues
-    let output_infos: Vec<(usize, String, u32, u32)> = app_data
+    let output_infos: Vec<(usize, super::VideoKey, (u32, u32), Placement)> = app_data
         .outputs
         .iter()
         .enumerate()
         .filter_map(|(idx, out_data)| {
-            let path = out_data.video_path.as_ref()?.clone();
-            Some((idx, path, out_data.width, out_data.height))
+            let video = out_data.video.as_ref()?.clone();
+            let size = (out_data.width, out_data.height);
+            Some((idx, video, size, out_data.video_placement))
         })
         .collect();
 
-    // Process each unique video path once
-    let mut processed_paths: std::collections::HashSet<String> = std::collections::HashSet::new();
+    // Process each unique video once
+    let mut processed: std::collections::HashSet<&super::VideoKey> =
+        std::collections::HashSet::new();
 
-    for (_first_idx, video_path, _, _) in &output_infos {
+    for (_first_idx, video, _, _) in &output_infos {
         // Skip if we already processed this video
-        if !processed_paths.insert(video_path.clone()) {
+        if !processed.insert(video) {
             continue;
         }
 
-        let video_manager_arc = match app_data.video_managers.get(video_path) {
+        let video_manager_arc = match app_data.video_managers.get(video) {
             Some(arc) => arc,
             None => {
-                log::warn!("Video path {} not found in shared managers", video_path);
+                log::warn!("Video {} not found in shared managers", video.path);
                 continue;
             }
         };
@@ -51,29 +55,32 @@ pub(super) fn update_video_frames(
         video_manager.update();
 
         // Deduplicate scaling operations: only call GPU once per unique resolution
-        // Collect unique resolutions for this video
-        let unique_resolutions: Vec<(u32, u32)> = output_infos
+        // and placement; collect those for this video
+        let unique_targets: Vec<((u32, u32), Placement)> = output_infos
             .iter()
-            .filter(|(_, path, _, _)| path == video_path)
-            .map(|(_, _, w, h)| (*w, *h))
+            .filter(|(_, out_video, _, _)| out_video == video)
+            .map(|(_, _, size, placement)| (*size, *placement))
             .collect::<std::collections::HashSet<_>>()
             .into_iter()
             .collect();
 
-        // Render each unique resolution once and cache the result
-        let mut resolution_cache: std::collections::HashMap<(u32, u32), Vec<u8>> =
+        // Render each unique target once and cache the result
+        let mut target_cache: std::collections::HashMap<((u32, u32), Placement), Vec<u8>> =
             std::collections::HashMap::new();
 
-        for (width, height) in unique_resolutions {
-            if let Some(frame_data) = video_manager.current_frame_data_scaled(width, height) {
-                resolution_cache.insert((width, height), frame_data);
+        for ((width, height), placement) in unique_targets {
+            if let Some(frame_data) =
+                video_manager.current_frame_data_scaled(width, height, placement)
+            {
+                target_cache.insert(((width, height), placement), frame_data);
             }
         }
 
         // Now assign the cached frames to each output
-        for (out_idx, out_path, out_width, out_height) in &output_infos {
-            if out_path == video_path {
-                if let Some(frame_data) = resolution_cache.get(&(*out_width, *out_height)) {
+        for (out_idx, out_video, (out_width, out_height), placement) in &output_infos {
+            if out_video == video {
+                if let Some(frame_data) = target_cache.get(&((*out_width, *out_height), *placement))
+                {
                     updates.push(FrameUpdate {
                         output_index: *out_idx,
                         argb_data: frame_data.clone(),
@@ -99,6 +106,12 @@ pub(super) fn update_video_frames(
             update.height
         );
 
+        // Mid-transition, the transition blends into the latest frame instead
+        if output_data.transition.is_some() {
+            output_data.pending_wallpaper_data = Some(update.argb_data);
+            continue;
+        }
+
         // Video frame is already scaled to monitor size
         let mut final_data = update.argb_data;
 
@@ -114,9 +127,11 @@ pub(super) fn update_video_frames(
 
         // For video frames, reuse the existing buffer when possible (same as shader frames)
         // This avoids creating 60+ new 14MB buffers per second for high-res videos
+        // (but not a 10-bit one left by a still image: every frame would need converting)
         if let Some(buffer) = &mut output_data.buffer
             && buffer.width() == update.width
             && buffer.height() == update.height
+            && buffer.format() == wl_shm::Format::Argb8888
         {
             if let Err(e) = buffer.write_image_data(&final_data) {
                 log::warn!("Failed to reuse video buffer: {}", e);
@@ -124,17 +139,7 @@ pub(super) fn update_video_frames(
                 // Successfully reused buffer - attach and commit
 
                 if let Some(layer_surface) = &output_data.layer_surface {
-                    layer_surface
-                        .wl_surface()
-                        .attach(Some(buffer.buffer()), 0, 0);
-
-                    layer_surface.wl_surface().damage_buffer(
-                        0,
-                        0,
-                        update.width as i32,
-                        update.height as i32,
-                    );
-
+                    buffer.attach_to(layer_surface.wl_surface());
                     layer_surface.wl_surface().commit();
                 }
 
@@ -145,23 +150,13 @@ pub(super) fn update_video_frames(
 
         // No existing buffer, wrong size, or reuse failed - create new one
         let mut buffer =
-            crate::buffer::ShmBuffer::new(app_data.shm.wl_shm(), update.width, update.height, qh)?;
+            output_data.new_buffer(app_data.shm.wl_shm(), update.width, update.height, qh)?;
 
         buffer.write_image_data(&final_data)?;
 
         // Attach and commit
         if let Some(layer_surface) = &output_data.layer_surface {
-            layer_surface
-                .wl_surface()
-                .attach(Some(buffer.buffer()), 0, 0);
-
-            layer_surface.wl_surface().damage_buffer(
-                0,
-                0,
-                update.width as i32,
-                update.height as i32,
-            );
-
+            buffer.attach_to(layer_surface.wl_surface());
             layer_surface.wl_surface().commit();
         }
 
@@ -236,20 +231,14 @@ pub(super) fn update_shader_frames(
         if let Some(buffer) = &mut output_data.buffer
             && buffer.width() == width
             && buffer.height() == height
+            && buffer.format() == wl_shm::Format::Argb8888
         {
             if let Err(e) = buffer.write_image_data(&frame_data) {
                 log::warn!("Failed to reuse shader buffer: {}", e);
             } else {
                 // Successfully reused buffer
                 if let Some(layer_surface) = &output_data.layer_surface {
-                    layer_surface
-                        .wl_surface()
-                        .attach(Some(buffer.buffer()), 0, 0);
-
-                    layer_surface
-                        .wl_surface()
-                        .damage_buffer(0, 0, width as i32, height as i32);
-
+                    buffer.attach_to(layer_surface.wl_surface());
                     layer_surface.wl_surface().commit();
                 }
 
@@ -258,24 +247,17 @@ pub(super) fn update_shader_frames(
         }
 
         // Create new buffer if needed
-        let mut buffer = crate::buffer::ShmBuffer::new(&app_data.shm.wl_shm(), width, height, qh)?;
+        let mut buffer = output_data.new_buffer(app_data.shm.wl_shm(), width, height, qh)?;
         buffer.write_image_data(&frame_data)?;
 
         // Commit to Wayland
         if let Some(layer_surface) = &output_data.layer_surface {
-            layer_surface
-                .wl_surface()
-                .attach(Some(buffer.buffer()), 0, 0);
-
-            layer_surface
-                .wl_surface()
-                .damage_buffer(0, 0, width as i32, height as i32);
-
+            buffer.attach_to(layer_surface.wl_surface());
             layer_surface.wl_surface().commit();
         }
 
         // Replace buffer directly (no pooling)
-        output_data.buffer = Some(buffer);
+        output_data.set_buffer(buffer);
     }
 
     Ok(())
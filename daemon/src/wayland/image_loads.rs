@@ -0,0 +1,240 @@
+//! Background image loads.
+//!
+//! Still images are decoded and scaled on the [`crate::image_loader`] pool so
+//! the event loop keeps drawing video and shader frames meanwhile. Finished
+//! loads are picked up here: the image and its frames go into the cache and,
+//! on outputs that haven't been given another wallpaper since, the image is
+//! shown and the waiting IPC client gets its per-output results.
+//!
+//! The next playlist entry is loaded the same way shortly before it is due, so
+//! the rotation finds every frame cached and applies instantly.
+
+use super::{ImageLoad, WallpaperDaemon};
+use crate::image_cache::{FrameSize, SourceKey};
+use crate::image_loader::{LoadId, LoadJob};
+use crate::wallpaper_manager::WallpaperManager;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use wayland_client::QueueHandle;
+
+/// How long before a playlist rotation the next entry is loaded
+const PRELOAD_AHEAD: Duration = Duration::from_secs(30);
+
+/// Queue an image to be decoded and scaled for the given outputs
+///
+/// Unless this is a preload, the outputs show the image once it's ready.
+pub(super) fn start_load(
+    app_data: &mut WallpaperDaemon,
+    load: ImageLoad,
+    targets: &[usize],
+) -> Result<LoadId> {
+    let key = SourceKey::for_path(Path::new(&load.path))?;
+
+    let mut sizes = Vec::new();
+    let mut profiles: Vec<crate::color_profile::DisplayProfile> = Vec::new();
+    for &index in targets {
+        let output_data = &app_data.outputs[index];
+        let size = FrameSize::new(
+            output_data.width,
+            output_data.height,
+            load.scale,
+            load.focus,
+            load.letterbox,
+            load.tone_mapping,
+        )
+        .with_profile(output_data.color_profile.as_ref());
+        if !sizes.contains(&size) {
+            sizes.push(size);
+        }
+        if let Some(profile) = &output_data.color_profile
+            && !profiles.iter().any(|known| known.id() == profile.id())
+        {
+            profiles.push(profile.clone());
+        }
+    }
+
+    let id = app_data.image_loader.submit(LoadJob {
+        path: PathBuf::from(&load.path),
+        // Only scaling is left if the image was decoded before
+        image: app_data.wallpaper_manager.cache_mut().image(&key),
+        key,
+        sizes,
+        ten_bit: load.ten_bit,
+        profiles,
+    });
+
+    log::info!(
+        "{} {} in the background for {} output(s)",
+        if load.preload {
+            "Preloading"
+        } else {
+            "Loading"
+        },
+        load.path,
+        targets.len()
+    );
+
+    if !load.preload {
+        for &index in targets {
+            app_data.outputs[index].pending_load = Some(id);
+        }
+    }
+
+    app_data.image_loads.insert(id, load);
+    Ok(id)
+}
+
+/// Send a command's outcome to `reply` once its load (image or video) has finished
+pub(super) fn reply_when_loaded(
+    app_data: &mut WallpaperDaemon,
+    id: LoadId,
+    reply: crate::CommandReply,
+) {
+    if let Some(load) = app_data.image_loads.get_mut(&id) {
+        load.reply = Some(reply);
+        return;
+    }
+    #[cfg(feature = "video")]
+    if let Some(load) = app_data.video_loads.get_mut(&id) {
+        load.reply = Some(reply);
+        return;
+    }
+
+    // Loads are only collected after commands, so this doesn't happen
+    reply.send(&Ok(Vec::new()));
+}
+
+/// Stop loads in flight from replacing wallpapers that were just set
+pub(super) fn supersede_loads(app_data: &mut WallpaperDaemon, results: &[common::OutputResult]) {
+    for output_data in &mut app_data.outputs {
+        if output_data.pending_load.is_none() {
+            continue;
+        }
+
+        let name = super::utils::output_name(&app_data.output_state, &output_data.output);
+        if results
+            .iter()
+            .any(|result| result.output == name && result.error.is_none())
+        {
+            output_data.pending_load = None;
+        }
+    }
+}
+
+/// Cache and show the loads that have finished
+pub(super) fn update_image_loads(
+    app_data: &mut WallpaperDaemon,
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Result<()> {
+    let finished = app_data.image_loader.poll();
+    if finished.is_empty() {
+        return Ok(());
+    }
+
+    for loaded in finished {
+        let Some(load) = app_data.image_loads.remove(&loaded.id) else {
+            continue;
+        };
+
+        let loaded_image = match loaded.result {
+            Ok(loaded_image) => loaded_image,
+            Err(e) => {
+                log::error!("Failed to load {}: {:#}", load.path, e);
+                for output_data in &mut app_data.outputs {
+                    if output_data.pending_load == Some(loaded.id) {
+                        output_data.pending_load = None;
+                    }
+                }
+                if let Some(reply) = load.reply {
+                    reply.send(&Err(e));
+                }
+                continue;
+            }
+        };
+
+        let cache = app_data.wallpaper_manager.cache_mut();
+        cache.insert_image(loaded.key.clone(), loaded_image.image.clone());
+        for (size, frame) in &loaded_image.frames {
+            cache.insert_frame(loaded.key.clone(), *size, frame.clone());
+        }
+
+        if load.preload {
+            log::info!("Preloaded {}", load.path);
+            continue;
+        }
+
+        let results =
+            super::commands::show_loaded_image(app_data, loaded.id, &load, &loaded_image, qh);
+        super::palette::schedule_palette_updates(app_data, &results);
+        if let Some(reply) = load.reply {
+            reply.send(&Ok(results));
+        }
+    }
+
+    super::event_loop::publish_cache_status(app_data);
+    Ok(())
+}
+
+/// Load the next playlist entry shortly before it is due
+///
+/// Does nothing if `preload_next` is off, or the next entry isn't a still image.
+pub(super) fn preload_next_wallpaper(app_data: &mut WallpaperDaemon) -> Result<()> {
+    let next = {
+        let Ok(state) = app_data.state.try_lock() else {
+            return Ok(());
+        };
+
+        let enabled = state
+            .config
+            .as_ref()
+            .is_none_or(|config| config.advanced.preload_next);
+        match &state.playlist {
+            Some(playlist) if enabled && playlist.time_until_rotation() <= PRELOAD_AHEAD => {
+                playlist.peek_next().map(Path::to_path_buf)
+            }
+            _ => None,
+        }
+    };
+
+    let Some(next) = next else {
+        return Ok(());
+    };
+    if app_data.preloaded.as_ref() == Some(&next) {
+        return Ok(());
+    }
+    app_data.preloaded = Some(next.clone());
+
+    if WallpaperManager::is_gif(&next)
+        || WallpaperManager::is_video(&next)
+        || WallpaperManager::is_svg(&next)
+    {
+        return Ok(());
+    }
+
+    // Playlist rotations apply to every output with Fill
+    let targets: Vec<usize> = app_data
+        .outputs
+        .iter()
+        .enumerate()
+        .filter(|(_, output_data)| {
+            output_data.configured && output_data.width > 0 && output_data.height > 0
+        })
+        .map(|(index, _)| index)
+        .collect();
+
+    let load = ImageLoad {
+        focus: super::commands::focal_point(&app_data.state, &next),
+        letterbox: super::commands::default_letterbox(&app_data.state),
+        tone_mapping: super::commands::tone_mapping(&app_data.state),
+        ten_bit: super::commands::ten_bit_frames(app_data),
+        path: next.to_string_lossy().to_string(),
+        preload: true,
+        output_filter: None,
+        scale: common::ScaleMode::Fill,
+        transition: None,
+        reply: None,
+    };
+    start_load(app_data, load, &targets)?;
+    Ok(())
+}
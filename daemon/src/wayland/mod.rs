@@ -7,26 +7,42 @@
 //! - event_loop: Main event loop and periodic task helpers
 //! - commands: Wallpaper command handlers (set image, video, shader, etc.)
 //! - frame_updates: Frame update logic for videos, GIFs, shaders
+//! - image_loads: Background image loads and playlist preloading
+//! - video_loads: Background video loads (prerolling the first frame)
 //! - overlay: Overlay effect management
+//! - audio: Volume of video audio, ducking and muting when idle
+//! - palette: Colour palette updates and theme template export
 //! - transitions: Transition animation handling
 //! - outputs: Output/monitor and layer surface management
+//! - single_pixel: Solid colours as compositor-scaled single-pixel buffers
 //! - event_handlers: Wayland protocol event handlers
 //! - utils: Helper functions and utilities
 
+#[cfg(feature = "video")]
+mod audio;
 mod commands;
 mod daemon;
 mod event_handlers;
 mod event_loop;
 mod frame_updates;
+mod image_loads;
 mod outputs;
 mod overlay;
+mod palette;
 mod reconnection;
+mod single_pixel;
 mod transitions;
 mod types;
 mod utils;
+#[cfg(feature = "video")]
+mod video_loads;
 
 // Re-export the main entry point
 pub use daemon::run;
 
 // Re-export types that other modules need
-pub(crate) use types::{FrameUpdate, OutputData, WallpaperDaemon};
+pub(crate) use types::{
+    FrameUpdate, ImageLoad, OutputData, StaticFrame, SvgSource, WallpaperDaemon,
+};
+#[cfg(feature = "video")]
+pub(crate) use types::{VideoKey, VideoLoad};
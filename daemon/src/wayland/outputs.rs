@@ -6,7 +6,6 @@ use smithay_client_toolkit::shell::{
 use wayland_client::{QueueHandle, protocol::wl_output};
 
 use super::{OutputData, WallpaperDaemon};
-use crate::WallpaperCommand;
 
 /// Synchronize output information to shared state
 pub(super) fn sync_outputs_to_shared_state(app_data: &mut WallpaperDaemon) {
@@ -14,14 +13,8 @@ pub(super) fn sync_outputs_to_shared_state(app_data: &mut WallpaperDaemon) {
         state.outputs.clear();
 
         for output_data in &app_data.outputs {
-            if let Some(info) = app_data.output_state.info(&output_data.output) {
-                let output_info = common::OutputInfo {
-                    name: info.name.clone().unwrap_or_else(|| "Unknown".to_string()),
-                    width: output_data.width,
-                    height: output_data.height,
-                    scale: info.scale_factor as f64,
-                    refresh_rate: None,
-                };
+            if app_data.output_state.info(&output_data.output).is_some() {
+                let output_info = super::utils::output_info(&app_data.output_state, output_data);
 
                 log::info!(
                     "Added output to shared state: {} ({}x{})",
@@ -43,68 +36,38 @@ pub(super) fn restore_wallpapers_from_state(
     app_data: &mut WallpaperDaemon,
     qh: &QueueHandle<WallpaperDaemon>,
 ) -> Result<()> {
-    let wallpapers = if let Ok(state) = app_data.state.try_lock() {
-        state.wallpapers.clone()
+    // Each output gets the command that last set its wallpaper, so it comes
+    // back with the same scale mode, focal point, letterbox and shader params
+    let commands: Vec<_> = if let Ok(state) = app_data.state.try_lock() {
+        state
+            .outputs
+            .iter()
+            .filter(|info| state.wallpapers.contains_key(&info.name))
+            .filter_map(|info| {
+                let command = state.assignments.recall(info)?;
+                Some((
+                    info.name.clone(),
+                    super::utils::replay_command(command, &info.name),
+                ))
+            })
+            .collect()
     } else {
         log::warn!("Could not acquire state lock to restore wallpapers");
         return Ok(());
     };
 
-    if wallpapers.is_empty() {
+    if commands.is_empty() {
         log::debug!("No wallpapers to restore");
         return Ok(());
     }
 
     log::info!(
         "Restoring {} wallpaper(s) after reconnection",
-        wallpapers.len()
+        commands.len()
     );
 
-    for (output_name, wallpaper_type) in wallpapers {
-        let cmd = match wallpaper_type {
-            common::WallpaperType::Image(path) => {
-                log::info!("Restoring image wallpaper on {}: {}", output_name, path);
-                WallpaperCommand::SetImage {
-                    path,
-                    output: Some(output_name.clone()),
-                    scale: common::ScaleMode::Fill, // Default scale mode
-                    transition: None,               // No transition on restore
-                }
-            }
-
-            common::WallpaperType::Video(path) => {
-                log::info!("Restoring video wallpaper on {}: {}", output_name, path);
-                WallpaperCommand::SetImage {
-                    path,
-                    output: Some(output_name.clone()),
-                    scale: common::ScaleMode::Fill,
-                    transition: None,
-                }
-            }
-
-            common::WallpaperType::Color(color) => {
-                log::info!("Restoring color wallpaper on {}: {}", output_name, color);
-                WallpaperCommand::SetColor {
-                    color,
-                    output: Some(output_name.clone()),
-                }
-            }
-
-            common::WallpaperType::Shader(shader) => {
-                log::info!("Restoring shader wallpaper on {}: {}", output_name, shader);
-                WallpaperCommand::SetShader {
-                    shader,
-                    output: Some(output_name.clone()),
-                    transition: None,
-                    params: None, // Use default params when restoring
-                }
-            }
-
-            common::WallpaperType::None => {
-                log::debug!("Skipping 'None' wallpaper for {}", output_name);
-                continue;
-            }
-        };
+    for (output_name, cmd) in commands {
+        log::info!("Restoring wallpaper on {}: {:?}", output_name, cmd);
 
         // Apply the wallpaper
         if let Err(e) = super::commands::handle_wallpaper_command(app_data, cmd, qh) {
@@ -115,6 +78,80 @@ pub(super) fn restore_wallpapers_from_state(
     Ok(())
 }
 
+/// Redraw the frame on an output in its new buffer transform
+pub(super) fn redraw_transformed(
+    shm: &wayland_client::protocol::wl_shm::WlShm,
+    output_data: &mut OutputData,
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Result<()> {
+    // Single-pixel colours look the same any way round
+    let Some(buffer) = &output_data.buffer else {
+        return Ok(());
+    };
+    let (width, height) = (buffer.width(), buffer.height());
+
+    let new_buffer = match (&output_data.scaled_frame, &output_data.deep_frame) {
+        (Some(scaled), _) if buffer.scale() > 1 => super::commands::scaled_frame_buffer(
+            shm,
+            (output_data.width, output_data.height),
+            buffer.scale(),
+            scaled,
+            output_data.buffer_transform,
+            qh,
+        )?,
+        (_, Some(deep))
+            if buffer.format() == wayland_client::protocol::wl_shm::Format::Xrgb2101010 =>
+        {
+            super::commands::deep_frame_buffer(
+                shm,
+                (width, height),
+                deep,
+                output_data.buffer_transform,
+                qh,
+            )?
+        }
+        _ => {
+            let frame = buffer.read_data()?;
+            let mut new_buffer = output_data.new_buffer(shm, width, height, qh)?;
+            new_buffer.write_image_data(&frame)?;
+            new_buffer
+        }
+    };
+
+    if let Some(layer_surface) = &output_data.layer_surface {
+        new_buffer.attach_to(layer_surface.wl_surface());
+        layer_surface.wl_surface().commit();
+    }
+    output_data.swap_buffer(new_buffer);
+
+    Ok(())
+}
+
+/// Load the display profile configured for an output, if any
+fn display_profile(
+    app_data: &WallpaperDaemon,
+    output: &wl_output::WlOutput,
+) -> Option<crate::color_profile::DisplayProfile> {
+    let infos = super::utils::output_infos(&app_data.output_state, &app_data.outputs);
+    let index = app_data.outputs.iter().position(|o| o.output == *output)?;
+    let name = &infos[index].name;
+    let path = {
+        let state = app_data.state.try_lock().ok()?;
+        state.config.as_ref()?.icc_profile(&infos[index], &infos)?
+    };
+
+    match crate::color_profile::DisplayProfile::load(&path) {
+        Ok(profile) => {
+            log::info!("Using colour profile {} for {}", path.display(), name);
+            Some(profile)
+        }
+        Err(e) => {
+            log::error!("{:#}", e);
+            None
+        }
+    }
+}
+
 /// Create a layer surface for an output
 pub(super) fn create_layer_surface(
     app_data: &mut WallpaperDaemon,
@@ -137,27 +174,59 @@ pub(super) fn create_layer_surface(
     layer_surface.set_keyboard_interactivity(KeyboardInteractivity::None);
     layer_surface.commit();
 
+    // Corrected by the surface's preferred transform on compositors that send it
+    let buffer_transform = app_data
+        .output_state
+        .info(&output)
+        .map(|info| info.transform)
+        .unwrap_or(wl_output::Transform::Normal);
+
     app_data.outputs.push(OutputData {
-        output,
+        output: output.clone(),
         layer_surface: Some(layer_surface),
         buffer: None,
         buffer_pool: Vec::new(),
+        single_pixel: None,
+        viewport: None,
         width: 0,
         height: 0,
-        scale: 1.0,
+        buffer_scale: 1,
         configured: false,
         #[allow(deprecated)]
         video_manager: None,
         #[cfg(feature = "video")]
-        video_path: None,
+        video: None,
+        #[cfg(feature = "video")]
+        video_placement: crate::frame_layout::Placement {
+            mode: common::ScaleMode::Fill,
+            letterbox: common::Letterbox::default(),
+        },
+        #[cfg(feature = "video")]
+        volume: app_data.volume,
         shader_manager: None,
         overlay_manager: None,
         transition: None,
         pending_wallpaper_data: None,
+        base_frame: None,
+        deep_frame: None,
+        scaled_frame: None,
+        svg_source: None,
+        gradient_source: None,
+        palette_due: None,
+        pending_load: None,
+        color_profile: None,
+        buffer_transform,
         #[cfg(feature = "gpu")]
         gpu_renderer: None,
     });
 
+    // Selectors in the config can depend on the other outputs, so the profile
+    // is looked up once this output is among them
+    let color_profile = display_profile(app_data, &output);
+    if let Some(output_data) = app_data.outputs.last_mut() {
+        output_data.color_profile = color_profile;
+    }
+
     log::info!("Created layer surface for output");
 
     Ok(())
@@ -1,7 +1,23 @@
 use anyhow::Result;
-use smithay_client_toolkit::output::OutputState;
-
-use super::OutputData;
+use smithay_client_toolkit::{output::OutputState, shell::WaylandSurface};
+use std::time::Duration;
+use wayland_client::QueueHandle;
+
+use super::utils::{output_name, output_result};
+use super::{OutputData, WallpaperDaemon};
+use crate::apply_overlay_or_warn;
+use crate::transition::EasingFunction;
+
+/// Get fade duration and easing, with no fade meaning an instant switch
+fn fade_timing(fade: Option<common::OverlayFade>) -> (Duration, EasingFunction) {
+    match fade {
+        Some(fade) => (
+            Duration::from_millis(fade.duration_ms as u64),
+            fade.easing.into(),
+        ),
+        None => (Duration::ZERO, EasingFunction::Linear),
+    }
+}
 
 /// Set overlay shader for outputs
 pub(super) fn set_overlay_shader(
@@ -10,51 +26,58 @@ pub(super) fn set_overlay_shader(
     overlay_name: &str,
     params: crate::overlay_shader::OverlayParams,
     output_filter: Option<&str>,
-) -> Result<()> {
+    fade: Option<common::OverlayFade>,
+) -> Result<Vec<common::OutputResult>> {
     log::info!(
-        "Setting overlay shader: {} for output: {:?}",
+        "Setting overlay shader: {} for output: {:?} (fade: {:?})",
         overlay_name,
-        output_filter
+        output_filter,
+        fade
     );
 
     let overlay = crate::overlay_shader::OverlayShader::from_str(overlay_name, &params)
-        .ok_or_else(|| anyhow::anyhow!("Unknown overlay: {}. Available: vignette, scanlines, film-grain, chromatic, crt, pixelate, tint", overlay_name))?;
+        .ok_or_else(|| common::WallpaperError::UnknownOverlay {
+            overlay: overlay_name.to_string(),
+        })?;
 
+    let mut results = Vec::new();
+    let selected = super::utils::select_outputs(output_state, outputs, output_filter)?;
     for output_data in outputs {
         if !output_data.configured {
             continue;
         }
 
         // Check output filter
-        if let Some(filter) = output_filter
-            && let Some(info) = output_state.info(&output_data.output)
-            && let Some(name) = &info.name
-            && name != filter
-            && filter != "all"
-        {
+        if !selected.contains(&output_data.output) {
             continue;
         }
 
-        let overlay_mgr = crate::overlay_shader::OverlayManager::new(overlay);
-        output_data.overlay_manager = Some(overlay_mgr);
+        // Animate from whatever is currently shown (instant switches still go through
+        // a zero-length animation so static wallpapers get redrawn)
+        let (duration, easing) = fade_timing(fade);
+        match &mut output_data.overlay_manager {
+            Some(overlay_mgr) => overlay_mgr.animate_to(overlay, duration, easing),
+            None => {
+                output_data.overlay_manager = Some(
+                    crate::overlay_shader::OverlayManager::with_fade_in(overlay, duration, easing),
+                );
+            }
+        }
 
-        // Get output name for logging
-        let output_name = if let Some(info) = output_state.info(&output_data.output) {
-            info.name.clone().unwrap_or_else(|| "unknown".to_string())
-        } else {
-            "unknown".to_string()
-        };
+        let name = output_name(output_state, &output_data.output);
 
         log::info!(
             "Applied overlay '{}' to output '{}' ({}x{})",
             overlay_name,
-            output_name,
+            name,
             output_data.width,
             output_data.height
         );
+
+        results.push(output_result(name, Ok(())));
     }
 
-    Ok(())
+    Ok(results)
 }
 
 /// Clear overlay shader for outputs
@@ -62,26 +85,114 @@ pub(super) fn clear_overlay_shader(
     outputs: &mut [OutputData],
     output_state: &OutputState,
     output_filter: Option<&str>,
-) -> Result<()> {
-    log::info!("Clearing overlay shader for output: {:?}", output_filter);
+    fade: Option<common::OverlayFade>,
+) -> Result<Vec<common::OutputResult>> {
+    log::info!(
+        "Clearing overlay shader for output: {:?} (fade: {:?})",
+        output_filter,
+        fade
+    );
 
+    let mut results = Vec::new();
+    let selected = super::utils::select_outputs(output_state, outputs, output_filter)?;
     for output_data in outputs {
         if !output_data.configured {
             continue;
         }
 
         // Check output filter
-        if let Some(filter) = output_filter
-            && let Some(info) = output_state.info(&output_data.output)
-            && let Some(name) = &info.name
-            && name != filter
-            && filter != "all"
-        {
+        if !selected.contains(&output_data.output) {
+            continue;
+        }
+
+        // The manager is dropped by update_overlay_animations once fully faded out
+        if let Some(overlay_mgr) = &mut output_data.overlay_manager {
+            let (duration, easing) = fade_timing(fade);
+            overlay_mgr.fade_out(duration, easing);
+            log::info!("Clearing overlay from output over {:?}", duration);
+        }
+
+        let name = output_name(output_state, &output_data.output);
+        results.push(output_result(name, Ok(())));
+    }
+
+    Ok(results)
+}
+
+/// Advance overlay fades and parameter changes.
+///
+/// Shader and video frames pick up the animated overlay as they re-render, but
+/// static wallpapers are only committed once, so they get redrawn from
+/// `base_frame` here while an animation is running. Outputs with no frame to
+/// redraw (solid colours) just have their animations run out.
+pub(super) fn update_overlay_animations(
+    app_data: &mut WallpaperDaemon,
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Result<()> {
+    for output_data in &mut app_data.outputs {
+        let shows_no_frame = output_data.shows_no_frame();
+        let Some(overlay_mgr) = &mut output_data.overlay_manager else {
             continue;
+        };
+
+        if shows_no_frame {
+            overlay_mgr.settle();
         }
 
-        output_data.overlay_manager = None;
-        log::info!("Cleared overlay from output");
+        if overlay_mgr.is_animating()
+            && output_data.shows_static_frame()
+            && let Some(mut frame) = output_data.base_frame.clone()
+        {
+            let width = output_data.width;
+            let height = output_data.height;
+
+            if frame.len() == (width * height * 4) as usize {
+                apply_overlay_or_warn!(
+                    apply_overlay_to_frame,
+                    output_data,
+                    &mut frame,
+                    width,
+                    height,
+                    "static frame"
+                );
+
+                // Update buffer - reuse if possible
+                if let Some(buffer) = &mut output_data.buffer
+                    && buffer.width() == width
+                    && buffer.height() == height
+                {
+                    buffer.write_image_data(&frame)?;
+                } else {
+                    let mut buffer =
+                        output_data.new_buffer(app_data.shm.wl_shm(), width, height, qh)?;
+                    buffer.write_image_data(&frame)?;
+                    output_data.set_buffer(buffer);
+                }
+
+                if let Some(layer_surface) = &output_data.layer_surface
+                    && let Some(buffer) = &output_data.buffer
+                {
+                    buffer.attach_to(layer_surface.wl_surface());
+                    layer_surface.wl_surface().commit();
+                }
+            } else {
+                log::warn!(
+                    "Base frame size mismatch ({} bytes for {}x{}), skipping overlay redraw",
+                    frame.len(),
+                    width,
+                    height
+                );
+            }
+        }
+
+        if output_data
+            .overlay_manager
+            .as_ref()
+            .is_some_and(|mgr| mgr.is_faded_out())
+        {
+            output_data.overlay_manager = None;
+            log::info!("Cleared overlay from output");
+        }
     }
 
     Ok(())
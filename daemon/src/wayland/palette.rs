@@ -0,0 +1,110 @@
+//! Colour palette updates.
+//!
+//! After a wallpaper change each affected output waits briefly, so transitions
+//! can finish and video or shader outputs have drawn a frame, then samples what
+//! it shows. The palette goes into the shared state for IPC queries and fills in
+//! the configured theme templates.
+
+use super::WallpaperDaemon;
+use super::utils::output_name;
+use crate::transition::OldFrame;
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+/// How long after a wallpaper change the palette is sampled
+const SAMPLE_DELAY: Duration = Duration::from_millis(500);
+
+/// Mark the outputs a command changed for palette sampling
+pub(super) fn schedule_palette_updates(
+    app_data: &mut WallpaperDaemon,
+    results: &[common::OutputResult],
+) {
+    let due = Instant::now() + SAMPLE_DELAY;
+
+    for output_data in &mut app_data.outputs {
+        let name = output_name(&app_data.output_state, &output_data.output);
+        if results
+            .iter()
+            .any(|result| result.output == name && result.error.is_none())
+        {
+            output_data.palette_due = Some(due);
+        }
+    }
+}
+
+/// Sample palettes that are due and export the configured templates
+pub(super) fn update_palettes(app_data: &mut WallpaperDaemon) -> Result<()> {
+    let now = Instant::now();
+
+    for output_data in &mut app_data.outputs {
+        if output_data.palette_due.is_none_or(|due| now < due) || output_data.transition.is_some() {
+            continue;
+        }
+
+        // Try again next tick if the IPC server holds the state
+        let Ok(mut state) = app_data.state.try_lock() else {
+            return Ok(());
+        };
+        output_data.palette_due = None;
+
+        let settings = state
+            .config
+            .as_ref()
+            .map(|config| config.palette.clone())
+            .unwrap_or_default();
+
+        // Prefer the frame without overlays; videos and shaders only have the buffer
+        let palette = match &output_data.base_frame {
+            Some(frame) => crate::palette::extract(frame, settings.colors),
+            None => match output_data.read_frame() {
+                Ok(Some(OldFrame::Frame(frame))) => {
+                    crate::palette::extract(&frame, settings.colors)
+                }
+                // A single pixel samples the same as a frame filled with it
+                Ok(Some(OldFrame::Color(pixel))) => {
+                    crate::palette::extract(&pixel, settings.colors)
+                }
+                Ok(None) => None,
+                // One unreadable buffer shouldn't stop the other outputs
+                Err(e) => {
+                    log::warn!(
+                        "Failed to read frame of {} for palette: {:#}",
+                        output_name(&app_data.output_state, &output_data.output),
+                        e
+                    );
+                    continue;
+                }
+            },
+        };
+        let Some(palette) = palette else {
+            continue;
+        };
+
+        let name = output_name(&app_data.output_state, &output_data.output);
+        log::info!(
+            "Palette for {}: {} (accent {})",
+            name,
+            palette.colors.join(" "),
+            palette.accent
+        );
+        state.palettes.insert(name.clone(), palette.clone());
+        let exported = settings.output.as_deref().is_none_or(|output| {
+            crate::output_selector::selected_names(Some(output), &state.outputs).contains(&name)
+        });
+        drop(state);
+
+        if exported {
+            for template in &settings.templates {
+                let source = shellexpand::tilde(&template.template).to_string();
+                let target = shellexpand::tilde(&template.target).to_string();
+
+                match crate::palette::export(source.as_ref(), target.as_ref(), &palette) {
+                    Ok(()) => log::debug!("Wrote palette template {}", target),
+                    Err(e) => log::warn!("Failed to export palette template: {:#}", e),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -12,7 +12,7 @@
 //! - `initial_reconnection_backoff_ms`: Initial backoff delay
 //! - `max_reconnection_backoff_ms`: Maximum backoff delay (exponential cap)
 
-use crate::{DaemonState, WallpaperCommand};
+use crate::{DaemonState, WallpaperRequest};
 use anyhow::Result;
 use std::sync::Arc;
 use tokio::sync::{Mutex, mpsc};
@@ -33,11 +33,11 @@ use tokio::sync::{Mutex, mpsc};
 /// Ok if normal exit, Err if max retries exceeded or non-retryable error
 pub(super) fn run_with_reconnect<F>(
     state: Arc<Mutex<DaemonState>>,
-    mut wallpaper_rx: mpsc::UnboundedReceiver<WallpaperCommand>,
+    mut wallpaper_rx: mpsc::UnboundedReceiver<WallpaperRequest>,
     mut run_fn: F,
 ) -> Result<()>
 where
-    F: FnMut(Arc<Mutex<DaemonState>>, &mut mpsc::UnboundedReceiver<WallpaperCommand>) -> Result<()>,
+    F: FnMut(Arc<Mutex<DaemonState>>, &mut mpsc::UnboundedReceiver<WallpaperRequest>) -> Result<()>,
 {
     // Get reconnection settings from config
     let (enable_reconnection, max_retries, initial_backoff, max_backoff) = {
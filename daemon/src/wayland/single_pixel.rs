@@ -0,0 +1,113 @@
+//! Solid colours as single-pixel buffers
+//!
+//! A full-resolution SHM buffer holding one colour is about 33 MB on a 4K
+//! output. When the compositor supports `wp_single_pixel_buffer_manager_v1`
+//! and `wp_viewporter`, a 1x1 buffer is attached instead and a viewport scales
+//! it to the whole surface. Without them colours fall back to SHM buffers.
+//!
+//! The viewport's destination is kept at the surface size, so full-size SHM
+//! buffers attached later are shown 1:1 through it. Transitions away from a
+//! colour blend against the colour itself rather than a full-size copy.
+
+use anyhow::Result;
+use smithay_client_toolkit::shell::WaylandSurface;
+use wayland_client::{QueueHandle, globals::GlobalList, protocol::wl_buffer};
+use wayland_protocols::wp::single_pixel_buffer::v1::client::wp_single_pixel_buffer_manager_v1::WpSinglePixelBufferManagerV1;
+use wayland_protocols::wp::viewporter::client::{
+    wp_viewport::WpViewport, wp_viewporter::WpViewporter,
+};
+
+use super::{OutputData, WallpaperDaemon};
+
+/// Globals needed to show single-pixel buffers
+pub struct SinglePixel {
+    manager: WpSinglePixelBufferManagerV1,
+    viewporter: WpViewporter,
+}
+
+impl SinglePixel {
+    /// Bind both globals, or None if the compositor lacks either of them
+    pub(super) fn bind(globals: &GlobalList, qh: &QueueHandle<WallpaperDaemon>) -> Option<Self> {
+        let manager = globals.bind(qh, 1..=1, ()).ok();
+        let viewporter = globals.bind(qh, 1..=1, ()).ok();
+
+        match (manager, viewporter) {
+            (Some(manager), Some(viewporter)) => {
+                log::info!("Solid colours will use single-pixel buffers");
+                Some(Self {
+                    manager,
+                    viewporter,
+                })
+            }
+            (manager, viewporter) => {
+                log::info!(
+                    "Solid colours will use SHM buffers (single-pixel buffers: {}, viewporter: {})",
+                    manager.is_some(),
+                    viewporter.is_some()
+                );
+                None
+            }
+        }
+    }
+
+    /// Show a solid colour on an output with a 1x1 buffer
+    pub(super) fn show(
+        &self,
+        output_data: &mut OutputData,
+        (r, g, b, a): (u8, u8, u8, u8),
+        qh: &QueueHandle<WallpaperDaemon>,
+    ) -> Result<()> {
+        let Some(layer_surface) = &output_data.layer_surface else {
+            anyhow::bail!("Output has no layer surface");
+        };
+        let surface = layer_surface.wl_surface();
+
+        let viewport = output_data
+            .viewport
+            .get_or_insert_with(|| self.viewporter.get_viewport(surface, qh, ()));
+        viewport.set_destination(output_data.width as i32, output_data.height as i32);
+
+        // Channels are premultiplied and span the whole u32 range
+        let channel = |value: u8| (value as u32 * a as u32 / 255) * 0x0101_0101;
+        let buffer = self.manager.create_u32_rgba_buffer(
+            channel(r),
+            channel(g),
+            channel(b),
+            a as u32 * 0x0101_0101,
+            qh,
+            (),
+        );
+
+        // A 1x1 buffer can't be shown at a larger buffer scale
+        surface.set_buffer_scale(1);
+        surface.attach(Some(&buffer), 0, 0);
+        surface.damage_buffer(0, 0, 1, 1);
+        surface.commit();
+
+        // The SHM buffer is no longer shown; it's freed once the compositor releases it
+        if let Some(old_buffer) = output_data.buffer.take() {
+            output_data.buffer_pool.push(old_buffer);
+        }
+        if let Some((old_buffer, _)) = output_data.single_pixel.replace((buffer, (r, g, b, a))) {
+            old_buffer.destroy();
+        }
+
+        Ok(())
+    }
+}
+
+/// Keep an output's viewport at its surface size after it is resized
+pub(super) fn resize_viewport(output_data: &OutputData) {
+    if let Some(viewport) = &output_data.viewport
+        && output_data.width > 0
+        && output_data.height > 0
+    {
+        viewport.set_destination(output_data.width as i32, output_data.height as i32);
+    }
+}
+
+wayland_client::delegate_noop!(WallpaperDaemon: WpSinglePixelBufferManagerV1);
+wayland_client::delegate_noop!(WallpaperDaemon: WpViewporter);
+wayland_client::delegate_noop!(WallpaperDaemon: WpViewport);
+// Single-pixel buffers are destroyed when replaced, so releases need no handling
+wayland_client::delegate_noop!(WallpaperDaemon: ignore wl_buffer::WlBuffer);
@@ -40,45 +40,63 @@ pub(super) fn update_transitions(
                 );
 
                 // Update buffer with final wallpaper - reuse if possible
-                if let Some(buffer) = &mut output_data.buffer {
+                // (high-bit-depth images end on their 10-bit frame, and
+                // SVGs on HiDPI outputs on their scaled one)
+                if let Some(scaled) = &output_data.scaled_frame
+                    && output_data.overlay_manager.is_none()
+                {
+                    output_data.set_buffer(super::commands::scaled_frame_buffer(
+                        app_data.shm.wl_shm(),
+                        (width, height),
+                        output_data.buffer_scale,
+                        scaled,
+                        output_data.buffer_transform,
+                        qh,
+                    )?);
+                } else if let Some(deep) = &output_data.deep_frame
+                    && output_data.overlay_manager.is_none()
+                {
+                    output_data.set_buffer(super::commands::deep_frame_buffer(
+                        app_data.shm.wl_shm(),
+                        (width, height),
+                        deep,
+                        output_data.buffer_transform,
+                        qh,
+                    )?);
+                } else if let Some(buffer) = &mut output_data.buffer {
                     if buffer.width() == width && buffer.height() == height {
                         buffer.write_image_data(&final_data)?;
                     } else {
                         // Wrong size, create new
-                        let mut new_buffer = crate::buffer::ShmBuffer::new(
-                            &app_data.shm.wl_shm(),
-                            width,
-                            height,
-                            qh,
-                        )?;
+                        let mut new_buffer =
+                            output_data.new_buffer(app_data.shm.wl_shm(), width, height, qh)?;
                         new_buffer.write_image_data(&final_data)?;
-                        output_data.buffer = Some(new_buffer);
+                        output_data.set_buffer(new_buffer);
                     }
                 } else {
                     // No buffer, create new
                     let mut buffer =
-                        crate::buffer::ShmBuffer::new(&app_data.shm.wl_shm(), width, height, qh)?;
+                        output_data.new_buffer(app_data.shm.wl_shm(), width, height, qh)?;
                     buffer.write_image_data(&final_data)?;
-                    output_data.buffer = Some(buffer);
+                    output_data.set_buffer(buffer);
                 }
 
                 // Commit to Wayland
                 if let Some(layer_surface) = &output_data.layer_surface
                     && let Some(buffer) = &output_data.buffer
                 {
-                    layer_surface
-                        .wl_surface()
-                        .attach(Some(buffer.buffer()), 0, 0);
-                    layer_surface
-                        .wl_surface()
-                        .damage_buffer(0, 0, width as i32, height as i32);
+                    buffer.attach_to(layer_surface.wl_surface());
                     layer_surface.wl_surface().commit();
                 }
             }
 
-            // Clear transition state
+            // Clear transition state (keep the new frame for overlay redraws,
+            // unless it's a video's, which keeps being redrawn anyway)
             output_data.transition = None;
-            output_data.pending_wallpaper_data = None;
+            let frame = output_data.pending_wallpaper_data.take();
+            #[cfg(feature = "video")]
+            let frame = frame.filter(|_| output_data.video.is_none());
+            output_data.base_frame = frame;
             continue;
         }
 
@@ -103,28 +121,22 @@ pub(super) fn update_transitions(
             } else {
                 // Wrong size, create new
                 let mut new_buffer =
-                    crate::buffer::ShmBuffer::new(&app_data.shm.wl_shm(), width, height, qh)?;
+                    output_data.new_buffer(app_data.shm.wl_shm(), width, height, qh)?;
                 new_buffer.write_image_data(&blended_frame)?;
-                output_data.buffer = Some(new_buffer);
+                output_data.set_buffer(new_buffer);
             }
         } else {
             // No buffer, create new
-            let mut buffer =
-                crate::buffer::ShmBuffer::new(&app_data.shm.wl_shm(), width, height, qh)?;
+            let mut buffer = output_data.new_buffer(app_data.shm.wl_shm(), width, height, qh)?;
             buffer.write_image_data(&blended_frame)?;
-            output_data.buffer = Some(buffer);
+            output_data.set_buffer(buffer);
         }
 
         // Attach and commit
         if let Some(layer_surface) = &output_data.layer_surface
             && let Some(buffer) = &output_data.buffer
         {
-            layer_surface
-                .wl_surface()
-                .attach(Some(buffer.buffer()), 0, 0);
-            layer_surface
-                .wl_surface()
-                .damage_buffer(0, 0, width as i32, height as i32);
+            buffer.attach_to(layer_surface.wl_surface());
             layer_surface.wl_surface().commit();
         }
     }
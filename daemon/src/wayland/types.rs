@@ -10,7 +10,10 @@ use smithay_client_toolkit::{
 };
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use wayland_client::{QueueHandle, protocol::wl_output};
+use wayland_client::{
+    QueueHandle,
+    protocol::{wl_buffer, wl_output},
+};
 
 use crate::DaemonState;
 use crate::wallpaper_manager::WallpaperManager;
@@ -24,11 +27,22 @@ pub struct WallpaperDaemon {
     pub(super) layer_shell: LayerShell,
     pub(super) output_state: OutputState,
     pub(super) shm: Shm,
+    /// Single-pixel buffer globals, if the compositor supports them
+    pub(super) single_pixel: Option<super::single_pixel::SinglePixel>,
     pub(super) outputs: Vec<OutputData>,
     pub(super) wallpaper_manager: WallpaperManager,
     pub(super) state: Arc<Mutex<DaemonState>>,
     pub(super) exit: bool,
     pub(super) resource_monitor: crate::resource_monitor::ResourceMonitor,
+    /// Decodes and scales still images (and prerolls videos) off the Wayland thread
+    pub(super) image_loader: crate::image_loader::ImageLoader,
+    /// Loads in flight, keyed by their loader id
+    pub(super) image_loads: std::collections::HashMap<crate::image_loader::LoadId, ImageLoad>,
+    /// Playlist entry last queued for preloading (so it's only queued once)
+    pub(super) preloaded: Option<std::path::PathBuf>,
+    /// Set once the initial configuration is applied; outputs configured
+    /// after that were plugged in while the daemon was running
+    pub(super) started: bool,
     /// Shared GPU renderer (if available and enabled)
     #[cfg(feature = "gpu")]
     pub(super) gpu_renderer: Option<std::sync::Arc<crate::gpu::GpuRenderer>>,
@@ -36,7 +50,19 @@ pub struct WallpaperDaemon {
     /// Each video is decoded once and GPU-scaled to each output's resolution
     #[cfg(feature = "video")]
     pub(super) video_managers:
-        std::collections::HashMap<String, Arc<Mutex<crate::video::VideoManager>>>,
+        std::collections::HashMap<VideoKey, Arc<Mutex<crate::video::VideoManager>>>,
+    /// Videos loading in the background, keyed by their loader id
+    #[cfg(feature = "video")]
+    pub(super) video_loads: std::collections::HashMap<crate::image_loader::LoadId, VideoLoad>,
+    /// Whether other applications are playing audio (video audio is ducked then)
+    #[cfg(feature = "video")]
+    pub(super) other_audio: crate::audio::OtherAudio,
+    /// Whether the session is idle (video audio is muted then)
+    #[cfg(feature = "video")]
+    pub(super) idle: bool,
+    /// Volume new outputs start with: the config's, until it's set for all outputs
+    #[cfg(feature = "video")]
+    pub(super) volume: crate::audio::Volume,
 }
 
 /// Per-output data and state.
@@ -53,29 +79,102 @@ pub struct OutputData {
     pub(super) buffer: Option<crate::buffer::ShmBuffer>,
     /// Pool of old buffers waiting to be released by compositor
     pub(super) buffer_pool: Vec<crate::buffer::ShmBuffer>,
+    /// 1x1 buffer and its colour, on screen while `buffer` is None
+    pub(super) single_pixel: Option<(wl_buffer::WlBuffer, (u8, u8, u8, u8))>,
+    /// Viewport scaling single-pixel buffers (created the first time one is shown)
+    pub(super) viewport: Option<wayland_protocols::wp::viewporter::client::wp_viewport::WpViewport>,
     pub(super) width: u32,
     pub(super) height: u32,
-    #[allow(dead_code)] // Scale field for future HiDPI support
-    pub(super) scale: f64,
+    /// Integer scale the compositor prefers for this output's surface
+    pub(super) buffer_scale: u32,
     pub(super) configured: bool,
-    /// DEPRECATED: Use video_path instead (GPU scaling allows single VideoManager per video)
+    /// DEPRECATED: Use video instead (GPU scaling allows single VideoManager per video)
     #[deprecated]
     #[allow(dead_code)] // Deprecated field kept during migration period
     pub(super) video_manager: Option<crate::video::VideoManager>,
-    /// Path to video file (references shared VideoManager in WallpaperDaemon::video_managers)
+    /// Video played (references shared VideoManager in WallpaperDaemon::video_managers)
+    #[cfg(feature = "video")]
+    pub(super) video: Option<VideoKey>,
+    /// How this output places its video's frames
+    #[cfg(feature = "video")]
+    pub(super) video_placement: crate::frame_layout::Placement,
+    /// Volume of this output's video audio
     #[cfg(feature = "video")]
-    pub(super) video_path: Option<String>,
+    pub(super) volume: crate::audio::Volume,
     pub(super) shader_manager: Option<crate::shader_manager::ShaderManager>,
     pub(super) overlay_manager: Option<crate::overlay_shader::OverlayManager>,
     /// Active transition (if any)
     pub(super) transition: Option<crate::transition::Transition>,
     /// Pending new wallpaper content (used during transitions)
     pub(super) pending_wallpaper_data: Option<Vec<u8>>,
+    /// Last static frame before overlays were applied (used to redraw overlay fades)
+    pub(super) base_frame: Option<Vec<u8>>,
+    /// 10-bit (XRGB2101010) copy of the new static frame, shown instead of it
+    /// while no overlay is drawn over it
+    pub(super) deep_frame: Option<std::sync::Arc<Vec<u8>>>,
+    /// Copy of the new static frame rendered at `buffer_scale`, likewise shown
+    /// instead of it while no overlay is drawn over it
+    pub(super) scaled_frame: Option<std::sync::Arc<Vec<u8>>>,
+    /// SVG shown on this output (re-rendered when the output is resized or rescaled)
+    pub(super) svg_source: Option<SvgSource>,
+    /// Gradient shown on this output (re-rendered when the output is resized)
+    pub(super) gradient_source: Option<common::Gradient>,
+    /// When to sample the colour palette after the wallpaper changed
+    pub(super) palette_due: Option<std::time::Instant>,
+    /// Image or video load that will replace this output's wallpaper when it finishes
+    pub(super) pending_load: Option<crate::image_loader::LoadId>,
+    /// Display profile image frames are converted to (from the output's config)
+    pub(super) color_profile: Option<crate::color_profile::DisplayProfile>,
+    /// How the output is rotated or flipped; new buffers hold frames turned to match
+    pub(super) buffer_transform: wl_output::Transform,
     /// GPU renderer for accelerated rendering (optional)
     #[cfg(feature = "gpu")]
     pub(super) gpu_renderer: Option<std::sync::Arc<crate::gpu::GpuRenderer>>,
 }
 
+impl OutputData {
+    /// Check if this output is showing a static frame that nothing else redraws
+    pub(super) fn shows_static_frame(&self) -> bool {
+        #[cfg(feature = "video")]
+        if self.video.is_some() {
+            return false;
+        }
+
+        self.base_frame.is_some() && self.shader_manager.is_none() && self.transition.is_none()
+    }
+
+    /// Check if nothing redraws this output's frames (e.g. it shows a solid colour)
+    pub(super) fn shows_no_frame(&self) -> bool {
+        #[cfg(feature = "video")]
+        if self.video.is_some() {
+            return false;
+        }
+
+        self.base_frame.is_none() && self.shader_manager.is_none() && self.transition.is_none()
+    }
+
+    /// Colour shown through a single-pixel buffer, if that's what is on screen
+    pub(super) fn single_pixel_color(&self) -> Option<(u8, u8, u8, u8)> {
+        match (&self.buffer, &self.single_pixel) {
+            (None, Some((_, color))) => Some(*color),
+            _ => None,
+        }
+    }
+
+    /// Copy of the frame on screen in ARGB8888 (just the colour for a
+    /// single-pixel buffer), or None if nothing is shown yet
+    pub(super) fn read_frame(&self) -> Result<Option<crate::transition::OldFrame>> {
+        if let Some((r, g, b, a)) = self.single_pixel_color() {
+            return Ok(Some(crate::transition::OldFrame::Color([b, g, r, a])));
+        }
+
+        self.buffer
+            .as_ref()
+            .map(|buffer| buffer.read_data().map(crate::transition::OldFrame::Frame))
+            .transpose()
+    }
+}
+
 impl Drop for OutputData {
     fn drop(&mut self) {
         log::info!(
@@ -90,6 +189,13 @@ impl Drop for OutputData {
         self.shader_manager = None;
         self.overlay_manager = None;
 
+        if let Some((buffer, _)) = self.single_pixel.take() {
+            buffer.destroy();
+        }
+        if let Some(viewport) = self.viewport.take() {
+            viewport.destroy();
+        }
+
         log::info!("OutputData::drop - Cleanup complete");
     }
 }
@@ -105,11 +211,13 @@ impl OutputData {
         qh: &QueueHandle<WallpaperDaemon>,
     ) -> Result<crate::buffer::ShmBuffer> {
         // Try to find a released buffer with matching dimensions
-        if let Some(index) = self
-            .buffer_pool
-            .iter()
-            .position(|buf| buf.width() == width && buf.height() == height && buf.is_released())
-        {
+        if let Some(index) = self.buffer_pool.iter().position(|buf| {
+            buf.width() == width
+                && buf.height() == height
+                && buf.transform() == self.buffer_transform
+                && buf.scale() == 1
+                && buf.is_released()
+        }) {
             let buffer = self.buffer_pool.swap_remove(index);
             log::debug!(
                 "Reusing buffer from pool ({}x{}, pool size: {})",
@@ -122,7 +230,36 @@ impl OutputData {
 
         // No suitable buffer found, create a new one
         log::debug!("Creating new buffer ({}x{})", width, height);
-        crate::buffer::ShmBuffer::new(shm.wl_shm(), width, height, qh)
+        self.new_buffer(shm.wl_shm(), width, height, qh)
+    }
+
+    /// Create an ARGB8888 buffer for `width`x`height` frames in the output's transform
+    pub(super) fn new_buffer(
+        &self,
+        shm: &wayland_client::protocol::wl_shm::WlShm,
+        width: u32,
+        height: u32,
+        qh: &QueueHandle<WallpaperDaemon>,
+    ) -> Result<crate::buffer::ShmBuffer> {
+        crate::buffer::ShmBuffer::with_format(
+            shm,
+            width,
+            height,
+            (
+                wayland_client::protocol::wl_shm::Format::Argb8888,
+                self.buffer_transform,
+            ),
+            qh,
+        )
+    }
+
+    /// Keep the SHM buffer now shown, destroying a single-pixel buffer it replaces
+    pub(super) fn set_buffer(&mut self, buffer: crate::buffer::ShmBuffer) {
+        // Its contents can't change, so it can go before the compositor releases it
+        if let Some((single_pixel, _)) = self.single_pixel.take() {
+            single_pixel.destroy();
+        }
+        self.buffer = Some(buffer);
     }
 
     /// Move the current buffer to the pool before replacing it
@@ -136,7 +273,7 @@ impl OutputData {
                 self.buffer_pool.len()
             );
         }
-        self.buffer = Some(new_buffer);
+        self.set_buffer(new_buffer);
     }
 
     /// Clean up released buffers from the pool
@@ -182,6 +319,77 @@ impl OutputData {
     }
 }
 
+/// An image being decoded in the background and what to do with it
+pub struct ImageLoad {
+    pub(super) path: String,
+    /// Only warm the cache (playlist preload) instead of showing the image
+    pub(super) preload: bool,
+    pub(super) output_filter: Option<String>,
+    pub(super) scale: common::ScaleMode,
+    /// Focal point from the command, a sidecar file or the config
+    pub(super) focus: Option<common::FocalPoint>,
+    /// Background for Fit and Center, from the command or the config
+    pub(super) letterbox: common::Letterbox,
+    /// How HDR images are tone mapped, from the config
+    pub(super) tone_mapping: crate::hdr::ToneMapping,
+    /// Also scale 10-bit frames (the config allows them and the compositor supports them)
+    pub(super) ten_bit: bool,
+    pub(super) transition: Option<common::TransitionType>,
+    /// Where to report the outcome, if an IPC client is waiting for it
+    pub(super) reply: Option<crate::CommandReply>,
+}
+
+/// A still frame to show on an output
+pub struct StaticFrame {
+    /// ARGB8888 frame at the output's size (also used for transitions and overlays)
+    pub(super) argb: Vec<u8>,
+    /// 10-bit (XRGB2101010) copy, shown instead while no overlay is drawn over it
+    pub(super) deep: Option<Arc<Vec<u8>>>,
+    /// Copy at the output's buffer scale (HiDPI), shown the same way
+    pub(super) scaled: Option<Arc<Vec<u8>>>,
+}
+
+impl StaticFrame {
+    /// A frame with only its 8-bit version
+    pub(super) fn new(argb: Vec<u8>) -> Self {
+        Self {
+            argb,
+            deep: None,
+            scaled: None,
+        }
+    }
+}
+
+/// A video shared by the outputs playing it
+///
+/// Frames decoded for Stretch are already the output's shape and can't be
+/// placed another way, so those videos are decoded apart from the others.
+#[cfg(feature = "video")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct VideoKey {
+    pub(super) path: String,
+    pub(super) stretched: bool,
+}
+
+/// A video being loaded and prerolled in the background, and where to show it
+#[cfg(feature = "video")]
+pub struct VideoLoad {
+    pub(super) key: VideoKey,
+    pub(super) placement: crate::frame_layout::Placement,
+    pub(super) output_filter: Option<String>,
+    pub(super) transition: Option<common::TransitionType>,
+    /// Where to report the outcome, if an IPC client is waiting for it
+    pub(super) reply: Option<crate::CommandReply>,
+}
+
+/// An SVG wallpaper and how it's scaled to its output
+#[derive(Debug, Clone)]
+pub struct SvgSource {
+    pub(super) path: String,
+    pub(super) scale: common::ScaleMode,
+    pub(super) letterbox: common::Letterbox,
+}
+
 /// Frame data ready for rendering (computed in parallel)
 pub struct FrameUpdate {
     pub(super) output_index: usize,
@@ -1,3 +1,5 @@
+use super::OutputData;
+
 /// Parse scale mode string to ScaleMode enum
 pub(super) fn parse_scale_mode(scale: &str) -> common::ScaleMode {
     match scale {
@@ -77,3 +79,150 @@ pub(super) fn parse_transition(transition: &str, duration: i32) -> common::Trans
         },
     }
 }
+
+/// Get the compositor-assigned name of an output ("unknown" until it's advertised)
+pub(super) fn output_name(
+    output_state: &smithay_client_toolkit::output::OutputState,
+    output: &wayland_client::protocol::wl_output::WlOutput,
+) -> String {
+    output_state
+        .info(output)
+        .and_then(|info| info.name)
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Describe an output for IPC clients and output selectors
+pub(super) fn output_info(
+    output_state: &smithay_client_toolkit::output::OutputState,
+    output_data: &OutputData,
+) -> common::OutputInfo {
+    let Some(info) = output_state.info(&output_data.output) else {
+        return common::OutputInfo {
+            name: "unknown".to_string(),
+            width: output_data.width,
+            height: output_data.height,
+            scale: 1.0,
+            ..Default::default()
+        };
+    };
+
+    // The wallpaper's size once configured, else the output's logical size
+    let (width, height) = if output_data.configured {
+        (output_data.width, output_data.height)
+    } else {
+        info.logical_size
+            .map(|(w, h)| (w.max(0) as u32, h.max(0) as u32))
+            .unwrap_or_default()
+    };
+    let serial = info.description.as_deref().and_then(|description| {
+        crate::output_selector::serial_from_description(description, &info.make, &info.model)
+    });
+
+    common::OutputInfo {
+        name: info.name.clone().unwrap_or_else(|| "unknown".to_string()),
+        width,
+        height,
+        scale: info.scale_factor as f64,
+        refresh_rate: info
+            .modes
+            .iter()
+            .find(|mode| mode.current)
+            .map(|mode| mode.refresh_rate.max(0) as u32),
+        description: info.description.clone(),
+        make: info.make.clone(),
+        model: info.model.clone(),
+        serial,
+        position: Some(info.logical_position.unwrap_or(info.location)),
+        physical_size: (
+            info.physical_size.0.max(0) as u32,
+            info.physical_size.1.max(0) as u32,
+        ),
+        transform: crate::output_transform::transform_name(info.transform).to_string(),
+    }
+}
+
+/// Describe every output (selectors can depend on the other outputs)
+pub(super) fn output_infos(
+    output_state: &smithay_client_toolkit::output::OutputState,
+    outputs: &[OutputData],
+) -> Vec<common::OutputInfo> {
+    outputs
+        .iter()
+        .map(|output_data| output_info(output_state, output_data))
+        .collect()
+}
+
+/// Outputs a command's output filter picks (every output without one)
+pub(super) fn select_outputs(
+    output_state: &smithay_client_toolkit::output::OutputState,
+    outputs: &[OutputData],
+    output_filter: Option<&str>,
+) -> anyhow::Result<Vec<wayland_client::protocol::wl_output::WlOutput>> {
+    let selector = match output_filter {
+        Some(filter) => filter.parse()?,
+        None => crate::output_selector::OutputSelector::All,
+    };
+
+    let infos = output_infos(output_state, outputs);
+
+    Ok(outputs
+        .iter()
+        .zip(&infos)
+        .filter(|(_, info)| selector.matches(info, &infos))
+        .map(|(output_data, _)| output_data.output.clone())
+        .collect())
+}
+
+/// Command showing the wallpaper an output's config section gives it in an orientation
+pub(super) fn configured_wallpaper(
+    output_cfg: &crate::config::OutputConfig,
+    output_name: &str,
+    orientation: crate::output_transform::Orientation,
+) -> Option<crate::WallpaperCommand> {
+    let wallpaper_path = output_cfg.wallpaper_for(orientation)?;
+    log::info!(
+        "Preparing wallpaper for {} ({:?}): {}",
+        output_name,
+        orientation,
+        wallpaper_path
+    );
+
+    Some(crate::WallpaperCommand::SetImage {
+        path: wallpaper_path.to_string(),
+        output: Some(output_name.to_string()),
+        scale: parse_scale_mode(&output_cfg.scale),
+        transition: Some(common::TransitionType::Fade {
+            duration_ms: output_cfg.duration as u32,
+        }),
+        focus: None,
+        letterbox: None,
+    })
+}
+
+/// A recorded wallpaper command (see [`crate::assignments`]) aimed at one output
+pub(super) fn replay_command(
+    command: &crate::WallpaperCommand,
+    output_name: &str,
+) -> crate::WallpaperCommand {
+    let mut command = command.clone();
+    match &mut command {
+        crate::WallpaperCommand::SetImage { output, .. }
+        | crate::WallpaperCommand::SetColor { output, .. }
+        | crate::WallpaperCommand::SetGradient { output, .. }
+        | crate::WallpaperCommand::SetShader { output, .. } => {
+            *output = Some(output_name.to_string());
+        }
+        _ => {}
+    }
+    command
+}
+
+/// Record the outcome of applying a command to one output
+pub(super) fn output_result(output: String, result: anyhow::Result<()>) -> common::OutputResult {
+    let error = result.err().map(|e| {
+        log::error!("Failed to apply command to output {}: {:#}", output, e);
+        crate::to_wallpaper_error(&e)
+    });
+
+    common::OutputResult { output, error }
+}
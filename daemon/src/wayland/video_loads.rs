@@ -0,0 +1,48 @@
+//! Background video loads.
+//!
+//! Loading a video and prerolling its first frame can take seconds, so it runs
+//! on the [`crate::image_loader`] pool like image decoding. Finished loads are
+//! picked up here: the video plays on the outputs that haven't been given
+//! another wallpaper since, starting with a transition into its first frame,
+//! and the waiting IPC client gets its per-output results.
+
+use super::WallpaperDaemon;
+use anyhow::Result;
+use wayland_client::QueueHandle;
+
+/// Play the videos that have finished prerolling
+pub(super) fn update_video_loads(
+    app_data: &mut WallpaperDaemon,
+    qh: &QueueHandle<WallpaperDaemon>,
+) -> Result<()> {
+    for loaded in app_data.image_loader.poll_videos() {
+        let Some(load) = app_data.video_loads.remove(&loaded.id) else {
+            continue;
+        };
+
+        let video_manager = match loaded.result {
+            Ok(video_manager) => video_manager,
+            Err(e) => {
+                log::error!("Failed to load video {}: {:#}", load.key.path, e);
+                for output_data in &mut app_data.outputs {
+                    if output_data.pending_load == Some(loaded.id) {
+                        output_data.pending_load = None;
+                    }
+                }
+                if let Some(reply) = load.reply {
+                    reply.send(&Err(e));
+                }
+                continue;
+            }
+        };
+
+        let results =
+            super::commands::show_loaded_video(app_data, loaded.id, &load, video_manager, qh);
+        super::palette::schedule_palette_updates(app_data, &results);
+        if let Some(reply) = load.reply {
+            reply.send(&Ok(results));
+        }
+    }
+
+    Ok(())
+}
@@ -1,7 +1,23 @@
 /// Integration tests for IPC communication
 /// These tests verify that commands and responses serialize correctly
 /// and can be sent over IPC boundaries
-use common::{Command, OverlayParams, Response, ScaleMode, ShaderParams, TransitionType};
+use common::{
+    CacheEntry, CacheStatus, ClientMessage, Command, Easing, FocalPoint, Gradient, GradientKind,
+    Letterbox, OutputInfo, OutputPalette, OutputResult, OverlayFade, OverlayParams, Palette,
+    PixelFormat, Reply, Request, Response, ScaleMode, ServerMessage, ShaderParams, TransitionType,
+    WallpaperError, WallpaperStatus, WallpaperType,
+};
+
+/// Split a byte stream into frame payloads
+fn read_frames(mut bytes: &[u8]) -> Vec<&[u8]> {
+    let mut frames = Vec::new();
+    while !bytes.is_empty() {
+        let len = common::decode_frame_len(bytes[..4].try_into().unwrap()).unwrap();
+        frames.push(&bytes[4..4 + len]);
+        bytes = &bytes[4 + len..];
+    }
+    frames
+}
 
 #[test]
 fn test_command_response_roundtrip() {
@@ -11,6 +27,8 @@ fn test_command_response_roundtrip() {
         output: Some("DP-1".to_string()),
         transition: Some(TransitionType::Fade { duration_ms: 500 }),
         scale: Some(ScaleMode::Fill),
+        focus: Some(FocalPoint { x: 0.25, y: 0.5 }),
+        letterbox: Some(Letterbox::Color([30, 30, 46])),
     };
 
     let json = serde_json::to_string(&cmd).unwrap();
@@ -23,6 +41,8 @@ fn test_command_response_roundtrip() {
             output,
             transition,
             scale,
+            focus,
+            letterbox,
         } => {
             assert_eq!(path, "/tmp/test.png");
             assert_eq!(output, Some("DP-1".to_string()));
@@ -31,9 +51,22 @@ fn test_command_response_roundtrip() {
                 Some(TransitionType::Fade { duration_ms: 500 })
             ));
             assert!(matches!(scale, Some(ScaleMode::Fill)));
+            assert_eq!(focus, Some(FocalPoint { x: 0.25, y: 0.5 }));
+            assert_eq!(letterbox, Some(Letterbox::Color([30, 30, 46])));
         }
         _ => panic!("Wrong command type"),
     }
+
+    // Clients that predate focal points and letterboxes leave the fields out
+    let old = r#"{"SetWallpaper":{"path":"/a.png","output":null,"transition":null,"scale":null}}"#;
+    assert!(matches!(
+        serde_json::from_str::<Command>(old).unwrap(),
+        Command::SetWallpaper {
+            focus: None,
+            letterbox: None,
+            ..
+        }
+    ));
 }
 
 #[test]
@@ -125,6 +158,7 @@ fn test_overlay_commands() {
             ..Default::default()
         }),
         output: Some("DP-1".to_string()),
+        fade: None,
     };
 
     let json = serde_json::to_string(&cmd).unwrap();
@@ -144,11 +178,52 @@ fn test_overlay_commands() {
     }
 
     // Test ClearOverlay
-    let cmd = Command::ClearOverlay { output: None };
+    let cmd = Command::ClearOverlay {
+        output: None,
+        fade: None,
+    };
     let json = serde_json::to_string(&cmd).unwrap();
     let _: Command = serde_json::from_str(&json).unwrap();
 }
 
+#[test]
+fn test_overlay_fade_commands() {
+    let cmd = Command::SetOverlay {
+        overlay: "vignette".to_string(),
+        params: Some(OverlayParams {
+            strength: Some(0.8),
+            ..Default::default()
+        }),
+        output: None,
+        fade: Some(OverlayFade {
+            duration_ms: 2000,
+            easing: Easing::Linear,
+        }),
+    };
+
+    let json = serde_json::to_string(&cmd).unwrap();
+    let deserialized: Command = serde_json::from_str(&json).unwrap();
+
+    match deserialized {
+        Command::SetOverlay { fade, .. } => {
+            let fade = fade.unwrap();
+            assert_eq!(fade.duration_ms, 2000);
+            assert_eq!(fade.easing, Easing::Linear);
+        }
+        _ => panic!("Wrong command type"),
+    }
+
+    // Older clients don't send the fade field at all
+    let legacy = r#"{"ClearOverlay":{"output":null}}"#;
+    match serde_json::from_str::<Command>(legacy).unwrap() {
+        Command::ClearOverlay { output, fade } => {
+            assert!(output.is_none());
+            assert!(fade.is_none());
+        }
+        _ => panic!("Wrong command type"),
+    }
+}
+
 #[test]
 fn test_transition_types_serialization() {
     let transitions = vec![
@@ -214,6 +289,54 @@ fn test_color_command() {
     }
 }
 
+#[test]
+fn test_gradient_command() {
+    let gradient = Gradient {
+        kind: GradientKind::Conic,
+        angle: -90.0,
+        stops: vec![
+            "#1e1e2e".parse().unwrap(),
+            "#89b4fa@0.8".parse().unwrap(),
+            "#f5c2e7".parse().unwrap(),
+        ],
+        dither: false,
+    };
+    let cmd = Command::SetGradient {
+        gradient: gradient.clone(),
+        output: None,
+        transition: Some(TransitionType::Fade { duration_ms: 300 }),
+    };
+
+    let json = serde_json::to_string(&cmd).unwrap();
+    match serde_json::from_str(&json).unwrap() {
+        Command::SetGradient {
+            gradient: deserialized,
+            output,
+            transition,
+        } => {
+            assert_eq!(deserialized, gradient);
+            assert_eq!(output, None);
+            assert!(matches!(
+                transition,
+                Some(TransitionType::Fade { duration_ms: 300 })
+            ));
+        }
+        _ => panic!("Wrong command type"),
+    }
+
+    // Query reports the gradient itself, so it can be restored
+    let status = WallpaperStatus {
+        output: "DP-1".to_string(),
+        wallpaper: WallpaperType::Gradient(gradient.clone()),
+    };
+    let json = serde_json::to_string(&status).unwrap();
+    let deserialized: WallpaperStatus = serde_json::from_str(&json).unwrap();
+    assert!(matches!(
+        deserialized.wallpaper,
+        WallpaperType::Gradient(restored) if restored == gradient
+    ));
+}
+
 #[test]
 fn test_performance_mode_command() {
     let cmd = Command::SetPerformanceMode {
@@ -230,3 +353,309 @@ fn test_performance_mode_command() {
         _ => panic!("Wrong command type"),
     }
 }
+
+#[test]
+fn test_legacy_protocol_detection() {
+    // Old clients send one bare JSON command per line
+    let legacy = serde_json::to_string(&Command::Ping).unwrap();
+    assert!(!common::is_framed(legacy.as_bytes()[0]));
+    let legacy = serde_json::to_string(&Command::ClearOverlay {
+        output: None,
+        fade: None,
+    })
+    .unwrap();
+    assert!(!common::is_framed(legacy.as_bytes()[0]));
+
+    let frame = common::encode_frame(&ClientMessage::Request(Request {
+        request_id: 1,
+        command: Command::Ping,
+        no_wait: false,
+    }))
+    .unwrap();
+    assert!(common::is_framed(frame[0]));
+}
+
+#[test]
+fn test_structured_errors() {
+    let reply = ServerMessage::Reply(Reply {
+        request_id: 4,
+        response: Response::Error(WallpaperError::UnknownOutput {
+            output: "HDMI-A-9".to_string(),
+        }),
+    });
+
+    // Scripts can match on the variant and its fields directly
+    let json = serde_json::to_value(&reply).unwrap();
+    assert_eq!(
+        json["Reply"]["response"]["Error"]["UnknownOutput"]["output"],
+        "HDMI-A-9"
+    );
+
+    let frame = common::encode_frame(&reply).unwrap();
+    match serde_json::from_slice::<ServerMessage>(read_frames(&frame)[0]).unwrap() {
+        ServerMessage::Reply(Reply {
+            response: Response::Error(err),
+            ..
+        }) => {
+            assert_eq!(err.code(), common::ErrorCode::UnknownOutput);
+            assert_eq!(err.to_string(), "Unknown output: HDMI-A-9");
+        }
+        _ => panic!("Wrong message type"),
+    }
+}
+
+#[test]
+fn test_applied_response() {
+    let resp = Response::Applied(vec![
+        OutputResult {
+            output: "DP-1".to_string(),
+            error: None,
+        },
+        OutputResult {
+            output: "HDMI-A-1".to_string(),
+            error: Some(WallpaperError::Gstreamer {
+                element: "vah264dec".to_string(),
+                message: "no element \"vah264dec\"".to_string(),
+            }),
+        },
+    ]);
+
+    let json = serde_json::to_string(&resp).unwrap();
+    let deserialized: Response = serde_json::from_str(&json).unwrap();
+
+    match &deserialized {
+        Response::Applied(results) => {
+            assert_eq!(results.len(), 2);
+            assert_eq!(results[0].output, "DP-1");
+            assert!(results[0].error.is_none());
+            assert_eq!(
+                results[1].error.as_ref().map(|e| e.code()),
+                Some(common::ErrorCode::Gstreamer)
+            );
+        }
+        _ => panic!("Wrong response type"),
+    }
+
+    // Legacy clients get the first failure, or Ok when everything applied
+    assert!(matches!(
+        deserialized.into_simple(),
+        Response::Error(WallpaperError::Gstreamer { .. })
+    ));
+    let ok = Response::Applied(vec![OutputResult {
+        output: "DP-1".to_string(),
+        error: None,
+    }]);
+    assert!(matches!(ok.into_simple(), Response::Ok));
+}
+
+#[test]
+fn test_image_data_request() {
+    // A 1080p RGBA frame fits in a single frame once base64-encoded
+    let bytes = vec![0x7f; 1920 * 1080 * 4];
+    let request = ClientMessage::Request(Request {
+        request_id: 1,
+        command: Command::SetImageData {
+            width: 1920,
+            height: 1080,
+            format: PixelFormat::Rgba8,
+            bytes,
+            output: Some("DP-1".to_string()),
+            transition: None,
+            scale: Some(ScaleMode::Fit),
+            letterbox: Some(Letterbox::Blur),
+        },
+        no_wait: false,
+    });
+
+    let frame = common::encode_frame(&request).unwrap();
+    assert!(frame.len() < common::MAX_FRAME_LEN);
+
+    match serde_json::from_slice::<ClientMessage>(read_frames(&frame)[0]).unwrap() {
+        ClientMessage::Request(Request {
+            command:
+                Command::SetImageData {
+                    width,
+                    height,
+                    format,
+                    bytes,
+                    ..
+                },
+            ..
+        }) => {
+            assert_eq!((width, height), (1920, 1080));
+            assert_eq!(format, PixelFormat::Rgba8);
+            assert_eq!(bytes.len(), 1920 * 1080 * 4);
+        }
+        _ => panic!("Wrong message type"),
+    }
+
+    // Download failures keep the URL
+    let err = WallpaperError::FetchFailed {
+        url: "https://example.com/wall.png".to_string(),
+        reason: "http status: 404".to_string(),
+    };
+    assert_eq!(err.code().as_str(), "fetch_failed");
+    assert!(err.to_string().contains("https://example.com/wall.png"));
+}
+
+#[test]
+fn test_capture_command() {
+    let cmd = Command::Capture {
+        output: Some("DP-1".to_string()),
+        path: "/tmp/out.png".to_string(),
+    };
+    assert_eq!(cmd.name(), "Capture");
+    assert!(Command::NAMES.contains(&"Capture"));
+
+    let json = serde_json::to_string(&cmd).unwrap();
+    match serde_json::from_str::<Command>(&json).unwrap() {
+        Command::Capture { output, path } => {
+            assert_eq!(output.as_deref(), Some("DP-1"));
+            assert_eq!(path, "/tmp/out.png");
+        }
+        _ => panic!("Wrong command type"),
+    }
+
+    // Without an output, all outputs are stitched together
+    let json = r#"{"Capture":{"output":null,"path":"/tmp/all.png"}}"#;
+    assert!(matches!(
+        serde_json::from_str::<Command>(json).unwrap(),
+        Command::Capture { output: None, .. }
+    ));
+}
+
+#[test]
+fn test_volume_command() {
+    let cmd = Command::SetVolume {
+        output: Some("DP-1".to_string()),
+        volume: Some(0.4),
+        muted: Some(false),
+    };
+    assert_eq!(cmd.name(), "SetVolume");
+    assert!(Command::NAMES.contains(&"SetVolume"));
+
+    let json = serde_json::to_string(&cmd).unwrap();
+    match serde_json::from_str::<Command>(&json).unwrap() {
+        Command::SetVolume {
+            output,
+            volume,
+            muted,
+        } => {
+            assert_eq!(output.as_deref(), Some("DP-1"));
+            assert_eq!(volume, Some(0.4));
+            assert_eq!(muted, Some(false));
+        }
+        _ => panic!("Wrong command type"),
+    }
+
+    // Muting alone keeps the volume
+    let json = r#"{"SetVolume":{"output":null,"muted":true}}"#;
+    assert!(matches!(
+        serde_json::from_str::<Command>(json).unwrap(),
+        Command::SetVolume {
+            output: None,
+            volume: None,
+            muted: Some(true),
+        }
+    ));
+}
+
+#[test]
+fn test_palette_response() {
+    let cmd = Command::GetPalette {
+        output: Some("DP-1".to_string()),
+    };
+    assert_eq!(cmd.name(), "GetPalette");
+    let json = serde_json::to_string(&cmd).unwrap();
+    assert!(matches!(
+        serde_json::from_str::<Command>(&json).unwrap(),
+        Command::GetPalette { output: Some(_) }
+    ));
+
+    let response = Response::Palette(vec![OutputPalette {
+        output: "DP-1".to_string(),
+        palette: Palette {
+            colors: vec!["#2e3440".to_string(), "#88c0d0".to_string()],
+            background: "#2e3440".to_string(),
+            foreground: "#eceff4".to_string(),
+            accent: "#88c0d0".to_string(),
+        },
+    }]);
+
+    let json = serde_json::to_string(&response).unwrap();
+    match serde_json::from_str::<Response>(&json).unwrap() {
+        Response::Palette(palettes) => {
+            assert_eq!(palettes.len(), 1);
+            assert_eq!(palettes[0].output, "DP-1");
+            assert_eq!(palettes[0].palette.colors.len(), 2);
+            assert_eq!(palettes[0].palette.accent, "#88c0d0");
+        }
+        _ => panic!("Wrong response type"),
+    }
+}
+
+/// Test cache status response serialization
+#[test]
+fn test_cache_status_response() {
+    let response = Response::Cache(CacheStatus {
+        bytes: 8_294_400,
+        limit_bytes: Some(500 * 1024 * 1024),
+        hits: 3,
+        misses: 1,
+        evictions: 0,
+        entries: vec![CacheEntry {
+            path: "/wallpapers/a.png".to_string(),
+            width: 1920,
+            height: 1080,
+            bytes: 8_294_400,
+            scale: Some(ScaleMode::Fill),
+        }],
+    });
+
+    let json = serde_json::to_string(&response).unwrap();
+    match serde_json::from_str::<Response>(&json).unwrap() {
+        Response::Cache(status) => {
+            assert_eq!(status.hits, 3);
+            assert_eq!(status.limit_bytes, Some(500 * 1024 * 1024));
+            assert_eq!(status.entries[0].scale, Some(ScaleMode::Fill));
+        }
+        _ => panic!("Wrong response type"),
+    }
+    assert!(Command::NAMES.contains(&Command::ClearCache.name()));
+}
+
+#[test]
+fn test_outputs_response() {
+    let response = Response::Outputs(vec![OutputInfo {
+        name: "DP-3".to_string(),
+        width: 1440,
+        height: 2560,
+        scale: 1.0,
+        refresh_rate: Some(59_951),
+        description: Some("Dell Inc. DELL U2720Q 8XYZ123 (DP-3)".to_string()),
+        make: "Dell Inc.".to_string(),
+        model: "DELL U2720Q".to_string(),
+        serial: Some("8XYZ123".to_string()),
+        position: Some((2560, 0)),
+        physical_size: (600, 340),
+        transform: "90".to_string(),
+    }]);
+
+    let json = serde_json::to_string(&response).unwrap();
+    match serde_json::from_str::<Response>(&json).unwrap() {
+        Response::Outputs(outputs) => {
+            assert_eq!(outputs[0].serial.as_deref(), Some("8XYZ123"));
+            assert_eq!(outputs[0].position, Some((2560, 0)));
+            assert_eq!(outputs[0].physical_size, (600, 340));
+            assert_eq!(outputs[0].transform, "90");
+        }
+        _ => panic!("Wrong response type"),
+    }
+
+    // Older daemons only send the name, size, scale and refresh rate
+    let old = r#"{"name":"DP-1","width":1920,"height":1080,"scale":1.0,"refresh_rate":null}"#;
+    let output: OutputInfo = serde_json::from_str(old).unwrap();
+    assert_eq!(output.name, "DP-1");
+    assert_eq!(output.position, None);
+    assert!(output.make.is_empty());
+}
@@ -43,6 +43,7 @@
             vulkan-headers
             libGL
             ffmpeg
+            dav1d
             gst_all_1.gstreamer
             gst_all_1.gst-plugins-base
             gst_all_1.gst-plugins-good
@@ -65,6 +66,7 @@
             vulkan-loader
             libGL
             ffmpeg
+            dav1d
             gst_all_1.gstreamer
             gst_all_1.gst-plugins-base
             gst_all_1.gst-plugins-good